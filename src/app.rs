@@ -0,0 +1,450 @@
+use axum::{
+    extract::DefaultBodyLimit,
+    routing::{get, post, put, delete},
+    Router,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tower_http::{
+    trace::{TraceLayer, OnResponse},
+    cors::{CorsLayer, Any},
+    compression::CompressionLayer,
+    services::{ServeDir, ServeFile},
+};
+use tracing::{Level, info, Span};
+use crate::config::Config;
+use crate::logging::LogLevel;
+use crate::middleware::access_log::AccessLog;
+use crate::middleware::client_ip::{ClientIp, ClientIpResolver};
+use crate::services::meme::MemeService;
+use crate::utils::error::AppError;
+use crate::utils::i18n::Locale;
+use crate::{handlers, metrics, middleware, openapi, services};
+#[cfg(feature = "graphql")]
+use crate::graphql;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Clone)]
+struct CustomOnResponse;
+
+impl<B> OnResponse<B> for CustomOnResponse {
+    fn on_response(self, response: &axum::response::Response<B>, latency: Duration, span: &Span) {
+        let status = response.status();
+        metrics::REQUESTS_BY_STATUS
+            .with_label_values(&[metrics::status_class(status.as_u16())])
+            .inc();
+        info!(parent: span,
+            status = %status,
+            latency = ?latency,
+            "响应完成"
+        );
+    }
+}
+
+/// 构建好的路由，已附加中间件与共享状态
+pub struct AppRouters {
+    /// 公共端口上的路由
+    pub app: Router,
+    /// 配置了 `server.admin_port` 时单独提供的管理接口、指标与调试接口，否则已合并进 `app`
+    pub admin: Option<Router>,
+}
+
+/// 按配置组装全部路由与中间件，`endpoints` 中关闭的接口不注册；
+/// 需要指定日志级别句柄、访问日志或单独开关可选中间件时使用 [`AppBuilder`]
+pub async fn build_router(config: &Config, state: Arc<RwLock<MemeService>>) -> Result<AppRouters, AppError> {
+    AppBuilder::new(config, state).build().await
+}
+
+/// 路由构建器：管理接口鉴权、客户端限流与响应压缩三个可选中间件默认按配置开启，
+/// 嵌入到其他程序时可以单独关闭
+pub struct AppBuilder<'a> {
+    config: &'a Config,
+    state: Arc<RwLock<MemeService>>,
+    log_level: Option<LogLevel>,
+    access_log: Option<Arc<AccessLog>>,
+    auth: bool,
+    rate_limit: bool,
+    compression: bool,
+}
+
+impl<'a> AppBuilder<'a> {
+    pub fn new(config: &'a Config, state: Arc<RwLock<MemeService>>) -> Self {
+        Self {
+            config,
+            state,
+            log_level: None,
+            access_log: None,
+            auth: config.endpoints.admin,
+            rate_limit: config.server.limits.requests_per_sec_per_client > 0,
+            compression: config.server.compression,
+        }
+    }
+
+    /// 运行时可替换的日志过滤层，设置后才注册 `/admin/log-level`
+    pub fn log_level(mut self, log_level: LogLevel) -> Self {
+        self.log_level = Some(log_level);
+        self
+    }
+
+    pub fn access_log(mut self, access_log: Option<Arc<AccessLog>>) -> Self {
+        self.access_log = access_log;
+        self
+    }
+
+    /// 是否注册由 API Key 或 JWT 保护的 `/admin/*` 管理接口，默认取 `endpoints.admin`
+    pub fn auth(mut self, enabled: bool) -> Self {
+        self.auth = enabled;
+        self
+    }
+
+    /// 是否按客户端 IP 限制公共接口的请求速率，默认在配置了 `server.limits.requests_per_sec_per_client` 时开启
+    pub fn rate_limit(mut self, enabled: bool) -> Self {
+        self.rate_limit = enabled;
+        self
+    }
+
+    /// 是否压缩响应，默认取 `server.compression`
+    pub fn compression(mut self, enabled: bool) -> Self {
+        self.compression = enabled;
+        self
+    }
+
+    pub async fn build(self) -> Result<AppRouters, AppError> {
+        let Self { config, state, log_level, access_log, auth, rate_limit, compression } = self;
+
+        // 内部接口：管理、指标与调试，配置了管理端口时只在管理端口提供
+        let endpoints = &config.endpoints;
+        let mut internal_routes = Router::new();
+        if auth {
+            internal_routes = internal_routes.merge(versioned(admin_routes(config, log_level)?));
+        }
+        if endpoints.metrics {
+            internal_routes = internal_routes.route("/metrics", get(handlers::meme::get_metrics));
+        }
+
+        // 调试接口，仅在启用 debug feature 时编译
+        #[cfg(feature = "debug")]
+        let internal_routes = {
+            tracing::warn!("已启用调试接口 /debug/*，请勿在生产环境使用");
+            internal_routes
+                .route("/debug/slow", get(handlers::debug::slow))
+                .route("/debug/error/:code", get(handlers::debug::error))
+                .route("/debug/fill-cache", post(handlers::debug::fill_cache))
+        };
+
+        // 返回图片的接口，启用防盗链时校验 Referer
+        let mut image_routes = Router::new()
+            .route("/memes/random", get(handlers::meme::random_meme))
+            .route("/memes/get/:id", get(handlers::meme::get_meme_by_id))
+            .route("/memes/get/by-name/:alias", get(handlers::meme::get_meme_by_alias))
+            .route("/memes/get/by-hash/:hash", get(handlers::meme::get_meme_by_hash));
+        if endpoints.caption {
+            image_routes = image_routes.route("/memes/get/:id/caption", get(handlers::meme::get_meme_caption));
+        }
+        if endpoints.icon {
+            image_routes = image_routes.route("/memes/get/:id/icon", get(handlers::meme::get_meme_icon));
+        }
+        if endpoints.collage {
+            image_routes = image_routes.route("/memes/collage", get(handlers::meme::get_collage));
+        }
+        // 允许空目录启动时，在有表情包之前返回 503
+        let image_routes = if config.storage.allow_empty {
+            image_routes.route_layer(axum::middleware::from_fn_with_state(
+                Arc::clone(&state),
+                middleware::catalog::require_memes,
+            ))
+        } else {
+            image_routes
+        };
+        // 分片模式下把请求交给负责该 ID 的节点，在 Referer 校验之后进行
+        let image_routes = match services::cluster::ShardRouter::new(&config.cluster) {
+            Some(router) => image_routes.route_layer(axum::middleware::from_fn_with_state(
+                Arc::new(router),
+                middleware::shard::route_to_owner,
+            )),
+            None => image_routes,
+        };
+        let image_routes = match middleware::referer::RefererPolicy::new(config)? {
+            Some(policy) => image_routes.route_layer(axum::middleware::from_fn_with_state(
+                Arc::new(policy),
+                middleware::referer::check_referer,
+            )),
+            None => image_routes,
+        };
+        // 按连接限速发送图片，位于其他图片中间件之外，防盗链替换图与分片转发的响应同样限速
+        let image_routes = image_routes.route_layer(axum::middleware::from_fn(middleware::throttle::throttle_responses));
+
+        // 构建公共 API 路由，`endpoints` 中关闭的接口不注册
+        let mut app = Router::new()
+            .merge(image_routes)
+            .route("/memes/random/query", post(handlers::meme::query_random_memes))
+            .route("/memes/health", get(handlers::meme::health_check));
+        if endpoints.list {
+            app = app.route("/memes/list", get(handlers::meme::list_memes));
+        }
+        if endpoints.search {
+            app = app.route("/memes/search", get(handlers::meme::search_memes));
+        }
+        if endpoints.on_this_day {
+            app = app.route("/memes/on-this-day", get(handlers::meme::get_on_this_day));
+        }
+        if endpoints.catalog {
+            app = app
+                .route("/memes/catalog", get(handlers::meme::get_catalog))
+                .route("/memes/changes", get(handlers::meme::get_changes))
+                .route("/memes/diff", post(handlers::meme::diff_catalog));
+        }
+        if endpoints.feed {
+            app = app.route("/memes/feed.atom", get(handlers::feed::get_feed));
+        }
+        if endpoints.report {
+            app = app.route("/memes/:id/report", post(handlers::meme::report_meme));
+        }
+        if endpoints.events {
+            app = app.route("/events", get(handlers::events::catalog_events));
+        }
+        if endpoints.info {
+            app = app.route("/memes/info/:id", get(handlers::meme::get_meme_info));
+        }
+        if endpoints.count {
+            app = app.route("/memes/count", get(handlers::meme::get_meme_count));
+        }
+        if endpoints.statistics {
+            app = app
+                .route("/statistics", get(handlers::statistics::get_statistics))
+                .route("/statistics/trending", get(handlers::statistics::get_trending));
+        }
+        // 迁移到稳定 ID 后，旧 ID 的图片与信息请求跳转到新 ID
+        let app = if state.read().await.has_id_redirects() {
+            app.route_layer(axum::middleware::from_fn_with_state(
+                Arc::clone(&state),
+                middleware::id_redirect::redirect_old_ids,
+            ))
+        } else {
+            app
+        };
+
+        // 公共 API 同时在 `/api/v1` 下提供，原路径保留为别名；页面、探针与节点间接口不加版本，也不限流
+        let app = versioned(app);
+        let app = match middleware::rate_limit::RateLimiter::new(&config.server.limits).filter(|_| rate_limit) {
+            Some(limiter) => app.layer(axum::middleware::from_fn_with_state(
+                Arc::new(limiter),
+                middleware::rate_limit::limit_requests,
+            )),
+            None => app,
+        };
+        let mut app = app
+            .route("/readyz", get(handlers::meme::readiness_check))
+            .route("/cluster/invalidate", post(handlers::cluster::invalidate));
+        if endpoints.gallery {
+            app = app
+                .route("/", get(|| async { axum::response::Redirect::to("/gallery") }))
+                .route("/gallery", get(handlers::gallery::get_gallery))
+                .route("/sitemap.xml", get(handlers::gallery::get_sitemap));
+        }
+
+        let (app, admin_app) = match config.server.admin_port {
+            Some(_) => (app, Some(internal_routes)),
+            None => (app.merge(internal_routes), None),
+        };
+
+        // 静态文件与网站图标
+        let app = match config.server.static_dir.as_deref() {
+            Some(dir) => {
+                let favicon_path = std::path::Path::new(dir).join("favicon.ico");
+                let app = app.nest_service("/static", ServeDir::new(dir));
+                if favicon_path.is_file() {
+                    app.route_service("/favicon.ico", ServeFile::new(favicon_path))
+                } else {
+                    app.route("/favicon.ico", get(handlers::assets::favicon))
+                }
+            }
+            None => app.route("/favicon.ico", get(handlers::assets::favicon)),
+        };
+
+        // 可选的 GraphQL 接口
+        #[cfg(feature = "graphql")]
+        let app = if config.endpoints.graphql {
+            let schema = graphql::build_schema(Arc::clone(&state));
+            app.route(
+                "/graphql",
+                get(graphql::graphiql).post_service(async_graphql_axum::GraphQL::new(schema)),
+            )
+        } else {
+            app
+        };
+
+        let app = if config.endpoints.swagger {
+            let docs: Router<_> = openapi::create_docs_router(config.swagger.clone(), &config.endpoints)?;
+            let docs = match middleware::auth::SwaggerAuth::new(config) {
+                Some(auth) => docs.layer(axum::middleware::from_fn_with_state(
+                    Arc::new(auth),
+                    middleware::auth::require_swagger_auth,
+                )),
+                None => docs,
+            };
+            app.merge(docs)
+        } else {
+            app
+        };
+
+        // 按客户端统计公共接口的请求，位于客户端 IP 解析之内
+        let clients = state.read().await.clients();
+        let app = app.layer(axum::middleware::from_fn_with_state(
+            clients,
+            middleware::client_stats::track_clients,
+        ));
+        let app = apply_layers(app, config, access_log.as_ref(), compression)?.with_state(Arc::clone(&state));
+        let admin_app = match admin_app {
+            Some(admin_app) => Some(apply_layers(admin_app, config, access_log.as_ref(), compression)?.with_state(state)),
+            None => None,
+        };
+
+        Ok(AppRouters { app, admin: admin_app })
+    }
+}
+
+/// 管理接口路由，需要 API Key 或 JWT
+fn admin_routes(
+    config: &Config,
+    log_level: Option<LogLevel>,
+) -> Result<Router<Arc<RwLock<MemeService>>>, AppError> {
+    let authenticator = Arc::new(middleware::auth::Authenticator::new(&config.admin)?);
+    let mut routes = Router::new()
+        .route(
+            "/admin/memes",
+            get(handlers::admin::list_memes)
+                .post(handlers::admin::upload_meme)
+                .layer(DefaultBodyLimit::max(config.storage.max_upload_bytes)),
+        )
+        .route("/admin/memes/:id", delete(handlers::admin::delete_meme))
+        .route("/admin/memes/:id/approve", post(handlers::admin::approve_meme))
+        .route("/admin/memes/:id/reject", post(handlers::admin::reject_meme))
+        .route("/admin/memes/:id/nsfw", put(handlers::admin::set_nsfw))
+        .route("/admin/memes/:id/reports", delete(handlers::admin::dismiss_reports))
+        .route("/admin/reports", get(handlers::admin::list_reports))
+        .route("/admin/memes/:id/pin", post(handlers::admin::pin_meme).delete(handlers::admin::unpin_meme))
+        .route("/admin/memes/:id/tags/approve", post(handlers::admin::approve_tags))
+        .route("/admin/memes/:id/tags/suggestions", delete(handlers::admin::reject_tags))
+        .route("/admin/tags/suggestions", get(handlers::admin::list_tag_suggestions))
+        .route("/admin/memes/:id/restore", post(handlers::admin::restore_meme))
+        .route("/admin/trash", get(handlers::admin::list_trash))
+        .route("/admin/collisions", get(handlers::admin::list_collisions))
+        .route("/admin/mime-mismatches", get(handlers::admin::list_mime_mismatches))
+        .route("/admin/clients", get(handlers::admin::top_clients))
+        .route("/admin/statistics/reset", post(handlers::admin::reset_statistics))
+        .route("/admin/cache/clear", post(handlers::admin::clear_cache))
+        .route("/admin/cache/entries", get(handlers::admin::list_cache_entries))
+        .route(
+            "/admin/cache/:cache/:key",
+            get(handlers::admin::get_cache_entry).delete(handlers::admin::evict_cache_entry),
+        )
+        .route("/admin/aliases", get(handlers::admin::list_aliases))
+        .route("/admin/aliases/:alias", put(handlers::admin::set_alias).delete(handlers::admin::delete_alias));
+    // 日志级别接口需要运行时可替换的过滤层
+    if let Some(log_level) = log_level {
+        routes = routes.route(
+            "/admin/log-level",
+            get(handlers::admin::get_log_level)
+                .put(handlers::admin::set_log_level)
+                .layer(axum::Extension(log_level)),
+        );
+    }
+
+    Ok(routes.route_layer(axum::middleware::from_fn_with_state(
+        authenticator,
+        middleware::auth::require_admin,
+    )))
+}
+
+/// 当前 API 版本的路由前缀，不兼容的改动将来在 `/api/v2` 下发布
+const API_V1_PREFIX: &str = "/api/v1";
+
+/// 同时在原路径与 [`API_V1_PREFIX`] 下注册路由；嵌套路由看到的路径不含前缀，中间件无需区分
+fn versioned<S: Clone + Send + Sync + 'static>(router: Router<S>) -> Router<S> {
+    router.clone().nest(API_V1_PREFIX, router)
+}
+
+/// 为公共接口与管理接口附加相同的日志、客户端 IP、CORS、响应头、语言协商与可选的压缩中间件
+fn apply_layers(
+    router: Router<Arc<RwLock<MemeService>>>,
+    config: &Config,
+    access_log: Option<&Arc<AccessLog>>,
+    compression: bool,
+) -> Result<Router<Arc<RwLock<MemeService>>>, AppError> {
+    let client_ip_resolver = Arc::new(ClientIpResolver::new(&config.server.proxy)?);
+    let extra_headers = Arc::new(middleware::headers::parse_extra_headers(&config.server.extra_headers)?);
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(Any)
+        .allow_headers(Any);
+
+    let router = router
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(|request: &axum::http::Request<_>| {
+                    let remote_addr = request
+                        .extensions()
+                        .get::<ClientIp>()
+                        .copied()
+                        .unwrap_or(ClientIp(None));
+
+                    tracing::span!(
+                        Level::INFO,
+                        "请求",
+                        method = %request.method(),
+                        uri = %request.uri(),
+                        ip = %remote_addr,
+                    )
+                })
+                .on_response(CustomOnResponse)
+        );
+
+    // 慢请求日志，需在客户端 IP 解析之后执行
+    let router = if config.server.slow_request_threshold_ms > 0 {
+        router.layer(axum::middleware::from_fn_with_state(
+            Duration::from_millis(config.server.slow_request_threshold_ms),
+            middleware::slow_log::log_slow_requests,
+        ))
+    } else {
+        router
+    };
+
+    // 访问日志，同样需在客户端 IP 解析之后执行
+    let router = match access_log {
+        Some(access_log) => router.layer(axum::middleware::from_fn_with_state(
+            Arc::clone(access_log),
+            middleware::access_log::log_access,
+        )),
+        None => router,
+    };
+
+    let router = router
+        // 客户端 IP 解析需在日志层之前完成
+        .layer(axum::middleware::from_fn_with_state(
+            client_ip_resolver,
+            middleware::client_ip::resolve_client_ip,
+        ))
+        .layer(cors)
+        .layer(axum::middleware::from_fn_with_state(
+            extra_headers,
+            middleware::headers::inject_headers,
+        ))
+        .layer(axum::middleware::from_fn(middleware::headers::svg_policy))
+        .layer(axum::middleware::from_fn_with_state(
+            Locale::parse(&config.server.default_locale).unwrap_or_default(),
+            middleware::locale::negotiate_locale,
+        ));
+
+    // 默认规则不压缩位图、视频与过小的响应
+    let router = if compression {
+        router.layer(CompressionLayer::new())
+    } else {
+        router
+    };
+
+    Ok(router)
+}
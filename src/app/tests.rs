@@ -0,0 +1,638 @@
+use std::io::Cursor;
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Method, Request, StatusCode},
+    response::Response,
+    Router,
+};
+use image::{ImageFormat, RgbaImage};
+use serde_json::Value;
+use tempfile::TempDir;
+use tower::ServiceExt;
+use super::{build_router, AppBuilder, AppRouters};
+use crate::config::{Config, IdScheme};
+use crate::logging::LogLevel;
+use crate::services::ids::{IdRedirects, IdRegistry};
+use crate::services::meme::{hash_content, meme_id_for, MemeService};
+use crate::services::storage::MemoryStorage;
+use crate::utils::error::AppError;
+
+const ADMIN_KEY: &str = "test-admin-key";
+
+/// 指定尺寸的纯色 PNG
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let img = RgbaImage::from_pixel(width, height, image::Rgba([200, 120, 40, 255]));
+    let mut cursor = Cursor::new(Vec::new());
+    img.write_to(&mut cursor, ImageFormat::Png).unwrap();
+    cursor.into_inner()
+}
+
+/// 测试配置与其持久化文件所在的临时目录，目录在测试结束时删除，测试期间需要一直持有
+fn test_config() -> (TempDir, Config) {
+    let dir = TempDir::new().unwrap();
+    let mut config = Config::for_test(dir.path());
+    config.admin.api_keys = vec![ADMIN_KEY.to_string()];
+    (dir, config)
+}
+
+/// 以内存中的文件与指定配置构建完整的路由
+async fn app_with(config: Config, files: Vec<(&str, Vec<u8>)>) -> Router {
+    let state = MemeService::new_for_test_with_config(config.clone(), files).await.unwrap();
+    let AppRouters { app, admin } = build_router(&config, state).await.unwrap();
+    assert!(admin.is_none());
+    app
+}
+
+async fn app() -> (TempDir, Router) {
+    let (dir, config) = test_config();
+    (dir, app_with(config, vec![("a.png", png(32, 24)), ("b.png", png(20, 20))]).await)
+}
+
+async fn send(app: &Router, request: Request<Body>) -> Response {
+    app.clone().oneshot(request).await.unwrap()
+}
+
+async fn get(app: &Router, uri: &str) -> Response {
+    send(app, Request::get(uri).body(Body::empty()).unwrap()).await
+}
+
+async fn query(app: &Router, body: &str) -> Response {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/memes/random/query")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    send(app, request).await
+}
+
+async fn body_bytes(response: Response) -> Vec<u8> {
+    to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec()
+}
+
+async fn body_json(response: Response) -> Value {
+    serde_json::from_slice(&body_bytes(response).await).unwrap()
+}
+
+#[tokio::test]
+async fn health_check_is_ok() {
+    let (_dir, app) = app().await;
+    assert_eq!(get(&app, "/memes/health").await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn random_meme_returns_an_image() {
+    let (_dir, app) = app().await;
+    let response = get(&app, "/memes/random").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+    assert!(image::load_from_memory(&body_bytes(response).await).is_ok());
+}
+
+#[tokio::test]
+async fn get_meme_by_id() {
+    let (_dir, app) = app().await;
+    let id = meme_id_for("a.png");
+    let response = get(&app, &format!("/memes/get/{}", id)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_bytes(response).await, png(32, 24));
+
+    let missing = id.wrapping_add(1);
+    assert_eq!(get(&app, &format!("/memes/get/{}", missing)).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn versioned_paths_match_legacy_paths() {
+    let (_dir, app) = app().await;
+    let id = meme_id_for("b.png");
+    let legacy = body_bytes(get(&app, &format!("/memes/get/{}", id)).await).await;
+    let response = get(&app, &format!("/api/v1/memes/get/{}", id)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_bytes(response).await, legacy);
+}
+
+#[tokio::test]
+async fn resizes_images() {
+    let (_dir, app) = app().await;
+    let response = get(&app, &format!("/memes/get/{}?width=16", meme_id_for("a.png"))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let img = image::load_from_memory(&body_bytes(response).await).unwrap();
+    assert_eq!((img.width(), img.height()), (16, 12));
+}
+
+#[tokio::test]
+async fn rejects_invalid_image_params() {
+    let (_dir, app) = app().await;
+    let id = meme_id_for("a.png");
+    for query in ["width=0", "quality=80", "fit=cover&width=10", "rotate=45"] {
+        let response = get(&app, &format!("/memes/get/{}?{}", id, query)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", query);
+    }
+}
+
+#[tokio::test]
+async fn lists_and_counts_memes() {
+    let (_dir, app) = app().await;
+    let list = body_json(get(&app, "/memes/list").await).await;
+    assert_eq!(list.as_array().map(Vec::len), Some(2));
+
+    let count = body_json(get(&app, "/memes/count").await).await;
+    assert_eq!(count["count"], 2);
+
+    let id = meme_id_for("a.png");
+    let info = body_json(get(&app, &format!("/memes/info/{}", id)).await).await;
+    assert_eq!(info["id"], id);
+}
+
+#[tokio::test]
+async fn disabled_endpoints_are_not_routed() {
+    let (_dir, mut config) = test_config();
+    config.endpoints.list = false;
+    let app = app_with(config, vec![("a.png", png(8, 8))]).await;
+    assert_eq!(get(&app, "/memes/list").await.status(), StatusCode::NOT_FOUND);
+    assert_eq!(get(&app, "/memes/count").await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn empty_catalog_returns_service_unavailable() {
+    let (_dir, mut config) = test_config();
+    config.storage.allow_empty = true;
+    let app = app_with(config, Vec::new()).await;
+    assert_eq!(get(&app, "/memes/random").await.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(get(&app, "/memes/health").await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn catalog_etag_follows_content() {
+    let (_dir, app) = app().await;
+    let response = get(&app, "/memes/catalog").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+    let body = body_bytes(response).await;
+
+    let request = Request::get("/memes/catalog")
+        .header(header::IF_NONE_MATCH, &etag)
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(&app, request).await.status(), StatusCode::NOT_MODIFIED);
+
+    let (_other_dir, other_config) = test_config();
+    let other = app_with(other_config, vec![("c.png", png(8, 8))]).await;
+    let response = get(&other, "/memes/catalog").await;
+    assert_ne!(response.headers()[header::ETAG], etag.as_str());
+    assert_ne!(body_bytes(response).await, body);
+}
+
+#[tokio::test]
+async fn seeded_random_is_stable_across_instances() {
+    let (_first_dir, first) = app().await;
+    let (_second_dir, second) = app().await;
+    let uri = "/memes/random?seed=quiz&format=json";
+    let a = get(&first, uri).await;
+    let b = get(&second, uri).await;
+    let fingerprint = a.headers()["x-catalog-fingerprint"].clone();
+    assert_eq!(b.headers()["x-catalog-fingerprint"], fingerprint);
+    assert_eq!(body_json(a).await["id"], body_json(b).await["id"]);
+
+    let (_other_dir, other_config) = test_config();
+    let other = app_with(other_config, vec![("a.png", png(32, 24))]).await;
+    let response = get(&other, uri).await;
+    assert_ne!(response.headers()["x-catalog-fingerprint"], fingerprint);
+}
+
+#[tokio::test]
+async fn feed_escapes_html_content_twice() {
+    let (_dir, config) = test_config();
+    let app = app_with(config, vec![("say \"hi\" & <bye>.png", png(8, 8))]).await;
+    let response = get(&app, "/memes/feed.atom").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let xml = String::from_utf8(body_bytes(response).await).unwrap();
+    assert!(xml.contains("alt=&quot;say &amp;quot;hi&amp;quot; &amp;amp; &amp;lt;bye&amp;gt;.png&quot;"), "{}", xml);
+}
+
+#[tokio::test]
+async fn collage_cache_key_tracks_content() {
+    let (_dir, app) = app().await;
+    let (a, b) = (meme_id_for("a.png"), meme_id_for("b.png"));
+    let response = get(&app, &format!("/memes/collage?ids={},{}&cell=64", a, b)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = Request::get("/admin/cache/entries")
+        .header("x-api-key", ADMIN_KEY)
+        .body(Body::empty())
+        .unwrap();
+    let entries = body_json(send(&app, request).await).await;
+    let expected = format!(
+        "collage:2:64:{}-{},{}-{}",
+        a,
+        &hash_content(&png(32, 24))[..12],
+        b,
+        &hash_content(&png(20, 20))[..12]
+    );
+    assert!(entries.as_array().unwrap().iter().any(|entry| entry["key"] == expected.as_str()), "{}", entries);
+}
+
+#[tokio::test]
+async fn negotiates_lossy_webp_for_jpeg() {
+    let img = RgbaImage::from_fn(128, 128, |x, y| image::Rgba([(x * 2) as u8, (y * 2) as u8, ((x * y) % 256) as u8, 255]));
+    let mut jpeg = Cursor::new(Vec::new());
+    image::DynamicImage::ImageRgba8(img).to_rgb8().write_to(&mut jpeg, ImageFormat::Jpeg).unwrap();
+    let jpeg = jpeg.into_inner();
+
+    let (_dir, mut config) = test_config();
+    config.resize.auto_negotiate = true;
+    let app = app_with(config, vec![("photo.jpg", jpeg.clone())]).await;
+    let request = Request::get(format!("/memes/get/{}", meme_id_for("photo.jpg")))
+        .header(header::ACCEPT, "image/avif,image/webp,*/*")
+        .body(Body::empty())
+        .unwrap();
+    let response = send(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "image/webp");
+    let webp = body_bytes(response).await;
+    assert!(webp.len() < jpeg.len(), "{} >= {}", webp.len(), jpeg.len());
+    assert_eq!(image::guess_format(&webp).unwrap(), ImageFormat::WebP);
+}
+
+#[tokio::test]
+async fn referer_policy_matches_request_authority() {
+    let (_dir, mut config) = test_config();
+    config.security.referer_policy.enabled = true;
+    let app = app_with(config, vec![("a.png", png(8, 8))]).await;
+    let uri = format!("/memes/get/{}", meme_id_for("a.png"));
+
+    let referer_from = |host: &str, referer: &str| {
+        Request::get(uri.as_str())
+            .header(header::HOST, host)
+            .header(header::REFERER, referer)
+            .body(Body::empty())
+            .unwrap()
+    };
+    for (host, referer) in [
+        ("memes.example.com:8080", "https://memes.example.com/page"),
+        ("[::1]:3000", "http://[::1]:3000/page"),
+        ("[::1]", "http://[::1]/"),
+    ] {
+        assert_eq!(send(&app, referer_from(host, referer)).await.status(), StatusCode::OK, "{}", host);
+    }
+    let response = send(&app, referer_from("[::1]:3000", "http://[::2]:3000/page")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let request = Request::get(format!("http://memes.example.com{}", uri))
+        .header(header::HOST, "other.example.com")
+        .header(header::REFERER, "https://memes.example.com/")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(&app, request).await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn corrupt_hit_stats_are_kept_aside() {
+    let (_dir, config) = test_config();
+    let stats_file = std::path::PathBuf::from(&config.statistics.persist_path);
+    std::fs::write(&stats_file, "{not json").unwrap();
+    let app = app_with(config, vec![("a.png", png(8, 8))]).await;
+
+    let trending = body_json(get(&app, "/statistics/trending").await).await;
+    assert_eq!(trending.as_array().map(Vec::len), Some(0));
+    assert_eq!(std::fs::read_to_string(stats_file.with_file_name("meme_stats.json.corrupt")).unwrap(), "{not json");
+}
+
+#[tokio::test]
+async fn lifetime_counters_survive_restarts() {
+    let (_dir, config) = test_config();
+    let counters_file = std::path::PathBuf::from(&config.statistics.counters_path);
+    let saved = r#"{"requests": 10, "cache_hits": 4, "cache_misses": 6, "uptime_secs": 60, "first_started_at": 1700000000, "restarts": 2}"#;
+    std::fs::write(&counters_file, saved).unwrap();
+    let app = app_with(config.clone(), vec![("a.png", png(8, 8))]).await;
+
+    let stats = body_json(get(&app, "/statistics").await).await;
+    assert_eq!(stats["lifetime"]["restarts"], 3);
+    assert!(stats["lifetime"]["total_requests"].as_u64().unwrap() >= 10);
+
+    std::fs::write(&counters_file, "{not json").unwrap();
+    let app = app_with(config, vec![("a.png", png(8, 8))]).await;
+    let stats = body_json(get(&app, "/statistics").await).await;
+    assert_eq!(stats["lifetime"]["restarts"], 0);
+    assert_eq!(std::fs::read_to_string(counters_file.with_file_name("counters.json.corrupt")).unwrap(), "{not json");
+}
+
+#[tokio::test]
+async fn file_deleted_between_reloads_is_not_found() {
+    let (_dir, config) = test_config();
+    let path = std::path::Path::new(&config.storage.memes_dir).join("a.png");
+    let storage = std::sync::Arc::new(MemoryStorage::new());
+    storage.insert(path.clone(), png(8, 8));
+    let state = MemeService::with_storage(std::sync::Arc::new(config), storage.clone()).await.unwrap();
+
+    // 持有读锁，删除触发的重载在断言完成前不会更新目录
+    let service = state.read().await;
+    storage.remove(&path);
+    let result = service.get_by_id(meme_id_for("a.png")).await;
+    assert!(matches!(result, Err(AppError::MemeNotFound { .. })));
+    assert!(!service.is_degraded());
+}
+
+#[test]
+fn migration_skips_old_ids_of_later_files() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("meme_ids.json");
+    let registry = IdRegistry::load(path.to_str().unwrap()).unwrap();
+    // b.png 迁移前的 ID 恰好是第一个新 ID
+    let old_id = |filename: &str| Some(if filename == "a.png" { 500 } else { 1 });
+    let ids = registry.assign_with(&[("a.png", None), ("b.png", None)], old_id).unwrap();
+    assert!(!ids.contains(&1) && !ids.contains(&500), "{:?}", ids);
+    assert_ne!(ids[0], ids[1]);
+}
+
+#[tokio::test]
+async fn admin_routes_require_api_key() {
+    let (_dir, app) = app().await;
+    assert_eq!(get(&app, "/admin/memes").await.status(), StatusCode::UNAUTHORIZED);
+
+    let request = Request::get("/admin/memes")
+        .header("x-api-key", ADMIN_KEY)
+        .body(Body::empty())
+        .unwrap();
+    let response = send(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_json(response).await.as_array().map(Vec::len), Some(2));
+}
+
+#[tokio::test]
+async fn admin_rejects_near_miss_keys() {
+    let (_dir, app) = app().await;
+    let near_misses = [&ADMIN_KEY[..ADMIN_KEY.len() - 1], "test-admin-kez", "test-admin-key-", ""];
+    for key in near_misses {
+        let request = Request::get("/admin/memes")
+            .header("x-api-key", key)
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(send(&app, request).await.status(), StatusCode::UNAUTHORIZED, "{:?}", key);
+    }
+}
+
+#[tokio::test]
+async fn deleting_a_meme_trashes_its_duplicates() {
+    let (_dir, mut config) = test_config();
+    config.storage.recursive = true;
+    let memes_dir = std::path::PathBuf::from(&config.storage.memes_dir);
+    std::fs::create_dir_all(memes_dir.join("nested")).unwrap();
+    std::fs::write(memes_dir.join("a.png"), png(8, 8)).unwrap();
+    std::fs::write(memes_dir.join("nested/copy.png"), png(8, 8)).unwrap();
+    let state = MemeService::new(std::sync::Arc::new(config.clone())).await.unwrap();
+    let AppRouters { app, .. } = build_router(&config, state).await.unwrap();
+
+    let request = Request::delete(format!("/admin/memes/{}", meme_id_for("a.png")))
+        .header("x-api-key", ADMIN_KEY)
+        .body(Body::empty())
+        .unwrap();
+    let response = send(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_json(response).await["filename"], "a.png");
+    assert!(!memes_dir.join("a.png").exists());
+    assert!(!memes_dir.join("nested/copy.png").exists());
+
+    let request = Request::get("/admin/trash")
+        .header("x-api-key", ADMIN_KEY)
+        .body(Body::empty())
+        .unwrap();
+    let trash = body_json(send(&app, request).await).await;
+    assert_eq!(trash.as_array().map(Vec::len), Some(2), "{}", trash);
+}
+
+#[tokio::test]
+async fn reports_quarantine_at_threshold() {
+    let (_dir, mut config) = test_config();
+    config.reports.quarantine_threshold = 1;
+    let app = app_with(config, vec![("a.png", png(8, 8))]).await;
+    let id = meme_id_for("a.png");
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(format!("/memes/{}/report", id))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(r#"{"reason": "spam"}"#))
+        .unwrap();
+    let response = send(&app, request).await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    assert_eq!(body_json(response).await["quarantined"], true);
+}
+
+#[tokio::test]
+async fn corrupt_pending_list_keeps_uploads_hidden() {
+    let (_dir, config) = test_config();
+    let pending_file = std::path::PathBuf::from(&config.storage.moderation_file);
+    std::fs::create_dir_all(pending_file.parent().unwrap()).unwrap();
+    let files = || vec![("a.png", png(8, 8)), ("pending.png", png(9, 9))];
+
+    std::fs::write(&pending_file, r#"["pending.png"]"#).unwrap();
+    let app = app_with(config.clone(), files()).await;
+    assert_eq!(body_json(get(&app, "/memes/count").await).await["count"], 1);
+    let id = meme_id_for("pending.png");
+    assert_eq!(get(&app, &format!("/memes/get/{}", id)).await.status(), StatusCode::NOT_FOUND);
+
+    // 写了一半的列表不能让待审核的上传被当作已通过，拒绝启动且保留原文件
+    std::fs::write(&pending_file, r#"["pending.p"#).unwrap();
+    assert!(MemeService::new_for_test_with_config(config, files()).await.is_err());
+    assert_eq!(std::fs::read_to_string(&pending_file).unwrap(), r#"["pending.p"#);
+}
+
+#[tokio::test]
+async fn serves_openapi_as_json_and_yaml() {
+    let (_dir, app) = app().await;
+    let json = body_json(get(&app, "/api-docs/openapi.json").await).await;
+    assert!(json["paths"]["/memes/random"].is_object());
+
+    let response = get(&app, "/api-docs/openapi.yaml").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/yaml");
+    let yaml: serde_yaml::Value = serde_yaml::from_slice(&body_bytes(response).await).unwrap();
+    assert!(yaml["paths"]["/memes/random"].is_mapping());
+}
+
+#[tokio::test]
+async fn admin_routes_can_be_disabled() {
+    let (_dir, mut config) = test_config();
+    config.endpoints.admin = false;
+    let app = app_with(config, vec![("a.png", png(8, 8))]).await;
+    let request = Request::get("/admin/memes")
+        .header("x-api-key", ADMIN_KEY)
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(&app, request).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn log_level_route_requires_handle() {
+    let (_dir, config) = test_config();
+    let request = || {
+        Request::get("/admin/log-level")
+            .header("x-api-key", ADMIN_KEY)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let app = app_with(config.clone(), vec![("a.png", png(8, 8))]).await;
+    assert_eq!(send(&app, request()).await.status(), StatusCode::NOT_FOUND);
+
+    let state = MemeService::new_for_test_with_config(config.clone(), vec![("a.png", png(8, 8))]).await.unwrap();
+    let (_layer, log_level) = LogLevel::new("info");
+    let app = AppBuilder::new(&config, state).log_level(log_level).build().await.unwrap().app;
+    let body = body_json(send(&app, request()).await).await;
+    assert_eq!(body["level"], "info");
+}
+
+#[tokio::test]
+async fn rate_limits_public_routes_per_client() {
+    let (_dir, mut config) = test_config();
+    config.server.limits.requests_per_sec_per_client = 1;
+    config.server.limits.burst_per_client = 2;
+    let app = app_with(config.clone(), vec![("a.png", png(8, 8))]).await;
+
+    assert_eq!(get(&app, "/memes/count").await.status(), StatusCode::OK);
+    assert_eq!(get(&app, "/memes/count").await.status(), StatusCode::OK);
+    let response = get(&app, "/memes/count").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key(header::RETRY_AFTER));
+    // 探针不限流
+    assert_ne!(get(&app, "/readyz").await.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let state = MemeService::new_for_test_with_config(config.clone(), vec![("a.png", png(8, 8))]).await.unwrap();
+    let app = AppBuilder::new(&config, state).rate_limit(false).build().await.unwrap().app;
+    for _ in 0..3 {
+        assert_eq!(get(&app, "/memes/count").await.status(), StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn compresses_json_but_not_images() {
+    let (_dir, mut config) = test_config();
+    config.server.compression = true;
+    let app = app_with(config, vec![("a.png", png(32, 24)), ("b.png", png(20, 20))]).await;
+    let gzip = |uri: &str| {
+        Request::get(uri)
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = send(&app, gzip("/memes/list")).await;
+    assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+
+    let response = send(&app, gzip(&format!("/memes/get/{}", meme_id_for("a.png")))).await;
+    assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+}
+
+#[tokio::test]
+async fn random_session_avoids_repeats_until_exhausted() {
+    let files = vec![("a.png", png(8, 8)), ("b.png", png(9, 9)), ("c.png", png(10, 10))];
+    let (_dir, config) = test_config();
+    let app = app_with(config, files).await;
+    let random_id = |session: &'static str| {
+        let app = app.clone();
+        async move {
+            let body = body_json(get(&app, &format!("/memes/random?format=json&session={}", session)).await).await;
+            body["id"].as_u64().unwrap()
+        }
+    };
+
+    let mut round: Vec<u64> = Vec::new();
+    for _ in 0..3 {
+        round.push(random_id("group-1").await);
+    }
+    let distinct: std::collections::HashSet<_> = round.iter().collect();
+    assert_eq!(distinct.len(), 3);
+
+    // 新一轮的第一张不与上一轮最后一张相同
+    assert_ne!(random_id("group-1").await, round[2]);
+}
+
+#[tokio::test]
+async fn stable_ids_redirect_old_ids() {
+    let (_dir, mut config) = test_config();
+    config.storage.id_scheme = IdScheme::Stable;
+    // 相当于事先运行过 `ids migrate`
+    IdRegistry::load(&config.storage.id_map_file).unwrap().assign(&[("a.png", None), ("b.png", None)]).unwrap();
+    let old_id = meme_id_for("a.png");
+    let redirects = [(old_id, 1)].into_iter().collect();
+    IdRedirects::save(&config.storage.id_redirects_file, &redirects).unwrap();
+    let app = app_with(config, vec![("a.png", png(8, 8)), ("b.png", png(9, 9))]).await;
+
+    let body = body_json(get(&app, "/memes/info/2").await).await;
+    assert_eq!(body["filename"], "b.png");
+
+    let response = get(&app, &format!("/memes/get/{}?width=4", old_id)).await;
+    assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(response.headers()[header::LOCATION], "/memes/get/1?width=4");
+
+    let response = get(&app, &format!("/api/v1/memes/info/{}", old_id)).await;
+    assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(response.headers()[header::LOCATION], "/api/v1/memes/info/1");
+
+    // 当前存在的 ID 不跳转
+    assert_eq!(get(&app, "/memes/get/1").await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn stable_ids_keep_aliases_set_before_migration() {
+    let (_dir, mut config) = test_config();
+    config.storage.id_scheme = IdScheme::Stable;
+    let old_id = meme_id_for("b.png");
+    let registry = IdRegistry::load(&config.storage.id_map_file).unwrap();
+    registry.assign_with(&[("a.png", None), ("b.png", None)], |filename| Some(meme_id_for(filename))).unwrap();
+    std::fs::write(&config.storage.aliases_file, format!("wow: {}\n", old_id)).unwrap();
+    let app = app_with(config.clone(), vec![("a.png", png(8, 8)), ("b.png", png(9, 9))]).await;
+
+    let response = get(&app, "/memes/get/by-name/wow").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_bytes(response).await, png(9, 9));
+    let aliases = std::fs::read_to_string(&config.storage.aliases_file).unwrap();
+    assert_eq!(aliases.trim(), "wow: 2");
+}
+
+#[tokio::test]
+async fn stable_ids_require_migrated_map() {
+    let (_dir, mut config) = test_config();
+    config.storage.id_scheme = IdScheme::Stable;
+    assert!(MemeService::new_for_test_with_config(config, vec![("a.png", png(8, 8))]).await.is_err());
+}
+
+#[tokio::test]
+async fn random_query_returns_distinct_memes() {
+    let (_dir, app) = app().await;
+    let response = query(&app, r#"{"formats": ["png"], "count": 5}"#).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let memes = body_json(response).await;
+    let ids: std::collections::HashSet<_> = memes.as_array().unwrap().iter().map(|m| m["id"].as_u64()).collect();
+    assert_eq!(ids.len(), 2);
+
+    let response = query(&app, r#"{"width": {"min": 30}}"#).await;
+    let memes = body_json(response).await;
+    assert_eq!(memes[0]["id"], meme_id_for("a.png"));
+}
+
+#[tokio::test]
+async fn random_query_rejects_invalid_filters() {
+    let (_dir, app) = app().await;
+    assert_eq!(query(&app, r#"{"width": {"min": 10, "max": 5}}"#).await.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(query(&app, r#"{"count": 0}"#).await.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(query(&app, r#"{"colour": "red"}"#).await.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(query(&app, r#"{"formats": ["gif"]}"#).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn changes_from_another_boot_require_resync() {
+    let (_dir, app) = app().await;
+    let current = body_json(get(&app, "/memes/changes?since_generation=0").await).await;
+    let (epoch, generation) = (current["epoch"].as_str().unwrap().to_string(), current["generation"].as_u64().unwrap());
+
+    let same = body_json(get(&app, &format!("/memes/changes?since_generation={}&epoch={}", generation, epoch)).await).await;
+    assert_eq!(same["resync_required"], false);
+
+    // 重启前的版本号比当前大，或 epoch 不同
+    let ahead = body_json(get(&app, &format!("/memes/changes?since_generation={}", generation + 56)).await).await;
+    assert_eq!(ahead["resync_required"], true);
+    let other = body_json(get(&app, &format!("/memes/changes?since_generation={}&epoch=other", generation)).await).await;
+    assert_eq!(other["resync_required"], true);
+}
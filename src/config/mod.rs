@@ -1,11 +1,15 @@
 use crate::utils::error::{AppError, Result};
+use crate::utils::i18n::Locale;
 use serde::{Deserialize, Serialize};
-use std::{fs, path::Path, sync::Arc};
+use std::{collections::BTreeMap, fs, path::Path, sync::Arc, time::Duration};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ProxyConfig {
     pub enabled: bool,
     pub ip_header: String,
+    /// 可信代理列表 (CIDR 或单个 IP)，解析代理头时从右向左跳过这些地址
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -14,23 +18,1039 @@ pub struct ServerConfig {
     pub port: u16,
     #[serde(default)]
     pub proxy: ProxyConfig,
+    /// 对外访问的基础地址 (例如 https://tokotoapi.moonpeaches.xyz)，
+    /// 用于在 JSON 响应中生成绝对 URL；未设置时根据请求头推断
+    #[serde(default)]
+    pub public_base_url: Option<String>,
+    /// 静态文件目录，挂载在 `/static` 下；其中的 favicon.ico 会替代内置图标
+    #[serde(default)]
+    pub static_dir: Option<String>,
+    /// 附加到所有响应上的响应头 (例如 X-Frame-Options)
+    #[serde(default)]
+    pub extra_headers: BTreeMap<String, String>,
+    /// 请求未携带可识别的 Accept-Language 时错误信息使用的语言 (zh-CN 或 en)
+    #[serde(default = "default_locale")]
+    pub default_locale: String,
+    /// 是否在图片响应中附加 `X-Meme-Source` 头 (取自来源信息中的 source)
+    #[serde(default)]
+    pub source_header: bool,
+    /// 是否在图片响应中附加 `X-Content-SHA256` 头，供镜像客户端校验内容完整性
+    #[serde(default)]
+    pub content_digest: bool,
+    /// 慢请求阈值（毫秒），超过时以 WARN 级别记录，为 0 时不记录
+    #[serde(default = "default_slow_request_threshold_ms")]
+    pub slow_request_threshold_ms: u64,
+    /// 管理端口：设置后管理接口、/metrics 与调试接口只在该端口提供，不再暴露在公共端口
+    #[serde(default)]
+    pub admin_port: Option<u16>,
+    /// 管理端口绑定的地址，默认只监听本机
+    #[serde(default = "default_admin_host")]
+    pub admin_host: String,
+    /// 按连接与按客户端的资源限制
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    /// 是否对 JSON、SVG、页面等响应启用 gzip/brotli 压缩，位图与视频不压缩
+    #[serde(default)]
+    pub compression: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct LimitsConfig {
+    /// 每个连接发送图片响应的速率上限（字节/秒），为 0 时不限速
+    #[serde(default)]
+    pub max_bytes_per_sec_per_conn: u64,
+    /// 每个客户端 IP 每秒可发起的公共接口请求数，超出时返回 429，为 0 时不限流
+    #[serde(default)]
+    pub requests_per_sec_per_client: u32,
+    /// 每个客户端 IP 允许的突发请求数，为 0 时等于 `requests_per_sec_per_client`
+    #[serde(default)]
+    pub burst_per_client: u32,
+}
+
+fn default_admin_host() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_slow_request_threshold_ms() -> u64 {
+    1000
+}
+
+fn default_locale() -> String {
+    "zh-CN".to_string()
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct StorageConfig {
     pub memes_dir: String,
+    /// 允许加载的文件扩展名
+    #[serde(default = "default_allowed_extensions")]
+    pub allowed_extensions: Vec<String>,
+    /// 允许加载的 MIME 类型，支持 `image/*` 通配
+    #[serde(default = "default_allowed_mime_types")]
+    pub allowed_mime_types: Vec<String>,
+    /// 是否通过文件头魔数校验文件确实是图片
+    #[serde(default = "default_true")]
+    pub sniff_content: bool,
+    /// 目录为空时仍然启动，图片接口返回 503 直到有文件加入；关闭时没有表情包会启动失败
+    #[serde(default)]
+    pub allow_empty: bool,
+    /// 加载 mp4 与 webm 短视频，不受 `allowed_extensions` 与 `allowed_mime_types` 限制
+    #[serde(default)]
+    pub allow_video: bool,
+    /// 回收站中文件的保留天数
+    #[serde(default = "default_trash_retention_days")]
+    pub trash_retention_days: u64,
+    /// 回收站清理任务的执行间隔（秒）
+    #[serde(default = "default_trash_purge_interval_secs")]
+    pub trash_purge_interval_secs: u64,
+    /// 表情包别名文件 (别名 -> 表情包 ID)
+    #[serde(default = "default_aliases_file")]
+    pub aliases_file: String,
+    /// 是否按文件内容去重：内容相同的多个文件只保留一个表情包条目
+    #[serde(default = "default_true")]
+    pub deduplicate: bool,
+    /// 上传的表情包是否需要审核后才对外提供
+    #[serde(default = "default_true")]
+    pub moderate_uploads: bool,
+    /// 待审核表情包列表的持久化文件
+    #[serde(default = "default_moderation_file")]
+    pub moderation_file: String,
+    /// 通过管理接口标记为 NSFW 的表情包列表的持久化文件
+    #[serde(default = "default_nsfw_file")]
+    pub nsfw_file: String,
+    /// 用户举报记录的持久化文件
+    #[serde(default = "default_reports_file")]
+    pub reports_file: String,
+    /// 举报者标识的 HMAC 密钥文件，不存在时自动生成随机密钥
+    #[serde(default = "default_report_secret_file")]
+    pub report_secret_file: String,
+    /// 通过管理接口固定在内存中的表情包列表的持久化文件
+    #[serde(default = "default_pins_file")]
+    pub pins_file: String,
+    /// 表情包标签 (含等待审核的候选标签) 的持久化文件
+    #[serde(default = "default_tags_file")]
+    pub tags_file: String,
+    /// OCR 识别出的表情包文字的持久化文件
+    #[serde(default = "default_text_file")]
+    pub text_file: String,
+    /// 目录快照文件，启动时先从快照提供服务再后台重新扫描；为空时不使用快照
+    #[serde(default = "default_snapshot_file")]
+    pub snapshot_file: String,
+    /// 表情包 ID 的生成方式
+    #[serde(default)]
+    pub id_scheme: IdScheme,
+    /// `id_scheme: stable` 时持久化的 ID 映射文件，由 `ids migrate` 生成，新文件的 ID 也追加到其中
+    #[serde(default = "default_id_map_file")]
+    pub id_map_file: String,
+    /// 旧 ID 到新 ID 的 301 跳转表，由 `ids migrate --redirects` 生成，文件不存在时不跳转
+    #[serde(default = "default_id_redirects_file")]
+    pub id_redirects_file: String,
+    /// 单个上传文件的大小上限（字节）
+    #[serde(default = "default_max_upload_bytes")]
+    pub max_upload_bytes: usize,
+    /// 上传时校验解码、去除元数据并将不常见的格式转换为 PNG
+    #[serde(default = "default_true")]
+    pub normalize_uploads: bool,
+    /// 规范化时长边的上限（像素），超出时缩小，为 0 时不限制
+    #[serde(default = "default_upload_max_dimension")]
+    pub upload_max_dimension: u32,
+    /// 是否加载子目录中的表情包 (隐藏目录除外)
+    #[serde(default)]
+    pub recursive: bool,
+    /// 递归扫描时同时读取的目录数
+    #[serde(default = "default_scan_concurrency")]
+    pub scan_concurrency: usize,
+    /// 扫描失败进入降级模式后首次重试的等待时间（秒），之后每次翻倍
+    #[serde(default = "default_rescan_backoff_initial_secs")]
+    pub rescan_backoff_initial_secs: u64,
+    /// 降级模式下重试间隔的上限（秒）
+    #[serde(default = "default_rescan_backoff_max_secs")]
+    pub rescan_backoff_max_secs: u64,
+}
+
+fn default_rescan_backoff_initial_secs() -> u64 {
+    5
+}
+
+fn default_rescan_backoff_max_secs() -> u64 {
+    300
+}
+
+fn default_scan_concurrency() -> usize {
+    8
+}
+
+fn default_allowed_extensions() -> Vec<String> {
+    ["jpg", "jpeg", "png", "gif", "webp", "bmp"]
+        .iter()
+        .map(|ext| ext.to_string())
+        .collect()
+}
+
+fn default_allowed_mime_types() -> Vec<String> {
+    vec!["image/*".to_string()]
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_moderation_file() -> String {
+    "data/pending_memes.json".to_string()
+}
+
+fn default_nsfw_file() -> String {
+    "data/nsfw_memes.json".to_string()
+}
+
+fn default_reports_file() -> String {
+    "data/meme_reports.json".to_string()
+}
+
+fn default_report_secret_file() -> String {
+    "data/report_secret.key".to_string()
+}
+
+fn default_pins_file() -> String {
+    "data/pinned_memes.json".to_string()
+}
+
+fn default_tags_file() -> String {
+    "data/meme_tags.json".to_string()
+}
+
+fn default_text_file() -> String {
+    "data/meme_text.json".to_string()
+}
+
+fn default_snapshot_file() -> String {
+    "data/catalog_snapshot.json".to_string()
+}
+
+fn default_id_map_file() -> String {
+    "data/meme_ids.json".to_string()
+}
+
+fn default_id_redirects_file() -> String {
+    "data/meme_id_redirects.json".to_string()
+}
+
+/// 表情包 ID 的生成方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdScheme {
+    /// 由文件名的哈希计算，文件改名后 ID 变化
+    #[default]
+    Filename,
+    /// 从 1 开始依次分配并保存在 `id_map_file` 中，文件改名 (内容不变) 后 ID 不变
+    Stable,
+}
+
+fn default_max_upload_bytes() -> usize {
+    20 * 1024 * 1024
+}
+
+fn default_upload_max_dimension() -> u32 {
+    4096
+}
+
+fn default_trash_retention_days() -> u64 {
+    30
+}
+
+fn default_trash_purge_interval_secs() -> u64 {
+    3600
+}
+
+fn default_aliases_file() -> String {
+    "aliases.yml".to_string()
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct AdminConfig {
+    /// 管理接口的 API Key 列表，为空时禁用所有管理接口
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+    /// JWT 鉴权，可与 API Key 同时使用
+    #[serde(default)]
+    pub jwt: JwtConfig,
+}
+
+/// 管理接口的 JWT 鉴权，`secret` 与 `jwks_url` 二选一，都未设置时不启用
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JwtConfig {
+    /// HS256/HS384/HS512 的共享密钥
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// OIDC 提供方的 JWKS 地址，用于校验 RS256/ES256 等非对称签名
+    #[serde(default)]
+    pub jwks_url: Option<String>,
+    /// 要求的 `iss`，为空时不校验
+    #[serde(default)]
+    pub issuer: Option<String>,
+    /// 要求的 `aud`，为空时不校验
+    #[serde(default)]
+    pub audience: Option<String>,
+    /// 角色声明的路径，用 `.` 访问嵌套字段，如 `realm_access.roles`
+    #[serde(default = "default_jwt_roles_claim")]
+    pub roles_claim: String,
+    /// 拥有全部管理权限的角色
+    #[serde(default = "default_jwt_admin_role")]
+    pub admin_role: String,
+    /// 只能上传表情包的角色
+    #[serde(default = "default_jwt_uploader_role")]
+    pub uploader_role: String,
+    /// JWKS 缓存时间（秒）
+    #[serde(default = "default_jwks_refresh_secs")]
+    pub jwks_refresh_secs: u64,
+    /// 校验 `exp`/`nbf` 时允许的时钟偏差（秒）
+    #[serde(default = "default_jwt_leeway_secs")]
+    pub leeway_secs: u64,
+}
+
+fn default_jwt_roles_claim() -> String {
+    "roles".to_string()
+}
+
+fn default_jwt_admin_role() -> String {
+    "admin".to_string()
+}
+
+fn default_jwt_uploader_role() -> String {
+    "uploader".to_string()
+}
+
+fn default_jwks_refresh_secs() -> u64 {
+    3600
+}
+
+fn default_jwt_leeway_secs() -> u64 {
+    60
+}
+
+impl Default for JwtConfig {
+    fn default() -> Self {
+        Self {
+            secret: None,
+            jwks_url: None,
+            issuer: None,
+            audience: None,
+            roles_claim: default_jwt_roles_claim(),
+            admin_role: default_jwt_admin_role(),
+            uploader_role: default_jwt_uploader_role(),
+            jwks_refresh_secs: default_jwks_refresh_secs(),
+            leeway_secs: default_jwt_leeway_secs(),
+        }
+    }
+}
+
+impl JwtConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.secret.is_some() || self.jwks_url.is_some()
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CacheConfig {
     pub max_size: u64,
     pub ttl_secs: u64,
+    /// 启动时是否预热内容缓存
+    #[serde(default)]
+    pub warmup: bool,
+    /// 预热时加载的表情包数量
+    #[serde(default = "default_warmup_count")]
+    pub warmup_count: usize,
+    /// 每个缓存按字节计算的容量上限（MB），为 0 时按 `max_size` 条目数限制
+    #[serde(default)]
+    pub max_memory_mb: u64,
+    /// 超过该大小（KB）的原图不进入内容缓存，直接从磁盘发送，为 0 时全部经过缓存
+    #[serde(default = "default_stream_threshold_kb")]
+    pub stream_threshold_kb: u64,
+    /// 预取队列长度：提前选出接下来的随机表情包并在后台读入内容缓存，为 0 时关闭
+    #[serde(default = "default_prefetch")]
+    pub prefetch: usize,
+    /// 原图内容缓存的过期策略，未设置存活时间时使用 `ttl_secs`
+    #[serde(default)]
+    pub content: CachePolicy,
+    /// 缩放、转码结果缓存的过期策略，未设置存活时间时使用 `ttl_secs` 的两倍
+    #[serde(default)]
+    pub resized: CachePolicy,
+    /// 记录缓存命中率与淘汰数的间隔（秒），为 0 时不记录
+    #[serde(default = "default_cache_stats_interval_secs")]
+    pub stats_interval_secs: u64,
+    /// 命中的条目接近过期时按概率提前刷新，避免热门条目同时过期造成集中未命中
+    #[serde(default)]
+    pub early_refresh: bool,
+    /// 提前刷新的窗口占存活时间的比例 (0-1)，剩余时间进入窗口后刷新概率从 0 线性增加到 1
+    #[serde(default = "default_early_refresh_ratio")]
+    pub early_refresh_ratio: f64,
+}
+
+fn default_early_refresh_ratio() -> f64 {
+    0.1
+}
+
+/// 缓存满时的淘汰策略
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheEvictionPolicy {
+    /// 按访问频率决定是否接纳新条目 (W-TinyLFU)，偶发的一次性请求不会挤掉热门条目
+    #[default]
+    TinyLfu,
+    /// 最近最少使用，新条目总是被接纳，适合访问集中在最近内容的场景
+    Lru,
+}
+
+/// 单个缓存的过期与淘汰策略，时间为 0 表示不按该方式过期
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct CachePolicy {
+    /// 写入后的存活时间（秒），到期后重新读取，保证内容新鲜
+    #[serde(default)]
+    pub time_to_live_secs: Option<u64>,
+    /// 闲置时间（秒），超过该时间未被访问的条目提前淘汰，热门条目一直保留到存活时间
+    #[serde(default)]
+    pub time_to_idle_secs: Option<u64>,
+    #[serde(default)]
+    pub eviction_policy: CacheEvictionPolicy,
+    /// 预分配的条目数，接近稳定后的条目数时可减少扩容
+    #[serde(default)]
+    pub initial_capacity: Option<usize>,
+}
+
+impl CachePolicy {
+    /// 存活时间，未设置时使用 `default_secs`
+    pub fn time_to_live(&self, default_secs: u64) -> Option<Duration> {
+        Some(self.time_to_live_secs.unwrap_or(default_secs))
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+    }
+
+    pub fn time_to_idle(&self) -> Option<Duration> {
+        self.time_to_idle_secs
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+    }
+}
+
+fn default_warmup_count() -> usize {
+    50
+}
+
+fn default_stream_threshold_kb() -> u64 {
+    1024
+}
+
+fn default_prefetch() -> usize {
+    4
+}
+
+fn default_cache_stats_interval_secs() -> u64 {
+    300
+}
+
+/// 日志文件轮转策略
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Hourly,
+    #[default]
+    Daily,
+    /// 按文件大小轮转，阈值为 `max_size_mb`
+    Size,
+    Never,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct LoggingConfig {
     pub directory: String,
     pub file_prefix: String,
+    #[serde(default)]
+    pub rotation: LogRotation,
+    /// 保留的日志文件数量，超出时删除最旧的文件，为 0 时不清理
+    #[serde(default = "default_log_max_files")]
+    pub max_files: usize,
+    /// 按大小轮转时单个日志文件的大小上限（MB）
+    #[serde(default = "default_log_max_size_mb")]
+    pub max_size_mb: u64,
+    /// 独立的访问日志
+    #[serde(default)]
+    pub access_log: AccessLogConfig,
+}
+
+/// 访问日志格式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLogFormat {
+    /// Apache/Nginx combined 格式，末尾附加耗时（毫秒）
+    #[default]
+    Combined,
+    /// 每行一个 JSON 对象
+    Json,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AccessLogConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub format: AccessLogFormat,
+    /// 访问日志文件前缀，写入 `logging.directory`，不能以应用日志的前缀开头
+    #[serde(default = "default_access_log_prefix")]
+    pub file_prefix: String,
+    #[serde(default)]
+    pub rotation: LogRotation,
+}
+
+fn default_access_log_prefix() -> String {
+    "access".to_string()
+}
+
+impl Default for AccessLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            format: AccessLogFormat::default(),
+            file_prefix: default_access_log_prefix(),
+            rotation: LogRotation::default(),
+        }
+    }
+}
+
+fn default_log_max_files() -> usize {
+    14
+}
+
+fn default_log_max_size_mb() -> u64 {
+    100
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StatisticsConfig {
+    /// 单个表情包访问统计的持久化文件
+    pub persist_path: String,
+    /// 持久化间隔（秒）
+    pub persist_interval_secs: u64,
+    /// 热度分数的半衰期（小时），用于计算"近期热门"
+    pub trending_half_life_hours: f64,
+    /// 请求数、缓存命中数等累计计数的持久化文件，重启后继续累计
+    #[serde(default = "default_counters_path")]
+    pub counters_path: String,
+    /// 按客户端 IP 统计时最多跟踪的客户端数
+    #[serde(default = "default_max_tracked_clients")]
+    pub max_tracked_clients: u64,
+}
+
+fn default_counters_path() -> String {
+    "data/counters.json".to_string()
+}
+
+fn default_max_tracked_clients() -> u64 {
+    10_000
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ClusterConfig {
+    /// 是否启用多实例缓存失效通知
+    pub enabled: bool,
+    /// 当前节点名称，用于日志
+    pub node_id: String,
+    /// 其他节点的地址 (例如 http://10.0.0.2:3000)
+    pub peers: Vec<String>,
+    /// 节点间通信的共享密钥
+    pub token: String,
+    /// 是否为主节点：主节点会定期轮询目录，弥补共享存储上收不到文件事件的问题
+    pub leader: bool,
+    /// 主节点轮询目录的间隔（秒），为 0 时不轮询
+    pub poll_interval_secs: u64,
+    /// 按 ID 分片，每个节点只处理自己负责的表情包
+    #[serde(default)]
+    pub shard: ShardConfig,
+}
+
+/// 请求不属于本节点时的处理方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShardMode {
+    /// 307 重定向到负责的节点
+    #[default]
+    Redirect,
+    /// 由本节点转发请求并返回结果
+    Proxy,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ShardConfig {
+    /// 是否启用分片，需同时启用 cluster
+    #[serde(default)]
+    pub enabled: bool,
+    /// 本节点的分片序号，从 0 开始
+    #[serde(default)]
+    pub index: usize,
+    /// 分片总数
+    #[serde(default = "default_shard_count")]
+    pub count: usize,
+    /// 按分片序号排列的全部节点地址 (包括本节点)，长度须等于 `count`
+    #[serde(default)]
+    pub nodes: Vec<String>,
+    #[serde(default)]
+    pub mode: ShardMode,
+}
+
+fn default_shard_count() -> usize {
+    1
+}
+
+impl Default for ShardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            index: 0,
+            count: default_shard_count(),
+            nodes: Vec::new(),
+            mode: ShardMode::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ContentConfig {
+    /// 随机与列表接口默认排除 NSFW 表情包，请求可通过 `?safe=false` 覆盖
+    pub safe_mode: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CdnConfig {
+    /// CDN 地址，设置后图片地址与重定向指向 CDN，由 CDN 回源到本服务的 `/memes/get/{id}`
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// URL 签名密钥，为空时不签名
+    #[serde(default)]
+    pub sign_key: String,
+    /// 签名 URL 的最短有效期（秒）
+    #[serde(default = "default_cdn_sign_ttl_secs")]
+    pub sign_ttl_secs: u64,
+}
+
+fn default_cdn_sign_ttl_secs() -> u64 {
+    3600
+}
+
+impl Default for CdnConfig {
+    fn default() -> Self {
+        Self {
+            base_url: None,
+            sign_key: String::new(),
+            sign_ttl_secs: default_cdn_sign_ttl_secs(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SecurityConfig {
+    /// 图片接口的 Referer 防盗链规则
+    #[serde(default)]
+    pub referer_policy: RefererPolicyConfig,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RefererPolicyConfig {
+    /// 是否校验图片接口的 Referer
+    #[serde(default)]
+    pub enabled: bool,
+    /// 允许嵌入图片的来源域名，`*.example.com` 匹配其所有子域名；本服务自己的域名总是允许
+    #[serde(default)]
+    pub allowed: Vec<String>,
+    /// 是否允许不带 Referer 的请求 (直接打开、命令行工具、浏览器隐私设置)
+    #[serde(default = "default_true")]
+    pub allow_empty: bool,
+    /// 拒绝时返回的图片 (例如带水印的提示图)，为空时返回 403
+    #[serde(default)]
+    pub blocked_image: Option<String>,
+}
+
+impl Default for RefererPolicyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allowed: Vec::new(),
+            allow_empty: true,
+            blocked_image: None,
+        }
+    }
+}
+
+/// 本地 ONNX 图像分类模型，为新的或没有标签的表情包生成候选标签 (需要使用 `ml` feature 构建)
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MlConfig {
+    /// ONNX 模型路径，为空时不生成候选标签
+    #[serde(default)]
+    pub model_path: Option<String>,
+    /// 标签文件，每行一个，顺序与模型输出一致
+    #[serde(default)]
+    pub labels_path: Option<String>,
+    /// 模型输入的边长（像素），输入形状为 [1, 3, input_size, input_size]
+    #[serde(default = "default_ml_input_size")]
+    pub input_size: u32,
+    /// 各通道归一化使用的均值与标准差 (RGB，像素值先缩放到 0-1)
+    #[serde(default = "default_ml_mean")]
+    pub mean: [f32; 3],
+    #[serde(default = "default_ml_std")]
+    pub std: [f32; 3],
+    /// 模型输出为 logits 时先做 softmax；多标签模型的 sigmoid 输出应设为 false
+    #[serde(default = "default_true")]
+    pub softmax: bool,
+    /// 每个表情包最多保留的候选标签数
+    #[serde(default = "default_ml_top_k")]
+    pub top_k: usize,
+    /// 候选标签的最低置信度
+    #[serde(default = "default_ml_min_score")]
+    pub min_score: f32,
+}
+
+fn default_ml_input_size() -> u32 {
+    224
+}
+
+fn default_ml_mean() -> [f32; 3] {
+    [0.485, 0.456, 0.406]
+}
+
+fn default_ml_std() -> [f32; 3] {
+    [0.229, 0.224, 0.225]
+}
+
+fn default_ml_top_k() -> usize {
+    5
+}
+
+fn default_ml_min_score() -> f32 {
+    0.2
+}
+
+impl Default for MlConfig {
+    fn default() -> Self {
+        Self {
+            model_path: None,
+            labels_path: None,
+            input_size: default_ml_input_size(),
+            mean: default_ml_mean(),
+            std: default_ml_std(),
+            softmax: true,
+            top_k: default_ml_top_k(),
+            min_score: default_ml_min_score(),
+        }
+    }
+}
+
+/// 识别表情包中的文字，用于 `/memes/search` 搜索
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OcrConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 外部 OCR 服务地址：POST 图片原始内容，返回 `{"text": "..."}`；为空时使用本地 tesseract (需要 `ocr` feature)
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// tesseract 语言包，多个用 `+` 连接
+    #[serde(default = "default_ocr_languages")]
+    pub languages: String,
+    /// 外部服务的请求超时（秒）
+    #[serde(default = "default_ocr_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_ocr_languages() -> String {
+    "chi_sim+eng".to_string()
+}
+
+fn default_ocr_timeout_secs() -> u64 {
+    30
+}
+
+impl Default for OcrConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: None,
+            languages: default_ocr_languages(),
+            timeout_secs: default_ocr_timeout_secs(),
+        }
+    }
+}
+
+/// 可关闭的接口，关闭的接口不注册路由 (返回 404)，也不出现在 OpenAPI 文档中；
+/// 随机、按 ID 获取与健康检查接口始终可用
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EndpointsConfig {
+    /// `/admin/*` 管理接口，由 API Key 或 JWT 鉴权
+    #[serde(default = "default_true")]
+    pub admin: bool,
+    /// `/memes/list`
+    #[serde(default = "default_true")]
+    pub list: bool,
+    /// `/memes/search`
+    #[serde(default = "default_true")]
+    pub search: bool,
+    /// `/memes/on-this-day`
+    #[serde(default = "default_true")]
+    pub on_this_day: bool,
+    /// `/memes/catalog`、`/memes/changes` 与 `/memes/diff`
+    #[serde(default = "default_true")]
+    pub catalog: bool,
+    /// `/memes/info/{id}`
+    #[serde(default = "default_true")]
+    pub info: bool,
+    /// `/memes/count`
+    #[serde(default = "default_true")]
+    pub count: bool,
+    /// `/memes/feed.atom`
+    #[serde(default = "default_true")]
+    pub feed: bool,
+    /// `POST /memes/{id}/report` 用户举报
+    #[serde(default = "default_true")]
+    pub report: bool,
+    /// `/memes/get/{id}/caption`
+    #[serde(default = "default_true")]
+    pub caption: bool,
+    /// `/memes/get/{id}/icon`
+    #[serde(default = "default_true")]
+    pub icon: bool,
+    /// `/memes/collage`
+    #[serde(default = "default_true")]
+    pub collage: bool,
+    /// `/events` 目录事件推送
+    #[serde(default = "default_true")]
+    pub events: bool,
+    /// `/gallery`、`/sitemap.xml` 与首页跳转
+    #[serde(default = "default_true")]
+    pub gallery: bool,
+    /// `/statistics` 与 `/statistics/trending`
+    #[serde(default = "default_true")]
+    pub statistics: bool,
+    /// Swagger UI 与 `/api-docs/openapi.json`、`/api-docs/openapi.yaml`
+    #[serde(default = "default_true")]
+    pub swagger: bool,
+    /// `/metrics`
+    #[serde(default = "default_true")]
+    pub metrics: bool,
+    /// `/graphql` (需要 `graphql` feature)
+    #[serde(default = "default_true")]
+    pub graphql: bool,
+}
+
+impl Default for EndpointsConfig {
+    fn default() -> Self {
+        Self {
+            admin: true,
+            list: true,
+            search: true,
+            on_this_day: true,
+            catalog: true,
+            info: true,
+            count: true,
+            feed: true,
+            report: true,
+            caption: true,
+            icon: true,
+            collage: true,
+            events: true,
+            gallery: true,
+            statistics: true,
+            swagger: true,
+            metrics: true,
+            graphql: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CaptionConfig {
+    /// 配文字体文件 (TTF/OTF)，需要包含中文字形，不存在时配文接口不可用
+    pub font_path: String,
+    /// 每段文字的最大字符数
+    pub max_text_len: usize,
+}
+
+/// 水印在图片上的位置
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WatermarkConfig {
+    /// 在随机与按 ID 获取的图片上叠加水印
+    #[serde(default)]
+    pub enabled: bool,
+    /// 水印图片 (建议使用透明背景的 PNG)，设置后优先于 `text`
+    #[serde(default)]
+    pub image_path: String,
+    /// 文字水印，使用 `caption.font_path` 字体绘制
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub position: WatermarkPosition,
+    /// 不透明度 (0-1]
+    #[serde(default = "default_watermark_opacity")]
+    pub opacity: f32,
+    /// 水印宽度占图片宽度的比例 (0-1]
+    #[serde(default = "default_watermark_scale")]
+    pub scale: f32,
+    /// 可以使用 `?nowatermark=true` 的 API Key，`admin.api_keys` 始终可以
+    #[serde(default)]
+    pub bypass_keys: Vec<String>,
+}
+
+fn default_watermark_opacity() -> f32 {
+    0.5
+}
+
+fn default_watermark_scale() -> f32 {
+    0.2
+}
+
+impl Default for WatermarkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            image_path: String::new(),
+            text: String::new(),
+            position: WatermarkPosition::default(),
+            opacity: default_watermark_opacity(),
+            scale: default_watermark_scale(),
+            bypass_keys: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReportsConfig {
+    /// 不同客户端的举报数达到该值时自动转为待审核，为 0 时不自动处理
+    #[serde(default = "default_quarantine_threshold")]
+    pub quarantine_threshold: usize,
+    /// 单个客户端在 `window_secs` 内最多提交的举报数，为 0 时不限制
+    #[serde(default = "default_reports_max_per_client")]
+    pub max_per_client: usize,
+    /// 举报频率限制的时间窗口（秒）
+    #[serde(default = "default_reports_window_secs")]
+    pub window_secs: u64,
+    /// 举报补充说明的最大长度（字符）
+    #[serde(default = "default_reports_max_note_chars")]
+    pub max_note_chars: usize,
+}
+
+fn default_quarantine_threshold() -> usize {
+    5
+}
+
+fn default_reports_max_per_client() -> usize {
+    10
+}
+
+fn default_reports_window_secs() -> u64 {
+    3600
+}
+
+fn default_reports_max_note_chars() -> usize {
+    500
+}
+
+impl Default for ReportsConfig {
+    fn default() -> Self {
+        Self {
+            quarantine_threshold: default_quarantine_threshold(),
+            max_per_client: default_reports_max_per_client(),
+            window_secs: default_reports_window_secs(),
+            max_note_chars: default_reports_max_note_chars(),
+        }
+    }
+}
+
+/// 随机接口配置
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RandomConfig {
+    /// 同时记住的 `?session=` 会话数上限，超出时淘汰最久未使用的会话；为 0 时忽略 `session` 参数
+    #[serde(default = "default_random_max_sessions")]
+    pub max_sessions: u64,
+    /// 会话空闲多久（秒）后被忘记
+    #[serde(default = "default_random_session_ttl_secs")]
+    pub session_ttl_secs: u64,
+    /// 每个会话最多记住的表情包数，超出时最早看过的可能再次出现
+    #[serde(default = "default_random_session_history")]
+    pub session_history: usize,
+}
+
+fn default_random_max_sessions() -> u64 {
+    10_000
+}
+
+fn default_random_session_ttl_secs() -> u64 {
+    3600
+}
+
+fn default_random_session_history() -> usize {
+    1000
+}
+
+impl Default for RandomConfig {
+    fn default() -> Self {
+        Self {
+            max_sessions: default_random_max_sessions(),
+            session_ttl_secs: default_random_session_ttl_secs(),
+            session_history: default_random_session_history(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WebhookConfig {
+    /// 接收事件的地址，为空时不发送
+    pub urls: Vec<String>,
+    /// HMAC-SHA256 签名密钥，为空时不签名
+    pub secret: String,
+    /// 单个地址的最大尝试次数
+    pub max_attempts: u32,
+    /// 单次请求超时（秒）
+    pub timeout_secs: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LoadSheddingConfig {
+    /// 进程常驻内存 (RSS) 上限（MB），超过时拒绝图片缩放请求，为 0 时不限制
+    pub max_rss_mb: u64,
+    /// 拒绝请求时返回的 Retry-After（秒）
+    pub retry_after_secs: u64,
+    /// 内存采样间隔（秒）
+    pub check_interval_secs: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ResizeConfig {
+    /// 图片处理专用线程数，为 0 时使用 CPU 核数
+    pub worker_threads: usize,
+    /// 排队中（含正在处理）的图片任务上限，超出时直接返回 503
+    pub max_queue: usize,
+    /// 未指定宽高时，长边超过该值的图片自动缩小到该尺寸以内，为 0 时返回原图
+    #[serde(default)]
+    pub default_max_dimension: u32,
+    /// 根据 `Accept` 请求头自动返回更小的有损 WebP 编码 (暂不支持 AVIF)
+    #[serde(default)]
+    pub auto_negotiate: bool,
+    /// 同时执行的图片任务数上限，为 0 时等于线程数
+    #[serde(default)]
+    pub max_concurrent: usize,
+    /// 排队等待执行的最长时间（毫秒），超时返回 503
+    #[serde(default = "default_queue_timeout_ms")]
+    pub queue_timeout_ms: u64,
+}
+
+fn default_queue_timeout_ms() -> u64 {
+    2000
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -43,6 +1063,27 @@ pub struct SwaggerConfig {
     pub contact_email: String,
     pub server_url: String,
     pub server_description: String,
+    /// 访问 Swagger UI 与 openapi.json 的 HTTP Basic 认证，未配置时公开
+    #[serde(default)]
+    pub auth: SwaggerAuthConfig,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SwaggerAuthConfig {
+    /// 用户名，与 `password` 一起设置后启用
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+    /// 同时接受 `admin.api_keys` 作为密码 (任意用户名) 或通过 `X-API-Key` 访问
+    #[serde(default)]
+    pub use_api_keys: bool,
+}
+
+impl SwaggerAuthConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.username.is_empty() || self.use_api_keys
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -54,6 +1095,38 @@ pub struct Config {
     pub logging: LoggingConfig,
     #[serde(default)]
     pub swagger: SwaggerConfig,
+    #[serde(default)]
+    pub statistics: StatisticsConfig,
+    #[serde(default)]
+    pub admin: AdminConfig,
+    #[serde(default)]
+    pub cluster: ClusterConfig,
+    #[serde(default)]
+    pub resize: ResizeConfig,
+    #[serde(default)]
+    pub load_shedding: LoadSheddingConfig,
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
+    #[serde(default)]
+    pub caption: CaptionConfig,
+    #[serde(default)]
+    pub watermark: WatermarkConfig,
+    #[serde(default)]
+    pub reports: ReportsConfig,
+    #[serde(default)]
+    pub random: RandomConfig,
+    #[serde(default)]
+    pub content: ContentConfig,
+    #[serde(default)]
+    pub cdn: CdnConfig,
+    #[serde(default)]
+    pub security: SecurityConfig,
+    #[serde(default)]
+    pub ml: MlConfig,
+    #[serde(default)]
+    pub ocr: OcrConfig,
+    #[serde(default)]
+    pub endpoints: EndpointsConfig,
 }
 
 impl Default for LoggingConfig {
@@ -61,6 +1134,10 @@ impl Default for LoggingConfig {
         Self {
             directory: "logs".to_string(),
             file_prefix: "jiangtokoto".to_string(),
+            rotation: LogRotation::default(),
+            max_files: default_log_max_files(),
+            max_size_mb: default_log_max_size_mb(),
+            access_log: AccessLogConfig::default(),
         }
     }
 }
@@ -70,6 +1147,76 @@ impl Default for ProxyConfig {
         Self {
             enabled: false,
             ip_header: "x-forwarded-for".to_string(),
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+impl Default for StatisticsConfig {
+    fn default() -> Self {
+        Self {
+            persist_path: "data/meme_stats.json".to_string(),
+            persist_interval_secs: 60,
+            trending_half_life_hours: 84.0,
+            counters_path: default_counters_path(),
+            max_tracked_clients: default_max_tracked_clients(),
+        }
+    }
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            node_id: "node-1".to_string(),
+            peers: Vec::new(),
+            token: String::new(),
+            leader: false,
+            poll_interval_secs: 30,
+            shard: ShardConfig::default(),
+        }
+    }
+}
+
+impl Default for CaptionConfig {
+    fn default() -> Self {
+        Self {
+            font_path: "assets/fonts/NotoSansCJKsc-Bold.otf".to_string(),
+            max_text_len: 100,
+        }
+    }
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            urls: Vec::new(),
+            secret: String::new(),
+            max_attempts: 3,
+            timeout_secs: 5,
+        }
+    }
+}
+
+impl Default for LoadSheddingConfig {
+    fn default() -> Self {
+        Self {
+            max_rss_mb: 0,
+            retry_after_secs: 5,
+            check_interval_secs: 5,
+        }
+    }
+}
+
+impl Default for ResizeConfig {
+    fn default() -> Self {
+        Self {
+            worker_threads: 0,
+            max_queue: 64,
+            default_max_dimension: 0,
+            auto_negotiate: false,
+            max_concurrent: 0,
+            queue_timeout_ms: default_queue_timeout_ms(),
         }
     }
 }
@@ -85,6 +1232,7 @@ impl Default for SwaggerConfig {
             contact_email: "support@example.com".to_string(),
             server_url: "https://api.jiangtokoto.cn".to_string(),
             server_description: "生产服务器".to_string(),
+            auth: SwaggerAuthConfig::default(),
         }
     }
 }
@@ -96,26 +1244,270 @@ impl Default for Config {
                 host: "0.0.0.0".to_string(),
                 port: 3001,
                 proxy: ProxyConfig::default(),
+                public_base_url: None,
+                static_dir: None,
+                extra_headers: BTreeMap::new(),
+                default_locale: default_locale(),
+                source_header: false,
+                content_digest: false,
+                slow_request_threshold_ms: default_slow_request_threshold_ms(),
+                admin_port: None,
+                admin_host: default_admin_host(),
+                limits: LimitsConfig::default(),
+                compression: false,
             },
             storage: StorageConfig {
                 memes_dir: "assets/jiangtokoto-images/images".to_string(),
+                allowed_extensions: default_allowed_extensions(),
+                allowed_mime_types: default_allowed_mime_types(),
+                sniff_content: true,
+                allow_empty: false,
+                allow_video: false,
+                trash_retention_days: default_trash_retention_days(),
+                trash_purge_interval_secs: default_trash_purge_interval_secs(),
+                aliases_file: default_aliases_file(),
+                deduplicate: true,
+                moderate_uploads: true,
+                moderation_file: default_moderation_file(),
+                nsfw_file: default_nsfw_file(),
+                reports_file: default_reports_file(),
+                report_secret_file: default_report_secret_file(),
+                pins_file: default_pins_file(),
+                tags_file: default_tags_file(),
+                text_file: default_text_file(),
+                snapshot_file: default_snapshot_file(),
+                id_scheme: IdScheme::default(),
+                id_map_file: default_id_map_file(),
+                id_redirects_file: default_id_redirects_file(),
+                max_upload_bytes: default_max_upload_bytes(),
+                normalize_uploads: true,
+                upload_max_dimension: default_upload_max_dimension(),
+                recursive: false,
+                scan_concurrency: default_scan_concurrency(),
+                rescan_backoff_initial_secs: default_rescan_backoff_initial_secs(),
+                rescan_backoff_max_secs: default_rescan_backoff_max_secs(),
             },
             cache: CacheConfig {
                 max_size: 100,
                 ttl_secs: 300,
+                warmup: false,
+                warmup_count: default_warmup_count(),
+                max_memory_mb: 0,
+                stream_threshold_kb: default_stream_threshold_kb(),
+                prefetch: default_prefetch(),
+                content: CachePolicy::default(),
+                resized: CachePolicy::default(),
+                stats_interval_secs: default_cache_stats_interval_secs(),
+                early_refresh: false,
+                early_refresh_ratio: default_early_refresh_ratio(),
             },
             logging: LoggingConfig::default(),
             swagger: SwaggerConfig::default(),
+            statistics: StatisticsConfig::default(),
+            admin: AdminConfig::default(),
+            cluster: ClusterConfig::default(),
+            resize: ResizeConfig::default(),
+            load_shedding: LoadSheddingConfig::default(),
+            webhooks: WebhookConfig::default(),
+            caption: CaptionConfig::default(),
+            watermark: WatermarkConfig::default(),
+            reports: ReportsConfig::default(),
+            random: RandomConfig::default(),
+            content: ContentConfig::default(),
+            cdn: CdnConfig::default(),
+            security: SecurityConfig::default(),
+            ml: MlConfig::default(),
+            ocr: OcrConfig::default(),
+            endpoints: EndpointsConfig::default(),
+        }
+    }
+}
+
+/// 环境变量覆盖配置的前缀，`PTK_SERVER__PORT` 对应 `server.port`
+pub const ENV_PREFIX: &str = "PTK_";
+
+/// 未通过 `--profile` 指定时，从该环境变量读取配置 profile
+pub const PROFILE_ENV: &str = "APP_ENV";
+
+/// 嵌套 include 的层数上限，防止循环引用
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// 要使用的配置 profile：命令行 `--profile <名称>` / `--profile=<名称>` 优先，其次是 `APP_ENV`
+pub fn selected_profile() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--profile" {
+            return args.next();
+        }
+        if let Some(profile) = arg.strip_prefix("--profile=") {
+            return Some(profile.to_string());
+        }
+    }
+    std::env::var(PROFILE_ENV).ok().filter(|profile| !profile.is_empty())
+}
+
+/// 递归合并配置：映射按键合并，其他值由 `overlay` 整体替换
+fn merge_yaml(base: &mut serde_yaml::Value, overlay: serde_yaml::Value) {
+    use serde_yaml::Value;
+
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_yaml(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// 读取配置文件并依次合并其中 `include` 列出的文件 (路径相对于当前文件，后者覆盖前者)；
+/// 被引用的文件不存在时跳过，便于只在部分环境提供 secrets 文件
+fn read_with_includes(path: &Path, depth: usize) -> Result<serde_yaml::Value> {
+    use serde_yaml::Value;
+
+    let config_str = fs::read_to_string(path)
+        .map_err(|e| AppError::Internal(format!("Failed to read config file {:?}: {}", path, e)))?;
+    let mut root: Value = serde_yaml::from_str(&config_str)
+        .map_err(|e| AppError::Internal(format!("Failed to parse config file {:?}: {}", path, e)))?;
+
+    let includes = match root.as_mapping_mut().and_then(|map| map.remove("include")) {
+        None | Some(Value::Null) => return Ok(root),
+        Some(Value::String(include)) => vec![include],
+        Some(Value::Sequence(items)) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::String(include) => Ok(include),
+                _ => Err(AppError::Config(format!("{:?}: include entries must be file paths", path))),
+            })
+            .collect::<Result<Vec<_>>>()?,
+        Some(_) => return Err(AppError::Config(format!("{:?}: include must be a path or a list of paths", path))),
+    };
+    if depth >= MAX_INCLUDE_DEPTH {
+        return Err(AppError::Config(format!("{:?}: includes are nested too deeply", path)));
+    }
+
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    for include in includes {
+        let include_path = dir.join(&include);
+        if !include_path.exists() {
+            tracing::warn!("配置文件 {:?} 引用的 {:?} 不存在，已跳过", path, include_path);
+            continue;
+        }
+        let included = read_with_includes(&include_path, depth + 1)?;
+        merge_yaml(&mut root, included);
+        tracing::info!("已合并配置文件 {:?}", include_path);
+    }
+    Ok(root)
+}
+
+/// 取出 `profiles` 段，将选中的 profile 合并到配置上；指定了不存在的 profile 时报错
+fn apply_profile(root: &mut serde_yaml::Value, profile: Option<&str>) -> Result<()> {
+    let profiles = root.as_mapping_mut().and_then(|map| map.remove("profiles"));
+    let Some(name) = profile else {
+        return Ok(());
+    };
+
+    let mut profiles = match profiles {
+        Some(serde_yaml::Value::Mapping(profiles)) => profiles,
+        _ => return Err(AppError::Config(format!("Profile '{}' requested but config has no profiles section", name))),
+    };
+    let Some(overlay) = profiles.remove(name) else {
+        let available: Vec<&str> = profiles.keys().filter_map(|key| key.as_str()).collect();
+        return Err(AppError::Config(format!(
+            "Unknown profile '{}', available profiles: {}",
+            name,
+            available.join(", ")
+        )));
+    };
+    merge_yaml(root, overlay);
+    tracing::info!("使用配置 profile: {}", name);
+    Ok(())
+}
+
+/// 收集 `PTK_<段>__<字段>` 形式的环境变量，返回 (小写的配置路径, 原始值)
+fn env_overrides() -> Vec<(Vec<String>, String)> {
+    let mut overrides: Vec<_> = std::env::vars()
+        .filter_map(|(key, value)| {
+            let path: Vec<String> = key
+                .strip_prefix(ENV_PREFIX)?
+                .split("__")
+                .map(|segment| segment.to_ascii_lowercase())
+                .collect();
+            // 只接受至少两级的路径，`PTK_CONFIG` 等单级变量另有用途
+            (path.len() >= 2 && path.iter().all(|s| !s.is_empty())).then_some((path, value))
+        })
+        .collect();
+    overrides.sort();
+    overrides
+}
+
+/// 将环境变量写入配置树；原值为字符串时保持字符串，否则按 YAML 解析（数字、布尔、列表等）
+fn apply_env_overrides(root: &mut serde_yaml::Value, overrides: &[(Vec<String>, String)]) -> Result<()> {
+    use serde_yaml::{Mapping, Value};
+
+    for (path, raw) in overrides {
+        let mut node = &mut *root;
+        for segment in path {
+            if !node.is_mapping() {
+                *node = Value::Mapping(Mapping::new());
+            }
+            let Value::Mapping(map) = node else {
+                unreachable!();
+            };
+            node = map.entry(Value::String(segment.clone())).or_insert(Value::Null);
         }
+
+        *node = if node.is_string() {
+            Value::String(raw.clone())
+        } else {
+            serde_yaml::from_str(raw).map_err(|e| {
+                AppError::Config(format!("Invalid value for {}{}: {}", ENV_PREFIX, path.join("__").to_uppercase(), e))
+            })?
+        };
+        tracing::info!("配置项 {} 已由环境变量覆盖", path.join("."));
     }
+    Ok(())
 }
 
 impl Config {
-    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Arc<Self>> {
+    /// 测试用配置：表情包目录、持久化文件与日志都放在 `root` 下，不使用目录快照。
+    /// `root` 应是测试自己持有的临时目录 (如 `tempfile::TempDir`)，测试结束时随之删除，
+    /// 并行的测试互不影响，也不会写入工作目录
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn for_test(root: &Path) -> Self {
+        let path = |name: &str| root.join(name).to_string_lossy().into_owned();
+
+        let mut config = Self::default();
+        config.storage.memes_dir = path("memes");
+        config.storage.aliases_file = path("aliases.json");
+        config.storage.moderation_file = path("pending_memes.json");
+        config.storage.nsfw_file = path("nsfw_memes.json");
+        config.storage.reports_file = path("meme_reports.json");
+        config.storage.report_secret_file = path("report_secret.key");
+        config.storage.pins_file = path("pinned_memes.json");
+        config.storage.tags_file = path("meme_tags.json");
+        config.storage.text_file = path("meme_text.json");
+        config.storage.id_map_file = path("meme_ids.json");
+        config.storage.id_redirects_file = path("meme_id_redirects.json");
+        config.storage.snapshot_file = String::new();
+        config.statistics.persist_path = path("meme_stats.json");
+        config.statistics.counters_path = path("counters.json");
+        config.logging.directory = path("logs");
+        config
+    }
+
+    /// 加载配置：配置文件 (含 `include`) → 选中的 profile → `PTK_` 环境变量，合并后再校验
+    pub fn load_from_file<P: AsRef<Path>>(path: P, profile: Option<&str>) -> Result<Arc<Self>> {
         let path = path.as_ref();
+        let overrides = env_overrides();
 
-        // 如果配置文件不存在，创建默认配置
-        if !path.exists() {
+        // 如果配置文件不存在，创建默认配置；完全由环境变量配置时不生成文件
+        if !path.exists() && overrides.is_empty() {
             // 检查示例配置文件是否存在
             let example_path = path.with_extension("yml.example");
             
@@ -145,11 +1537,20 @@ impl Config {
             }
         }
 
-        // 读取现有配置
-        let config_str = fs::read_to_string(path)
-            .map_err(|e| AppError::Internal(format!("Failed to read config file: {}", e)))?;
+        // 读取现有配置，没有配置文件时以默认配置为基础
+        let mut root = if path.exists() {
+            read_with_includes(path, 0)?
+        } else {
+            tracing::info!("配置文件 {:?} 不存在，使用默认配置与环境变量", path);
+            serde_yaml::to_value(Config::default())
+                .map_err(|e| AppError::Internal(format!("序列化默认配置失败: {}", e)))?
+        };
 
-        let config: Config = serde_yaml::from_str(&config_str)
+        // profile 覆盖基础配置，环境变量优先于配置文件
+        apply_profile(&mut root, profile)?;
+        apply_env_overrides(&mut root, &overrides)?;
+
+        let config: Config = serde_yaml::from_value(root)
             .map_err(|e| AppError::Internal(format!("Failed to parse config file: {}", e)))?;
 
         // 验证配置
@@ -166,6 +1567,25 @@ impl Config {
     }
 
     pub fn validate(&self) -> Result<()> {
+        if self.storage.rescan_backoff_initial_secs == 0
+            || self.storage.rescan_backoff_max_secs < self.storage.rescan_backoff_initial_secs
+        {
+            return Err(AppError::Internal(
+                "storage.rescan_backoff_initial_secs must be > 0 and not exceed rescan_backoff_max_secs".to_string(),
+            ));
+        }
+
+        let jwt = &self.admin.jwt;
+        if jwt.secret.is_some() && jwt.jwks_url.is_some() {
+            return Err(AppError::Internal("admin.jwt: secret and jwks_url are mutually exclusive".to_string()));
+        }
+        if jwt.secret.as_deref().is_some_and(|secret| secret.len() < 32) {
+            return Err(AppError::Internal("admin.jwt.secret must be at least 32 bytes".to_string()));
+        }
+        if jwt.is_enabled() && jwt.roles_claim.is_empty() {
+            return Err(AppError::Internal("admin.jwt.roles_claim must not be empty".to_string()));
+        }
+
         if self.cache.max_size == 0 {
             return Err(AppError::Internal("Cache max_size must be greater than 0".to_string()));
         }
@@ -174,6 +1594,54 @@ impl Config {
             return Err(AppError::Internal("Cache TTL must be greater than 0".to_string()));
         }
         
+        if self.load_shedding.max_rss_mb > 0 && self.load_shedding.check_interval_secs == 0 {
+            return Err(AppError::Internal("Load shedding check_interval_secs must be greater than 0".to_string()));
+        }
+        
+        if self.cache.warmup && self.cache.warmup_count as u64 > self.cache.max_size {
+            return Err(AppError::Internal("Cache warmup_count must not exceed max_size".to_string()));
+        }
+        if self.cache.prefetch as u64 > self.cache.max_size {
+            return Err(AppError::Internal("Cache prefetch must not exceed max_size".to_string()));
+        }
+        
+        if self.logging.rotation == LogRotation::Size && self.logging.max_size_mb == 0 {
+            return Err(AppError::Internal("Logging max_size_mb must be greater than 0 for size rotation".to_string()));
+        }
+        
+        let access_log = &self.logging.access_log;
+        if access_log.enabled && access_log.rotation == LogRotation::Size && self.logging.max_size_mb == 0 {
+            return Err(AppError::Internal("Logging max_size_mb must be greater than 0 for size rotation".to_string()));
+        }
+        
+        if access_log.enabled && (access_log.file_prefix.is_empty() || access_log.file_prefix.starts_with(&self.logging.file_prefix)) {
+            return Err(AppError::Internal("Access log file_prefix must not be empty or start with the application log prefix".to_string()));
+        }
+        
+        if self.statistics.persist_interval_secs == 0 {
+            return Err(AppError::Internal("Statistics persist_interval_secs must be greater than 0".to_string()));
+        }
+        
+        if self.statistics.trending_half_life_hours <= 0.0 {
+            return Err(AppError::Internal("Statistics trending_half_life_hours must be greater than 0".to_string()));
+        }
+        
+        if Locale::parse(&self.server.default_locale).is_none() {
+            return Err(AppError::Internal(format!("Unsupported default_locale: {}", self.server.default_locale)));
+        }
+        
+        if self.storage.scan_concurrency == 0 {
+            return Err(AppError::Internal("Storage scan_concurrency must be greater than 0".to_string()));
+        }
+
+        if self.storage.max_upload_bytes == 0 {
+            return Err(AppError::Internal("Storage max_upload_bytes must be greater than 0".to_string()));
+        }
+        
+        if self.resize.max_queue == 0 {
+            return Err(AppError::Internal("Resize max_queue must be greater than 0".to_string()));
+        }
+        
         if self.server.port == 0 {
             return Err(AppError::Internal("Server port must be greater than 0".to_string()));
         }
@@ -186,6 +1654,103 @@ impl Config {
             return Err(AppError::Internal("Memes directory path cannot be empty".to_string()));
         }
         
+        if self.caption.max_text_len == 0 {
+            return Err(AppError::Internal("Caption max_text_len must be greater than 0".to_string()));
+        }
+
+        let watermark = &self.watermark;
+        if watermark.enabled {
+            if watermark.image_path.is_empty() && watermark.text.trim().is_empty() {
+                return Err(AppError::Internal("Watermark requires image_path or text".to_string()));
+            }
+            if !(watermark.opacity > 0.0 && watermark.opacity <= 1.0) {
+                return Err(AppError::Internal("Watermark opacity must be in (0, 1]".to_string()));
+            }
+            if !(watermark.scale > 0.0 && watermark.scale <= 1.0) {
+                return Err(AppError::Internal("Watermark scale must be in (0, 1]".to_string()));
+            }
+        }
+
+        if self.endpoints.report && self.reports.max_per_client > 0 && self.reports.window_secs == 0 {
+            return Err(AppError::Internal("Reports window_secs must be greater than 0".to_string()));
+        }
+
+        if self.storage.id_scheme == IdScheme::Stable && self.storage.id_map_file.is_empty() {
+            return Err(AppError::Internal("Storage id_map_file is required when id_scheme is stable".to_string()));
+        }
+
+        if self.random.max_sessions > 0 && (self.random.session_ttl_secs == 0 || self.random.session_history == 0) {
+            return Err(AppError::Internal("Random session_ttl_secs and session_history must be greater than 0".to_string()));
+        }
+
+        if self.cache.early_refresh && !(self.cache.early_refresh_ratio > 0.0 && self.cache.early_refresh_ratio < 1.0) {
+            return Err(AppError::Internal("Cache early_refresh_ratio must be in (0, 1)".to_string()));
+        }
+
+        if self.server.admin_port == Some(self.server.port) {
+            return Err(AppError::Internal("Server admin_port must differ from port".to_string()));
+        }
+
+        if !self.webhooks.urls.is_empty() && self.webhooks.max_attempts == 0 {
+            return Err(AppError::Internal("Webhook max_attempts must be greater than 0".to_string()));
+        }
+
+        if self.cluster.enabled && self.cluster.token.is_empty() {
+            return Err(AppError::Internal("Cluster token cannot be empty when cluster is enabled".to_string()));
+        }
+
+        let swagger_auth = &self.swagger.auth;
+        if !swagger_auth.username.is_empty() && swagger_auth.password.is_empty() {
+            return Err(AppError::Internal("Swagger auth password cannot be empty when username is set".to_string()));
+        }
+        if swagger_auth.use_api_keys && self.admin.api_keys.is_empty() {
+            return Err(AppError::Internal("Swagger auth use_api_keys requires admin api_keys".to_string()));
+        }
+
+        let shard = &self.cluster.shard;
+        if shard.enabled {
+            if !self.cluster.enabled {
+                return Err(AppError::Internal("Cluster shard requires cluster to be enabled".to_string()));
+            }
+            if shard.count == 0 || shard.index >= shard.count {
+                return Err(AppError::Internal("Cluster shard index must be less than count".to_string()));
+            }
+            if shard.nodes.len() != shard.count {
+                return Err(AppError::Internal("Cluster shard nodes must list exactly count addresses".to_string()));
+            }
+        }
+        
+        if self.storage.trash_purge_interval_secs == 0 {
+            return Err(AppError::Internal("Storage trash_purge_interval_secs must be greater than 0".to_string()));
+        }
+        
+        if self.storage.allowed_extensions.is_empty() {
+            return Err(AppError::Internal("Storage allowed_extensions cannot be empty".to_string()));
+        }
+        
+        if self.storage.allowed_mime_types.is_empty() {
+            return Err(AppError::Internal("Storage allowed_mime_types cannot be empty".to_string()));
+        }
+
+        if self.ml.model_path.is_some() && self.ml.labels_path.is_none() {
+            return Err(AppError::Internal("ML labels_path is required when model_path is set".to_string()));
+        }
+
+        if self.ml.input_size == 0 || self.ml.top_k == 0 || self.ml.std.contains(&0.0) {
+            return Err(AppError::Internal("ML input_size, top_k and std must be greater than 0".to_string()));
+        }
+
+        if self.ocr.enabled && self.ocr.endpoint.is_some() && self.ocr.timeout_secs == 0 {
+            return Err(AppError::Internal("OCR timeout_secs must be greater than 0".to_string()));
+        }
+
+        if let Some(pattern) = self.security.referer_policy.allowed.iter().find(|p| p.is_empty() || p.contains('/')) {
+            return Err(AppError::Internal(format!(
+                "Security referer_policy.allowed entries must be host names, got '{}'",
+                pattern
+            )));
+        }
+        
         Ok(())
     }
 }
@@ -0,0 +1,133 @@
+use std::net::{SocketAddr, TcpListener};
+use std::path::Path;
+use serde::Serialize;
+use crate::config::Config;
+use crate::services::storage::{FsStorage, Storage};
+use crate::utils::media;
+
+/// `--check` 的检查结果，以 JSON 输出到标准输出
+#[derive(Debug, Default, Serialize)]
+pub struct CheckReport {
+    pub ok: bool,
+    pub config: CheckItem,
+    pub bind: Vec<BindCheck>,
+    pub memes_dir: Option<String>,
+    pub files_scanned: usize,
+    pub images_ok: usize,
+    /// 无法读取、类型不符或无法解码的文件
+    pub problems: Vec<FileProblem>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CheckItem {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BindCheck {
+    pub addr: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileProblem {
+    pub file: String,
+    pub error: String,
+}
+
+/// 检查配置、端口与表情包目录，输出报告并返回进程退出码 (全部通过时为 0)
+pub async fn run(config_path: &str, profile: Option<&str>) -> i32 {
+    let report = check(config_path, profile).await;
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("序列化检查报告失败: {}", e),
+    }
+    if report.ok { 0 } else { 1 }
+}
+
+async fn check(config_path: &str, profile: Option<&str>) -> CheckReport {
+    let mut report = CheckReport::default();
+
+    let config = match Config::load_from_file(config_path, profile) {
+        Ok(config) => config,
+        Err(e) => {
+            report.config.error = Some(e.to_string());
+            return report;
+        }
+    };
+    report.config.ok = true;
+
+    report.bind.push(check_bind(&config.server.host, config.server.port));
+    if let Some(admin_port) = config.server.admin_port {
+        report.bind.push(check_bind(&config.server.admin_host, admin_port));
+    }
+
+    report.memes_dir = Some(config.storage.memes_dir.clone());
+    check_memes(&config, &mut report).await;
+
+    report.ok = report.bind.iter().all(|bind| bind.ok) && report.problems.is_empty();
+    report
+}
+
+/// 尝试绑定端口后立即释放
+fn check_bind(host: &str, port: u16) -> BindCheck {
+    let addr = format!("{}:{}", host, port);
+    let result = addr
+        .parse::<SocketAddr>()
+        .map_err(|e| format!("Invalid address: {}", e))
+        .and_then(|socket| TcpListener::bind(socket).map(drop).map_err(|e| e.to_string()));
+    BindCheck {
+        addr,
+        ok: result.is_ok(),
+        error: result.err(),
+    }
+}
+
+/// 完整读取并解码每个会被加载的图片
+async fn check_memes(config: &Config, report: &mut CheckReport) {
+    let storage_config = &config.storage;
+    let storage = FsStorage::new(storage_config);
+    let files = match storage.list().await {
+        Ok(files) => files,
+        Err(e) => {
+            report.problems.push(FileProblem {
+                file: storage_config.memes_dir.clone(),
+                error: format!("Failed to list memes directory: {}", e),
+            });
+            return;
+        }
+    };
+
+    for path in files {
+        let filename = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if media::is_hidden_or_temp(&filename) || !media::extension_allowed(&path, &storage_config.allowed_extensions) {
+            continue;
+        }
+        report.files_scanned += 1;
+
+        let file = display_path(&path, Path::new(&storage_config.memes_dir));
+        match check_image(&storage, &path, storage_config.sniff_content).await {
+            Ok(()) => report.images_ok += 1,
+            Err(error) => report.problems.push(FileProblem { file, error }),
+        }
+    }
+}
+
+async fn check_image(storage: &FsStorage, path: &Path, sniff_content: bool) -> Result<(), String> {
+    let content = storage.read(path).await.map_err(|e| format!("Failed to read file: {}", e))?;
+    if sniff_content && media::sniff_image_mime(&content).is_none() {
+        return Err("File content is not a recognized image".to_string());
+    }
+    tokio::task::spawn_blocking(move || media::decode(&content).map(drop))
+        .await
+        .map_err(|e| format!("Decode task failed: {}", e))?
+        .map_err(|e| format!("Failed to decode image: {}", e))
+}
+
+fn display_path(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root).unwrap_or(path).to_string_lossy().to_string()
+}
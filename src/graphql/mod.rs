@@ -0,0 +1,191 @@
+use std::sync::Arc;
+use async_graphql::{
+    http::GraphiQLSource, Context, EmptyMutation, EmptySubscription, InputObject, Object,
+    Schema, SimpleObject,
+};
+use axum::response::{Html, IntoResponse};
+use tokio::sync::RwLock;
+use crate::models::meme::Meme;
+use crate::services::meme::MemeService;
+
+pub type MemeSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// 单次查询最多返回的表情包数量
+const MAX_PAGE_SIZE: usize = 500;
+
+#[derive(SimpleObject)]
+pub struct MemeObject {
+    id: u32,
+    filename: String,
+    mime_type: String,
+    size_bytes: u64,
+    /// 原图的 SHA-256 (十六进制)
+    content_hash: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    /// 站内图片地址
+    path: String,
+}
+
+impl From<&Meme> for MemeObject {
+    fn from(meme: &Meme) -> Self {
+        Self {
+            id: meme.id,
+            filename: meme.filename.clone(),
+            mime_type: meme.mime_type.clone(),
+            size_bytes: meme.size_bytes,
+            content_hash: meme.content_hash.clone(),
+            width: meme.width,
+            height: meme.height,
+            path: format!("/memes/get/{}", meme.id),
+        }
+    }
+}
+
+#[derive(InputObject, Default)]
+pub struct MemeFilter {
+    /// 按 MIME 类型精确匹配，例如 `image/gif`
+    mime_type: Option<String>,
+    /// 文件名包含的子串（忽略大小写）
+    filename_contains: Option<String>,
+    min_size_bytes: Option<u64>,
+    max_size_bytes: Option<u64>,
+    /// 排除 NSFW 表情包，不指定时使用 `content.safe_mode`
+    safe: Option<bool>,
+}
+
+impl MemeFilter {
+    fn matches(&self, meme: &Meme) -> bool {
+        if let Some(mime_type) = &self.mime_type {
+            if !meme.mime_type.eq_ignore_ascii_case(mime_type) {
+                return false;
+            }
+        }
+        if let Some(needle) = &self.filename_contains {
+            if !meme.filename.to_lowercase().contains(&needle.to_lowercase()) {
+                return false;
+            }
+        }
+        if self.min_size_bytes.is_some_and(|min| meme.size_bytes < min) {
+            return false;
+        }
+        if self.max_size_bytes.is_some_and(|max| meme.size_bytes > max) {
+            return false;
+        }
+        true
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct MemePage {
+    total: usize,
+    items: Vec<MemeObject>,
+}
+
+#[derive(SimpleObject)]
+pub struct StatisticsObject {
+    total_requests: u64,
+    requests_last_minute: u64,
+    requests_last_5min: u64,
+    requests_last_15min: u64,
+    total_memes: usize,
+    cache_hits: u64,
+    cache_misses: u64,
+}
+
+#[derive(SimpleObject)]
+pub struct TrendingObject {
+    meme: MemeObject,
+    score: f64,
+    total_hits: u64,
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// 根据 ID 查询表情包
+    async fn meme(&self, ctx: &Context<'_>, id: u32) -> Option<MemeObject> {
+        let service = ctx.data_unchecked::<Arc<RwLock<MemeService>>>().read().await;
+        service.get_meme(id).map(MemeObject::from)
+    }
+
+    /// 按条件分页查询表情包，结果按 ID 排序
+    async fn memes(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<MemeFilter>,
+        #[graphql(default = 0)] offset: usize,
+        #[graphql(default = 50)] limit: usize,
+    ) -> MemePage {
+        let service = ctx.data_unchecked::<Arc<RwLock<MemeService>>>().read().await;
+        let filter = filter.unwrap_or_default();
+        let safe = filter.safe.unwrap_or(service.config().content.safe_mode);
+
+        let mut memes: Vec<&Meme> = service.get_all_memes()
+            .into_iter()
+            .map(|(_, meme)| meme)
+            .filter(|meme| !(safe && meme.nsfw) && filter.matches(meme))
+            .collect();
+        memes.sort_by_key(|meme| meme.id);
+
+        MemePage {
+            total: memes.len(),
+            items: memes.into_iter()
+                .skip(offset)
+                .take(limit.min(MAX_PAGE_SIZE))
+                .map(MemeObject::from)
+                .collect(),
+        }
+    }
+
+    /// 服务统计信息
+    async fn statistics(&self, ctx: &Context<'_>) -> StatisticsObject {
+        let service = ctx.data_unchecked::<Arc<RwLock<MemeService>>>().read().await;
+        let (cache_hits, cache_misses) = service.get_cache_stats();
+
+        StatisticsObject {
+            total_requests: service.get_request_count(),
+            requests_last_minute: service.get_requests_last_minute(),
+            requests_last_5min: service.get_requests_last_5_minutes(),
+            requests_last_15min: service.get_requests_last_15_minutes(),
+            total_memes: service.get_total_memes(),
+            cache_hits,
+            cache_misses,
+        }
+    }
+
+    /// 近期热门表情包
+    async fn trending(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 10)] limit: usize,
+    ) -> Vec<TrendingObject> {
+        let service = ctx.data_unchecked::<Arc<RwLock<MemeService>>>().read().await;
+
+        service.get_meme_stats()
+            .trending(service.get_total_memes())
+            .into_iter()
+            .filter_map(|(id, score, total_hits)| {
+                service.get_meme(id).map(|meme| TrendingObject {
+                    meme: MemeObject::from(meme),
+                    score,
+                    total_hits,
+                })
+            })
+            .take(limit.min(MAX_PAGE_SIZE))
+            .collect()
+    }
+}
+
+/// 构建 GraphQL Schema，复用 MemeService 作为数据源
+pub fn build_schema(state: Arc<RwLock<MemeService>>) -> MemeSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(state)
+        .finish()
+}
+
+/// GraphiQL 调试页面
+pub async fn graphiql() -> impl IntoResponse {
+    Html(GraphiQLSource::build().endpoint("/graphql").finish())
+}
@@ -0,0 +1,951 @@
+use std::{collections::{BTreeMap, HashMap}, sync::Arc};
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+use utoipa::{IntoParams, ToSchema};
+use crate::logging::LogLevel;
+use crate::middleware::auth::Principal;
+use crate::models::meme::{Meme, MemeStatus};
+use crate::services::meme::{normalize_filename, CacheEntry, CacheTarget, IdCollision, MemeService, MimeMismatch, ReloadTrigger};
+use crate::services::clients::ClientStat;
+use crate::services::reports::Report;
+use crate::services::tags::SuggestedTag;
+use crate::services::trash::TrashEntry;
+use crate::utils::error::AppError;
+
+/// 删除表情包（移入回收站）
+///
+/// 开启去重时内容相同的重复文件一并移入回收站，响应只包含表情包本身的条目，其余条目见 `/admin/trash`
+#[utoipa::path(
+    delete,
+    path = "/admin/memes/{id}",
+    tag = "admin",
+    params(
+        ("id" = u32, Path, description = "表情包ID")
+    ),
+    responses(
+        (status = 200, description = "表情包已移入回收站", body = TrashEntry),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "表情包不存在")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete_meme(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(id): Path<u32>,
+) -> Result<Json<TrashEntry>, AppError> {
+    let service = state.read().await;
+    let meme = service.find_meme(id)
+        .ok_or(AppError::MemeNotFound { id })?;
+
+    let entry = move_to_trash(&service, meme).await?;
+    service.moderation().resolve(&meme.filename)?;
+    service.request_reload(ReloadTrigger::Admin);
+
+    Ok(Json(entry))
+}
+
+/// 将表情包连同内容相同的重复文件一起移入回收站，返回表情包本身的回收站条目；
+/// 只移走保留的文件时，下次重载会有一个重复文件以另一个 ID 重新出现
+async fn move_to_trash(service: &MemeService, meme: &Meme) -> Result<TrashEntry, AppError> {
+    let entry = service.trash().move_to_trash(&meme.path, meme.id).await?;
+    for (filename, path) in meme.duplicates.iter().zip(&meme.duplicate_paths) {
+        service.trash().move_to_trash(path, service.id_for_filename(filename)).await?;
+    }
+    Ok(entry)
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct UploadQuery {
+    /// 保存的文件名（含扩展名）
+    #[param(example = "funny_meme.jpg")]
+    pub filename: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct AdminListQuery {
+    /// 按审核状态筛选，不指定时返回全部
+    pub status: Option<MemeStatus>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ModeratedMeme {
+    #[schema(example = 1)]
+    pub id: u32,
+    #[schema(example = "funny_meme.jpg")]
+    pub filename: String,
+    pub status: MemeStatus,
+    #[schema(example = "image/jpeg")]
+    pub mime_type: String,
+    #[schema(example = 1024)]
+    pub size_bytes: u64,
+}
+
+impl From<&Meme> for ModeratedMeme {
+    fn from(meme: &Meme) -> Self {
+        Self {
+            id: meme.id,
+            filename: meme.filename.clone(),
+            status: meme.status,
+            mime_type: meme.mime_type.clone(),
+            size_bytes: meme.size_bytes,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UploadResponse {
+    #[schema(example = 1)]
+    pub id: u32,
+    #[schema(example = "funny_meme.jpg")]
+    pub filename: String,
+    pub status: MemeStatus,
+}
+
+/// 上传表情包
+///
+/// 请求体为图片的原始内容；启用审核时上传后处于待审核状态。
+/// 启用 `storage.normalize_uploads` 时图片会先被校验与规范化，BMP / TIFF 等格式转换为 PNG 并改用 `.png` 扩展名
+#[utoipa::path(
+    post,
+    path = "/admin/memes",
+    tag = "admin",
+    params(UploadQuery),
+    request_body(content = Vec<u8>, content_type = "application/octet-stream", description = "图片文件内容"),
+    responses(
+        (status = 201, description = "表情包已上传", body = UploadResponse),
+        (status = 400, description = "文件名或文件类型不合法，或同名文件已存在"),
+        (status = 401, description = "未授权"),
+        (status = 413, description = "文件过大")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn upload_meme(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Query(query): Query<UploadQuery>,
+    principal: Option<Extension<Principal>>,
+    body: Bytes,
+) -> Result<(StatusCode, Json<UploadResponse>), AppError> {
+    let service = state.read().await;
+    let (id, filename, status) = service.store_upload(&query.filename, &body).await?;
+    let subject = principal.and_then(|Extension(p)| p.subject);
+    info!(id, filename = %filename, subject = ?subject, "收到上传的表情包");
+
+    Ok((StatusCode::CREATED, Json(UploadResponse {
+        id,
+        filename,
+        status,
+    })))
+}
+
+/// 按审核状态列出表情包
+#[utoipa::path(
+    get,
+    path = "/admin/memes",
+    tag = "admin",
+    params(AdminListQuery),
+    responses(
+        (status = 200, description = "成功返回表情包列表", body = Vec<ModeratedMeme>),
+        (status = 401, description = "未授权")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_memes(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Query(query): Query<AdminListQuery>,
+) -> Json<Vec<ModeratedMeme>> {
+    let service = state.read().await;
+    Json(service.get_memes_by_status(query.status)
+        .into_iter()
+        .map(ModeratedMeme::from)
+        .collect())
+}
+
+/// 审核通过表情包
+#[utoipa::path(
+    post,
+    path = "/admin/memes/{id}/approve",
+    tag = "admin",
+    params(
+        ("id" = u32, Path, description = "表情包ID")
+    ),
+    responses(
+        (status = 200, description = "表情包已审核通过", body = ModeratedMeme),
+        (status = 400, description = "表情包不在待审核状态"),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "表情包不存在")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn approve_meme(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(id): Path<u32>,
+) -> Result<Json<ModeratedMeme>, AppError> {
+    let service = state.read().await;
+    let meme = service.find_meme(id)
+        .ok_or(AppError::MemeNotFound { id })?;
+
+    let mut resolved = false;
+    for filename in meme.filenames() {
+        resolved |= service.moderation().resolve(filename)?;
+    }
+    if !resolved {
+        return Err(AppError::BadRequest(format!("Meme {} is not pending", id)));
+    }
+    // 审核通过即视为举报已处理，避免下一次举报立即再次触发自动下架
+    service.reports().clear(&meme.filename)?;
+    service.request_reload(ReloadTrigger::Admin);
+
+    let mut approved = ModeratedMeme::from(meme);
+    approved.status = MemeStatus::Approved;
+    Ok(Json(approved))
+}
+
+/// 拒绝待审核的表情包（移入回收站）
+///
+/// 内容相同的重复文件同样一并移入回收站
+#[utoipa::path(
+    post,
+    path = "/admin/memes/{id}/reject",
+    tag = "admin",
+    params(
+        ("id" = u32, Path, description = "表情包ID")
+    ),
+    responses(
+        (status = 200, description = "表情包已拒绝并移入回收站", body = TrashEntry),
+        (status = 400, description = "表情包不在待审核状态"),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "表情包不存在")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn reject_meme(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(id): Path<u32>,
+) -> Result<Json<TrashEntry>, AppError> {
+    let service = state.read().await;
+    let meme = service.find_meme(id)
+        .ok_or(AppError::MemeNotFound { id })?;
+
+    if !meme.filenames().any(|filename| service.moderation().is_pending(filename)) {
+        return Err(AppError::BadRequest(format!("Meme {} is not pending", id)));
+    }
+
+    let entry = move_to_trash(&service, meme).await?;
+    for filename in meme.filenames() {
+        service.moderation().resolve(filename)?;
+    }
+    service.request_reload(ReloadTrigger::Admin);
+
+    Ok(Json(entry))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SetNsfwRequest {
+    #[schema(example = true)]
+    pub nsfw: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct NsfwFlag {
+    #[schema(example = 1)]
+    pub id: u32,
+    #[schema(example = true)]
+    pub nsfw: bool,
+}
+
+/// 标记或取消标记 NSFW 表情包
+///
+/// 来源信息文件中标记为 `nsfw: true` 的表情包无法通过该接口取消
+#[utoipa::path(
+    put,
+    path = "/admin/memes/{id}/nsfw",
+    tag = "admin",
+    params(
+        ("id" = u32, Path, description = "表情包ID")
+    ),
+    request_body = SetNsfwRequest,
+    responses(
+        (status = 200, description = "标记已更新", body = NsfwFlag),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "表情包不存在")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn set_nsfw(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(id): Path<u32>,
+    Json(request): Json<SetNsfwRequest>,
+) -> Result<Json<NsfwFlag>, AppError> {
+    let service = state.read().await;
+    let meme = service.find_meme(id)
+        .ok_or(AppError::MemeNotFound { id })?;
+
+    let mut changed = service.nsfw().set(&meme.filename, request.nsfw)?;
+    // 取消标记时一并清除以磁盘上的文件名记录的旧标记
+    if !request.nsfw {
+        if let Some(original) = &meme.original_filename {
+            changed |= service.nsfw().set(original, false)?;
+        }
+    }
+    if changed {
+        service.request_reload(ReloadTrigger::Admin);
+    }
+
+    let from_metadata = meme.metadata.as_ref().is_some_and(|m| m.nsfw);
+    Ok(Json(NsfwFlag {
+        id: meme.id,
+        nsfw: request.nsfw || from_metadata,
+    }))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReportedMeme {
+    #[schema(example = 1)]
+    pub id: u32,
+    #[schema(example = "funny_meme.jpg")]
+    pub filename: String,
+    /// 当前审核状态，自动下架的表情包为 `pending`
+    pub status: Option<MemeStatus>,
+    pub reports: Vec<Report>,
+}
+
+/// 查看用户举报
+///
+/// 按举报数从多到少排列
+#[utoipa::path(
+    get,
+    path = "/admin/reports",
+    tag = "admin",
+    responses(
+        (status = 200, description = "成功返回被举报的表情包", body = Vec<ReportedMeme>),
+        (status = 401, description = "未授权")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_reports(
+    State(state): State<Arc<RwLock<MemeService>>>,
+) -> Json<Vec<ReportedMeme>> {
+    let service = state.read().await;
+    let mut reported: Vec<ReportedMeme> = service.reports().list()
+        .into_iter()
+        .map(|(filename, reports)| {
+            let id = service.id_for_filename(&filename);
+            ReportedMeme {
+                id,
+                status: service.find_meme(id).map(|meme| meme.status),
+                filename,
+                reports,
+            }
+        })
+        .collect();
+    reported.sort_by(|a, b| b.reports.len().cmp(&a.reports.len()).then(a.id.cmp(&b.id)));
+    Json(reported)
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DismissedReports {
+    #[schema(example = 1)]
+    pub id: u32,
+    /// 清除的举报数
+    #[schema(example = 3)]
+    pub dismissed: usize,
+}
+
+/// 驳回表情包的全部举报
+///
+/// 只清除举报记录；已自动下架的表情包需要另外调用 approve 恢复
+#[utoipa::path(
+    delete,
+    path = "/admin/memes/{id}/reports",
+    tag = "admin",
+    params(
+        ("id" = u32, Path, description = "表情包ID")
+    ),
+    responses(
+        (status = 200, description = "举报已清除", body = DismissedReports),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "表情包不存在")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn dismiss_reports(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(id): Path<u32>,
+) -> Result<Json<DismissedReports>, AppError> {
+    let service = state.read().await;
+    let meme = service.find_meme(id)
+        .ok_or(AppError::MemeNotFound { id })?;
+    let dismissed = service.reports().clear(&meme.filename)?;
+    info!("已驳回表情包 {} 的 {} 条举报", id, dismissed);
+    Ok(Json(DismissedReports { id: meme.id, dismissed }))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PinState {
+    #[schema(example = 1)]
+    pub id: u32,
+    #[schema(example = true)]
+    pub pinned: bool,
+}
+
+/// 固定表情包
+///
+/// 固定的表情包常驻内存，不受内容缓存的容量与 TTL 淘汰影响，重载后自动重新读取
+#[utoipa::path(
+    post,
+    path = "/admin/memes/{id}/pin",
+    tag = "admin",
+    params(
+        ("id" = u32, Path, description = "表情包ID")
+    ),
+    responses(
+        (status = 200, description = "表情包已固定", body = PinState),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "表情包不存在")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn pin_meme(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(id): Path<u32>,
+) -> Result<Json<PinState>, AppError> {
+    let service = state.read().await;
+    let meme = service.find_meme(id)
+        .ok_or(AppError::MemeNotFound { id })?;
+
+    if service.pin(meme).await? {
+        info!("已固定表情包 {} ({})", meme.id, meme.filename);
+    }
+    Ok(Json(PinState { id: meme.id, pinned: true }))
+}
+
+/// 取消固定表情包
+#[utoipa::path(
+    delete,
+    path = "/admin/memes/{id}/pin",
+    tag = "admin",
+    params(
+        ("id" = u32, Path, description = "表情包ID")
+    ),
+    responses(
+        (status = 200, description = "已取消固定", body = PinState),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "表情包不存在")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn unpin_meme(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(id): Path<u32>,
+) -> Result<Json<PinState>, AppError> {
+    let service = state.read().await;
+    let meme = service.find_meme(id)
+        .ok_or(AppError::MemeNotFound { id })?;
+
+    if service.unpin(meme)? {
+        info!("已取消固定表情包 {} ({})", meme.id, meme.filename);
+    }
+    Ok(Json(PinState { id: meme.id, pinned: false }))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TagSuggestions {
+    #[schema(example = 1)]
+    pub id: u32,
+    #[schema(example = "funny_meme.jpg")]
+    pub filename: String,
+    pub suggested: Vec<SuggestedTag>,
+}
+
+/// 查看等待审核的候选标签
+///
+/// 候选标签由 `ml.model_path` 配置的本地模型在重载或上传后生成，需要以 `--features ml` 编译
+#[utoipa::path(
+    get,
+    path = "/admin/tags/suggestions",
+    tag = "admin",
+    responses(
+        (status = 200, description = "成功返回带候选标签的表情包", body = Vec<TagSuggestions>),
+        (status = 401, description = "未授权")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_tag_suggestions(
+    State(state): State<Arc<RwLock<MemeService>>>,
+) -> Json<Vec<TagSuggestions>> {
+    let service = state.read().await;
+    let ids: HashMap<&str, u32> = service.get_memes_by_status(None)
+        .into_iter()
+        .map(|meme| (meme.filename.as_str(), meme.id))
+        .collect();
+    Json(service.tags().pending()
+        .into_iter()
+        .filter_map(|(filename, suggested)| {
+            let id = *ids.get(filename.as_str())?;
+            Some(TagSuggestions { id, filename, suggested })
+        })
+        .collect())
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ApproveTagsRequest {
+    /// 要通过的标签，省略时通过全部候选标签
+    #[schema(example = json!(["cat", "funny"]))]
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct MemeTags {
+    #[schema(example = 1)]
+    pub id: u32,
+    #[schema(example = json!(["cat", "funny"]))]
+    pub tags: Vec<String>,
+}
+
+/// 通过候选标签
+///
+/// 未列出的候选标签视为被拒绝；通过的标签在重载后出现在 `/memes/info` 的 `tags` 中
+#[utoipa::path(
+    post,
+    path = "/admin/memes/{id}/tags/approve",
+    tag = "admin",
+    params(
+        ("id" = u32, Path, description = "表情包ID")
+    ),
+    request_body = ApproveTagsRequest,
+    responses(
+        (status = 200, description = "标签已通过", body = MemeTags),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "表情包不存在")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn approve_tags(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(id): Path<u32>,
+    Json(request): Json<ApproveTagsRequest>,
+) -> Result<Json<MemeTags>, AppError> {
+    let service = state.read().await;
+    let meme = service.find_meme(id)
+        .ok_or(AppError::MemeNotFound { id })?;
+
+    let tags = service.tags().approve(&meme.filename, request.tags)?;
+    service.request_reload(ReloadTrigger::Admin);
+    info!("已通过表情包 {} 的标签: {:?}", meme.id, tags);
+
+    Ok(Json(MemeTags { id: meme.id, tags }))
+}
+
+/// 拒绝全部候选标签
+#[utoipa::path(
+    delete,
+    path = "/admin/memes/{id}/tags/suggestions",
+    tag = "admin",
+    params(
+        ("id" = u32, Path, description = "表情包ID")
+    ),
+    responses(
+        (status = 204, description = "候选标签已清除"),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "表情包不存在或没有候选标签")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn reject_tags(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(id): Path<u32>,
+) -> Result<StatusCode, AppError> {
+    let service = state.read().await;
+    let meme = service.find_meme(id)
+        .ok_or(AppError::MemeNotFound { id })?;
+
+    if !service.tags().reject(&meme.filename)? {
+        return Err(AppError::NotFound(format!("Meme {} has no tag suggestions", id)));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 从回收站恢复表情包
+#[utoipa::path(
+    post,
+    path = "/admin/memes/{id}/restore",
+    tag = "admin",
+    params(
+        ("id" = u32, Path, description = "表情包ID")
+    ),
+    responses(
+        (status = 200, description = "表情包已恢复", body = TrashEntry),
+        (status = 400, description = "同名文件已存在"),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "回收站中不存在该表情包")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn restore_meme(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(id): Path<u32>,
+) -> Result<Json<TrashEntry>, AppError> {
+    let service = state.read().await;
+    let entry = service.trash().restore(id, |filename| service.id_for_filename(&normalize_filename(filename))).await?;
+    service.request_reload(ReloadTrigger::Admin);
+
+    Ok(Json(entry))
+}
+
+/// 查看回收站
+#[utoipa::path(
+    get,
+    path = "/admin/trash",
+    tag = "admin",
+    responses(
+        (status = 200, description = "成功返回回收站中的表情包", body = Vec<TrashEntry>),
+        (status = 401, description = "未授权")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_trash(
+    State(state): State<Arc<RwLock<MemeService>>>,
+) -> Result<Json<Vec<TrashEntry>>, AppError> {
+    let service = state.read().await;
+    Ok(Json(service.trash().list(|filename| service.id_for_filename(&normalize_filename(filename))).await?))
+}
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct TopClientsQuery {
+    /// 返回的客户端数量，默认 20，最大 1000
+    #[schema(example = 20)]
+    limit: Option<usize>,
+}
+
+/// 查看请求最多的客户端
+#[utoipa::path(
+    get,
+    path = "/admin/clients",
+    tag = "admin",
+    params(TopClientsQuery),
+    responses(
+        (status = 200, description = "成功返回最近一小时内请求最多的客户端", body = Vec<ClientStat>),
+        (status = 401, description = "未授权")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn top_clients(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Query(query): Query<TopClientsQuery>,
+) -> Json<Vec<ClientStat>> {
+    let limit = query.limit.unwrap_or(20).clamp(1, 1000);
+    Json(state.read().await.clients().top_clients(limit))
+}
+
+/// 查看表情包 ID 冲突
+#[utoipa::path(
+    get,
+    path = "/admin/collisions",
+    tag = "admin",
+    responses(
+        (status = 200, description = "成功返回最近一次重载检测到的 ID 冲突", body = Vec<IdCollision>),
+        (status = 401, description = "未授权")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_collisions(
+    State(state): State<Arc<RwLock<MemeService>>>,
+) -> Json<Vec<IdCollision>> {
+    Json(state.read().await.collisions().to_vec())
+}
+
+/// 查看内容与扩展名不符的文件
+///
+/// 需要开启 `storage.sniff_content`；这些文件按实际类型提供，建议修正扩展名
+#[utoipa::path(
+    get,
+    path = "/admin/mime-mismatches",
+    tag = "admin",
+    responses(
+        (status = 200, description = "成功返回最近一次重载发现的扩展名与实际类型不符的文件", body = Vec<MimeMismatch>),
+        (status = 401, description = "未授权")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_mime_mismatches(
+    State(state): State<Arc<RwLock<MemeService>>>,
+) -> Json<Vec<MimeMismatch>> {
+    Json(state.read().await.mime_mismatches().to_vec())
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SetAliasRequest {
+    #[schema(example = 1)]
+    pub id: u32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AliasEntry {
+    #[schema(example = "wow")]
+    pub alias: String,
+    #[schema(example = 1)]
+    pub id: u32,
+}
+
+/// 查看所有表情包别名
+#[utoipa::path(
+    get,
+    path = "/admin/aliases",
+    tag = "admin",
+    responses(
+        (status = 200, description = "成功返回别名表 (别名 -> 表情包ID)", body = BTreeMap<String, u32>),
+        (status = 401, description = "未授权")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_aliases(
+    State(state): State<Arc<RwLock<MemeService>>>,
+) -> Json<BTreeMap<String, u32>> {
+    Json(state.read().await.aliases().list())
+}
+
+/// 设置表情包别名
+#[utoipa::path(
+    put,
+    path = "/admin/aliases/{alias}",
+    tag = "admin",
+    params(
+        ("alias" = String, Path, description = "表情包别名")
+    ),
+    request_body = SetAliasRequest,
+    responses(
+        (status = 200, description = "别名已设置", body = AliasEntry),
+        (status = 400, description = "别名不合法"),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "表情包不存在")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn set_alias(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(alias): Path<String>,
+    Json(request): Json<SetAliasRequest>,
+) -> Result<Json<AliasEntry>, AppError> {
+    let service = state.read().await;
+    if service.get_meme(request.id).is_none() {
+        return Err(AppError::MemeNotFound { id: request.id });
+    }
+
+    let alias = service.aliases().set(&alias, request.id)?;
+    Ok(Json(AliasEntry { alias, id: request.id }))
+}
+
+/// 删除表情包别名
+#[utoipa::path(
+    delete,
+    path = "/admin/aliases/{alias}",
+    tag = "admin",
+    params(
+        ("alias" = String, Path, description = "表情包别名")
+    ),
+    responses(
+        (status = 200, description = "别名已删除", body = AliasEntry),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "别名不存在")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete_alias(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(alias): Path<String>,
+) -> Result<Json<AliasEntry>, AppError> {
+    let service = state.read().await;
+    match service.aliases().remove(&alias)? {
+        Some(id) => Ok(Json(AliasEntry { alias, id })),
+        None => Err(AppError::NotFound(format!("Alias '{}' not found", alias))),
+    }
+}
+
+/// 重置统计数据
+///
+/// 清空表情包访问统计、请求与缓存计数 (包括历次运行的累计值) 与客户端统计
+#[utoipa::path(
+    post,
+    path = "/admin/statistics/reset",
+    tag = "admin",
+    responses(
+        (status = 204, description = "统计数据已重置"),
+        (status = 401, description = "未授权")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn reset_statistics(
+    State(state): State<Arc<RwLock<MemeService>>>,
+) -> Result<StatusCode, AppError> {
+    state.read().await.reset_statistics()?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct LogLevelBody {
+    /// `RUST_LOG` 格式的过滤规则
+    #[schema(example = "info,jiangtokoto_server=debug")]
+    pub level: String,
+}
+
+/// 查看当前日志级别
+#[utoipa::path(
+    get,
+    path = "/admin/log-level",
+    tag = "admin",
+    responses(
+        (status = 200, description = "成功返回当前的日志过滤规则", body = LogLevelBody),
+        (status = 401, description = "未授权")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_log_level(
+    Extension(log_level): Extension<LogLevel>,
+) -> Result<Json<LogLevelBody>, AppError> {
+    Ok(Json(LogLevelBody { level: log_level.current()? }))
+}
+
+/// 调整日志级别
+///
+/// 立即生效，无需重启；重启后恢复为 `LOG_LEVEL` 环境变量指定的级别
+#[utoipa::path(
+    put,
+    path = "/admin/log-level",
+    tag = "admin",
+    request_body = LogLevelBody,
+    responses(
+        (status = 200, description = "日志级别已更新", body = LogLevelBody),
+        (status = 400, description = "过滤规则无法解析"),
+        (status = 401, description = "未授权")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn set_log_level(
+    Extension(log_level): Extension<LogLevel>,
+    principal: Option<Extension<Principal>>,
+    Json(request): Json<LogLevelBody>,
+) -> Result<Json<LogLevelBody>, AppError> {
+    let previous = log_level.current()?;
+    let level = log_level.set(&request.level)?;
+    let subject = principal.and_then(|Extension(p)| p.subject);
+    warn!(previous = %previous, level = %level, subject = ?subject, "日志级别已调整");
+    Ok(Json(LogLevelBody { level }))
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ClearCacheQuery {
+    /// 要清空的缓存：content、resized 或 all (默认)
+    #[serde(default)]
+    target: CacheTarget,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ClearCacheResponse {
+    /// 清除的条目数
+    #[schema(example = 42)]
+    pub cleared: u64,
+}
+
+/// 清空缓存
+///
+/// 用于清除有问题的缓存条目而无需重启；固定的表情包不受影响
+#[utoipa::path(
+    post,
+    path = "/admin/cache/clear",
+    tag = "admin",
+    params(ClearCacheQuery),
+    responses(
+        (status = 200, description = "缓存已清空", body = ClearCacheResponse),
+        (status = 401, description = "未授权")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn clear_cache(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Query(query): Query<ClearCacheQuery>,
+) -> Json<ClearCacheResponse> {
+    let cleared = state.read().await.clear_cache(query.target).await;
+    Json(ClearCacheResponse { cleared })
+}
+
+/// 查看缓存条目
+#[utoipa::path(
+    get,
+    path = "/admin/cache/entries",
+    tag = "admin",
+    responses(
+        (status = 200, description = "成功返回缓存中的所有条目及其大小", body = Vec<CacheEntry>),
+        (status = 401, description = "未授权")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_cache_entries(
+    State(state): State<Arc<RwLock<MemeService>>>,
+) -> Json<Vec<CacheEntry>> {
+    Json(state.read().await.cache_entries())
+}
+
+/// 查看单个缓存条目
+#[utoipa::path(
+    get,
+    path = "/admin/cache/{cache}/{key}",
+    tag = "admin",
+    params(
+        ("cache" = CacheTarget, Path, description = "缓存：content 或 resized"),
+        ("key" = String, Path, description = "缓存键，原图缓存为表情包 ID，处理后的图片为 `ID:参数`")
+    ),
+    responses(
+        (status = 200, description = "成功返回条目大小与写入时间", body = CacheEntry),
+        (status = 400, description = "缓存名称或键无效"),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "条目不在缓存中")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_cache_entry(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path((cache, key)): Path<(CacheTarget, String)>,
+) -> Result<Json<CacheEntry>, AppError> {
+    state.read().await
+        .cache_entry(cache, &key)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("Cache entry {} not found", key)))
+}
+
+/// 移除单个缓存条目
+///
+/// 文件在磁盘上修复后，用于清除仍在提供的错误缓存，下次请求时重新生成
+#[utoipa::path(
+    delete,
+    path = "/admin/cache/{cache}/{key}",
+    tag = "admin",
+    params(
+        ("cache" = CacheTarget, Path, description = "缓存：content 或 resized"),
+        ("key" = String, Path, description = "缓存键，原图缓存为表情包 ID，处理后的图片为 `ID:参数`")
+    ),
+    responses(
+        (status = 204, description = "条目已移除"),
+        (status = 400, description = "缓存名称或键无效"),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "条目不在缓存中")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn evict_cache_entry(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path((cache, key)): Path<(CacheTarget, String)>,
+) -> Result<StatusCode, AppError> {
+    if state.read().await.evict_cache_entry(cache, &key).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound(format!("Cache entry {} not found", key)))
+    }
+}
@@ -0,0 +1,19 @@
+use axum::{
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+
+/// 内置的网站图标，未在静态目录中提供 favicon.ico 时使用
+static BUILTIN_FAVICON: &[u8] = include_bytes!("../../static/favicon.ico");
+
+/// 内置网站图标
+pub async fn favicon() -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "image/x-icon"),
+            (header::CACHE_CONTROL, "public, max-age=86400"),
+        ],
+        BUILTIN_FAVICON,
+    )
+}
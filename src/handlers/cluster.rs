@@ -0,0 +1,37 @@
+use std::sync::Arc;
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use tokio::sync::RwLock;
+use tracing::info;
+use crate::services::cluster::{InvalidateMessage, CLUSTER_TOKEN_HEADER};
+use crate::services::meme::{MemeService, ReloadTrigger};
+use crate::utils::error::AppError;
+
+/// 接收其他节点的缓存失效通知（集群内部接口，不在 API 文档中公开）
+pub async fn invalidate(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    headers: HeaderMap,
+    Json(message): Json<InvalidateMessage>,
+) -> Result<StatusCode, AppError> {
+    let service = state.read().await;
+    let bus = service.cluster()
+        .ok_or_else(|| AppError::NotFound("Cluster mode is disabled".to_string()))?;
+
+    let token = headers.get(CLUSTER_TOKEN_HEADER).and_then(|h| h.to_str().ok());
+    if !bus.verify_token(token) {
+        return Err(AppError::Unauthorized("Invalid cluster token".to_string()));
+    }
+
+    info!(
+        from = %message.node_id,
+        generation = message.generation,
+        node = %bus.node_id(),
+        "收到其他节点的缓存失效通知"
+    );
+    service.request_reload(ReloadTrigger::Peer);
+
+    Ok(StatusCode::ACCEPTED)
+}
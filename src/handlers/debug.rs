@@ -0,0 +1,64 @@
+//! 压测与故障演练用的调试接口，仅在启用 `debug` feature 时编译
+use std::{sync::Arc, time::Duration};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::RwLock;
+use crate::services::meme::MemeService;
+
+/// /debug/slow 最长等待时间
+const MAX_SLOW_MS: u64 = 60_000;
+/// /debug/fill-cache 单次最多写入的条目数
+const MAX_FILL_COUNT: usize = 100_000;
+
+#[derive(Deserialize)]
+pub struct SlowQuery {
+    ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct FillCacheQuery {
+    count: Option<usize>,
+    size_bytes: Option<usize>,
+}
+
+/// 延迟指定毫秒数后响应，用于演练超时
+pub async fn slow(Query(query): Query<SlowQuery>) -> impl IntoResponse {
+    let ms = query.ms.unwrap_or(1000).min(MAX_SLOW_MS);
+    tokio::time::sleep(Duration::from_millis(ms)).await;
+    Json(json!({ "slept_ms": ms }))
+}
+
+/// 返回指定的 HTTP 状态码，用于演练错误路径
+pub async fn error(Path(code): Path<u16>) -> impl IntoResponse {
+    let status = StatusCode::from_u16(code)
+        .ok()
+        .filter(|s| s.is_client_error() || s.is_server_error())
+        .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+    (status, Json(json!({
+        "error": status.canonical_reason().unwrap_or("Unknown"),
+        "message": format!("Debug error {}", status.as_u16())
+    })))
+}
+
+/// 向压缩图片缓存写入大量占位条目，用于演练缓存淘汰
+pub async fn fill_cache(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Query(query): Query<FillCacheQuery>,
+) -> impl IntoResponse {
+    let count = query.count.unwrap_or(1000).min(MAX_FILL_COUNT);
+    let size_bytes = query.size_bytes.unwrap_or(1024);
+    let entries = state.read().await.debug_fill_cache(count, size_bytes).await;
+
+    Json(json!({
+        "inserted": count,
+        "size_bytes": size_bytes,
+        "resized_cache_entries": entries
+    }))
+}
@@ -0,0 +1,49 @@
+use std::{convert::Infallible, sync::Arc};
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use serde_json::json;
+use tokio::sync::RwLock;
+use tokio_stream::{wrappers::{errors::BroadcastStreamRecvError, BroadcastStream}, Stream, StreamExt};
+use tracing::debug;
+use crate::services::meme::MemeService;
+
+/// 目录事件推送 (Server-Sent Events)
+///
+/// 连接后先收到 `ready` 事件 (当前目录版本号)，之后推送 `reload_started`、`reload_completed`、
+/// `reload_failed`、`memes_added` 与 `memes_removed`，数据为与事件同名的 JSON。
+/// 客户端处理过慢、错过事件时收到 `resync`，应通过 `/memes/changes` 补齐
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = "memes",
+    responses(
+        (status = 200, description = "目录事件流", content_type = "text/event-stream", body = crate::services::events::CatalogEvent)
+    )
+)]
+pub async fn catalog_events(
+    State(state): State<Arc<RwLock<MemeService>>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let (epoch, generation, rx) = {
+        let state = state.read().await;
+        (state.epoch().to_string(), state.generation(), state.events().subscribe())
+    };
+    debug!(generation, "新的目录事件订阅");
+
+    let ready = Event::default()
+        .event("ready")
+        .json_data(json!({ "epoch": epoch, "generation": generation }))
+        .unwrap_or_default();
+    let events = BroadcastStream::new(rx).filter_map(|message| match message {
+        Ok(event) => Event::default().event(event.name()).json_data(&event).ok(),
+        Err(BroadcastStreamRecvError::Lagged(missed)) => Some(
+            Event::default()
+                .event("resync")
+                .json_data(json!({ "missed": missed }))
+                .unwrap_or_default(),
+        ),
+    });
+
+    Sse::new(tokio_stream::once(ready).chain(events).map(Ok)).keep_alive(KeepAlive::default())
+}
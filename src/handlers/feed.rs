@@ -0,0 +1,113 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap},
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use std::{sync::Arc, time::SystemTime};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tokio::sync::RwLock;
+use utoipa::{IntoParams, ToSchema};
+use crate::services::meme::MemeService;
+use crate::utils::url::UrlBuilder;
+
+/// 默认返回的条目数
+const DEFAULT_FEED_LIMIT: usize = 50;
+/// 条目数上限
+const MAX_FEED_LIMIT: usize = 200;
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct FeedQuery {
+    /// 返回的条目数，默认 50，最多 200
+    #[schema(example = 50)]
+    limit: Option<usize>,
+    /// 排除 NSFW 表情包，不指定时使用 `content.safe_mode`
+    #[schema(example = true)]
+    safe: Option<bool>,
+}
+
+/// 新表情包 Atom 订阅
+///
+/// 按文件修改时间列出最近加入的表情包，可在阅读器或频道机器人中订阅
+#[utoipa::path(
+    get,
+    path = "/memes/feed.atom",
+    tag = "memes",
+    params(FeedQuery),
+    responses(
+        (status = 200, description = "成功返回 Atom 订阅", content_type = "application/atom+xml")
+    )
+)]
+pub async fn get_feed(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Query(query): Query<FeedQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let service = state.read().await;
+    let urls = UrlBuilder::from_request(service.config(), &headers);
+    let limit = query.limit.unwrap_or(DEFAULT_FEED_LIMIT).clamp(1, MAX_FEED_LIMIT);
+    let safe = query.safe.unwrap_or(service.config().content.safe_mode);
+    let recent = service.recent_memes(limit, safe);
+
+    let feed_url = urls.url("/memes/feed.atom");
+    let updated = recent
+        .first()
+        .map(|(_, modified)| *modified)
+        .unwrap_or_else(SystemTime::now);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("  <title>{}</title>\n", escape(&service.config().swagger.title)));
+    xml.push_str(&format!("  <id>{}</id>\n", escape(&feed_url)));
+    xml.push_str(&format!("  <link rel=\"self\" href=\"{}\"/>\n", escape(&feed_url)));
+    xml.push_str(&format!("  <updated>{}</updated>\n", rfc3339(updated)));
+
+    for (meme, modified) in recent {
+        let image_url = urls.url(&format!("/memes/get/{}", meme.id));
+        let author = meme.metadata.as_ref().and_then(|m| m.author.as_deref());
+
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!("    <title>{}</title>\n", escape(&meme.filename)));
+        xml.push_str(&format!("    <id>{}</id>\n", escape(&image_url)));
+        xml.push_str(&format!("    <updated>{}</updated>\n", rfc3339(modified)));
+        xml.push_str(&format!("    <link rel=\"alternate\" href=\"{}\"/>\n", escape(&image_url)));
+        xml.push_str(&format!(
+            "    <link rel=\"enclosure\" type=\"{}\" length=\"{}\" href=\"{}\"/>\n",
+            escape(&meme.mime_type),
+            meme.size_bytes,
+            escape(&image_url)
+        ));
+        if let Some(author) = author {
+            xml.push_str(&format!("    <author><name>{}</name></author>\n", escape(author)));
+        }
+        // 内容是 HTML：属性值先按 HTML 转义，整段再按 XML 转义
+        let html = format!("<img src=\"{}\" alt=\"{}\"/>", escape(&image_url), escape(&meme.filename));
+        xml.push_str(&format!("    <content type=\"html\">{}</content>\n", escape(&html)));
+        xml.push_str("  </entry>\n");
+    }
+    xml.push_str("</feed>\n");
+
+    ([(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")], xml)
+}
+
+fn rfc3339(time: SystemTime) -> String {
+    OffsetDateTime::from(time)
+        .format(&Rfc3339)
+        .unwrap_or_default()
+}
+
+/// 转义 XML / HTML 特殊字符
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,163 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap},
+    response::{Html, IntoResponse},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tokio::sync::RwLock;
+use utoipa::{IntoParams, ToSchema};
+use crate::handlers::feed::escape;
+use crate::models::meme::Meme;
+use crate::services::meme::MemeService;
+use crate::utils::url::UrlBuilder;
+
+/// 每页默认显示的表情包数
+const DEFAULT_PER_PAGE: usize = 60;
+/// 每页数量上限
+const MAX_PER_PAGE: usize = 200;
+/// 缩略图的最大边长（像素）
+const THUMBNAIL_SIZE: u32 = 240;
+/// 单个 sitemap 的 URL 数量上限 (协议规定)
+const MAX_SITEMAP_URLS: usize = 50_000;
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct GalleryQuery {
+    /// 页码，从 1 开始
+    #[schema(example = 1)]
+    page: Option<usize>,
+    /// 每页数量，默认 60，最多 200
+    #[schema(example = 60)]
+    per_page: Option<usize>,
+}
+
+/// 按 ID 排序的公开表情包，开启 `content.safe_mode` 时不含 NSFW 表情包
+fn public_memes(service: &MemeService) -> Vec<&Meme> {
+    let safe_mode = service.config().content.safe_mode;
+    let mut memes: Vec<&Meme> = service.get_all_memes()
+        .into_iter()
+        .map(|(_, meme)| meme)
+        .filter(|meme| !(safe_mode && meme.nsfw))
+        .collect();
+    memes.sort_by_key(|meme| meme.id);
+    memes
+}
+
+/// 表情包画廊
+///
+/// 供浏览器访问的简单 HTML 页面，缩略图通过缩放接口懒加载
+#[utoipa::path(
+    get,
+    path = "/gallery",
+    tag = "memes",
+    params(GalleryQuery),
+    responses(
+        (status = 200, description = "成功返回画廊页面", content_type = "text/html")
+    )
+)]
+pub async fn get_gallery(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Query(query): Query<GalleryQuery>,
+    headers: HeaderMap,
+) -> Html<String> {
+    let service = state.read().await;
+    let urls = UrlBuilder::from_request(service.config(), &headers);
+    let memes = public_memes(&service);
+
+    let per_page = query.per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+    let pages = memes.len().div_ceil(per_page).max(1);
+    let page = query.page.unwrap_or(1).clamp(1, pages);
+    let title = escape(&service.config().swagger.title);
+
+    let mut html = String::from("<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    html.push_str(&format!("<title>{}</title>\n", title));
+    html.push_str(&format!(
+        "<link rel=\"alternate\" type=\"application/atom+xml\" href=\"{}\">\n",
+        escape(&urls.url("/memes/feed.atom"))
+    ));
+    html.push_str(concat!(
+        "<style>",
+        "body{font-family:sans-serif;margin:0 auto;max-width:1200px;padding:16px}",
+        ".grid{display:grid;grid-template-columns:repeat(auto-fill,minmax(160px,1fr));gap:8px}",
+        ".grid a{display:flex;align-items:center;justify-content:center;aspect-ratio:1;background:#f3f3f3}",
+        ".grid img,.grid video{max-width:100%;max-height:100%}",
+        "nav{display:flex;gap:16px;justify-content:center;margin:16px 0}",
+        "</style>\n</head>\n<body>\n",
+    ));
+    html.push_str(&format!(
+        "<h1>{}</h1>\n<p>共 {} 个表情包 · <a href=\"{}\">API 文档</a></p>\n<div class=\"grid\">\n",
+        title,
+        memes.len(),
+        escape(&urls.url("/swagger-ui"))
+    ));
+
+    for meme in memes.iter().skip((page - 1) * per_page).take(per_page) {
+        // 视频不能缩放，用静音循环播放的 <video> 代替缩略图
+        if meme.is_video() {
+            html.push_str(&format!(
+                "<a href=\"{}\"><video src=\"{}\" muted loop autoplay playsinline preload=\"metadata\"></video></a>\n",
+                escape(&urls.meme_url(meme.id)),
+                escape(&urls.media_url(&format!("/memes/get/{}", meme.id)))
+            ));
+            continue;
+        }
+        let thumbnail = urls.media_url(&format!("/memes/get/{}?width={size}&height={size}", meme.id, size = THUMBNAIL_SIZE));
+        html.push_str(&format!(
+            "<a href=\"{}\"><img src=\"{}\" alt=\"{}\" loading=\"lazy\"></a>\n",
+            escape(&urls.meme_url(meme.id)),
+            escape(&thumbnail),
+            escape(&meme.filename)
+        ));
+    }
+    html.push_str("</div>\n<nav>\n");
+
+    let page_link = |page: usize| escape(&urls.url(&format!("/gallery?page={}&per_page={}", page, per_page)));
+    if page > 1 {
+        html.push_str(&format!("<a href=\"{}\">上一页</a>\n", page_link(page - 1)));
+    }
+    html.push_str(&format!("<span>{} / {}</span>\n", page, pages));
+    if page < pages {
+        html.push_str(&format!("<a href=\"{}\">下一页</a>\n", page_link(page + 1)));
+    }
+    html.push_str("</nav>\n</body>\n</html>\n");
+
+    Html(html)
+}
+
+/// 站点地图
+///
+/// 列出所有公开表情包的图片地址，超过 50000 个时只列出 ID 最小的部分
+#[utoipa::path(
+    get,
+    path = "/sitemap.xml",
+    tag = "memes",
+    responses(
+        (status = 200, description = "成功返回站点地图", content_type = "application/xml")
+    )
+)]
+pub async fn get_sitemap(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let service = state.read().await;
+    let urls = UrlBuilder::from_request(service.config(), &headers);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    xml.push_str(&format!("  <url><loc>{}</loc></url>\n", escape(&urls.url("/gallery"))));
+
+    for meme in public_memes(&service).into_iter().take(MAX_SITEMAP_URLS - 1) {
+        xml.push_str(&format!("  <url><loc>{}</loc>", escape(&urls.meme_url(meme.id))));
+        let lastmod = service.modified_time(meme)
+            .and_then(|modified| OffsetDateTime::from(modified).format(&Rfc3339).ok());
+        if let Some(lastmod) = lastmod {
+            xml.push_str(&format!("<lastmod>{}</lastmod>", lastmod));
+        }
+        xml.push_str("</url>\n");
+    }
+    xml.push_str("</urlset>\n");
+
+    ([(header::CONTENT_TYPE, "application/xml; charset=utf-8")], xml)
+}
@@ -1,37 +1,102 @@
 use axum::{
-    extract::{State, Path, Query},
-    http::{header, HeaderMap, StatusCode},
-    response::IntoResponse,
+    body::Body,
+    extract::{Request, State, Path, Query},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 use tokio::sync::RwLock;
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
 use tracing::info;
 use serde::Serialize;
 use serde::Deserialize;
 
 use utoipa::ToSchema;
 
-use crate::services::meme::MemeService;
+use crate::models::meme::{Meme, MemeMetadata, Orientation};
+use crate::middleware::auth::extract_api_key;
+use crate::middleware::client_ip::ClientIp;
+use crate::middleware::slow_log::ServedMeme;
+use crate::handlers::params::{ImageParams, ImageQuery};
+use crate::services::meme::{hash_content, variant_key, CacheStatus, MAX_COLLAGE_IMAGES, IconFormat, MediaKind, MemeService, Original, RandomFilter, RandomSeed, RedirectTarget, ReloadReport, TagFilter};
+use crate::services::reports::ReportReason;
+use crate::services::watcher::WatcherStatus;
+use crate::services::watermark::Watermarker;
 use crate::utils::error::AppError;
+use crate::utils::negotiate;
+use crate::utils::url::UrlBuilder;
 use crate::metrics::{REQUEST_COUNTER, RESPONSE_TIME};
 
+/// 表情包出处响应头
+const SOURCE_HEADER: HeaderName = HeaderName::from_static("x-meme-source");
+/// 响应体 SHA-256 响应头
+const DIGEST_HEADER: HeaderName = HeaderName::from_static("x-content-sha256");
+/// 拼图中的表情包 ID 响应头
+const MEME_IDS_HEADER: HeaderName = HeaderName::from_static("x-meme-ids");
+/// 去重会话标识的最大长度
+const MAX_SESSION_LEN: usize = 128;
+/// `POST /memes/random/query` 一次最多返回的表情包数
+const MAX_QUERY_COUNT: usize = 20;
+
 #[derive(Deserialize, ToSchema, utoipa::IntoParams)]
 pub struct RandomMemeQuery {
+    /// 重定向到 `/memes/get/{id}`，图片处理参数原样保留
     #[schema(example = false)]
     redirect: Option<bool>,
-    #[schema(example = 300)]
-    width: Option<u32>,
-    #[schema(example = 300)]
-    height: Option<u32>,
+    /// 图片方向: landscape / portrait / square
+    orientation: Option<Orientation>,
+    /// 只返回图片 (`image`) 或只返回短视频 (`video`)，未指定时不限
+    media: Option<MediaKind>,
+    #[schema(example = 200)]
+    min_width: Option<u32>,
+    #[schema(example = 1920)]
+    max_width: Option<u32>,
+    #[schema(example = 200)]
+    min_height: Option<u32>,
+    #[schema(example = 1080)]
+    max_height: Option<u32>,
+    /// 排除 NSFW 表情包，不指定时使用 `content.safe_mode`
+    #[schema(example = true)]
+    safe: Option<bool>,
+    /// format=json 时附带原图的 SHA-256
+    #[serde(default)]
+    digest: bool,
+    /// 随机种子：目录内容相同时，相同的种子、`n` 与筛选条件总是返回同一个表情包
+    #[schema(example = "quiz-42")]
+    seed: Option<String>,
+    /// 同一种子下的序号，默认 0，依次递增可得到一串确定的结果
+    #[schema(example = 0)]
+    n: Option<u64>,
+    /// 去重会话标识 (最长 128 字符)，如群号：同一会话在所有符合条件的表情包都返回过之前不会重复，与 `seed` 同时指定时忽略
+    #[schema(example = "group-123456")]
+    session: Option<String>,
+}
+
+impl RandomMemeQuery {
+    /// 指定 `max_bytes` 时优先选择不超过该大小的表情包，没有时再缩小并重新压缩
+    fn filter(&self, max_bytes: Option<usize>, safe_mode: bool) -> RandomFilter {
+        RandomFilter {
+            orientation: self.orientation,
+            min_width: self.min_width,
+            max_width: self.max_width,
+            min_height: self.min_height,
+            max_height: self.max_height,
+            max_bytes: max_bytes.map(|b| b as u64),
+            safe: self.safe.unwrap_or(safe_mode),
+            media: self.media,
+            seed: self.seed.clone().map(|seed| RandomSeed { seed, n: self.n.unwrap_or(0) }),
+            session: self.session.clone(),
+            ..Default::default()
+        }
+    }
 }
 
 #[derive(Deserialize, ToSchema, utoipa::IntoParams)]
 pub struct GetMemeQuery {
-    #[schema(example = 300)]
-    width: Option<u32>,
-    #[schema(example = 300)]
-    height: Option<u32>,
+    /// `external` 时 302 重定向到来源信息中的 `canonical_url`，没有该地址时返回 404
+    redirect: Option<RedirectTarget>,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -44,6 +109,355 @@ pub struct MemeListItem {
     pub filename: String,
     #[schema(example = 1024)]
     pub size_bytes: u64,
+    /// 原图的 SHA-256 (十六进制)，文件改名后不变，可通过 `/memes/get/by-hash/{hash}` 获取
+    #[schema(example = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08")]
+    pub content_hash: Option<String>,
+    #[schema(example = 640)]
+    pub width: Option<u32>,
+    #[schema(example = 480)]
+    pub height: Option<u32>,
+    #[schema(example = false)]
+    pub nsfw: bool,
+    #[schema(example = "https://tokotoapi.moonpeaches.xyz/memes/get/1")]
+    pub url: String,
+    /// 平均颜色，可用作图片加载前的占位色；尚未计算时为空，会在后台补全
+    #[schema(example = "#d4a373")]
+    pub color: Option<String>,
+    /// 拍摄或创作日期 (YYYY-MM-DD)，来自来源信息或图片 EXIF
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "2021-05-20")]
+    pub created_on: Option<String>,
+}
+
+impl MemeListItem {
+    fn new(meme: &Meme, urls: &UrlBuilder, color: Option<String>) -> Self {
+        Self {
+            id: meme.id,
+            mime_type: meme.mime_type.clone(),
+            filename: meme.filename.clone(),
+            size_bytes: meme.size_bytes,
+            content_hash: meme.content_hash.clone(),
+            width: meme.width,
+            height: meme.height,
+            nsfw: meme.nsfw,
+            url: urls.meme_url(meme.id),
+            color,
+            created_on: meme.created_on.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct ListMemesQuery {
+    /// 排除 NSFW 表情包，不指定时使用 `content.safe_mode`
+    #[schema(example = true)]
+    safe: Option<bool>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct MemeInfo {
+    #[schema(example = 1)]
+    pub id: u32,
+    #[schema(example = "image/jpeg")]
+    pub mime_type: String,
+    #[schema(example = "funny_meme.jpg")]
+    pub filename: String,
+    #[schema(example = 1024)]
+    pub size_bytes: u64,
+    /// 原图的 SHA-256 (十六进制)，文件改名后不变，可通过 `/memes/get/by-hash/{hash}` 获取
+    #[schema(example = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08")]
+    pub content_hash: Option<String>,
+    #[schema(example = 640)]
+    pub width: Option<u32>,
+    #[schema(example = 480)]
+    pub height: Option<u32>,
+    #[schema(example = false)]
+    pub nsfw: bool,
+    #[schema(example = "https://tokotoapi.moonpeaches.xyz/memes/get/1")]
+    pub url: String,
+    /// 来源信息，未提供 `.meta.yml` 时为空
+    pub attribution: Option<MemeMetadata>,
+    /// 平均颜色，可用作图片加载前的占位色；无法解码时为空
+    #[schema(example = "#d4a373")]
+    pub color: Option<String>,
+    /// 审核通过的标签
+    #[schema(example = json!(["cat", "reaction"]))]
+    pub tags: Vec<String>,
+    /// 拍摄或创作日期 (YYYY-MM-DD)，来自来源信息或图片 EXIF
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "2021-05-20")]
+    pub created_on: Option<String>,
+    /// 图中识别出的文字，未启用 OCR 或没有文字时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "我太难了")]
+    pub text: Option<String>,
+    /// 原图的 SHA-256 (十六进制)，仅在请求 `digest=true` 时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08")]
+    pub sha256: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct MemeInfoQuery {
+    /// 附带原图的 SHA-256
+    #[serde(default)]
+    digest: bool,
+}
+
+impl MemeInfo {
+    /// `digest` 为真时附带原图的 SHA-256 (与 `content_hash` 相同，保留以兼容旧客户端)
+    fn with_digest(mut self, meme: &Meme, digest: bool) -> Self {
+        if digest {
+            self.sha256 = meme.content_hash.clone();
+        }
+        self
+    }
+
+    fn with_color(mut self, color: Option<String>) -> Self {
+        self.color = color;
+        self
+    }
+
+    fn with_text(mut self, text: Option<String>) -> Self {
+        self.text = text;
+        self
+    }
+
+    fn new(meme: &Meme, urls: &UrlBuilder) -> Self {
+        Self {
+            id: meme.id,
+            mime_type: meme.mime_type.clone(),
+            filename: meme.filename.clone(),
+            size_bytes: meme.size_bytes,
+            content_hash: meme.content_hash.clone(),
+            width: meme.width,
+            height: meme.height,
+            nsfw: meme.nsfw,
+            url: urls.meme_url(meme.id),
+            attribution: meme.metadata.clone(),
+            color: None,
+            tags: meme.tags.clone(),
+            created_on: meme.created_on.clone(),
+            text: None,
+            sha256: None,
+        }
+    }
+}
+
+/// 请求的缩放尺寸：未指定宽高且未要求原图时，长边超过 `resize.default_max_dimension` 的图片
+/// 默认缩小到该尺寸以内 (保持宽高比)
+fn requested_dimensions(
+    state: &MemeService,
+    meme: Option<&Meme>,
+    (width, height): (Option<u32>, Option<u32>),
+    original: bool,
+) -> (Option<u32>, Option<u32>) {
+    if width.is_some() || height.is_some() || original {
+        return (width, height);
+    }
+    let limit = state.config().resize.default_max_dimension;
+    let oversized = meme
+        .and_then(|meme| meme.width.zip(meme.height))
+        .is_some_and(|(w, h)| limit > 0 && w.max(h) > limit);
+    if oversized {
+        (Some(limit), Some(limit))
+    } else {
+        (None, None)
+    }
+}
+
+/// 内容超出客户端大小上限时重新压缩为 JPEG 并更新 Content-Type；
+/// `variant` 为缩放与变换参数对应的缓存键后缀
+async fn fit_within(
+    state: &MemeService,
+    id: u32,
+    variant: &str,
+    (content, cache): (Vec<u8>, CacheStatus),
+    max_bytes: Option<usize>,
+    resp_headers: &mut HeaderMap,
+) -> Result<(Vec<u8>, CacheStatus), AppError> {
+    let Some(max_bytes) = max_bytes.filter(|max| content.len() > *max) else {
+        return Ok((content, cache));
+    };
+
+    match state.fit_to_max_bytes(id, variant, content, max_bytes).await {
+        Ok(fitted) => {
+            resp_headers.insert(header::CONTENT_TYPE, "image/jpeg".parse().unwrap());
+            Ok(fitted)
+        }
+        Err(e) => {
+            info!("压缩图片到 {} 字节失败: {}", max_bytes, e);
+            Err(e)
+        }
+    }
+}
+
+/// 条件请求与 Range 相关的请求头，转发给 [`ServeFile`]
+const FILE_REQUEST_HEADERS: [HeaderName; 4] = [
+    header::RANGE,
+    header::IF_RANGE,
+    header::IF_MODIFIED_SINCE,
+    header::IF_UNMODIFIED_SINCE,
+];
+
+/// 直接从磁盘发送原图，由 [`ServeFile`] 处理 Content-Length、Last-Modified 与 Range 请求，
+/// 不经过内存缓冲；`resp_headers` 覆盖到响应头上
+async fn serve_file(path: &std::path::Path, meme_id: u32, resp_headers: HeaderMap, headers: &HeaderMap) -> Response {
+    let mut request = Request::new(Body::empty());
+    for name in FILE_REQUEST_HEADERS {
+        if let Some(value) = headers.get(&name) {
+            request.headers_mut().insert(name, value.clone());
+        }
+    }
+
+    let mut response = match ServeFile::new(path).oneshot(request).await {
+        Ok(response) => response.map(Body::new),
+        Err(never) => match never {},
+    };
+    if response.status().is_success() {
+        response.headers_mut().extend(resp_headers);
+    }
+    response.extensions_mut().insert(ServedMeme { id: meme_id, cache: CacheStatus::Bypass });
+    response
+}
+
+/// 原图可以直接从磁盘发送且不需要按大小重新压缩时返回文件路径
+fn streamable<'a>(original: &'a Original, meme: &Meme, max_bytes: Option<usize>) -> Option<&'a std::path::Path> {
+    match original {
+        Original::File(path) if max_bytes.is_none_or(|max| meme.size_bytes <= max as u64) => Some(path),
+        _ => None,
+    }
+}
+
+/// 启用 `resize.auto_negotiate` 且客户端接受 WebP 时，改为发送更小的有损 WebP 编码并更新 Content-Type；
+/// 目前不编码 AVIF，只接受 AVIF 的客户端收到原格式
+async fn negotiate_format(
+    state: &MemeService,
+    id: u32,
+    variant: &str,
+    (content, cache): (Vec<u8>, CacheStatus),
+    headers: &HeaderMap,
+    resp_headers: &mut HeaderMap,
+) -> (Vec<u8>, CacheStatus) {
+    if !state.config().resize.auto_negotiate {
+        return (content, cache);
+    }
+    resp_headers.insert(header::VARY, HeaderValue::from_static("accept"));
+
+    let content_type = resp_headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    if matches!(content_type, "image/webp" | "image/gif" | "image/svg+xml") || content_type.starts_with("video/") {
+        return (content, cache);
+    }
+    let accept = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()).unwrap_or("");
+    // 只在客户端明确列出 WebP 时转换，`*/*` 不算
+    if negotiate::quality(accept, "image/webp") <= 0.0 || !accept.to_ascii_lowercase().contains("image/webp") {
+        return (content, cache);
+    }
+
+    match state.negotiate_webp(id, variant, &content).await {
+        Some(negotiated) => {
+            resp_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("image/webp"));
+            negotiated
+        }
+        None => (content, cache),
+    }
+}
+
+/// 启用水印且请求没有通过 API Key 跳过时需要加水印
+fn wants_watermark(state: &MemeService, nowatermark: bool, headers: &HeaderMap) -> bool {
+    state.watermarker().is_some_and(|w| !w.is_bypassed(nowatermark, extract_api_key(headers)))
+}
+
+/// 通过 API Key 跳过水印的响应不允许共享缓存，避免 CDN 把无水印的图片发给其他人
+fn insert_bypass_header(headers: &mut HeaderMap, state: &MemeService, watermark: bool) {
+    if !watermark && state.watermarker().is_some() {
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("private, no-store"));
+    }
+}
+
+/// 叠加水印并更新 Content-Type；`variant` 追加 `:wm`，之后的格式协商与压缩使用加水印后的缓存键
+async fn apply_watermark(
+    state: &MemeService,
+    id: u32,
+    variant: &mut String,
+    (content, cache): (Vec<u8>, CacheStatus),
+    resp_headers: &mut HeaderMap,
+) -> Result<(Vec<u8>, CacheStatus), AppError> {
+    let content_type = resp_headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    if !Watermarker::applies_to(&content_type) {
+        return Ok((content, cache));
+    }
+    let marked = state.watermarked(id, variant, (content, cache), &content_type).await?;
+    resp_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(Watermarker::output_mime(&content_type)));
+    variant.push_str(":wm");
+    Ok(marked)
+}
+
+/// 启用 `server.source_header` 时，在图片响应中附加 `X-Meme-Source` 头
+fn insert_source_header(headers: &mut HeaderMap, meme: &Meme, enabled: bool) {
+    if !enabled {
+        return;
+    }
+    let source = meme.metadata.as_ref().and_then(|m| m.source.as_deref());
+    if let Some(value) = source.and_then(|s| HeaderValue::from_str(s).ok()) {
+        headers.insert(SOURCE_HEADER, value);
+    }
+}
+
+/// 重定向到来源信息中的原图地址
+fn external_redirect(state: &MemeService, id: u32) -> Response {
+    let Some(meme) = state.get_meme(id) else {
+        return AppError::MemeNotFound { id }.into_response();
+    };
+    let location = meme.metadata.as_ref()
+        .and_then(|m| m.canonical_url.as_deref())
+        .and_then(|url| HeaderValue::from_str(url).ok());
+    let Some(location) = location else {
+        return AppError::NotFound(format!("Meme {} has no external URL", id)).into_response();
+    };
+    info!(meme_id = meme.id, "重定向到原图地址");
+    (StatusCode::FOUND, [(header::LOCATION, location)]).into_response()
+}
+
+/// 原图是否已经或将要被转码：内容协商改变了 Content-Type，或超出大小上限需要重新压缩
+fn is_transcoded(resp_headers: &HeaderMap, meme: &Meme, len: usize, max_bytes: Option<usize>) -> bool {
+    let content_type = resp_headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok());
+    content_type != Some(meme.mime_type.as_str()) || max_bytes.is_some_and(|max| len > max)
+}
+
+/// 启用 `server.content_digest` 时附加 `X-Content-SHA256` 头；未经处理的原图使用加载时计算的哈希，
+/// 缩放、转码或重新压缩后的内容在此计算
+fn insert_digest_header(headers: &mut HeaderMap, meme: &Meme, content: Option<&[u8]>, enabled: bool) {
+    if !enabled {
+        return;
+    }
+    let digest = match content {
+        Some(content) => hash_content(content),
+        None => match &meme.content_hash {
+            Some(hash) => hash.clone(),
+            None => return,
+        },
+    };
+    if let Ok(value) = HeaderValue::from_str(&digest) {
+        headers.insert(DIGEST_HEADER, value);
+    }
+}
+
+/// 指定种子时的目录内容指纹，客户端可据此确认多个玩家看到的是同一份目录
+const FINGERPRINT_HEADER: &str = "x-catalog-fingerprint";
+
+fn with_fingerprint(mut response: Response, fingerprint: Option<String>) -> Response {
+    if let Some(value) = fingerprint.and_then(|f| HeaderValue::from_str(&f).ok()) {
+        response.headers_mut().insert(FINGERPRINT_HEADER, value);
+    }
+    response
 }
 
 #[derive(Serialize, ToSchema)]
@@ -57,85 +471,313 @@ pub struct MemeCount {
     get,
     path = "/memes/random",
     tag = "memes",
-    params(RandomMemeQuery),
+    params(RandomMemeQuery, ImageQuery),
     responses(
         (status = 200, description = "成功返回随机表情包图片", content_type = "image/*"),
+        (status = 200, description = "format=json 时返回表情包信息", body = MemeInfo),
         (status = 302, description = "重定向到指定表情包", headers(
             ("Location" = String, description = "重定向URL")
         )),
-        (status = 500, description = "服务器内部错误")
+        (status = 400, description = "图片处理参数无效"),
+        (status = 404, description = "没有符合筛选条件的表情包"),
+        (status = 500, description = "服务器内部错误"),
+        (status = 503, description = "图片处理队列已满")
     )
 )]
 pub async fn random_meme(
     State(state): State<Arc<RwLock<MemeService>>>,
     Query(query): Query<RandomMemeQuery>,
-) -> impl IntoResponse {
+    params: ImageParams,
+    headers: HeaderMap,
+) -> Response {
     REQUEST_COUNTER.inc();
     let _timer = crate::metrics::Timer::new(&RESPONSE_TIME);
+    if query.session.as_ref().is_some_and(|s| s.is_empty() || s.chars().count() > MAX_SESSION_LEN) {
+        return AppError::BadRequest(format!("session must be 1 to {} characters", MAX_SESSION_LEN)).into_response();
+    }
     let state = state.read().await;
     
-    match state.get_random().await {
-        Ok((meme, content)) => {
-            // 如果设置了 redirect 参数，则重定向到 get 端点
+    let filter = query.filter(params.max_bytes, state.config().content.safe_mode);
+    let fingerprint = filter.seed.is_some().then(|| state.catalog_fingerprint().to_string());
+    match state.get_random_original(&filter).await {
+        Ok((meme, original)) => {
+            // JSON 模式：返回表情包信息及其绝对地址
+            if params.json {
+                let urls = UrlBuilder::from_request(state.config(), &headers);
+                let color = state.dominant_color(meme).await;
+                let info = MemeInfo::new(meme, &urls).with_digest(meme, query.digest).with_color(color).with_text(state.meme_text(meme));
+                return with_fingerprint(Json(info).into_response(), fingerprint);
+            }
+
+            // 如果设置了 redirect 参数，则重定向到 get 端点，保留图片处理参数（不包含 redirect 参数）
             if query.redirect.unwrap_or(false) {
-                let mut headers = HeaderMap::new();
                 let mut redirect_url = format!("/memes/get/{}", meme.id);
-                
-                // 添加压缩参数到重定向 URL（不包含 redirect 参数）
-                if query.width.is_some() || query.height.is_some() {
+                let query_string = params.to_query_string();
+                if !query_string.is_empty() {
                     redirect_url.push('?');
-                    let mut params = Vec::new();
-                    if let Some(width) = query.width {
-                        params.push(format!("width={}", width));
-                    }
-                    if let Some(height) = query.height {
-                        params.push(format!("height={}", height));
-                    }
-                    redirect_url.push_str(&params.join("&"));
+                    redirect_url.push_str(&query_string);
                 }
                 
+                // 配置了 CDN 时重定向到 CDN 上的相同路径
+                let urls = UrlBuilder::from_request(state.config(), &headers);
+                let location = urls.cdn_url(&redirect_url).unwrap_or(redirect_url);
+                let mut headers = HeaderMap::new();
                 headers.insert(
                     header::LOCATION,
-                    redirect_url.parse().unwrap()
+                    location.parse().unwrap()
                 );
-                return (StatusCode::FOUND, headers, Vec::new());
+                return with_fingerprint((StatusCode::FOUND, headers, Vec::new()).into_response(), fingerprint);
             }
 
             let mut resp_headers = HeaderMap::new();
+            let video = meme.is_video();
+            let params = if video { params.without_processing() } else { params };
+            let transform = params.transform;
+            let (width, height) = requested_dimensions(&state, Some(meme), (params.width, params.height), params.original);
+            let processed = width.is_some() || height.is_some() || !transform.is_identity();
+            let watermark = !video && wants_watermark(&state, params.nowatermark, &headers);
             
             // 使用优化的压缩图片方法
-            let (final_meme, content) = if query.width.is_some() || query.height.is_some() {
-                match state.get_resized_image(meme.id, query.width, query.height).await {
-                    Ok((resized_meme, resized_content)) => {
-                        resp_headers.insert(header::CONTENT_TYPE, "image/png".parse().unwrap());
-                        (resized_meme, resized_content)
+            let (final_meme, content, cache) = if processed {
+                match state.get_resized_image(meme.id, width, height, transform).await {
+                    Ok((resized_meme, resized_content, resized_cache)) => {
+                        resp_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(transform.content_type()));
+                        (resized_meme, resized_content, resized_cache)
+                    }
+                    Err(e @ (AppError::BadRequest(_) | AppError::ServiceUnavailable(_) | AppError::Overloaded { .. })) => {
+                        info!("获取压缩图片失败: {}", e);
+                        return e.into_response();
                     }
                     Err(e) => {
                         info!("获取压缩图片失败: {}", e);
-                        return (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new(), Vec::new());
+                        return (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new(), Vec::new()).into_response();
                     }
                 }
             } else {
                 resp_headers.insert(header::CONTENT_TYPE, meme.mime_type.parse().unwrap());
-                (meme, content)
+                if let Some(path) = streamable(&original, meme, params.max_bytes).filter(|_| !watermark) {
+                    insert_source_header(&mut resp_headers, meme, state.config().server.source_header);
+                    insert_digest_header(&mut resp_headers, meme, None, state.config().server.content_digest);
+                    insert_bypass_header(&mut resp_headers, &state, watermark);
+                    info!(meme_id = meme.id, file_size = meme.size_bytes, "Streaming random meme");
+                    return with_fingerprint(serve_file(path, meme.id, resp_headers, &headers).await, fingerprint);
+                }
+                match state.original_bytes(original).await {
+                    Ok((content, cache)) => (meme, content, cache),
+                    Err(e) => {
+                        info!("读取表情包失败: {}", e);
+                        return (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new(), Vec::new()).into_response();
+                    }
+                }
+            };
+
+            let mut variant = variant_key(width, height, transform);
+            let (content, cache) = if watermark {
+                match apply_watermark(&state, final_meme.id, &mut variant, (content, cache), &mut resp_headers).await {
+                    Ok(marked) => marked,
+                    Err(e) => return e.into_response(),
+                }
+            } else {
+                (content, cache)
             };
 
+            // 超出客户端大小上限时重新压缩
+            let max_bytes = params.max_bytes;
+            let (content, cache) = negotiate_format(&state, final_meme.id, &variant, (content, cache), &headers, &mut resp_headers).await;
+            let transformed = processed || watermark || is_transcoded(&resp_headers, final_meme, content.len(), max_bytes);
+            let (content, cache) = match fit_within(&state, final_meme.id, &variant, (content, cache), max_bytes, &mut resp_headers).await {
+                Ok(fitted) => fitted,
+                Err(e) => return e.into_response(),
+            };
+            insert_source_header(&mut resp_headers, final_meme, state.config().server.source_header);
+            insert_digest_header(&mut resp_headers, final_meme, transformed.then_some(content.as_slice()), state.config().server.content_digest);
+            insert_bypass_header(&mut resp_headers, &state, watermark);
+
             // 记录访问信息
             info!(
                 meme_id = final_meme.id,
                 mime_type = %final_meme.mime_type,
                 file_size = final_meme.size_bytes,
-                cache_used = query.width.is_some() || query.height.is_some(),
+                cache_used = processed,
                 "Serving random meme"
             );
 
-            (StatusCode::OK, resp_headers, content)
+            let mut response = (StatusCode::OK, resp_headers, content).into_response();
+            response.extensions_mut().insert(ServedMeme { id: final_meme.id, cache });
+            with_fingerprint(response, fingerprint)
+        }
+        Err(e @ (AppError::NotFound(_) | AppError::ServiceUnavailable(_))) => {
+            info!("获取表情包失败: {}", e);
+            e.into_response()
         }
         Err(_) => {
             info!("获取表情包失败");
-            (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new(), Vec::new())
+            (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new(), Vec::new()).into_response()
+        }
+    }
+}
+
+/// 数值范围，两端都可省略
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ValueRange {
+    #[schema(example = 200)]
+    pub min: Option<u64>,
+    #[schema(example = 1920)]
+    pub max: Option<u64>,
+}
+
+impl ValueRange {
+    fn validate(&self, name: &str) -> Result<(), AppError> {
+        if let (Some(min), Some(max)) = (self.min, self.max) {
+            if min > max {
+                return Err(AppError::BadRequest(format!("{}.min must not exceed {}.max", name, name)));
+            }
         }
+        Ok(())
     }
+
+    fn dimension(value: Option<u64>) -> Option<u32> {
+        value.map(|v| v.min(u32::MAX as u64) as u32)
+    }
+}
+
+/// 标签条件，比较不区分大小写
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TagQuery {
+    /// 至少包含其中一个
+    #[serde(default)]
+    #[schema(example = json!(["猫", "狗"]))]
+    pub any: Vec<String>,
+    /// 包含全部
+    #[serde(default)]
+    pub all: Vec<String>,
+    /// 不包含其中任何一个
+    #[serde(default)]
+    #[schema(example = json!(["蛇"]))]
+    pub none: Vec<String>,
+}
+
+/// 随机查询条件，所有字段都可省略
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RandomQueryRequest {
+    #[serde(default)]
+    pub tags: TagQuery,
+    /// 宽度范围（像素），尺寸未知的表情包不满足任何尺寸条件
+    #[serde(default)]
+    pub width: ValueRange,
+    /// 高度范围（像素）
+    #[serde(default)]
+    pub height: ValueRange,
+    /// 文件大小范围（字节）
+    #[serde(default)]
+    pub size_bytes: ValueRange,
+    /// 允许的格式，可以是扩展名 (`png`、`jpg`、`gif`、`svg`、`mp4`) 或 MIME 类型，为空时不限
+    #[serde(default)]
+    #[schema(example = json!(["png", "gif"]))]
+    pub formats: Vec<String>,
+    pub orientation: Option<Orientation>,
+    pub media: Option<MediaKind>,
+    /// 排除 NSFW 表情包，不指定时使用 `content.safe_mode`
+    pub safe: Option<bool>,
+    /// 随机种子，语义同 `GET /memes/random?seed=`，结果依次为序号 `n`、`n + 1`… 的单个结果，跳过重复的
+    #[schema(example = "quiz-42")]
+    pub seed: Option<String>,
+    #[schema(example = 0)]
+    pub n: Option<u64>,
+    /// 去重会话标识，语义同 `GET /memes/random?session=`
+    #[schema(example = "group-123456")]
+    pub session: Option<String>,
+    /// 返回的表情包数 (1-20)，默认 1；符合条件的表情包不足时返回全部
+    #[schema(example = 3)]
+    pub count: Option<usize>,
+    /// 附带原图的 SHA-256
+    #[serde(default)]
+    pub digest: bool,
+}
+
+/// 扩展名写法统一为 MIME 子类型
+fn normalize_format(format: &str) -> String {
+    let format = format.trim().trim_start_matches('.').to_ascii_lowercase();
+    match format.as_str() {
+        "jpg" => "jpeg".to_string(),
+        "svg" => "svg+xml".to_string(),
+        "image/jpg" => "image/jpeg".to_string(),
+        _ => format,
+    }
+}
+
+impl RandomQueryRequest {
+    fn filter(&self, safe_mode: bool) -> Result<RandomFilter, AppError> {
+        self.width.validate("width")?;
+        self.height.validate("height")?;
+        self.size_bytes.validate("size_bytes")?;
+        if self.session.as_ref().is_some_and(|s| s.is_empty() || s.chars().count() > MAX_SESSION_LEN) {
+            return Err(AppError::BadRequest(format!("session must be 1 to {} characters", MAX_SESSION_LEN)));
+        }
+
+        Ok(RandomFilter {
+            orientation: self.orientation,
+            min_width: ValueRange::dimension(self.width.min),
+            max_width: ValueRange::dimension(self.width.max),
+            min_height: ValueRange::dimension(self.height.min),
+            max_height: ValueRange::dimension(self.height.max),
+            max_bytes: None,
+            safe: self.safe.unwrap_or(safe_mode),
+            media: self.media,
+            seed: self.seed.clone().map(|seed| RandomSeed { seed, n: self.n.unwrap_or(0) }),
+            session: self.session.clone(),
+            tags: TagFilter {
+                any: self.tags.any.clone(),
+                all: self.tags.all.clone(),
+                none: self.tags.none.clone(),
+            },
+            formats: self.formats.iter().map(|f| normalize_format(f)).filter(|f| !f.is_empty()).collect(),
+            min_size: self.size_bytes.min,
+            max_size: self.size_bytes.max,
+        })
+    }
+}
+
+/// 按 JSON 条件随机查询表情包
+///
+/// 适合查询串难以表达的复杂条件 (标签组合、多个格式、大小范围)，一次可返回多个互不相同的表情包，
+/// 只返回表情包信息，图片通过其中的 `url` 获取
+#[utoipa::path(
+    post,
+    path = "/memes/random/query",
+    tag = "memes",
+    request_body = RandomQueryRequest,
+    responses(
+        (status = 200, description = "符合条件的随机表情包信息", body = Vec<MemeInfo>),
+        (status = 400, description = "查询条件无效"),
+        (status = 404, description = "没有符合条件的表情包")
+    )
+)]
+pub async fn query_random_memes(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    headers: HeaderMap,
+    Json(request): Json<RandomQueryRequest>,
+) -> Result<Response, AppError> {
+    REQUEST_COUNTER.inc();
+    let _timer = crate::metrics::Timer::new(&RESPONSE_TIME);
+    let count = request.count.unwrap_or(1);
+    if !(1..=MAX_QUERY_COUNT).contains(&count) {
+        return Err(AppError::BadRequest(format!("count must be between 1 and {}", MAX_QUERY_COUNT)));
+    }
+
+    let state = state.read().await;
+    let filter = request.filter(state.config().content.safe_mode)?;
+    let fingerprint = filter.seed.is_some().then(|| state.catalog_fingerprint().to_string());
+    let urls = UrlBuilder::from_request(state.config(), &headers);
+    let mut memes = Vec::new();
+    for meme in state.get_random_memes(&filter, count)? {
+        let color = state.dominant_color(meme).await;
+        memes.push(MemeInfo::new(meme, &urls).with_digest(meme, request.digest).with_color(color).with_text(state.meme_text(meme)));
+    }
+    Ok(with_fingerprint(Json(memes).into_response(), fingerprint))
 }
 
 /// 获取表情包列表
@@ -143,31 +785,316 @@ pub async fn random_meme(
     get,
     path = "/memes/list",
     tag = "memes",
+    params(ListMemesQuery),
     responses(
         (status = 200, description = "成功返回表情包列表", body = Vec<MemeListItem>)
     )
 )]
 pub async fn list_memes(
     State(state): State<Arc<RwLock<MemeService>>>,
+    Query(query): Query<ListMemesQuery>,
+    headers: HeaderMap,
 ) -> Json<Vec<MemeListItem>> {
     let service = state.read().await;
+    let urls = UrlBuilder::from_request(service.config(), &headers);
     let memes = service.get_all_memes();
+    let safe = query.safe.unwrap_or(service.config().content.safe_mode);
     
     let mut meme_list: Vec<MemeListItem> = memes.into_iter()
-        .map(|(id, meme)| MemeListItem {
-            id: *id,
-            mime_type: meme.mime_type.clone(),
-            filename: meme.filename.clone(),
-            size_bytes: meme.size_bytes,
-        })
+        .filter(|(_, meme)| !(safe && meme.nsfw))
+        .map(|(id, meme)| MemeListItem::new(meme, &urls, service.known_color(*id)))
         .collect();
     
     // 按 id 排序
     meme_list.sort_by_key(|meme| meme.id);
+    service.fill_colors();
     
     Json(meme_list)
 }
 
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct SearchQuery {
+    /// 搜索词，匹配文件名、标签与图中识别出的文字，忽略大小写与空白
+    #[schema(example = "我太难了")]
+    q: String,
+    /// 排除 NSFW 表情包，不指定时使用 `content.safe_mode`
+    #[schema(example = true)]
+    safe: Option<bool>,
+    /// 返回的最大数量，默认 50，最大 500
+    #[schema(example = 50)]
+    limit: Option<usize>,
+}
+
+/// 搜索表情包
+///
+/// 文件名匹配的结果排在前面，其次是标签，最后是图中文字；图中文字需要启用 `ocr.enabled`，
+/// 新表情包在重载后由后台任务识别
+#[utoipa::path(
+    get,
+    path = "/memes/search",
+    tag = "memes",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "成功返回匹配的表情包", body = Vec<MemeListItem>)
+    )
+)]
+pub async fn search_memes(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Query(query): Query<SearchQuery>,
+    headers: HeaderMap,
+) -> Json<Vec<MemeListItem>> {
+    let service = state.read().await;
+    let urls = UrlBuilder::from_request(service.config(), &headers);
+    let safe = query.safe.unwrap_or(service.config().content.safe_mode);
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+
+    let results = service.search(&query.q, safe)
+        .into_iter()
+        .take(limit)
+        .map(|meme| MemeListItem::new(meme, &urls, service.known_color(meme.id)))
+        .collect();
+    service.fill_colors();
+
+    Json(results)
+}
+
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct OnThisDayQuery {
+    /// 月与日 `MM-DD`，默认为当天 (UTC)
+    #[schema(example = "05-20")]
+    date: Option<String>,
+    /// 排除 NSFW 表情包，不指定时使用 `content.safe_mode`
+    #[schema(example = true)]
+    safe: Option<bool>,
+    /// 返回的最大数量，默认 50，最大 500
+    #[schema(example = 50)]
+    limit: Option<usize>,
+}
+
+/// 解析 `MM-DD`，允许 2 月 29 日
+fn parse_month_day(date: &str) -> Option<(u8, u8)> {
+    let (month, day) = date.split_once('-')?;
+    let month: u8 = month.parse().ok()?;
+    let day: u8 = day.parse().ok()?;
+    let month = time::Month::try_from(month).ok()?;
+    (1..=month.length(2024)).contains(&day).then_some((month as u8, day))
+}
+
+/// 获取历史上的今天
+///
+/// 返回拍摄或创作日期与指定月日相同的表情包，日期来自 `.meta.yml` 中的 `date` 或图片 EXIF
+/// 中的拍摄时间；平年的 2 月 28 日同时返回 2 月 29 日的表情包
+#[utoipa::path(
+    get,
+    path = "/memes/on-this-day",
+    tag = "memes",
+    params(OnThisDayQuery),
+    responses(
+        (status = 200, description = "成功返回表情包列表", body = Vec<MemeListItem>),
+        (status = 400, description = "日期格式无效")
+    )
+)]
+pub async fn get_on_this_day(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Query(query): Query<OnThisDayQuery>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<MemeListItem>>, AppError> {
+    let today = time::OffsetDateTime::now_utc().date();
+    let ((month, day), leap_day) = match query.date.as_deref() {
+        Some(date) => {
+            let month_day = parse_month_day(date)
+                .ok_or_else(|| AppError::BadRequest(format!("Invalid date '{}', expected MM-DD", date)))?;
+            (month_day, false)
+        }
+        None => {
+            let leap_day = !time::util::is_leap_year(today.year())
+                && today.month() == time::Month::February
+                && today.day() == 28;
+            ((today.month() as u8, today.day()), leap_day)
+        }
+    };
+
+    let service = state.read().await;
+    let urls = UrlBuilder::from_request(service.config(), &headers);
+    let safe = query.safe.unwrap_or(service.config().content.safe_mode);
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+
+    let results = service.on_this_day(&format!("{:02}-{:02}", month, day), leap_day, safe)
+        .into_iter()
+        .take(limit)
+        .map(|meme| MemeListItem::new(meme, &urls, service.known_color(meme.id)))
+        .collect();
+    service.fill_colors();
+
+    Ok(Json(results))
+}
+
+/// 获取完整表情包目录 (仅元数据)
+///
+/// 目录在每次重载后预先序列化并以 brotli / zstd 压缩，根据 `Accept-Encoding` 返回对应编码，
+/// ETag 为目录 JSON 的哈希 (区分编码)，可配合 `If-None-Match` 实现增量同步
+#[utoipa::path(
+    get,
+    path = "/memes/catalog",
+    tag = "memes",
+    responses(
+        (status = 200, description = "成功返回表情包目录", body = crate::services::catalog::Catalog),
+        (status = 304, description = "目录未变化")
+    )
+)]
+pub async fn get_catalog(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    headers: HeaderMap,
+) -> Response {
+    let catalog = state.read().await.catalog();
+    let accept_encoding = headers
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("");
+
+    let (encoding, body) = if negotiate::accepts(accept_encoding, "br") {
+        ("br", catalog.brotli.clone())
+    } else if negotiate::accepts(accept_encoding, "zstd") {
+        ("zstd", catalog.zstd.clone())
+    } else {
+        ("identity", catalog.json.clone())
+    };
+    let etag = catalog.etag(encoding);
+
+    let mut resp_headers = HeaderMap::new();
+    resp_headers.insert(header::ETAG, etag.parse().unwrap());
+    resp_headers.insert(header::VARY, "Accept-Encoding".parse().unwrap());
+    resp_headers.insert(header::CACHE_CONTROL, "no-cache".parse().unwrap());
+
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|h| h.to_str().ok())
+        .map(|v| v.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*"))
+        .unwrap_or(false);
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, resp_headers).into_response();
+    }
+
+    resp_headers.insert(header::CONTENT_TYPE, "application/json".parse().unwrap());
+    if encoding != "identity" {
+        resp_headers.insert(header::CONTENT_ENCODING, encoding.parse().unwrap());
+    }
+
+    (StatusCode::OK, resp_headers, body).into_response()
+}
+
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct ChangesQuery {
+    /// 客户端已同步到的目录版本号
+    #[schema(example = 3)]
+    since_generation: u64,
+    /// 同步时响应中的 `epoch`；与当前不同 (服务已重启或请求到了其他实例) 时要求重新同步
+    #[schema(example = "5f1c0a9e3b7d2c4e")]
+    epoch: Option<String>,
+}
+
+/// 获取目录变更
+///
+/// 返回指定版本之后新增与移除的表情包 ID；版本过旧时 `resync_required` 为 true，
+/// 需要通过 `/memes/catalog` 重新获取完整目录
+#[utoipa::path(
+    get,
+    path = "/memes/changes",
+    tag = "memes",
+    params(ChangesQuery),
+    responses(
+        (status = 200, description = "成功返回目录变更", body = crate::services::catalog::CatalogChanges)
+    )
+)]
+pub async fn get_changes(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Query(query): Query<ChangesQuery>,
+) -> Json<crate::services::catalog::CatalogChanges> {
+    Json(state.read().await.changes_since(query.since_generation, query.epoch.as_deref()))
+}
+
+/// 单次比较请求中 ID 与哈希的数量上限
+const MAX_DIFF_ENTRIES: usize = 100_000;
+
+/// 比较镜像与当前目录
+///
+/// 提交镜像端已有的表情包 ID 和/或内容哈希，返回缺少、多余与内容已变化的表情包，
+/// 镜像无需下载完整列表自行比较
+#[utoipa::path(
+    post,
+    path = "/memes/diff",
+    tag = "memes",
+    request_body = crate::services::catalog::DiffRequest,
+    responses(
+        (status = 200, description = "成功返回差异", body = crate::services::catalog::CatalogDiff),
+        (status = 400, description = "请求中的条目过多")
+    )
+)]
+pub async fn diff_catalog(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Json(request): Json<crate::services::catalog::DiffRequest>,
+) -> Result<Json<crate::services::catalog::CatalogDiff>, AppError> {
+    if request.entry_count() > MAX_DIFF_ENTRIES {
+        return Err(AppError::BadRequest(format!("At most {} ids and hashes per request", MAX_DIFF_ENTRIES)));
+    }
+    Ok(Json(state.read().await.diff_catalog(&request)))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ReportRequest {
+    pub reason: ReportReason,
+    /// 补充说明，长度不超过 `reports.max_note_chars`
+    #[schema(example = "包含他人隐私信息")]
+    pub note: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReportReceipt {
+    #[schema(example = 1)]
+    pub id: u32,
+    /// 该表情包是否因举报数达到阈值而转为待审核
+    #[schema(example = false)]
+    pub quarantined: bool,
+}
+
+/// 举报表情包
+///
+/// 同一客户端对同一表情包重复举报只记一次；不同客户端的举报数达到
+/// `reports.quarantine_threshold` 时表情包自动转为待审核，等待管理员处理
+#[utoipa::path(
+    post,
+    path = "/memes/{id}/report",
+    tag = "memes",
+    params(
+        ("id" = u32, Path, description = "表情包ID")
+    ),
+    request_body = ReportRequest,
+    responses(
+        (status = 202, description = "举报已记录", body = ReportReceipt),
+        (status = 400, description = "补充说明过长"),
+        (status = 404, description = "表情包不存在"),
+        (status = 429, description = "举报过于频繁，见 Retry-After")
+    )
+)]
+pub async fn report_meme(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(id): Path<u32>,
+    ClientIp(ip): ClientIp,
+    Json(request): Json<ReportRequest>,
+) -> Result<(StatusCode, Json<ReportReceipt>), AppError> {
+    let service = state.read().await;
+    let max_note_chars = service.config().reports.max_note_chars;
+    let note = request.note
+        .map(|note| note.trim().to_string())
+        .filter(|note| !note.is_empty());
+    if note.as_ref().is_some_and(|note| note.chars().count() > max_note_chars) {
+        return Err(AppError::BadRequest(format!("note must be at most {} characters", max_note_chars)));
+    }
+
+    let (_, quarantined) = service.report_meme(id, request.reason, note, ip)?;
+    Ok((StatusCode::ACCEPTED, Json(ReportReceipt { id, quarantined })))
+}
+
 /// 根据ID获取表情包
 #[utoipa::path(
     get,
@@ -175,61 +1102,402 @@ pub async fn list_memes(
     tag = "memes",
     params(
         ("id" = u32, Path, description = "表情包ID"),
-        GetMemeQuery
+        GetMemeQuery,
+        ImageQuery
     ),
     responses(
         (status = 200, description = "成功返回指定表情包图片", content_type = "image/*"),
-        (status = 404, description = "表情包不存在"),
-        (status = 500, description = "服务器内部错误")
+        (status = 302, description = "重定向到原图的规范地址 (`redirect=external`)"),
+        (status = 400, description = "图片处理参数无效"),
+        (status = 404, description = "表情包不存在，或请求重定向但没有规范地址"),
+        (status = 500, description = "服务器内部错误"),
+        (status = 503, description = "图片处理队列已满")
     )
 )]
 pub async fn get_meme_by_id(
     State(state): State<Arc<RwLock<MemeService>>>,
     Path(id): Path<u32>,
     Query(query): Query<GetMemeQuery>,
-) -> impl IntoResponse {
+    params: ImageParams,
+    headers: HeaderMap,
+) -> Response {
     REQUEST_COUNTER.inc();
     let _timer = crate::metrics::Timer::new(&RESPONSE_TIME);
     let state = state.read().await;
+    if query.redirect == Some(RedirectTarget::External) {
+        return external_redirect(&state, id);
+    }
+    if params.json {
+        return AppError::BadRequest("format=json is only supported by /memes/random".to_string()).into_response();
+    }
+    let video = state.get_meme(id).is_some_and(Meme::is_video);
+    let params = if video { params.without_processing() } else { params };
+    let transform = params.transform;
+    let (width, height) = requested_dimensions(&state, state.get_meme(id), (params.width, params.height), params.original);
+    let processed = width.is_some() || height.is_some() || !transform.is_identity();
+    let watermark = !video && wants_watermark(&state, params.nowatermark, &headers);
     
     // 使用优化的压缩图片方法
-    let result = if query.width.is_some() || query.height.is_some() {
-        state.get_resized_image(id, query.width, query.height).await
+    let result = if processed {
+        state.get_resized_image(id, width, height, transform).await
     } else {
-        state.get_by_id(id).await
+        match state.get_original(id).await {
+            Ok((meme, original)) => {
+                if let Some(path) = streamable(&original, meme, params.max_bytes).filter(|_| !watermark) {
+                    let mut resp_headers = HeaderMap::new();
+                    resp_headers.insert(header::CONTENT_TYPE, meme.mime_type.parse().unwrap());
+                    insert_source_header(&mut resp_headers, meme, state.config().server.source_header);
+                    insert_digest_header(&mut resp_headers, meme, None, state.config().server.content_digest);
+                    insert_bypass_header(&mut resp_headers, &state, watermark);
+                    info!(meme_id = meme.id, file_size = meme.size_bytes, "Streaming meme by ID");
+                    return serve_file(path, meme.id, resp_headers, &headers).await;
+                }
+                state.original_bytes(original).await.map(|(content, cache)| (meme, content, cache))
+            }
+            Err(e) => Err(e),
+        }
     };
     
     match result {
-        Ok((meme, content)) => {
+        Ok((meme, content, cache)) => {
             let mut resp_headers = HeaderMap::new();
             
             // 根据是否压缩设置正确的Content-Type
-            if query.width.is_some() || query.height.is_some() {
-                resp_headers.insert(header::CONTENT_TYPE, "image/png".parse().unwrap());
+            if processed {
+                resp_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(transform.content_type()));
             } else {
                 resp_headers.insert(header::CONTENT_TYPE, meme.mime_type.parse().unwrap());
             }
+
+            let mut variant = variant_key(width, height, transform);
+            let (content, cache) = if watermark {
+                match apply_watermark(&state, meme.id, &mut variant, (content, cache), &mut resp_headers).await {
+                    Ok(marked) => marked,
+                    Err(e) => return e.into_response(),
+                }
+            } else {
+                (content, cache)
+            };
+
+            // 超出客户端大小上限时重新压缩
+            let max_bytes = params.max_bytes;
+            let (content, cache) = negotiate_format(&state, meme.id, &variant, (content, cache), &headers, &mut resp_headers).await;
+            let transformed = processed || watermark || is_transcoded(&resp_headers, meme, content.len(), max_bytes);
+            let (content, cache) = match fit_within(&state, meme.id, &variant, (content, cache), max_bytes, &mut resp_headers).await {
+                Ok(fitted) => fitted,
+                Err(e) => return e.into_response(),
+            };
+            insert_source_header(&mut resp_headers, meme, state.config().server.source_header);
+            insert_digest_header(&mut resp_headers, meme, transformed.then_some(content.as_slice()), state.config().server.content_digest);
+            insert_bypass_header(&mut resp_headers, &state, watermark);
             
             // 记录访问信息
             info!(
                 meme_id = meme.id,
                 mime_type = %meme.mime_type,
                 file_size = meme.size_bytes,
-                cache_used = query.width.is_some() || query.height.is_some(),
+                cache_used = processed,
                 "Serving meme by ID"
             );
 
-            (StatusCode::OK, resp_headers, content)
+            let mut response = (StatusCode::OK, resp_headers, content).into_response();
+            response.extensions_mut().insert(ServedMeme { id: meme.id, cache });
+            response
         }
         Err(AppError::NotFound(msg)) => {
             info!("获取表情包失败: {}", msg);
-            (StatusCode::NOT_FOUND, HeaderMap::new(), Vec::new())
+            (StatusCode::NOT_FOUND, HeaderMap::new(), Vec::new()).into_response()
+        }
+        Err(e @ (AppError::BadRequest(_) | AppError::ServiceUnavailable(_) | AppError::Overloaded { .. })) => {
+            info!("获取表情包失败: {}", e);
+            e.into_response()
         }
         Err(_) => {
             info!("获取表情包失败");
-            (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new(), Vec::new())
+            (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new(), Vec::new()).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct CaptionQuery {
+    /// 顶部文字
+    #[schema(example = "当你写完代码")]
+    #[serde(default)]
+    top: String,
+    /// 底部文字
+    #[schema(example = "发现需求改了")]
+    #[serde(default)]
+    bottom: String,
+    /// 不加水印，需要携带 `watermark.bypass_keys` 或 `admin.api_keys` 中的 API Key，否则忽略
+    #[serde(default)]
+    nowatermark: bool,
+}
+
+/// 生成带上下配文的表情包
+#[utoipa::path(
+    get,
+    path = "/memes/get/{id}/caption",
+    tag = "memes",
+    params(
+        ("id" = u32, Path, description = "表情包ID"),
+        CaptionQuery
+    ),
+    responses(
+        (status = 200, description = "成功返回配文后的图片", content_type = "image/png"),
+        (status = 400, description = "文字过长"),
+        (status = 404, description = "表情包不存在"),
+        (status = 503, description = "未配置字体或图片处理队列已满")
+    )
+)]
+pub async fn get_meme_caption(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(id): Path<u32>,
+    Query(query): Query<CaptionQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    REQUEST_COUNTER.inc();
+    let _timer = crate::metrics::Timer::new(&RESPONSE_TIME);
+    let state = state.read().await;
+
+    let watermark = wants_watermark(&state, query.nowatermark, &headers);
+    let (meme, content, cache) = state.get_captioned(id, &query.top, &query.bottom, watermark).await?;
+    info!(meme_id = meme.id, "Serving captioned meme");
+
+    let mut resp_headers = HeaderMap::new();
+    resp_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("image/png"));
+    insert_bypass_header(&mut resp_headers, &state, watermark);
+    let mut response = (resp_headers, content).into_response();
+    response.extensions_mut().insert(ServedMeme { id: meme.id, cache });
+    Ok(response)
+}
+
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct IconQuery {
+    /// 图标边长（像素）：16、32、48、64、128 或 256，默认 64
+    #[schema(example = 64)]
+    size: Option<u32>,
+    /// 图标格式：png (默认) 或 ico
+    #[serde(default)]
+    format: IconFormat,
+    /// 不加水印，需要携带 `watermark.bypass_keys` 或 `admin.api_keys` 中的 API Key，否则忽略
+    #[serde(default)]
+    nowatermark: bool,
+}
+
+/// 生成表情包图标
+///
+/// 等比缩放后居中放在透明的正方形背景上，适合作为机器人头像或网站图标，结果会被缓存
+#[utoipa::path(
+    get,
+    path = "/memes/get/{id}/icon",
+    tag = "memes",
+    params(
+        ("id" = u32, Path, description = "表情包ID"),
+        IconQuery
+    ),
+    responses(
+        (status = 200, description = "成功返回图标", content_type = "image/png"),
+        (status = 400, description = "不支持的尺寸"),
+        (status = 404, description = "表情包不存在"),
+        (status = 503, description = "图片处理队列已满")
+    )
+)]
+pub async fn get_meme_icon(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(id): Path<u32>,
+    Query(query): Query<IconQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    REQUEST_COUNTER.inc();
+    let _timer = crate::metrics::Timer::new(&RESPONSE_TIME);
+    let state = state.read().await;
+
+    let watermark = wants_watermark(&state, query.nowatermark, &headers);
+    let (meme, content, cache) = state.get_icon(id, query.size.unwrap_or(64), query.format, watermark).await?;
+    info!(meme_id = meme.id, "Serving meme icon");
+
+    let mut resp_headers = HeaderMap::new();
+    resp_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(query.format.mime_type()));
+    insert_bypass_header(&mut resp_headers, &state, watermark);
+    let mut response = (resp_headers, content).into_response();
+    response.extensions_mut().insert(ServedMeme { id: meme.id, cache });
+    Ok(response)
+}
+
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct CollageQuery {
+    /// 逗号分隔的表情包 ID，按顺序逐行排列；省略时随机选择
+    #[schema(example = "1,2,3,4")]
+    ids: Option<String>,
+    /// 随机选择的数量，默认 4，最多 16；指定 `ids` 时忽略
+    #[schema(example = 4)]
+    count: Option<usize>,
+    /// 列数，默认为接近正方形的布局
+    #[schema(example = 2)]
+    cols: Option<u32>,
+    /// 每格的边长（像素），64 到 512，默认 256
+    #[schema(example = 256)]
+    cell: Option<u32>,
+    /// 随机选择时排除 NSFW 表情包，不指定时使用 `content.safe_mode`
+    #[schema(example = true)]
+    safe: Option<bool>,
+    /// 不加水印，需要携带 `watermark.bypass_keys` 或 `admin.api_keys` 中的 API Key，否则忽略
+    #[serde(default)]
+    nowatermark: bool,
+}
+
+/// 生成拼图
+///
+/// 把多个表情包按网格拼成一张 JPEG，适合机器人发送“今日精选”。结果按表情包与布局缓存，
+/// `X-Meme-Ids` 响应头给出拼图中的表情包 ID
+#[utoipa::path(
+    get,
+    path = "/memes/collage",
+    tag = "memes",
+    params(CollageQuery),
+    responses(
+        (status = 200, description = "成功返回拼图", content_type = "image/jpeg"),
+        (status = 400, description = "ID、数量、列数或边长无效"),
+        (status = 404, description = "表情包不存在"),
+        (status = 503, description = "图片处理队列已满")
+    )
+)]
+pub async fn get_collage(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Query(query): Query<CollageQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    REQUEST_COUNTER.inc();
+    let _timer = crate::metrics::Timer::new(&RESPONSE_TIME);
+    let state = state.read().await;
+
+    let ids = match &query.ids {
+        Some(ids) => ids
+            .split(',')
+            .map(|id| id.trim().parse::<u32>())
+            .collect::<std::result::Result<Vec<u32>, _>>()
+            .map_err(|_| AppError::BadRequest(format!("Invalid meme IDs: {}", ids)))?,
+        None => {
+            let count = query.count.unwrap_or(4).clamp(1, MAX_COLLAGE_IMAGES);
+            let safe = query.safe.unwrap_or(state.config().content.safe_mode);
+            state.random_collage_ids(count, safe)
         }
+    };
+    // 默认列数取 ⌈√n⌉，得到接近正方形的网格
+    let cols = query.cols.unwrap_or_else(|| (ids.len() as f64).sqrt().ceil() as u32);
+    let watermark = wants_watermark(&state, query.nowatermark, &headers);
+    let (content, cache) = state.get_collage(&ids, cols, query.cell.unwrap_or(256), watermark).await?;
+    let ids = ids.iter().map(u32::to_string).collect::<Vec<_>>().join(",");
+    info!(meme_ids = %ids, ?cache, "Serving collage");
+
+    let mut resp_headers = HeaderMap::new();
+    resp_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("image/jpeg"));
+    insert_bypass_header(&mut resp_headers, &state, watermark);
+    if let Ok(value) = HeaderValue::from_str(&ids) {
+        resp_headers.insert(MEME_IDS_HEADER, value);
     }
+    Ok((resp_headers, content).into_response())
+}
+
+/// 根据别名获取表情包
+#[utoipa::path(
+    get,
+    path = "/memes/get/by-name/{alias}",
+    tag = "memes",
+    params(
+        ("alias" = String, Path, description = "表情包别名"),
+        GetMemeQuery,
+        ImageQuery
+    ),
+    responses(
+        (status = 200, description = "成功返回指定表情包图片", content_type = "image/*"),
+        (status = 404, description = "别名或表情包不存在"),
+        (status = 500, description = "服务器内部错误")
+    )
+)]
+pub async fn get_meme_by_alias(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(alias): Path<String>,
+    query: Query<GetMemeQuery>,
+    params: ImageParams,
+    headers: HeaderMap,
+) -> Response {
+    // 迁移到稳定 ID 前设置的别名指向旧 ID，与 ID 路径一样跳转到新 ID
+    let id = {
+        let service = state.read().await;
+        service.aliases().resolve(&alias).map(|id| service.redirected_id(id).unwrap_or(id))
+    };
+    match id {
+        Some(id) => get_meme_by_id(State(state), Path(id), query, params, headers).await.into_response(),
+        None => AppError::NotFound(format!("Alias '{}' not found", alias)).into_response(),
+    }
+}
+
+/// 根据内容哈希获取表情包
+///
+/// 数字 ID 由文件名计算，文件改名后会变化；内容哈希不变，适合镜像长期引用
+#[utoipa::path(
+    get,
+    path = "/memes/get/by-hash/{hash}",
+    tag = "memes",
+    params(
+        ("hash" = String, Path, description = "原图的 SHA-256 (十六进制)"),
+        GetMemeQuery,
+        ImageQuery
+    ),
+    responses(
+        (status = 200, description = "成功返回指定表情包图片", content_type = "image/*"),
+        (status = 400, description = "哈希格式无效"),
+        (status = 404, description = "没有该内容的表情包"),
+        (status = 500, description = "服务器内部错误")
+    )
+)]
+pub async fn get_meme_by_hash(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(hash): Path<String>,
+    query: Query<GetMemeQuery>,
+    params: ImageParams,
+    headers: HeaderMap,
+) -> Response {
+    if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return AppError::BadRequest("Hash must be a 64-character hex SHA-256".to_string()).into_response();
+    }
+    let id = state.read().await.id_for_hash(&hash);
+    match id {
+        Some(id) => get_meme_by_id(State(state), Path(id), query, params, headers).await.into_response(),
+        None => AppError::NotFound(format!("No meme with hash {}", hash)).into_response(),
+    }
+}
+
+/// 获取表情包信息
+#[utoipa::path(
+    get,
+    path = "/memes/info/{id}",
+    tag = "memes",
+    params(
+        ("id" = u32, Path, description = "表情包ID"),
+        MemeInfoQuery
+    ),
+    responses(
+        (status = 200, description = "成功返回表情包信息", body = MemeInfo),
+        (status = 404, description = "表情包不存在")
+    )
+)]
+pub async fn get_meme_info(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Path(id): Path<u32>,
+    Query(query): Query<MemeInfoQuery>,
+    headers: HeaderMap,
+) -> Result<Json<MemeInfo>, AppError> {
+    let service = state.read().await;
+    let meme = service.get_meme(id)
+        .ok_or(AppError::MemeNotFound { id })?;
+    let urls = UrlBuilder::from_request(service.config(), &headers);
+
+    let color = service.dominant_color(meme).await;
+
+    Ok(Json(MemeInfo::new(meme, &urls).with_digest(meme, query.digest).with_color(color).with_text(service.meme_text(meme))))
 }
 
 /// 获取表情包总数
@@ -250,17 +1518,153 @@ pub async fn get_meme_count(
     })
 }
 
+/// 深度检查中单项探测的超时时间，超时视为存储挂起
+const DEEP_CHECK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct HealthQuery {
+    /// 是否执行深度检查 (读取存储、检查日志目录可写)
+    #[serde(default)]
+    deep: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct HealthCheck {
+    #[schema(example = "storage_read")]
+    pub name: String,
+    #[schema(example = true)]
+    pub ok: bool,
+    #[schema(example = 3)]
+    pub duration_ms: u64,
+    /// 失败原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DeepHealth {
+    #[schema(example = true)]
+    pub healthy: bool,
+    pub checks: Vec<HealthCheck>,
+}
+
+/// 执行单项探测并计时，超过 [`DEEP_CHECK_TIMEOUT`] 视为失败
+async fn run_check<F>(name: &str, probe: F) -> HealthCheck
+where
+    F: std::future::Future<Output = std::io::Result<()>>,
+{
+    let started = std::time::Instant::now();
+    let error = match tokio::time::timeout(DEEP_CHECK_TIMEOUT, probe).await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some(format!("timed out after {:?}", DEEP_CHECK_TIMEOUT)),
+    };
+
+    HealthCheck {
+        name: name.to_string(),
+        ok: error.is_none(),
+        duration_ms: started.elapsed().as_millis() as u64,
+        error,
+    }
+}
+
 /// 健康检查
+///
+/// 默认只表示进程存活；`?deep=true` 时随机读取一个表情包文件测量存储延迟，
+/// 并检查日志目录可写，用于区分服务存活与存储 (如 NFS) 挂起
 #[utoipa::path(
     get,
     path = "/memes/health",
     tag = "memes",
+    params(HealthQuery),
     responses(
-        (status = 200, description = "服务健康")
+        (status = 200, description = "服务健康；深度检查时返回各项结果", body = DeepHealth),
+        (status = 503, description = "深度检查存在失败项", body = DeepHealth)
     )
 )]
-pub async fn health_check() -> impl IntoResponse {
-    StatusCode::OK
+pub async fn health_check(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Query(query): Query<HealthQuery>,
+) -> Response {
+    if !query.deep {
+        return StatusCode::OK.into_response();
+    }
+
+    // 探测期间不持有锁，避免存储挂起时阻塞重载
+    let (storage, meme_path, log_dir) = {
+        let service = state.read().await;
+        (service.storage(), service.random_meme_path(), PathBuf::from(&service.config().logging.directory))
+    };
+
+    let storage = run_check("storage_read", async {
+        let path = meme_path.ok_or_else(|| std::io::Error::other("no memes loaded"))?;
+        storage.read(&path).await.map(|_| ())
+    })
+    .await;
+
+    let logs = run_check("log_dir_writable", async {
+        let probe = log_dir.join(format!(".health-{}", std::process::id()));
+        tokio::fs::write(&probe, b"ok").await?;
+        tokio::fs::remove_file(&probe).await
+    })
+    .await;
+
+    let checks = vec![storage, logs];
+    let healthy = checks.iter().all(|check| check.ok);
+    let status = if healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(DeepHealth { healthy, checks })).into_response()
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Readiness {
+    #[schema(example = true)]
+    pub ready: bool,
+    #[schema(example = 100)]
+    pub total_memes: usize,
+    pub watcher: WatcherStatus,
+    /// 表情包目录不可用，正在用缓存与上一次的目录提供服务并按退避时间重试扫描；降级不影响 `ready`
+    #[schema(example = false)]
+    pub degraded: bool,
+    /// 没有可用的表情包；开启 `storage.allow_empty` 时仍视为就绪
+    #[schema(example = false)]
+    pub empty: bool,
+    /// 最近一次重载的结果，重载失败时仍继续提供上一次加载的表情包
+    pub last_reload: Option<ReloadReport>,
+}
+
+/// 就绪检查：表情包目录已加载 (开启 `storage.allow_empty` 时可以为空) 且文件监控正常时返回 200，否则返回 503
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "memes",
+    responses(
+        (status = 200, description = "服务就绪", body = Readiness),
+        (status = 503, description = "服务未就绪", body = Readiness)
+    )
+)]
+pub async fn readiness_check(
+    State(state): State<Arc<RwLock<MemeService>>>,
+) -> impl IntoResponse {
+    let service = state.read().await;
+    let watcher = service.watcher_status();
+    let total_memes = service.get_total_memes();
+    let empty = total_memes == 0;
+    let ready = (!empty || service.config().storage.allow_empty) && watcher.healthy;
+
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(Readiness {
+        ready,
+        total_memes,
+        watcher,
+        degraded: service.is_degraded(),
+        empty,
+        last_reload: service.last_reload().cloned(),
+    }))
 }
 
 /// 获取Prometheus指标
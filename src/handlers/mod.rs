@@ -1,2 +1,11 @@
+pub mod admin;
+pub mod assets;
+pub mod cluster;
+#[cfg(feature = "debug")]
+pub mod debug;
+pub mod events;
+pub mod feed;
+pub mod gallery;
 pub mod meme;
+pub mod params;
 pub mod statistics;
\ No newline at end of file
@@ -0,0 +1,187 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::{request::Parts, HeaderMap, HeaderName},
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::services::meme::{CropRegion, Fit, Flip, ImageTransform, OutputFormat};
+use crate::utils::error::AppError;
+
+/// 客户端可接受的最大响应大小请求头
+const MAX_BYTES_HEADER: HeaderName = HeaderName::from_static("x-max-bytes");
+/// 缩放宽高的上限
+pub const MAX_IMAGE_DIMENSION: u32 = 8192;
+
+/// 图片处理的查询参数，由 [`ImageParams`] 统一校验
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImageQuery {
+    /// 缩放宽度 (1-8192)
+    #[param(example = 300)]
+    width: Option<u32>,
+    /// 缩放高度 (1-8192)
+    #[param(example = 300)]
+    height: Option<u32>,
+    /// 同时指定宽高时的缩放方式：contain (默认，保持宽高比)、cover (居中裁剪填满) 或 fill (拉伸)
+    fit: Option<Fit>,
+    /// 输出格式：png (默认)、jpeg 或 webp；随机接口还可以为 `json`，返回表情包信息而不是图片
+    #[param(example = "jpeg")]
+    format: Option<String>,
+    /// JPEG 质量 (1-100)，默认 85，仅在 `format=jpeg` 时有效
+    #[param(example = 80)]
+    quality: Option<u8>,
+    /// 裁剪区域 `x,y,w,h`（原图像素坐标），在旋转与缩放之前执行，必须完整位于图片内
+    #[param(example = "10,20,200,150")]
+    crop: Option<String>,
+    /// 顺时针旋转角度：90、180 或 270
+    #[param(example = 90)]
+    rotate: Option<u16>,
+    /// 翻转方向：h (水平) 或 v (垂直)，在旋转之后执行
+    flip: Option<Flip>,
+    /// 响应大小上限（字节），超出时自动缩小并重新压缩为 JPEG；也可通过 `X-Max-Bytes` 请求头指定
+    #[param(example = 1048576)]
+    max_bytes: Option<usize>,
+    /// 返回原图，不应用 `resize.default_max_dimension` 默认缩放
+    #[serde(default)]
+    original: bool,
+    /// 不加水印，需要携带 `watermark.bypass_keys` 或 `admin.api_keys` 中的 API Key，否则忽略
+    #[serde(default)]
+    nowatermark: bool,
+}
+
+/// 校验后的图片处理参数，参数无效时以 400 拒绝请求
+#[derive(Debug, Clone)]
+pub struct ImageParams {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// 客户端可接受的最大响应大小，`?max_bytes=` 优先于 `X-Max-Bytes` 请求头
+    pub max_bytes: Option<usize>,
+    pub original: bool,
+    /// 请求跳过水印，是否允许由 [`crate::services::watermark::Watermarker`] 根据 API Key 判断
+    pub nowatermark: bool,
+    /// 请求返回 JSON 信息 (`format=json`)
+    pub json: bool,
+    pub transform: ImageTransform,
+}
+
+impl ImageParams {
+    fn parse(query: ImageQuery, headers: &HeaderMap) -> Result<Self, AppError> {
+        for (name, value) in [("width", query.width), ("height", query.height)] {
+            if value.is_some_and(|v| v == 0 || v > MAX_IMAGE_DIMENSION) {
+                return Err(AppError::BadRequest(format!("{} must be between 1 and {}", name, MAX_IMAGE_DIMENSION)));
+            }
+        }
+
+        let (format, json) = match query.format.as_deref() {
+            None => (None, false),
+            Some(value) if value.eq_ignore_ascii_case("json") => (None, true),
+            Some(value) => match OutputFormat::parse(value) {
+                Some(format) => (Some(format), false),
+                None => return Err(AppError::BadRequest("format must be one of png, jpeg, webp, json".to_string())),
+            },
+        };
+
+        if let Some(quality) = query.quality {
+            if !(1..=100).contains(&quality) {
+                return Err(AppError::BadRequest("quality must be between 1 and 100".to_string()));
+            }
+            if format != Some(OutputFormat::Jpeg) {
+                return Err(AppError::BadRequest("quality requires format=jpeg".to_string()));
+            }
+        }
+
+        let fit = query.fit.unwrap_or_default();
+        if fit != Fit::Contain && (query.width.is_none() || query.height.is_none()) {
+            return Err(AppError::BadRequest("fit=cover and fit=fill require both width and height".to_string()));
+        }
+
+        let crop = query.crop.as_deref().map(CropRegion::parse).transpose()?;
+        let transform = ImageTransform::new(query.rotate, query.flip)?
+            .with_crop(crop)
+            .with_output(fit, format, query.quality);
+
+        let max_bytes = query.max_bytes.or_else(|| {
+            headers
+                .get(MAX_BYTES_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse().ok())
+        });
+
+        Ok(Self {
+            width: query.width,
+            height: query.height,
+            max_bytes,
+            original: query.original,
+            nowatermark: query.nowatermark,
+            json,
+            transform,
+        })
+    }
+
+    /// 视频不经过图片处理：忽略缩放、变换与大小上限，原样发送
+    pub fn without_processing(self) -> Self {
+        Self {
+            width: None,
+            height: None,
+            max_bytes: None,
+            original: true,
+            transform: ImageTransform::default(),
+            ..self
+        }
+    }
+
+    /// 重新拼接为查询参数，用于重定向到 `/memes/get/{id}`；`format=json` 不包含在内
+    pub fn to_query_string(&self) -> String {
+        let mut params = Vec::new();
+        if let Some(width) = self.width {
+            params.push(format!("width={}", width));
+        }
+        if let Some(height) = self.height {
+            params.push(format!("height={}", height));
+        }
+        let transform = &self.transform;
+        if transform.fit != Fit::Contain {
+            params.push(format!("fit={}", transform.fit.key()));
+        }
+        if let Some(format) = transform.format {
+            params.push(format!("format={}", format.key()));
+        }
+        if let Some(quality) = transform.quality {
+            params.push(format!("quality={}", quality));
+        }
+        if let Some(crop) = transform.crop {
+            params.push(format!("crop={},{},{},{}", crop.x, crop.y, crop.width, crop.height));
+        }
+        if transform.rotate != 0 {
+            params.push(format!("rotate={}", transform.rotate));
+        }
+        match transform.flip {
+            Some(Flip::Horizontal) => params.push("flip=h".to_string()),
+            Some(Flip::Vertical) => params.push("flip=v".to_string()),
+            None => {}
+        }
+        if let Some(max_bytes) = self.max_bytes {
+            params.push(format!("max_bytes={}", max_bytes));
+        }
+        if self.original {
+            params.push("original=true".to_string());
+        }
+        if self.nowatermark {
+            params.push("nowatermark=true".to_string());
+        }
+        params.join("&")
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ImageParams {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<ImageQuery>::try_from_uri(&parts.uri)
+            .map_err(|e| AppError::BadRequest(e.body_text()))?;
+        ImageParams::parse(query, &parts.headers)
+    }
+}
@@ -1,15 +1,15 @@
 use std::sync::Arc;
 use axum::{
-    extract::State,
+    extract::{Query, State},
     Json,
 };
+use serde::Deserialize;
 use tokio::sync::RwLock;
 use utoipa::ToSchema;
 use crate::services::meme::MemeService;
-use crate::metrics::{
-    SERVICE_UPTIME_SECONDS, TOTAL_MEMES, LAST_UPDATED_TIMESTAMP,
-    CACHE_HITS, CACHE_MISSES, CACHE_HIT_RATE
-};
+use crate::services::stats::CounterSnapshot;
+use crate::services::user_agents::AgentCount;
+use crate::metrics::{SERVICE_UPTIME_SECONDS, TOTAL_MEMES, LAST_UPDATED_TIMESTAMP};
 use time::OffsetDateTime;
 
 #[derive(serde::Serialize, ToSchema)]
@@ -36,6 +36,59 @@ pub struct Statistics {
     cache_misses: u64,
     #[schema(example = 80.0)]
     cache_hit_rate: f64,
+    /// 本次启动以来的计数
+    since_restart: CounterTotals,
+    /// 包含历次运行的累计计数
+    lifetime: CounterTotals,
+    /// 最近一小时内有请求的客户端 IP 数 (近似值)
+    #[schema(example = 42)]
+    unique_clients_last_hour: u64,
+    /// 本次启动以来按 User-Agent 分组的请求数 (机器人框架、浏览器、命令行工具等)，按请求数降序
+    user_agents: Vec<AgentCount>,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+pub struct CounterTotals {
+    #[schema(example = 1000)]
+    total_requests: u64,
+    #[schema(example = 800)]
+    cache_hits: u64,
+    #[schema(example = 200)]
+    cache_misses: u64,
+    #[schema(example = 80.0)]
+    cache_hit_rate: f64,
+    #[schema(example = 3600)]
+    uptime_seconds: u64,
+    /// 首次启动时间
+    #[schema(example = "2024-01-01T00:00:00Z")]
+    first_started_at: String,
+    #[schema(example = 3)]
+    restarts: u64,
+}
+
+impl From<CounterSnapshot> for CounterTotals {
+    fn from(snapshot: CounterSnapshot) -> Self {
+        let total_cache_requests = snapshot.cache_hits + snapshot.cache_misses;
+        let cache_hit_rate = if total_cache_requests > 0 {
+            (snapshot.cache_hits as f64 / total_cache_requests as f64) * 100.0
+        } else {
+            0.0
+        };
+        let first_started_at = OffsetDateTime::from_unix_timestamp(snapshot.first_started_at as i64)
+            .ok()
+            .and_then(|dt| dt.format(&time::format_description::well_known::Rfc3339).ok())
+            .unwrap_or_else(|| "Unknown".to_string());
+
+        Self {
+            total_requests: snapshot.requests,
+            cache_hits: snapshot.cache_hits,
+            cache_misses: snapshot.cache_misses,
+            cache_hit_rate,
+            uptime_seconds: snapshot.uptime_secs,
+            first_started_at,
+            restarts: snapshot.restarts,
+        }
+    }
 }
 
 /// 获取服务器统计信息
@@ -93,11 +146,6 @@ pub async fn get_statistics(
     SERVICE_UPTIME_SECONDS.set(service_uptime as f64);
     TOTAL_MEMES.set(service.get_total_memes() as f64);
     LAST_UPDATED_TIMESTAMP.set(last_updated_timestamp as f64);
-    CACHE_HITS.reset();
-    CACHE_HITS.inc_by(cache_hits as f64);
-    CACHE_MISSES.reset();
-    CACHE_MISSES.inc_by(cache_misses as f64);
-    CACHE_HIT_RATE.set(cache_hit_rate / 100.0); // 转换为 0-1 范围
     
     Json(Statistics {
         total_requests: service.get_request_count(),
@@ -111,5 +159,63 @@ pub async fn get_statistics(
         cache_hits,
         cache_misses,
         cache_hit_rate,
+        since_restart: service.counters().since_restart().into(),
+        lifetime: service.counters().lifetime().into(),
+        unique_clients_last_hour: service.clients().unique_clients_last_hour().await,
+        user_agents: service.clients().user_agents(),
     })
+}
+
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct TrendingQuery {
+    /// 返回的表情包数量，默认 10，最大 100
+    #[schema(example = 10)]
+    limit: Option<usize>,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+pub struct TrendingMeme {
+    #[schema(example = 1)]
+    id: u32,
+    #[schema(example = "funny_meme.jpg")]
+    filename: String,
+    #[schema(example = 12.5)]
+    score: f64,
+    #[schema(example = 300)]
+    total_hits: u64,
+}
+
+/// 获取近期热门表情包
+#[utoipa::path(
+    get,
+    path = "/statistics/trending",
+    tag = "statistics",
+    params(TrendingQuery),
+    responses(
+        (status = 200, description = "成功返回按热度排序的表情包", body = Vec<TrendingMeme>)
+    )
+)]
+pub async fn get_trending(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    Query(query): Query<TrendingQuery>,
+) -> Json<Vec<TrendingMeme>> {
+    let limit = query.limit.unwrap_or(10).min(100);
+    let service = state.read().await;
+
+    // 已被删除的表情包不再出现在热门列表中
+    let trending = service.get_meme_stats()
+        .trending(service.get_total_memes())
+        .into_iter()
+        .filter_map(|(id, score, total_hits)| {
+            service.get_meme(id).map(|meme| TrendingMeme {
+                id,
+                filename: meme.filename.clone(),
+                score,
+                total_hits,
+            })
+        })
+        .take(limit)
+        .collect();
+
+    Json(trending)
 }
\ No newline at end of file
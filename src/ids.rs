@@ -0,0 +1,158 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use serde::Serialize;
+use crate::config::Config;
+use crate::services::ids::{IdRedirects, IdRegistry};
+use crate::services::meme::{hash_content, meme_id_for, normalize_filename, salted_meme_id};
+use crate::services::storage::{FsStorage, Storage};
+use crate::utils::media;
+
+/// `ids migrate` 的结果，以 JSON 输出到标准输出
+#[derive(Debug, Default, Serialize)]
+pub struct MigrateReport {
+    pub ok: bool,
+    pub id_map_file: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirects_file: Option<String>,
+    /// 表情包目录中的图片数
+    pub files: usize,
+    /// 迁移后 ID 映射表中的记录数
+    pub mapped: usize,
+    /// 新旧 ID 不同的记录数
+    pub redirects: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 命令行第一个位置参数为 `ids` 时返回其后的参数 (跳过 `--profile`)
+pub fn command_args() -> Option<Vec<String>> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--profile" {
+            args.next();
+            continue;
+        }
+        if arg.starts_with("--profile=") {
+            continue;
+        }
+        return (arg == "ids").then(|| args.collect());
+    }
+    None
+}
+
+/// 执行 `ids` 子命令，返回进程退出码
+pub async fn run(config_path: &str, profile: Option<&str>, args: &[String]) -> i32 {
+    let (command, flags) = args.split_first().map_or(("", &[][..]), |(command, flags)| (command.as_str(), flags));
+    let write_redirects = match (command, flags) {
+        ("migrate", []) => false,
+        ("migrate", [flag]) if flag == "--redirects" => true,
+        _ => {
+            eprintln!("用法: ids migrate [--redirects]");
+            return 2;
+        }
+    };
+
+    let report = migrate(config_path, profile, write_redirects).await;
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("序列化迁移报告失败: {}", e),
+    }
+    if report.ok { 0 } else { 1 }
+}
+
+/// 为目录中的每个文件分配稳定 ID，并记录按文件名计算的旧 ID；重复执行不会改变已分配的 ID
+async fn migrate(config_path: &str, profile: Option<&str>, write_redirects: bool) -> MigrateReport {
+    let mut report = MigrateReport::default();
+    let config = match Config::load_from_file(config_path, profile) {
+        Ok(config) => config,
+        Err(e) => {
+            report.error = Some(e.to_string());
+            return report;
+        }
+    };
+    let storage_config = &config.storage;
+    report.id_map_file = storage_config.id_map_file.clone();
+
+    let files = match list_files(&config).await {
+        Ok(files) => files,
+        Err(e) => {
+            report.error = Some(e);
+            return report;
+        }
+    };
+    report.files = files.len();
+
+    let old_ids = filename_ids(files.iter().map(|(filename, _)| filename.as_str()));
+    let entries: Vec<(&str, Option<&str>)> = files.iter()
+        .map(|(filename, hash)| (filename.as_str(), Some(hash.as_str())))
+        .collect();
+    let registry = match IdRegistry::load(&storage_config.id_map_file) {
+        Ok(registry) => registry,
+        Err(e) => {
+            report.error = Some(e.to_string());
+            return report;
+        }
+    };
+    if let Err(e) = registry.assign_with(&entries, |filename| old_ids.get(filename).copied()) {
+        report.error = Some(e.to_string());
+        return report;
+    }
+    report.mapped = registry.entry_count();
+
+    let redirects = registry.redirects();
+    report.redirects = redirects.len();
+    if write_redirects {
+        report.redirects_file = Some(storage_config.id_redirects_file.clone());
+        if let Err(e) = IdRedirects::save(&storage_config.id_redirects_file, &redirects) {
+            report.error = Some(e.to_string());
+            return report;
+        }
+    }
+
+    report.ok = true;
+    report
+}
+
+/// 列出会被加载的图片：`(NFC 形式的相对路径, 内容哈希)`
+async fn list_files(config: &Config) -> Result<Vec<(String, String)>, String> {
+    let storage_config = &config.storage;
+    let storage = FsStorage::new(storage_config);
+    let paths = storage.list().await.map_err(|e| format!("Failed to list memes directory: {}", e))?;
+
+    let mut files = Vec::new();
+    for path in paths {
+        let basename = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if media::is_hidden_or_temp(&basename) || !media::extension_allowed(&path, &storage_config.allowed_extensions) {
+            continue;
+        }
+        let relative = path.strip_prefix(&storage_config.memes_dir)
+            .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+            .map_err(|_| format!("File {} is outside the memes directory", path.display()))?;
+        let content = storage.read(&path).await.map_err(|e| format!("Failed to read {}: {}", relative, e))?;
+        files.push((normalize_filename(&relative), hash_content(&content)));
+    }
+    Ok(files)
+}
+
+/// 按文件名计算的 ID，冲突时与全新启动时一样按文件名顺序保留第一个，其余改用加盐的 ID
+fn filename_ids<'a>(filenames: impl Iterator<Item = &'a str>) -> HashMap<String, u32> {
+    let mut by_id: BTreeMap<u32, Vec<&str>> = BTreeMap::new();
+    for filename in filenames {
+        by_id.entry(meme_id_for(filename)).or_default().push(filename);
+    }
+
+    let mut used: HashSet<u32> = by_id.keys().copied().collect();
+    let mut ids = HashMap::new();
+    for (id, mut filenames) in by_id {
+        filenames.sort_unstable();
+        ids.insert(filenames[0].to_string(), id);
+        for filename in &filenames[1..] {
+            let new_id = (1..)
+                .map(|salt| salted_meme_id(filename, salt))
+                .find(|candidate| !used.contains(candidate))
+                .expect("u32 ID 空间耗尽");
+            used.insert(new_id);
+            ids.insert(filename.to_string(), new_id);
+        }
+    }
+    ids
+}
@@ -0,0 +1,49 @@
+//! 表情包 API 服务，既可以作为独立进程运行，也可以作为库嵌入到其他 axum 程序中
+//! (例如 QQ 机器人)，与宿主共用一个进程和端口。
+//!
+//! ```no_run
+//! use std::net::SocketAddr;
+//! use jiangtokoto_server::{build_router, Config, MemeService};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let config = Config::load_from_file("config.yml", None)?;
+//!     let state = MemeService::new(config.clone()).await?;
+//!     let memes = build_router(&config, state).await?.app;
+//!
+//!     let app = axum::Router::new()
+//!         .route("/bot/ping", axum::routing::get(|| async { "pong" }))
+//!         .merge(memes);
+//!     let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await?;
+//!     axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! 返回的路由已附加全部中间件与共享状态，可以直接 `merge` 或 `nest` 到宿主的路由中；
+//! 需要 `/metrics` 输出时宿主应先调用一次 [`metrics::init_metrics`]。
+//!
+//! 宿主必须像上例一样用 `into_make_service_with_connect_info::<SocketAddr>()` 启动服务：
+//! 限流、按 IP 统计与 `server.proxy.trusted_proxies` 都依赖连接的对端地址，缺少时所有请求
+//! 只能按转发头识别，没有转发头的请求共用同一个限流桶。
+
+pub mod app;
+pub mod config;
+pub mod doctor;
+pub mod ids;
+pub mod logging;
+pub mod metrics;
+pub mod middleware;
+pub mod server;
+pub mod services;
+pub mod utils;
+mod handlers;
+mod models;
+mod openapi;
+#[cfg(feature = "graphql")]
+mod graphql;
+
+pub use app::{build_router, AppBuilder, AppRouters};
+pub use config::Config;
+pub use services::meme::MemeService;
+pub use utils::error::AppError;
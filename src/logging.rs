@@ -0,0 +1,229 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing_appender::{
+    non_blocking::{NonBlocking, WorkerGuard},
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{fmt::writer::BoxMakeWriter, reload, EnvFilter, Registry};
+use crate::config::{LogRotation, LoggingConfig};
+use crate::utils::error::{AppError, Result};
+
+/// 旧日志清理任务的执行间隔
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(600);
+
+/// 按配置的轮转策略创建文件日志 writer
+pub fn file_writer(config: &LoggingConfig) -> io::Result<BoxMakeWriter> {
+    let rotation = match config.rotation {
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Never => Rotation::NEVER,
+        LogRotation::Size => {
+            let writer = SizeRollingWriter::open(
+                Path::new(&config.directory),
+                &config.file_prefix,
+                config.max_size_mb * 1024 * 1024,
+            )?;
+            return Ok(BoxMakeWriter::new(Mutex::new(writer)));
+        }
+    };
+
+    let appender = RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(&config.file_prefix)
+        .filename_suffix("log")
+        .build(&config.directory)
+        .map_err(io::Error::other)?;
+    Ok(BoxMakeWriter::new(appender))
+}
+
+/// 运行时可调整的日志过滤规则，供 `/admin/log-level` 使用
+#[derive(Clone)]
+pub struct LogLevel {
+    handle: reload::Handle<EnvFilter, Registry>,
+}
+
+impl LogLevel {
+    /// 创建可替换的过滤层，`directives` 为 `RUST_LOG` 格式的初始规则 (如 `info` 或 `info,jiangtokoto_server=debug`)
+    pub fn new(directives: &str) -> (reload::Layer<EnvFilter, Registry>, Self) {
+        let (layer, handle) = reload::Layer::new(EnvFilter::new(directives));
+        (layer, Self { handle })
+    }
+
+    /// 当前生效的过滤规则
+    pub fn current(&self) -> Result<String> {
+        self.handle
+            .with_current(|filter| filter.to_string())
+            .map_err(|e| AppError::Internal(format!("Failed to read log level: {}", e)))
+    }
+
+    /// 替换过滤规则，规则无法解析时返回 BadRequest 且不做修改；返回新规则
+    pub fn set(&self, directives: &str) -> Result<String> {
+        let filter = EnvFilter::try_new(directives.trim())
+            .map_err(|e| AppError::BadRequest(format!("Invalid log level '{}': {}", directives, e)))?;
+        let applied = filter.to_string();
+        self.handle
+            .reload(filter)
+            .map_err(|e| AppError::Internal(format!("Failed to update log level: {}", e)))?;
+        Ok(applied)
+    }
+}
+
+/// 访问日志使用的配置：沿用应用日志的目录、保留数量与大小上限，前缀与轮转策略取自 `access_log`
+pub fn access_log_config(config: &LoggingConfig) -> LoggingConfig {
+    LoggingConfig {
+        file_prefix: config.access_log.file_prefix.clone(),
+        rotation: config.access_log.rotation,
+        ..config.clone()
+    }
+}
+
+/// 创建访问日志 writer，由后台线程写入文件，请求处理不等待磁盘 I/O；
+/// 返回的 guard 需要保留到进程退出，丢弃时会刷新剩余的日志
+pub fn access_writer(config: &LoggingConfig) -> io::Result<(NonBlocking, WorkerGuard)> {
+    let config = access_log_config(config);
+    let rotation = match config.rotation {
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Never => Rotation::NEVER,
+        LogRotation::Size => {
+            let writer = SizeRollingWriter::open(
+                Path::new(&config.directory),
+                &config.file_prefix,
+                config.max_size_mb * 1024 * 1024,
+            )?;
+            return Ok(tracing_appender::non_blocking(writer));
+        }
+    };
+
+    let appender = RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(&config.file_prefix)
+        .filename_suffix("log")
+        .build(&config.directory)
+        .map_err(io::Error::other)?;
+    Ok(tracing_appender::non_blocking(appender))
+}
+
+/// 按文件大小轮转的日志 writer
+///
+/// 当前日志写入 `<prefix>.log`，超过大小上限后重命名为 `<prefix>.<毫秒时间戳>.log` 并重新打开
+struct SizeRollingWriter {
+    path: PathBuf,
+    directory: PathBuf,
+    prefix: String,
+    max_bytes: u64,
+    file: File,
+    written: u64,
+}
+
+impl SizeRollingWriter {
+    fn open(directory: &Path, prefix: &str, max_bytes: u64) -> io::Result<Self> {
+        let path = directory.join(format!("{}.log", prefix));
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            directory: directory.to_path_buf(),
+            prefix: prefix.to_string(),
+            max_bytes,
+            file,
+            written,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let rotated = self.directory.join(format!("{}.{}.log", self.prefix, millis));
+        fs::rename(&self.path, rotated)?;
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for SizeRollingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            // 轮转失败时继续写入当前文件，不丢日志
+            if let Err(e) = self.rotate() {
+                eprintln!("日志文件轮转失败: {}", e);
+            }
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// 启动旧日志清理任务，只保留最新的 `max_files` 个日志文件
+pub fn start_cleanup_task(config: &LoggingConfig) {
+    if config.max_files == 0 {
+        return;
+    }
+
+    let directory = PathBuf::from(&config.directory);
+    let prefix = config.file_prefix.clone();
+    let max_files = config.max_files;
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+        loop {
+            interval.tick().await;
+            let directory = directory.clone();
+            let prefix = prefix.clone();
+            let result = tokio::task::spawn_blocking(move || cleanup(&directory, &prefix, max_files)).await;
+            match result {
+                Ok(Ok(0)) => {}
+                Ok(Ok(removed)) => tracing::info!("已清理 {} 个旧日志文件", removed),
+                Ok(Err(e)) => tracing::error!("清理旧日志文件失败: {}", e),
+                Err(e) => tracing::error!("日志清理任务执行失败: {}", e),
+            }
+        }
+    });
+}
+
+/// 按修改时间删除最旧的日志文件，返回删除的数量
+fn cleanup(directory: &Path, prefix: &str, max_files: usize) -> io::Result<usize> {
+    let mut files: Vec<(SystemTime, PathBuf)> = fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with(prefix) && name.ends_with(".log")
+        })
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+
+    if files.len() <= max_files {
+        return Ok(0);
+    }
+
+    // 最新的文件在前
+    files.sort_by_key(|file| std::cmp::Reverse(file.0));
+    let mut removed = 0;
+    for (_, path) in files.into_iter().skip(max_files) {
+        match fs::remove_file(&path) {
+            Ok(()) => removed += 1,
+            Err(e) => tracing::warn!("删除日志文件 {} 失败: {}", path.display(), e),
+        }
+    }
+    Ok(removed)
+}
@@ -1,44 +1,26 @@
-use axum::{
-    routing::get,
-    Router,
-    extract::ConnectInfo,
-};
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
-use tower_http::{
-    trace::{TraceLayer, OnResponse},
-    cors::{CorsLayer, Any},
-};
-use tracing::{Level, info, Span};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use tracing_appender::rolling::{RollingFileAppender, Rotation};
-use crate::utils::error::AppError;
-
-#[derive(Clone)]
-struct CustomOnResponse;
-
-impl<B> OnResponse<B> for CustomOnResponse {
-    fn on_response(self, response: &axum::response::Response<B>, latency: Duration, span: &Span) {
-        let status = response.status();
-        info!(parent: span,
-            status = %status,
-            latency = ?latency,
-            "响应完成"
-        );
-    }
-}
-
-mod config;
-mod handlers;
-mod models;
-mod services;
-mod utils;
-mod openapi;
-mod metrics;
+use jiangtokoto_server::{app, config, doctor, ids, logging, metrics, middleware, server, services, AppError};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // 加载配置文件，路径可由 PTK_CONFIG 指定，PTK_<段>__<字段> 环境变量覆盖其中的配置项
+    let config_path = std::env::var("PTK_CONFIG").unwrap_or_else(|_| "config.yml".to_string());
+
+    // 配置 profile 由 --profile 或 APP_ENV 指定
+    let profile = config::selected_profile();
+
+    // ids migrate: 迁移到稳定 ID，输出 JSON 报告后退出
+    if let Some(args) = ids::command_args() {
+        std::process::exit(ids::run(&config_path, profile.as_deref(), &args).await);
+    }
+
+    // --check: 只做部署前自检，输出 JSON 报告后退出
+    if std::env::args().skip(1).any(|arg| arg == "--check") {
+        std::process::exit(doctor::run(&config_path, profile.as_deref()).await);
+    }
+
     // 初始化指标
     metrics::init_metrics();
     
@@ -46,26 +28,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let start_time = std::time::SystemTime::now();
     metrics::set_service_start_time(start_time);
     
-    // 加载配置文件
-    let config = config::Config::load_from_file("config.yml")?;
+    let config = config::Config::load_from_file(config_path, profile.as_deref())?;
     
     // 确保日志目录存在
     std::fs::create_dir_all(&config.logging.directory)?;
 
-    // 设置文件日志appender
-    let file_appender = RollingFileAppender::builder()
-        .rotation(Rotation::DAILY)
-        .filename_prefix(&config.logging.file_prefix)
-        .filename_suffix("log")
-        .build(&config.logging.directory)  // 使用 build() 方法直接指定目录
+    // 设置文件日志appender，按配置的策略轮转
+    let file_appender = logging::file_writer(&config.logging)
         .expect("创建日志文件失败");
 
     // 初始化日志系统
     let log_level = std::env::var("LOG_LEVEL")
         .unwrap_or_else(|_| "info".to_string());
     
+    // 过滤层可在运行时通过 /admin/log-level 替换
+    let (filter, log_level) = logging::LogLevel::new(&log_level);
     tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(log_level))
+        .with(filter)
         .with(tracing_subscriber::fmt::layer()
             .with_writer(file_appender)
             .with_ansi(false)
@@ -78,77 +57,58 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .init();
 
     tracing::info!("日志系统初始化完成");
+    logging::start_cleanup_task(&config.logging);
+
+    // 独立的访问日志，guard 需保留到进程退出
+    let (access_log, _access_log_guard) = if config.logging.access_log.enabled {
+        let (writer, guard) = logging::access_writer(&config.logging)?;
+        logging::start_cleanup_task(&logging::access_log_config(&config.logging));
+        let access_log = middleware::access_log::AccessLog::new(writer, config.logging.access_log.format);
+        (Some(Arc::new(access_log)), Some(guard))
+    } else {
+        (None, None)
+    };
     tracing::info!("Configuration loaded successfully");
 
     // 初始化 MemeService
-    let state = services::meme::MemeService::new(
-        &config.storage.memes_dir,
-        config.cache.max_size,
-        config.cache.ttl_secs,
-    ).await?;
-
-    // 配置 CORS
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
-
-    // 构建应用路由
-    let config_clone = Arc::new(config.clone());
-    let app = Router::new()
-        .route("/", get(|| async { axum::response::Redirect::to("/swagger-ui") }))
-        .route("/memes/random", get(handlers::meme::random_meme))
-        .route("/memes/list", get(handlers::meme::list_memes))
-        .route("/memes/get/:id", get(handlers::meme::get_meme_by_id))
-        .route("/memes/health", get(handlers::meme::health_check))
-        .route("/memes/count", get(handlers::meme::get_meme_count))
-        .route("/statistics", get(handlers::statistics::get_statistics))
-        .route("/metrics", get(handlers::meme::get_metrics))
-        .merge(openapi::create_swagger_ui(config.swagger.clone()))
-        .layer(
-            TraceLayer::new_for_http()
-                .make_span_with(move |request: &axum::http::Request<_>| {
-                    let remote_addr = if config_clone.server.proxy.enabled {
-                        request
-                            .headers()
-                            .get(&config_clone.server.proxy.ip_header)
-                            .and_then(|h| h.to_str().ok())
-                            .map(|s| s.split(',').next().unwrap_or(s).trim().to_string())
-                            .unwrap_or_else(|| "unknown".to_string())
-                    } else {
-                        request
-                            .extensions()
-                            .get::<ConnectInfo<SocketAddr>>()
-                            .map(|ci| ci.0.ip().to_string())
-                            .unwrap_or_else(|| "unknown".to_string())
-                    };
-
-                    tracing::span!(
-                        Level::INFO,
-                        "请求",
-                        method = %request.method(),
-                        uri = %request.uri(),
-                        ip = %remote_addr,
-                    )
-                })
-                .on_response(CustomOnResponse)
-        )
-        .layer(cors)
-        .with_state(state);
+    let state = services::meme::MemeService::new(Arc::clone(&config)).await?;
+
+    // 在开始接受请求之前预热缓存
+    if config.cache.warmup {
+        state.read().await.warmup_cache(config.cache.warmup_count).await;
+    }
+
+    // 组装路由与中间件
+    let app::AppRouters { app, admin: admin_app } = app::AppBuilder::new(&config, state)
+        .log_level(log_level)
+        .access_log(access_log)
+        .build()
+        .await?;
 
     // 设置服务器地址
     let addr: SocketAddr = format!("{}:{}", config.server.host, config.server.port)
         .parse()
         .map_err(|e| AppError::Internal(format!("Invalid address: {}", e)))?;
-    tracing::info!("服务器启动在 {}", addr);
 
-    // 启动服务器
+    // 启动服务器 (同时支持 HTTP/1.1 与 HTTP/2)
     let listener = tokio::net::TcpListener::bind(addr).await?;
     tracing::info!("服务器启动在 {}", addr);
-    axum::serve(
-        listener,
-        app.into_make_service_with_connect_info::<SocketAddr>()
-    ).await?;
+
+    match (admin_app, config.server.admin_port) {
+        (Some(admin_app), Some(admin_port)) => {
+            let admin_addr: SocketAddr = format!("{}:{}", config.server.admin_host, admin_port)
+                .parse()
+                .map_err(|e| AppError::Internal(format!("Invalid admin address: {}", e)))?;
+            let admin_listener = tokio::net::TcpListener::bind(admin_addr).await?;
+            tracing::info!("管理接口启动在 {}", admin_addr);
+
+            tokio::try_join!(
+                server::serve(listener, app, &config.server.limits),
+                server::serve(admin_listener, admin_app, &config.server.limits),
+            )?;
+        }
+        _ => server::serve(listener, app, &config.server.limits).await?,
+    }
 
     Ok(())
-}
\ No newline at end of file
+}
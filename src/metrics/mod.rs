@@ -1,4 +1,4 @@
-use prometheus::{Counter, Histogram, Gauge, Registry, Encoder, TextEncoder, Opts, HistogramOpts};
+use prometheus::{Counter, CounterVec, Histogram, Gauge, GaugeVec, Registry, Encoder, TextEncoder, Opts, HistogramOpts};
 use lazy_static::lazy_static;
 use std::time::{Instant, SystemTime};
 use std::sync::OnceLock;
@@ -13,26 +13,120 @@ lazy_static! {
         Opts::new("meme_requests_total", "Total number of meme requests")
     ).unwrap();
     
+    // TODO: prometheus 0.13 不支持 exemplar，接入 OpenTelemetry 后为延迟直方图附加 trace ID
     pub static ref RESPONSE_TIME: Histogram = Histogram::with_opts(
         HistogramOpts::new("meme_response_duration_seconds", "Response time for meme requests")
     ).unwrap();
     
-    pub static ref CACHE_HIT_RATE: Gauge = Gauge::with_opts(
-        Opts::new("meme_cache_hit_rate", "Cache hit rate")
+    // 原图缓存与压缩图缓存分开统计，标签 cache=content|resized
+    pub static ref CACHE_ENTRIES: GaugeVec = GaugeVec::new(
+        Opts::new("meme_cache_entries", "Number of entries in each cache"),
+        &["cache"]
     ).unwrap();
     
-    pub static ref CACHE_SIZE: Gauge = Gauge::with_opts(
-        Opts::new("meme_cache_size", "Current cache size")
+    pub static ref CACHE_BYTES: GaugeVec = GaugeVec::new(
+        Opts::new("meme_cache_bytes", "Estimated bytes held by each cache"),
+        &["cache"]
     ).unwrap();
     
     pub static ref ACTIVE_CONNECTIONS: Gauge = Gauge::with_opts(
         Opts::new("meme_active_connections", "Number of active connections")
     ).unwrap();
     
+    pub static ref CONNECTIONS_TOTAL: Counter = Counter::with_opts(
+        Opts::new("meme_connections_total", "Total number of accepted connections")
+    ).unwrap();
+    
+    pub static ref REQUESTS_BY_PROTOCOL: CounterVec = CounterVec::new(
+        Opts::new("meme_requests_by_protocol_total", "Total number of HTTP requests by protocol version"),
+        &["protocol"]
+    ).unwrap();
+    
+    pub static ref REQUESTS_BY_STATUS: CounterVec = CounterVec::new(
+        Opts::new("meme_requests_by_status", "Total number of HTTP responses by status class"),
+        &["status"]
+    ).unwrap();
+    
+    pub static ref REQUESTS_BY_USER_AGENT: CounterVec = CounterVec::new(
+        Opts::new("meme_requests_by_user_agent_total", "Total number of public API requests by client User-Agent family"),
+        &["category", "client"]
+    ).unwrap();
+    
+    pub static ref SLOW_REQUESTS: Counter = Counter::with_opts(
+        Opts::new("slow_requests_total", "Total number of requests exceeding the slow request threshold")
+    ).unwrap();
+    
     pub static ref IMAGE_PROCESSING_TIME: Histogram = Histogram::with_opts(
         HistogramOpts::new("meme_image_processing_duration_seconds", "Time spent processing images")
     ).unwrap();
     
+    pub static ref IMAGE_QUEUE_DEPTH: Gauge = Gauge::with_opts(
+        Opts::new("meme_image_queue_depth", "Number of image processing jobs queued or running")
+    ).unwrap();
+    
+    pub static ref IMAGE_QUEUE_REJECTED: Counter = Counter::with_opts(
+        Opts::new("meme_image_queue_rejected_total", "Total number of image processing jobs rejected due to a full queue")
+    ).unwrap();
+
+    pub static ref IMAGE_QUEUE_TIMEOUTS: Counter = Counter::with_opts(
+        Opts::new("meme_image_queue_timeouts_total", "Total number of image processing jobs rejected after waiting too long for a slot")
+    ).unwrap();
+    
+    pub static ref PROCESS_RSS_BYTES: Gauge = Gauge::with_opts(
+        Opts::new("process_resident_memory_bytes_sampled", "Resident memory of the process as sampled by the load shedder")
+    ).unwrap();
+    
+    pub static ref SHED_REQUESTS: Counter = Counter::with_opts(
+        Opts::new("meme_shed_requests_total", "Total number of resize requests rejected under memory pressure")
+    ).unwrap();
+    
+    // 按分片处理的请求，标签 result=redirected|proxied|fallback
+    pub static ref SHARD_REQUESTS: CounterVec = CounterVec::new(
+        Opts::new("meme_shard_requests_total", "Total number of image requests handed to the owning shard"),
+        &["result"]
+    ).unwrap();
+
+    pub static ref HOTLINKS_BLOCKED: Counter = Counter::with_opts(
+        Opts::new("meme_hotlinks_blocked_total", "Total number of image requests rejected by the referer policy")
+    ).unwrap();
+    
+    pub static ref STREAMED_RESPONSES: Counter = Counter::with_opts(
+        Opts::new("meme_streamed_responses_total", "Total number of original images sent directly from disk without caching")
+    ).unwrap();
+
+    pub static ref COALESCED_READS: Counter = Counter::with_opts(
+        Opts::new("meme_coalesced_reads_total", "Total number of cold reads served by waiting for an in-flight read of the same meme")
+    ).unwrap();
+
+    pub static ref MEME_REPORTS: CounterVec = CounterVec::new(
+        Opts::new("meme_reports_total", "Total number of user reports recorded by reason"),
+        &["reason"]
+    ).unwrap();
+
+    pub static ref MEMES_QUARANTINED: Counter = Counter::with_opts(
+        Opts::new("meme_quarantined_total", "Total number of memes moved back to pending after reaching the report threshold")
+    ).unwrap();
+
+    pub static ref RATE_LIMITED_REQUESTS: Counter = Counter::with_opts(
+        Opts::new("rate_limited_requests_total", "Total number of requests rejected by the per-client rate limit")
+    ).unwrap();
+
+    pub static ref RANDOM_SESSIONS: Gauge = Gauge::with_opts(
+        Opts::new("meme_random_sessions", "Number of random sessions currently remembered for deduplication")
+    ).unwrap();
+
+    pub static ref ID_REDIRECTS: Counter = Counter::with_opts(
+        Opts::new("meme_id_redirects_total", "Total number of requests for pre-migration meme IDs redirected to the new ID")
+    ).unwrap();
+
+    pub static ref PINNED_MEMES: Gauge = Gauge::with_opts(
+        Opts::new("meme_pinned_total", "Number of memes pinned in memory")
+    ).unwrap();
+
+    pub static ref PINNED_BYTES: Gauge = Gauge::with_opts(
+        Opts::new("meme_pinned_bytes", "Total size in bytes of pinned meme contents")
+    ).unwrap();
+    
     // 新增的统计指标
     pub static ref SERVICE_UPTIME_SECONDS: Gauge = Gauge::with_opts(
         Opts::new("service_uptime_seconds", "Service uptime in seconds")
@@ -46,22 +140,108 @@ lazy_static! {
         Opts::new("last_updated_timestamp", "Last updated timestamp (Unix timestamp)")
     ).unwrap();
     
-    pub static ref CACHE_HITS: Counter = Counter::with_opts(
-        Opts::new("cache_hits_total", "Total number of cache hits")
+    pub static ref CACHE_HITS: CounterVec = CounterVec::new(
+        Opts::new("meme_cache_hits_total", "Total number of cache hits by cache"),
+        &["cache"]
+    ).unwrap();
+    
+    pub static ref CACHE_MISSES: CounterVec = CounterVec::new(
+        Opts::new("meme_cache_misses_total", "Total number of cache misses by cache"),
+        &["cache"]
+    ).unwrap();
+    
+    // 最近一个统计窗口 (`cache.stats_interval_secs`) 内的命中率
+    pub static ref CACHE_HIT_RATIO: GaugeVec = GaugeVec::new(
+        Opts::new("meme_cache_hit_ratio", "Cache hit ratio over the last stats window by cache"),
+        &["cache"]
+    ).unwrap();
+
+    pub static ref CACHE_EVICTIONS: CounterVec = CounterVec::new(
+        Opts::new("meme_cache_evictions_total", "Total number of entries evicted for capacity or expiry by cache"),
+        &["cache"]
+    ).unwrap();
+    
+    pub static ref CACHE_EARLY_REFRESHES: CounterVec = CounterVec::new(
+        Opts::new("meme_cache_early_refreshes_total", "Total number of cache entries refreshed before expiry by cache"),
+        &["cache"]
+    ).unwrap();
+    
+    pub static ref RELOADS_TOTAL: CounterVec = CounterVec::new(
+        Opts::new("reloads_total", "Total number of meme catalog reloads by result"),
+        &["result"]
+    ).unwrap();
+    
+    pub static ref RELOAD_DURATION: Histogram = Histogram::with_opts(
+        HistogramOpts::new("reload_duration_seconds", "Time spent reloading the meme catalog")
+            .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0])
+    ).unwrap();
+    
+    pub static ref SKIPPED_FILES: CounterVec = CounterVec::new(
+        Opts::new("meme_skipped_files_total", "Files in memes_dir skipped during reload"),
+        &["reason"]
+    ).unwrap();
+    
+    pub static ref DUPLICATE_FILES: Gauge = Gauge::with_opts(
+        Opts::new("meme_duplicate_files", "Number of files merged into another meme because of identical content")
+    ).unwrap();
+    
+    pub static ref ID_COLLISIONS: Gauge = Gauge::with_opts(
+        Opts::new("meme_id_collisions", "Number of meme IDs shared by more than one file in the last reload")
+    ).unwrap();
+
+    pub static ref MIME_MISMATCHES: Gauge = Gauge::with_opts(
+        Opts::new("meme_mime_mismatches", "Number of files whose content type differs from their extension in the last reload")
+    ).unwrap();
+    
+    pub static ref WATCHER_HEALTHY: Gauge = Gauge::with_opts(
+        Opts::new("watcher_healthy", "Whether the memes directory watcher is healthy (1) or not (0)")
     ).unwrap();
     
-    pub static ref CACHE_MISSES: Counter = Counter::with_opts(
-        Opts::new("cache_misses_total", "Total number of cache misses")
+    pub static ref WATCHER_RESTARTS: Counter = Counter::with_opts(
+        Opts::new("watcher_restarts_total", "Number of times the memes directory watcher was re-registered")
+    ).unwrap();
+    
+    pub static ref STORAGE_DEGRADED: Gauge = Gauge::with_opts(
+        Opts::new("meme_storage_degraded", "Whether the memes directory is unavailable and the last-known catalog is being served (1) or not (0)")
+    ).unwrap();
+    
+    pub static ref CACHE_WARMUP_LOADED: Gauge = Gauge::with_opts(
+        Opts::new("meme_cache_warmup_loaded", "Number of memes preloaded into the cache during warmup")
+    ).unwrap();
+    
+    pub static ref PREFETCHED_MEMES: Counter = Counter::with_opts(
+        Opts::new("meme_prefetch_loaded_total", "Number of upcoming random memes read into the content cache ahead of time")
     ).unwrap();
 }
 
 pub fn init_metrics() {
     REGISTRY.register(Box::new(REQUEST_COUNTER.clone())).unwrap();
     REGISTRY.register(Box::new(RESPONSE_TIME.clone())).unwrap();
-    REGISTRY.register(Box::new(CACHE_HIT_RATE.clone())).unwrap();
-    REGISTRY.register(Box::new(CACHE_SIZE.clone())).unwrap();
+    REGISTRY.register(Box::new(CACHE_ENTRIES.clone())).unwrap();
+    REGISTRY.register(Box::new(CACHE_BYTES.clone())).unwrap();
     REGISTRY.register(Box::new(ACTIVE_CONNECTIONS.clone())).unwrap();
+    REGISTRY.register(Box::new(CONNECTIONS_TOTAL.clone())).unwrap();
+    REGISTRY.register(Box::new(REQUESTS_BY_PROTOCOL.clone())).unwrap();
+    REGISTRY.register(Box::new(REQUESTS_BY_STATUS.clone())).unwrap();
+    REGISTRY.register(Box::new(REQUESTS_BY_USER_AGENT.clone())).unwrap();
+    REGISTRY.register(Box::new(SLOW_REQUESTS.clone())).unwrap();
     REGISTRY.register(Box::new(IMAGE_PROCESSING_TIME.clone())).unwrap();
+    REGISTRY.register(Box::new(IMAGE_QUEUE_DEPTH.clone())).unwrap();
+    REGISTRY.register(Box::new(IMAGE_QUEUE_REJECTED.clone())).unwrap();
+    REGISTRY.register(Box::new(IMAGE_QUEUE_TIMEOUTS.clone())).unwrap();
+    REGISTRY.register(Box::new(PROCESS_RSS_BYTES.clone())).unwrap();
+    REGISTRY.register(Box::new(SHED_REQUESTS.clone())).unwrap();
+    REGISTRY.register(Box::new(HOTLINKS_BLOCKED.clone())).unwrap();
+    REGISTRY.register(Box::new(SHARD_REQUESTS.clone())).unwrap();
+    REGISTRY.register(Box::new(STREAMED_RESPONSES.clone())).unwrap();
+    REGISTRY.register(Box::new(COALESCED_READS.clone())).unwrap();
+    REGISTRY.register(Box::new(MEME_REPORTS.clone())).unwrap();
+    REGISTRY.register(Box::new(MEMES_QUARANTINED.clone())).unwrap();
+    REGISTRY.register(Box::new(RATE_LIMITED_REQUESTS.clone())).unwrap();
+    REGISTRY.register(Box::new(RANDOM_SESSIONS.clone())).unwrap();
+    REGISTRY.register(Box::new(ID_REDIRECTS.clone())).unwrap();
+    REGISTRY.register(Box::new(PINNED_MEMES.clone())).unwrap();
+    REGISTRY.register(Box::new(PINNED_BYTES.clone())).unwrap();
     
     // 注册新增的指标
     REGISTRY.register(Box::new(SERVICE_UPTIME_SECONDS.clone())).unwrap();
@@ -69,6 +249,20 @@ pub fn init_metrics() {
     REGISTRY.register(Box::new(LAST_UPDATED_TIMESTAMP.clone())).unwrap();
     REGISTRY.register(Box::new(CACHE_HITS.clone())).unwrap();
     REGISTRY.register(Box::new(CACHE_MISSES.clone())).unwrap();
+    REGISTRY.register(Box::new(CACHE_EVICTIONS.clone())).unwrap();
+    REGISTRY.register(Box::new(CACHE_EARLY_REFRESHES.clone())).unwrap();
+    REGISTRY.register(Box::new(CACHE_HIT_RATIO.clone())).unwrap();
+    REGISTRY.register(Box::new(CACHE_WARMUP_LOADED.clone())).unwrap();
+    REGISTRY.register(Box::new(STORAGE_DEGRADED.clone())).unwrap();
+    REGISTRY.register(Box::new(PREFETCHED_MEMES.clone())).unwrap();
+    REGISTRY.register(Box::new(RELOADS_TOTAL.clone())).unwrap();
+    REGISTRY.register(Box::new(RELOAD_DURATION.clone())).unwrap();
+    REGISTRY.register(Box::new(SKIPPED_FILES.clone())).unwrap();
+    REGISTRY.register(Box::new(DUPLICATE_FILES.clone())).unwrap();
+    REGISTRY.register(Box::new(ID_COLLISIONS.clone())).unwrap();
+    REGISTRY.register(Box::new(MIME_MISMATCHES.clone())).unwrap();
+    REGISTRY.register(Box::new(WATCHER_HEALTHY.clone())).unwrap();
+    REGISTRY.register(Box::new(WATCHER_RESTARTS.clone())).unwrap();
 }
 
 /// 设置服务启动时间
@@ -112,6 +306,17 @@ impl Drop for Timer {
     }
 }
 
+/// 状态码分类标签 (例如 `2xx`、`5xx`)
+pub fn status_class(status: u16) -> &'static str {
+    match status {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
 #[macro_export]
 macro_rules! time_operation {
     ($histogram:expr, $operation:expr) => {{
@@ -0,0 +1,153 @@
+use std::{
+    io::Write,
+    sync::Arc,
+    time::Instant,
+};
+use axum::{
+    body::HttpBody,
+    extract::{Request, State},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tracing_appender::non_blocking::NonBlocking;
+use crate::config::AccessLogFormat;
+use crate::middleware::client_ip::ClientIp;
+
+/// 访问日志，每个请求写入一行
+#[derive(Clone)]
+pub struct AccessLog {
+    writer: NonBlocking,
+    format: AccessLogFormat,
+}
+
+impl AccessLog {
+    pub fn new(writer: NonBlocking, format: AccessLogFormat) -> Self {
+        Self { writer, format }
+    }
+}
+
+/// 一次请求的访问记录
+struct AccessEntry {
+    time: OffsetDateTime,
+    ip: ClientIp,
+    method: String,
+    uri: String,
+    version: String,
+    status: u16,
+    bytes: Option<u64>,
+    latency_ms: u64,
+    referer: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl AccessEntry {
+    /// `ip - - [10/Oct/2026:13:55:36 +0000] "GET /memes/random HTTP/1.1" 200 2326 "referer" "ua" 12`
+    fn combined(&self) -> String {
+        let t = self.time;
+        let month = t.month().to_string();
+        format!(
+            "{} - - [{:02}/{}/{}:{:02}:{:02}:{:02} +0000] \"{} {} {}\" {} {} \"{}\" \"{}\" {}\n",
+            self.ip,
+            t.day(),
+            &month[..3],
+            t.year(),
+            t.hour(),
+            t.minute(),
+            t.second(),
+            self.method,
+            self.uri,
+            self.version,
+            self.status,
+            self.bytes.map_or_else(|| "-".to_string(), |b| b.to_string()),
+            quote(self.referer.as_deref()),
+            quote(self.user_agent.as_deref()),
+            self.latency_ms,
+        )
+    }
+
+    fn json(&self) -> String {
+        let entry = serde_json::json!({
+            "time": self.time.format(&Rfc3339).unwrap_or_default(),
+            "ip": self.ip.0.map(|ip| ip.to_string()),
+            "method": self.method,
+            "uri": self.uri,
+            "version": self.version,
+            "status": self.status,
+            "bytes": self.bytes,
+            "latency_ms": self.latency_ms,
+            "referer": self.referer,
+            "user_agent": self.user_agent,
+        });
+        format!("{}\n", entry)
+    }
+}
+
+/// combined 格式中的引号字段，缺失时为 `-`，转义引号与反斜杠
+fn quote(value: Option<&str>) -> String {
+    match value {
+        Some(value) => value.replace('\\', "\\\\").replace('"', "\\\""),
+        None => "-".to_string(),
+    }
+}
+
+fn header_value(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string)
+}
+
+/// 记录每个请求的方法、路径、状态码、耗时、响应字节数、客户端 IP 与 User-Agent，需在客户端 IP 解析之后执行
+pub async fn log_access(
+    State(log): State<Arc<AccessLog>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let start = Instant::now();
+    let time = OffsetDateTime::now_utc();
+    let method = request.method().to_string();
+    let uri = request
+        .uri()
+        .path_and_query()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let version = format!("{:?}", request.version());
+    let ip = request
+        .extensions()
+        .get::<ClientIp>()
+        .copied()
+        .unwrap_or(ClientIp(None));
+    let referer = header_value(request.headers(), header::REFERER);
+    let user_agent = header_value(request.headers(), header::USER_AGENT);
+
+    let response = next.run(request).await;
+
+    // 流式响应没有 Content-Length 时使用响应体的确切大小，无法得知时记为 `-`
+    let bytes = response
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .or_else(|| response.body().size_hint().exact());
+    let entry = AccessEntry {
+        time,
+        ip,
+        method,
+        uri,
+        version,
+        status: response.status().as_u16(),
+        bytes,
+        latency_ms: start.elapsed().as_millis() as u64,
+        referer,
+        user_agent,
+    };
+    let line = match log.format {
+        AccessLogFormat::Combined => entry.combined(),
+        AccessLogFormat::Json => entry.json(),
+    };
+    let mut writer = log.writer.clone();
+    if let Err(e) = writer.write_all(line.as_bytes()) {
+        tracing::warn!("写入访问日志失败: {}", e);
+    }
+
+    response
+}
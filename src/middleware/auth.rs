@@ -0,0 +1,191 @@
+use std::sync::Arc;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use tracing::warn;
+use crate::config::{AdminConfig, Config};
+use crate::services::jwt::JwtVerifier;
+use crate::utils::error::{AppError, Result};
+use crate::utils::secret;
+
+/// 从请求头中提取 API Key，支持 `Authorization: Bearer <key>` 与 `X-API-Key: <key>`
+pub fn extract_api_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| headers.get("x-api-key").and_then(|h| h.to_str().ok()))
+        .map(|key| key.trim())
+}
+
+/// 管理接口的调用者角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// 全部管理接口
+    Admin,
+    /// 只能上传表情包
+    Uploader,
+}
+
+impl Role {
+    fn allows(self, method: &Method, path: &str) -> bool {
+        match self {
+            Role::Admin => true,
+            Role::Uploader => method == Method::POST && path == "/admin/memes",
+        }
+    }
+}
+
+/// 通过鉴权的调用者，写入请求扩展供处理函数使用
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub role: Role,
+    /// JWT 的 `sub`；使用 API Key 时为空
+    pub subject: Option<String>,
+}
+
+/// 管理接口的鉴权方式：静态 API Key 与可选的 JWT
+#[derive(Debug)]
+pub struct Authenticator {
+    api_keys: Vec<String>,
+    jwt: Option<JwtVerifier>,
+    admin_role: String,
+    uploader_role: String,
+}
+
+impl Authenticator {
+    pub fn new(config: &AdminConfig) -> Result<Self> {
+        Ok(Self {
+            api_keys: config.api_keys.clone(),
+            jwt: JwtVerifier::new(&config.jwt)?,
+            admin_role: config.jwt.admin_role.clone(),
+            uploader_role: config.jwt.uploader_role.clone(),
+        })
+    }
+
+    fn is_enabled(&self) -> bool {
+        !self.api_keys.is_empty() || self.jwt.is_some()
+    }
+
+    /// 先按 API Key 匹配，不匹配且启用了 JWT 时按令牌校验并根据角色声明授权
+    async fn authenticate(&self, credential: &str) -> Result<Principal> {
+        if secret::contains(&self.api_keys, credential) {
+            return Ok(Principal { role: Role::Admin, subject: None });
+        }
+        let Some(jwt) = &self.jwt else {
+            return Err(AppError::Unauthorized("Invalid API key".to_string()));
+        };
+
+        let token = jwt.verify(credential).await?;
+        let role = if token.roles.contains(&self.admin_role) {
+            Role::Admin
+        } else if token.roles.contains(&self.uploader_role) {
+            Role::Uploader
+        } else {
+            return Err(AppError::Forbidden("Token has no admin or uploader role".to_string()));
+        };
+        Ok(Principal { role, subject: token.subject })
+    }
+}
+
+/// 管理接口鉴权中间件
+pub async fn require_admin(
+    State(auth): State<Arc<Authenticator>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response> {
+    if !auth.is_enabled() {
+        return Err(AppError::Forbidden("Admin API is disabled".to_string()));
+    }
+
+    let Some(credential) = extract_api_key(request.headers()) else {
+        return Err(AppError::Unauthorized("Missing API key".to_string()));
+    };
+
+    let principal = match auth.authenticate(credential).await {
+        Ok(principal) => principal,
+        Err(e) => {
+            warn!(uri = %request.uri(), "管理接口鉴权失败: {}", e);
+            return Err(e);
+        }
+    };
+    if !principal.role.allows(request.method(), request.uri().path()) {
+        warn!(uri = %request.uri(), subject = ?principal.subject, "角色 {:?} 无权访问", principal.role);
+        return Err(AppError::Forbidden("Insufficient role for this endpoint".to_string()));
+    }
+
+    request.extensions_mut().insert(principal);
+    Ok(next.run(request).await)
+}
+
+/// Swagger UI 与 openapi.json 的 HTTP Basic 认证
+#[derive(Debug)]
+pub struct SwaggerAuth {
+    /// 配置的用户名与密码
+    credentials: Option<(String, String)>,
+    /// 接受为密码的 API Key
+    api_keys: Vec<String>,
+}
+
+impl SwaggerAuth {
+    /// 未配置 `swagger.auth` 时返回 None
+    pub fn new(config: &Config) -> Option<Self> {
+        let auth = &config.swagger.auth;
+        if !auth.is_enabled() {
+            return None;
+        }
+        Some(Self {
+            credentials: (!auth.username.is_empty()).then(|| (auth.username.clone(), auth.password.clone())),
+            api_keys: if auth.use_api_keys { config.admin.api_keys.clone() } else { Vec::new() },
+        })
+    }
+
+    fn allows(&self, headers: &HeaderMap) -> bool {
+        if let Some(key) = extract_api_key(headers) {
+            if secret::contains(&self.api_keys, key) {
+                return true;
+            }
+        }
+
+        let Some((username, password)) = basic_credentials(headers) else {
+            return false;
+        };
+        let matches_user = self.credentials.as_ref().is_some_and(|(u, p)| (*u == username) & secret::eq(p, &password));
+        matches_user | secret::contains(&self.api_keys, &password)
+    }
+}
+
+/// 解析 `Authorization: Basic <base64(user:password)>`
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let encoded = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (username, password) = decoded.split_once(':')?;
+    Some((username.to_string(), password.to_string()))
+}
+
+/// 文档鉴权中间件，未通过时返回 401 与 `WWW-Authenticate`，浏览器会弹出登录框
+pub async fn require_swagger_auth(
+    State(auth): State<Arc<SwaggerAuth>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if auth.allows(request.headers()) {
+        return next.run(request).await;
+    }
+    if request.headers().contains_key(header::AUTHORIZATION) {
+        warn!(uri = %request.uri(), "API 文档鉴权失败");
+    }
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, HeaderValue::from_static("Basic realm=\"API docs\", charset=\"UTF-8\""))],
+    )
+        .into_response()
+}
@@ -0,0 +1,21 @@
+use std::sync::Arc;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tokio::sync::RwLock;
+use crate::services::meme::MemeService;
+use crate::utils::error::AppError;
+
+/// 开启 `storage.allow_empty` 且目录中还没有可用的表情包时，图片接口返回 503
+pub async fn require_memes(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if state.read().await.get_total_memes() == 0 {
+        return AppError::ServiceUnavailable("No memes available yet, waiting for files to arrive".to_string()).into_response();
+    }
+    next.run(request).await
+}
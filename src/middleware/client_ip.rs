@@ -0,0 +1,175 @@
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{request::Parts, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use ipnet::IpNet;
+use crate::config::ProxyConfig;
+use crate::utils::error::{AppError, Result};
+
+/// 解析后的客户端 IP，由 [`resolve_client_ip`] 中间件写入请求扩展，
+/// 日志、统计等模块统一从这里读取，避免各自解析代理头
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl std::fmt::Display for ClientIp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(ip) => write!(f, "{}", ip),
+            None => write!(f, "unknown"),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> std::result::Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<ClientIp>().copied().unwrap_or(ClientIp(None)))
+    }
+}
+
+/// 客户端 IP 解析器
+///
+/// 启用代理时从右向左解析代理头，跳过 `trusted_proxies` 中的可信代理，
+/// 第一个不可信的地址即为客户端地址；直连地址本身不可信时忽略代理头
+#[derive(Debug, Clone)]
+pub struct ClientIpResolver {
+    enabled: bool,
+    ip_header: String,
+    trusted: Vec<IpNet>,
+}
+
+/// 解析 CIDR 或单个 IP 地址
+pub fn parse_trusted_proxy(value: &str) -> Result<IpNet> {
+    let value = value.trim();
+    value.parse::<IpNet>()
+        .or_else(|_| value.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| AppError::Config(format!("Invalid trusted proxy: {}", value)))
+}
+
+impl ClientIpResolver {
+    pub fn new(config: &ProxyConfig) -> Result<Self> {
+        let trusted = config.trusted_proxies
+            .iter()
+            .map(|p| parse_trusted_proxy(p))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            enabled: config.enabled,
+            ip_header: config.ip_header.clone(),
+            trusted,
+        })
+    }
+
+    fn is_trusted(&self, ip: &IpAddr) -> bool {
+        self.trusted.iter().any(|net| net.contains(ip))
+    }
+
+    pub fn resolve(&self, headers: &HeaderMap, peer: Option<IpAddr>) -> Option<IpAddr> {
+        if !self.enabled {
+            return peer;
+        }
+
+        // 直连方不是可信代理时，代理头可能是伪造的
+        if let Some(peer_ip) = peer {
+            if !self.trusted.is_empty() && !self.is_trusted(&peer_ip) {
+                return peer;
+            }
+        }
+
+        let hops: Vec<IpAddr> = headers
+            .get_all(self.ip_header.as_str())
+            .iter()
+            .filter_map(|h| h.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(|s| s.trim().parse::<IpAddr>().ok())
+            .collect();
+
+        hops.iter()
+            .rev()
+            .find(|ip| !self.is_trusted(ip))
+            .or_else(|| hops.first())
+            .copied()
+            .or(peer)
+    }
+}
+
+/// 解析客户端 IP 并写入请求扩展
+pub async fn resolve_client_ip(
+    State(resolver): State<Arc<ClientIpResolver>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ci| ci.0.ip());
+    let client_ip = ClientIp(resolver.resolve(request.headers(), peer));
+    request.extensions_mut().insert(client_ip);
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn resolver(trusted: &[&str]) -> ClientIpResolver {
+        ClientIpResolver::new(&ProxyConfig {
+            enabled: true,
+            ip_header: "x-forwarded-for".to_string(),
+            trusted_proxies: trusted.iter().map(|p| p.to_string()).collect(),
+        })
+        .unwrap()
+    }
+
+    fn headers(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append("x-forwarded-for", HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn ip(value: &str) -> Option<IpAddr> {
+        Some(value.parse().unwrap())
+    }
+
+    #[test]
+    fn untrusted_peer_ignores_headers() {
+        let resolver = resolver(&["10.0.0.0/8"]);
+        let resolved = resolver.resolve(&headers(&["1.1.1.1"]), ip("203.0.113.5"));
+        assert_eq!(resolved, ip("203.0.113.5"));
+    }
+
+    #[test]
+    fn skips_trusted_hops_from_the_right() {
+        let resolver = resolver(&["10.0.0.0/8", "192.168.1.1"]);
+        let resolved = resolver.resolve(&headers(&["6.6.6.6, 1.1.1.1, 10.0.0.2, 192.168.1.1"]), ip("10.0.0.1"));
+        assert_eq!(resolved, ip("1.1.1.1"));
+    }
+
+    #[test]
+    fn falls_back_to_first_hop_when_all_are_trusted() {
+        let resolver = resolver(&["10.0.0.0/8"]);
+        let resolved = resolver.resolve(&headers(&["10.0.0.3, 10.0.0.2"]), ip("10.0.0.1"));
+        assert_eq!(resolved, ip("10.0.0.3"));
+    }
+
+    #[test]
+    fn joins_multiple_header_lines() {
+        let resolver = resolver(&["10.0.0.0/8"]);
+        let resolved = resolver.resolve(&headers(&["6.6.6.6, 1.1.1.1", "10.0.0.2"]), ip("10.0.0.1"));
+        assert_eq!(resolved, ip("1.1.1.1"));
+    }
+}
@@ -0,0 +1,23 @@
+use std::sync::Arc;
+use axum::{
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+use crate::middleware::client_ip::ClientIp;
+use crate::services::clients::ClientTracker;
+
+/// 按客户端 IP 与 User-Agent 统计请求，需在客户端 IP 解析之后执行
+pub async fn track_clients(
+    State(tracker): State<Arc<ClientTracker>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Some(ClientIp(Some(ip))) = request.extensions().get::<ClientIp>().copied() {
+        tracker.record(ip).await;
+    }
+    let user_agent = request.headers().get(header::USER_AGENT).and_then(|v| v.to_str().ok());
+    tracker.record_user_agent(user_agent);
+    next.run(request).await
+}
@@ -0,0 +1,57 @@
+use std::{collections::BTreeMap, sync::Arc};
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use crate::utils::error::{AppError, Result};
+use crate::utils::svg::SVG_MIME;
+
+/// SVG 响应的内容安全策略：禁止脚本与外部资源，只允许内联样式和内嵌位图
+const SVG_CSP: &str = "default-src 'none'; style-src 'unsafe-inline'; img-src data:; sandbox";
+
+/// 将配置中的 `server.extra_headers` 解析为 HeaderMap，名称或值非法时报错
+pub fn parse_extra_headers(headers: &BTreeMap<String, String>) -> Result<HeaderMap> {
+    let mut map = HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| AppError::Config(format!("Invalid header name '{}': {}", name, e)))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|e| AppError::Config(format!("Invalid value for header '{}': {}", name, e)))?;
+        map.insert(name, value);
+    }
+    Ok(map)
+}
+
+/// 为所有响应附加额外的响应头，处理器已设置的同名响应头不会被覆盖
+pub async fn inject_headers(
+    State(extra): State<Arc<HeaderMap>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    for (name, value) in extra.iter() {
+        if !headers.contains_key(name) {
+            headers.insert(name.clone(), value.clone());
+        }
+    }
+    response
+}
+
+/// SVG 响应附加内容安全策略与 `nosniff`，即使清理有遗漏，直接打开图片时也不会执行脚本
+pub async fn svg_policy(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    let is_svg = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with(SVG_MIME));
+    if is_svg {
+        let headers = response.headers_mut();
+        headers.insert(header::CONTENT_SECURITY_POLICY, HeaderValue::from_static(SVG_CSP));
+        headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    }
+    response
+}
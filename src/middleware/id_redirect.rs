@@ -0,0 +1,46 @@
+use std::sync::Arc;
+use axum::{
+    extract::{OriginalUri, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tokio::sync::RwLock;
+use crate::metrics::ID_REDIRECTS;
+use crate::services::meme::MemeService;
+
+/// 带 ID 的路径前缀，其后的第一段为表情包 ID
+const ID_PREFIXES: [&str; 2] = ["/memes/get/", "/memes/info/"];
+
+/// 请求迁移前的旧 ID (当前目录中已不存在) 时返回 301，跳转到新 ID 的同一路径，保留子路径与查询参数
+pub async fn redirect_old_ids(
+    State(state): State<Arc<RwLock<MemeService>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    let Some((prefix, id, rest)) = ID_PREFIXES.iter().find_map(|prefix| {
+        let tail = path.strip_prefix(prefix)?;
+        let (id, rest) = tail.split_at(tail.find('/').unwrap_or(tail.len()));
+        Some((*prefix, id.parse::<u32>().ok()?, rest))
+    }) else {
+        return next.run(request).await;
+    };
+    let new_id = state.read().await.redirected_id(id);
+    let Some(new_id) = new_id else {
+        return next.run(request).await;
+    };
+
+    // 挂在 `/api/v1` 等前缀下时，请求路径已去掉前缀，从原始路径中找回
+    let original = request.extensions().get::<OriginalUri>().map_or(path, |uri| uri.path());
+    let mount = original.strip_suffix(path).unwrap_or("");
+    let query = request.uri().query().map(|query| format!("?{}", query)).unwrap_or_default();
+    let location = format!("{}{}{}{}{}", mount, prefix, new_id, rest, query);
+    match HeaderValue::from_str(&location) {
+        Ok(location) => {
+            ID_REDIRECTS.inc();
+            (StatusCode::MOVED_PERMANENTLY, [(header::LOCATION, location)]).into_response()
+        }
+        Err(_) => next.run(request).await,
+    }
+}
@@ -0,0 +1,23 @@
+use axum::{
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+use crate::utils::i18n::{self, Locale};
+
+/// 根据 `Accept-Language` 协商响应语言，错误信息会按此语言输出
+pub async fn negotiate_locale(
+    State(default_locale): State<Locale>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let locale = request
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(Locale::from_accept_language)
+        .unwrap_or(default_locale);
+
+    i18n::with_locale(locale, next.run(request)).await
+}
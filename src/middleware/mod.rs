@@ -0,0 +1,13 @@
+pub mod access_log;
+pub mod auth;
+pub mod catalog;
+pub mod client_ip;
+pub mod client_stats;
+pub mod headers;
+pub mod id_redirect;
+pub mod locale;
+pub mod rate_limit;
+pub mod referer;
+pub mod shard;
+pub mod slow_log;
+pub mod throttle;
\ No newline at end of file
@@ -0,0 +1,80 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Arc,
+    time::Instant,
+};
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use parking_lot::Mutex;
+use crate::config::LimitsConfig;
+use crate::metrics;
+use crate::middleware::client_ip::ClientIp;
+use crate::utils::error::AppError;
+
+/// 记录的客户端超过该数量时清理已回满的令牌桶
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// 按客户端 IP 的请求令牌桶，由 [`crate::app::AppBuilder`] 在配置了
+/// `server.limits.requests_per_sec_per_client` 时挂在公共接口上
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<Option<IpAddr>, (f64, Instant)>>,
+}
+
+impl RateLimiter {
+    /// 未配置限流时返回 `None`
+    pub fn new(config: &LimitsConfig) -> Option<Self> {
+        if config.requests_per_sec_per_client == 0 {
+            return None;
+        }
+        let rate = config.requests_per_sec_per_client as f64;
+        let burst = match config.burst_per_client {
+            0 => rate,
+            burst => burst as f64,
+        };
+        Some(Self {
+            rate,
+            burst,
+            buckets: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// 取出一个令牌，不足时返回需要等待的秒数
+    fn acquire(&self, ip: Option<IpAddr>) -> Result<(), u64> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock();
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            buckets.retain(|_, (tokens, last)| *tokens + now.duration_since(*last).as_secs_f64() * self.rate < self.burst);
+        }
+
+        let (tokens, last) = buckets.entry(ip).or_insert((self.burst, now));
+        *tokens = (*tokens + now.duration_since(*last).as_secs_f64() * self.rate).min(self.burst);
+        *last = now;
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - *tokens) / self.rate).ceil().max(1.0) as u64)
+        }
+    }
+}
+
+/// 超出客户端的请求速率时返回 429 与 `Retry-After`，需在客户端 IP 解析之后执行
+pub async fn limit_requests(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ip = request.extensions().get::<ClientIp>().and_then(|ip| ip.0);
+    if let Err(retry_after_secs) = limiter.acquire(ip) {
+        metrics::RATE_LIMITED_REQUESTS.inc();
+        return AppError::TooManyRequests { retry_after_secs }.into_response();
+    }
+    next.run(request).await
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::{debug, warn};
+use crate::config::ShardMode;
+use crate::metrics::SHARD_REQUESTS;
+use crate::services::cluster::{ShardRouter, CLUSTER_TOKEN_HEADER};
+
+/// 不转发给负责节点的逐跳请求头
+const HOP_HEADERS: [header::HeaderName; 4] = [
+    header::HOST,
+    header::CONNECTION,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// 把 `/memes/get/{id}` 及其子路径的请求交给负责该 ID 的节点：
+/// 重定向模式返回 307，转发模式由本节点代为请求；负责节点不可用时在本地处理
+pub async fn route_to_owner(
+    State(router): State<Arc<ShardRouter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let token = request.headers().get(CLUSTER_TOKEN_HEADER).and_then(|v| v.to_str().ok());
+    if router.is_forwarded(token) {
+        return next.run(request).await;
+    }
+    let Some(owner) = meme_id(request.uri().path()).and_then(|id| router.owner(id)) else {
+        return next.run(request).await;
+    };
+
+    let path = request.uri().path_and_query().map_or("/", |p| p.as_str());
+    let url = format!("{}{}", owner, path);
+    match router.mode() {
+        ShardMode::Redirect => {
+            SHARD_REQUESTS.with_label_values(&["redirected"]).inc();
+            match HeaderValue::from_str(&url) {
+                Ok(location) => (StatusCode::TEMPORARY_REDIRECT, [(header::LOCATION, location)]).into_response(),
+                Err(_) => next.run(request).await,
+            }
+        }
+        // 请求体不是 Sync，先取出方法与请求头，避免在 await 期间持有 `&Request`
+        ShardMode::Proxy => match proxy(&router, request.method().clone(), request.headers().clone(), &url).await {
+            Ok(response) => {
+                SHARD_REQUESTS.with_label_values(&["proxied"]).inc();
+                response
+            }
+            Err(e) => {
+                SHARD_REQUESTS.with_label_values(&["fallback"]).inc();
+                warn!("转发到分片节点 {} 失败，改为本地处理: {}", owner, e);
+                next.run(request).await
+            }
+        },
+    }
+}
+
+/// 转发请求并原样返回负责节点的响应；只有连接失败等错误才返回 Err，节点返回的错误状态码照常透传
+async fn proxy(router: &ShardRouter, method: Method, mut headers: HeaderMap, url: &str) -> reqwest::Result<Response> {
+    for name in &HOP_HEADERS {
+        headers.remove(name);
+    }
+    if let Ok(token) = HeaderValue::from_str(router.token()) {
+        headers.insert(CLUSTER_TOKEN_HEADER, token);
+    }
+
+    let upstream = router.client()
+        .request(method, url)
+        .headers(headers)
+        .send()
+        .await?;
+    debug!(url, status = upstream.status().as_u16(), "已转发到分片节点");
+
+    let status = upstream.status();
+    let mut headers = upstream.headers().clone();
+    for name in &HOP_HEADERS {
+        headers.remove(name);
+    }
+    let body = upstream.bytes().await?;
+
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    *response.headers_mut() = headers;
+    Ok(response)
+}
+
+/// 从 `/memes/get/{id}`、`/memes/get/{id}/caption` 等路径中取出 ID，按名称获取等其他路径返回 None
+fn meme_id(path: &str) -> Option<u32> {
+    path.strip_prefix("/memes/get/")?
+        .split('/')
+        .next()?
+        .parse()
+        .ok()
+}
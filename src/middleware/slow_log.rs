@@ -0,0 +1,57 @@
+use std::time::{Duration, Instant};
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use tracing::warn;
+use crate::metrics::SLOW_REQUESTS;
+use crate::middleware::client_ip::ClientIp;
+use crate::services::meme::CacheStatus;
+
+/// 图片处理器写入响应扩展的信息，供慢请求日志使用
+#[derive(Debug, Clone, Copy)]
+pub struct ServedMeme {
+    pub id: u32,
+    pub cache: CacheStatus,
+}
+
+/// 记录耗时超过阈值的请求，附带路由、表情包 ID、缓存状态与客户端 IP
+pub async fn log_slow_requests(
+    State(threshold): State<Duration>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let start = Instant::now();
+    let method = request.method().clone();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let client_ip = request
+        .extensions()
+        .get::<ClientIp>()
+        .copied()
+        .unwrap_or(ClientIp(None));
+
+    let response = next.run(request).await;
+
+    let latency = start.elapsed();
+    if latency >= threshold {
+        SLOW_REQUESTS.inc();
+        let served = response.extensions().get::<ServedMeme>();
+        warn!(
+            method = %method,
+            route = %route,
+            status = response.status().as_u16(),
+            latency_ms = latency.as_millis() as u64,
+            meme_id = served.map(|s| s.id),
+            cache = served.map(|s| s.cache.as_str()),
+            ip = %client_ip,
+            "慢请求"
+        );
+    }
+
+    response
+}
@@ -0,0 +1,132 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use axum::{
+    body::{Body, BodyDataStream, Bytes, HttpBody},
+    extract::Request,
+    middleware::Next,
+    response::Response,
+};
+use parking_lot::Mutex;
+use tokio::time::Sleep;
+use tokio_stream::Stream;
+
+/// 每次发送的最小分片，避免限速很低时把响应切得过碎
+const MIN_SLICE_BYTES: usize = 1024;
+
+/// 单个连接的出站令牌桶，桶容量为一秒的流量；由 [`crate::server::serve`] 为每个连接创建一个，
+/// 写入请求扩展，同一 HTTP/2 连接上的多个请求共享
+#[derive(Debug, Clone)]
+pub struct ConnectionThrottle(Arc<TokenBucket>);
+
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    state: Mutex<(f64, Instant)>,
+}
+
+impl ConnectionThrottle {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        let rate = bytes_per_sec as f64;
+        Self(Arc::new(TokenBucket {
+            rate,
+            state: Mutex::new((rate, Instant::now())),
+        }))
+    }
+
+    /// 每次发送的分片大小，约为十分之一秒的流量
+    fn slice_len(&self) -> usize {
+        ((self.0.rate / 10.0) as usize).max(MIN_SLICE_BYTES)
+    }
+
+    /// 取出 `len` 字节的令牌，令牌不足时允许透支，返回发送前需要等待的时间
+    fn reserve(&self, len: usize) -> Duration {
+        let bucket = &self.0;
+        let mut state = bucket.state.lock();
+        let (tokens, last) = &mut *state;
+        let now = Instant::now();
+        *tokens = (*tokens + now.duration_since(*last).as_secs_f64() * bucket.rate).min(bucket.rate);
+        *last = now;
+        *tokens -= len as f64;
+        if *tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-*tokens / bucket.rate)
+        }
+    }
+}
+
+/// 按连接的令牌桶限速发送的响应体
+struct ThrottledStream {
+    inner: BodyDataStream,
+    throttle: ConnectionThrottle,
+    // 已取得令牌、等待发送的分片
+    ready: Option<Bytes>,
+    // 尚未取得令牌的剩余数据
+    remaining: Bytes,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl Stream for ThrottledStream {
+    type Item = Result<Bytes, axum::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if let Some(sleep) = this.sleep.as_mut() {
+                if sleep.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                this.sleep = None;
+            }
+            if let Some(slice) = this.ready.take() {
+                return Poll::Ready(Some(Ok(slice)));
+            }
+
+            if this.remaining.is_empty() {
+                match Pin::new(&mut this.inner).poll_next(cx) {
+                    Poll::Ready(Some(Ok(chunk))) => this.remaining = chunk,
+                    other => return other,
+                }
+                if this.remaining.is_empty() {
+                    continue;
+                }
+            }
+
+            let len = this.remaining.len().min(this.throttle.slice_len());
+            let slice = this.remaining.split_to(len);
+            let wait = this.throttle.reserve(slice.len());
+            if wait.is_zero() {
+                return Poll::Ready(Some(Ok(slice)));
+            }
+            this.ready = Some(slice);
+            this.sleep = Some(Box::pin(tokio::time::sleep(wait)));
+        }
+    }
+}
+
+/// 开启 `server.limits.max_bytes_per_sec_per_conn` 时，按连接限速发送图片响应，
+/// 避免单个客户端占满家用服务器的上行带宽
+pub async fn throttle_responses(request: Request, next: Next) -> Response {
+    let Some(throttle) = request.extensions().get::<ConnectionThrottle>().cloned() else {
+        return next.run(request).await;
+    };
+
+    let response = next.run(request).await;
+    if response.body().size_hint().exact() == Some(0) {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let stream = ThrottledStream {
+        inner: body.into_data_stream(),
+        throttle,
+        ready: None,
+        remaining: Bytes::new(),
+        sleep: None,
+    };
+    Response::from_parts(parts, Body::from_stream(stream))
+}
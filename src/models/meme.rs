@@ -1,13 +1,128 @@
 use std::path::PathBuf;
 use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+
+/// 表情包审核状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MemeStatus {
+    /// 已审核，可以对外提供
+    #[default]
+    Approved,
+    /// 待审核，不会被随机选中或对外提供
+    Pending,
+}
+
+/// 图片方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    Landscape,
+    Portrait,
+    Square,
+}
+
+/// 表情包来源信息，从图片旁的 `<文件名>.meta.yml` 读取
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct MemeMetadata {
+    #[schema(example = "peach")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[schema(example = "https://example.com/original-post")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[schema(example = "CC BY 4.0")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// 原图的规范地址 (本表情包为镜像时)，`/memes/get/{id}?redirect=external` 重定向到该地址
+    #[schema(example = "https://example.com/images/original.jpg")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical_url: Option<String>,
+    /// 拍摄或创作日期 (YYYY-MM-DD)，优先于图片 EXIF 中的拍摄日期
+    #[schema(example = "2021-05-20")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    /// 是否为 NSFW 内容
+    #[schema(example = false)]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub nsfw: bool,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Meme {
     pub id: u32,
     pub path: PathBuf,
     pub mime_type: String,
+    /// NFC 形式的文件名 (相对于表情包目录)
     pub filename: String,
+    /// 磁盘上的文件名与 NFC 形式不同时 (如 macOS 上的 NFD 形式) 保存原始形式
+    #[serde(default)]
+    pub original_filename: Option<String>,
     pub size_bytes: u64,
+    /// 文件内容的 SHA-256 (十六进制)，文件改名后不变；读取失败时为空
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// 与本表情包内容完全相同的其他文件名
+    #[serde(default)]
+    pub duplicates: Vec<String>,
+    /// `duplicates` 中各文件在磁盘上的路径，删除表情包时一并移入回收站
+    #[serde(default)]
+    pub duplicate_paths: Vec<PathBuf>,
+    #[serde(default)]
+    pub status: MemeStatus,
+    /// 来源信息 (作者、出处、许可协议)
+    #[serde(default)]
+    pub metadata: Option<MemeMetadata>,
+    /// 图片宽度（像素），无法读取时为空
+    #[serde(default)]
+    pub width: Option<u32>,
+    /// 图片高度（像素），无法读取时为空
+    #[serde(default)]
+    pub height: Option<u32>,
+    /// 来源信息文件或管理接口标记的 NSFW 内容
+    #[serde(default)]
+    pub nsfw: bool,
+    /// 审核通过的标签
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 拍摄或创作日期 (YYYY-MM-DD)，来自来源信息或 EXIF，都没有时为空
+    #[serde(default)]
+    pub created_on: Option<String>,
+}
+
+impl Meme {
+    pub fn is_approved(&self) -> bool {
+        self.status == MemeStatus::Approved
+    }
+
+    /// NFC 形式与 (不同时) 磁盘上的文件名；按文件名记录的数据可能使用其中任一形式
+    pub fn filenames(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.filename.as_str()).chain(self.original_filename.as_deref())
+    }
+
+    /// 创作日期的月与日 (`MM-DD`)
+    pub fn anniversary(&self) -> Option<&str> {
+        self.created_on.as_deref().and_then(|date| date.get(5..10))
+    }
+
+    pub fn is_svg(&self) -> bool {
+        self.mime_type == crate::utils::svg::SVG_MIME
+    }
+
+    /// 短视频 (mp4/webm)，需要开启 `storage.allow_video`
+    pub fn is_video(&self) -> bool {
+        self.mime_type.starts_with("video/")
+    }
+
+    /// 图片方向，尺寸未知时为空
+    pub fn orientation(&self) -> Option<Orientation> {
+        let (width, height) = (self.width?, self.height?);
+        Some(match width.cmp(&height) {
+            std::cmp::Ordering::Greater => Orientation::Landscape,
+            std::cmp::Ordering::Less => Orientation::Portrait,
+            std::cmp::Ordering::Equal => Orientation::Square,
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1,34 +1,199 @@
-use utoipa::OpenApi;
+use axum::{http::header, routing::get, Router};
+use utoipa::{Modify, OpenApi};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
 use utoipa_swagger_ui::SwaggerUi;
-use crate::config::SwaggerConfig;
+use crate::config::{EndpointsConfig, SwaggerConfig};
+use crate::utils::error::{AppError, Result};
+
+/// JSON 格式的接口文档地址
+pub const OPENAPI_JSON_PATH: &str = "/api-docs/openapi.json";
+/// YAML 格式的接口文档地址
+pub const OPENAPI_YAML_PATH: &str = "/api-docs/openapi.yaml";
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
         crate::handlers::meme::random_meme,
+        crate::handlers::meme::query_random_memes,
         crate::handlers::meme::list_memes,
+        crate::handlers::meme::get_catalog,
+        crate::handlers::meme::get_changes,
+        crate::handlers::meme::diff_catalog,
+        crate::handlers::meme::report_meme,
+        crate::handlers::feed::get_feed,
+        crate::handlers::gallery::get_gallery,
+        crate::handlers::gallery::get_sitemap,
         crate::handlers::meme::get_meme_by_id,
+        crate::handlers::meme::get_meme_caption,
+        crate::handlers::meme::get_meme_icon,
+        crate::handlers::meme::get_collage,
+        crate::handlers::events::catalog_events,
+        crate::handlers::meme::get_meme_by_alias,
+        crate::handlers::meme::get_meme_by_hash,
+        crate::handlers::meme::get_meme_info,
+        crate::handlers::meme::search_memes,
+        crate::handlers::meme::get_on_this_day,
         crate::handlers::meme::get_meme_count,
         crate::handlers::meme::health_check,
-        crate::handlers::statistics::get_statistics
+        crate::handlers::meme::readiness_check,
+        crate::handlers::statistics::get_statistics,
+        crate::handlers::statistics::get_trending,
+        crate::handlers::admin::upload_meme,
+        crate::handlers::admin::list_memes,
+        crate::handlers::admin::approve_meme,
+        crate::handlers::admin::reject_meme,
+        crate::handlers::admin::set_nsfw,
+        crate::handlers::admin::list_reports,
+        crate::handlers::admin::dismiss_reports,
+        crate::handlers::admin::pin_meme,
+        crate::handlers::admin::unpin_meme,
+        crate::handlers::admin::list_tag_suggestions,
+        crate::handlers::admin::approve_tags,
+        crate::handlers::admin::reject_tags,
+        crate::handlers::admin::delete_meme,
+        crate::handlers::admin::restore_meme,
+        crate::handlers::admin::list_trash,
+        crate::handlers::admin::list_collisions,
+        crate::handlers::admin::list_mime_mismatches,
+        crate::handlers::admin::top_clients,
+        crate::handlers::admin::reset_statistics,
+        crate::handlers::admin::clear_cache,
+        crate::handlers::admin::get_log_level,
+        crate::handlers::admin::set_log_level,
+        crate::handlers::admin::list_cache_entries,
+        crate::handlers::admin::get_cache_entry,
+        crate::handlers::admin::evict_cache_entry,
+        crate::handlers::admin::list_aliases,
+        crate::handlers::admin::set_alias,
+        crate::handlers::admin::delete_alias
     ),
     components(
         schemas(
             crate::handlers::meme::RandomMemeQuery,
+            crate::handlers::meme::RandomQueryRequest,
+            crate::handlers::meme::TagQuery,
+            crate::handlers::meme::ValueRange,
             crate::handlers::meme::GetMemeQuery,
+            crate::handlers::meme::CaptionQuery,
+            crate::handlers::meme::IconQuery,
+            crate::handlers::feed::FeedQuery,
+            crate::handlers::gallery::GalleryQuery,
             crate::handlers::meme::MemeListItem,
+            crate::handlers::meme::ListMemesQuery,
+            crate::handlers::meme::SearchQuery,
+            crate::handlers::meme::OnThisDayQuery,
+            crate::handlers::meme::MemeInfo,
+            crate::handlers::meme::MemeInfoQuery,
+            crate::services::catalog::Catalog,
+            crate::services::catalog::CatalogEntry,
+            crate::services::catalog::CatalogChanges,
+            crate::services::catalog::DiffRequest,
+            crate::handlers::meme::ReportRequest,
+            crate::handlers::meme::ReportReceipt,
+            crate::services::catalog::KnownMeme,
+            crate::services::catalog::CatalogDiff,
             crate::handlers::meme::MemeCount,
-            crate::handlers::statistics::Statistics
+            crate::handlers::meme::Readiness,
+            crate::handlers::meme::HealthQuery,
+            crate::handlers::meme::HealthCheck,
+            crate::handlers::meme::DeepHealth,
+            crate::services::watcher::WatcherStatus,
+            crate::services::meme::ReloadReport,
+            crate::services::meme::ReloadTrigger,
+            crate::services::events::CatalogEvent,
+            crate::handlers::statistics::Statistics,
+            crate::handlers::statistics::CounterTotals,
+            crate::handlers::statistics::TrendingQuery,
+            crate::handlers::statistics::TrendingMeme,
+            crate::services::trash::TrashEntry,
+            crate::services::meme::IdCollision,
+            crate::services::meme::MimeMismatch,
+            crate::handlers::admin::TopClientsQuery,
+            crate::services::clients::ClientStat,
+            crate::services::user_agents::AgentCount,
+            crate::services::user_agents::AgentCategory,
+            crate::handlers::admin::ClearCacheResponse,
+            crate::handlers::admin::LogLevelBody,
+            crate::services::meme::CacheTarget,
+            crate::services::meme::CacheEntry,
+            crate::services::meme::ReassignedId,
+            crate::handlers::admin::SetNsfwRequest,
+            crate::handlers::admin::NsfwFlag,
+            crate::handlers::admin::ReportedMeme,
+            crate::handlers::admin::DismissedReports,
+            crate::services::reports::Report,
+            crate::services::reports::ReportReason,
+            crate::handlers::admin::PinState,
+            crate::handlers::admin::TagSuggestions,
+            crate::handlers::admin::ApproveTagsRequest,
+            crate::handlers::admin::MemeTags,
+            crate::services::tags::SuggestedTag,
+            crate::handlers::admin::SetAliasRequest,
+            crate::handlers::admin::AliasEntry,
+            crate::handlers::admin::ModeratedMeme,
+            crate::handlers::admin::UploadResponse,
+            crate::models::meme::MemeStatus,
+            crate::models::meme::MemeMetadata,
+            crate::models::meme::Orientation,
+            crate::services::meme::Flip,
+            crate::services::meme::Fit,
+            crate::services::meme::MediaKind,
+            crate::services::meme::RedirectTarget,
+            crate::services::meme::IconFormat
         )
     ),
+    modifiers(&SecurityAddon),
     tags(
         (name = "memes", description = "表情包相关API"),
-        (name = "statistics", description = "统计信息API")
+        (name = "statistics", description = "统计信息API"),
+        (name = "admin", description = "管理API (需要 API Key)")
     )
 )]
 pub struct ApiDoc;
 
-pub fn create_openapi_spec(config: &SwaggerConfig) -> utoipa::openapi::OpenApi {
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "api_key",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("x-api-key"))),
+            );
+        }
+    }
+}
+
+/// 关闭的接口对应的文档路径
+fn disabled_paths(endpoints: &EndpointsConfig) -> Vec<&'static str> {
+    let groups: [(bool, &[&str]); 14] = [
+        (endpoints.list, &["/memes/list"]),
+        (endpoints.search, &["/memes/search"]),
+        (endpoints.on_this_day, &["/memes/on-this-day"]),
+        (endpoints.catalog, &["/memes/catalog", "/memes/changes", "/memes/diff"]),
+        (endpoints.info, &["/memes/info/{id}"]),
+        (endpoints.count, &["/memes/count"]),
+        (endpoints.feed, &["/memes/feed.atom"]),
+        (endpoints.report, &["/memes/{id}/report"]),
+        (endpoints.caption, &["/memes/get/{id}/caption"]),
+        (endpoints.icon, &["/memes/get/{id}/icon"]),
+        (endpoints.collage, &["/memes/collage"]),
+        (endpoints.events, &["/events"]),
+        (endpoints.gallery, &["/gallery", "/sitemap.xml"]),
+        (endpoints.statistics, &["/statistics", "/statistics/trending"]),
+    ];
+    let mut paths: Vec<&'static str> = groups
+        .into_iter()
+        .filter(|(enabled, _)| !enabled)
+        .flat_map(|(_, paths)| paths.iter().copied())
+        .collect();
+    if !endpoints.metrics {
+        paths.push("/metrics");
+    }
+    paths
+}
+
+pub fn create_openapi_spec(config: &SwaggerConfig, endpoints: &EndpointsConfig) -> utoipa::openapi::OpenApi {
     let mut openapi = ApiDoc::openapi();
     
     // 更新 info 部分
@@ -49,12 +214,32 @@ pub fn create_openapi_spec(config: &SwaggerConfig) -> utoipa::openapi::OpenApi {
             .description(Some(config.server_description.clone()))
             .build()
     ]);
+
+    // 不记录已关闭的接口
+    for path in disabled_paths(endpoints) {
+        openapi.paths.paths.remove(path);
+    }
+    if !endpoints.admin {
+        openapi.paths.paths.retain(|path, _| !path.starts_with("/admin/"));
+    }
     
     openapi
 }
 
-pub fn create_swagger_ui(config: SwaggerConfig) -> SwaggerUi {
-    let openapi_spec = create_openapi_spec(&config);
-    SwaggerUi::new(config.endpoint)
-        .url("/api-docs/openapi.json", openapi_spec)
+/// Swagger UI 与 JSON、YAML 两种格式的接口文档，YAML 在启动时生成一次
+pub fn create_docs_router<S: Clone + Send + Sync + 'static>(config: SwaggerConfig, endpoints: &EndpointsConfig) -> Result<Router<S>> {
+    let openapi_spec = create_openapi_spec(&config, endpoints);
+    let yaml = serde_yaml::to_string(&openapi_spec)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize OpenAPI spec as YAML: {}", e)))?;
+
+    let swagger_ui: Router<S> = SwaggerUi::new(config.endpoint)
+        .url(OPENAPI_JSON_PATH, openapi_spec)
+        .into();
+    Ok(swagger_ui.route(
+        OPENAPI_YAML_PATH,
+        get(move || {
+            let yaml = yaml.clone();
+            async move { ([(header::CONTENT_TYPE, "application/yaml")], yaml) }
+        }),
+    ))
 }
\ No newline at end of file
@@ -0,0 +1,72 @@
+use std::net::SocketAddr;
+use axum::{extract::ConnectInfo, http::Request, Router};
+use hyper::body::Incoming;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+};
+use tokio::net::TcpListener;
+use tower::ServiceExt;
+use tracing::{debug, warn};
+use crate::config::LimitsConfig;
+use crate::metrics::{ACTIVE_CONNECTIONS, CONNECTIONS_TOTAL, REQUESTS_BY_PROTOCOL};
+use crate::middleware::throttle::ConnectionThrottle;
+
+/// 连接计数守卫，连接处理任务结束时自动减少活跃连接数
+struct ConnectionGuard;
+
+impl ConnectionGuard {
+    fn new() -> Self {
+        ACTIVE_CONNECTIONS.inc();
+        CONNECTIONS_TOTAL.inc();
+        Self
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        ACTIVE_CONNECTIONS.dec();
+    }
+}
+
+/// 启动 HTTP 服务，同一端口同时支持 HTTP/1.1 与 HTTP/2 (h2c)，
+/// 并统计活跃连接数与各协议的请求数；配置了限速时为每个连接创建一个令牌桶
+pub async fn serve(listener: TcpListener, app: Router, limits: &LimitsConfig) -> std::io::Result<()> {
+    let max_bytes_per_sec = limits.max_bytes_per_sec_per_conn;
+    loop {
+        let (socket, remote_addr) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                // 例如文件描述符耗尽，稍后重试而不是退出
+                warn!("接受连接失败: {}", e);
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+
+        let tower_service = app.clone();
+        tokio::spawn(async move {
+            let _guard = ConnectionGuard::new();
+            let socket = TokioIo::new(socket);
+            let throttle = (max_bytes_per_sec > 0).then(|| ConnectionThrottle::new(max_bytes_per_sec));
+
+            let hyper_service = hyper::service::service_fn(move |mut request: Request<Incoming>| {
+                request.extensions_mut().insert(ConnectInfo::<SocketAddr>(remote_addr));
+                if let Some(throttle) = &throttle {
+                    request.extensions_mut().insert(throttle.clone());
+                }
+                REQUESTS_BY_PROTOCOL
+                    .with_label_values(&[&format!("{:?}", request.version())])
+                    .inc();
+                tower_service.clone().oneshot(request)
+            });
+
+            if let Err(e) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(socket, hyper_service)
+                .await
+            {
+                debug!(remote_addr = %remote_addr, "连接处理结束: {}", e);
+            }
+        });
+    }
+}
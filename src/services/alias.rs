@@ -0,0 +1,104 @@
+use std::{collections::BTreeMap, path::PathBuf};
+use parking_lot::RwLock;
+use tracing::info;
+use crate::utils::error::{AppError, Result};
+use crate::utils::persist;
+
+/// 别名最大长度
+const MAX_ALIAS_LEN: usize = 64;
+
+/// 表情包别名存储，将 `wow` 这类易记的名称映射到表情包 ID，
+/// 保存在 YAML 文件中，通过管理接口修改后立即写回
+#[derive(Debug)]
+pub struct AliasStore {
+    path: PathBuf,
+    aliases: RwLock<BTreeMap<String, u32>>,
+}
+
+/// 规范化并校验别名：统一为小写，只允许字母、数字、`-` 与 `_`
+pub fn normalize_alias(name: &str) -> Result<String> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() || name.len() > MAX_ALIAS_LEN {
+        return Err(AppError::BadRequest(format!(
+            "Alias must be between 1 and {} characters",
+            MAX_ALIAS_LEN
+        )));
+    }
+    if !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        return Err(AppError::BadRequest(format!(
+            "Alias '{}' may only contain letters, digits, '-' and '_'",
+            name
+        )));
+    }
+    Ok(name)
+}
+
+impl AliasStore {
+    /// 从文件加载别名，文件不存在或无法解析时从空表开始
+    pub fn load(path: &str) -> Self {
+        let path = PathBuf::from(path);
+        let aliases = match persist::load_yaml::<BTreeMap<String, u32>>(&path, "别名文件") {
+            Some(aliases) => {
+                info!("已加载 {} 个表情包别名", aliases.len());
+                aliases
+            }
+            None => BTreeMap::new(),
+        };
+
+        Self {
+            path,
+            aliases: RwLock::new(aliases),
+        }
+    }
+
+    pub fn resolve(&self, name: &str) -> Option<u32> {
+        let name = normalize_alias(name).ok()?;
+        self.aliases.read().get(&name).copied()
+    }
+
+    pub fn list(&self) -> BTreeMap<String, u32> {
+        self.aliases.read().clone()
+    }
+
+    /// 设置别名，返回规范化后的名称
+    pub fn set(&self, name: &str, id: u32) -> Result<String> {
+        let name = normalize_alias(name)?;
+        let mut aliases = self.aliases.write();
+        aliases.insert(name.clone(), id);
+        self.save(&aliases)?;
+        Ok(name)
+    }
+
+    /// 删除别名，返回被删除别名对应的 ID
+    pub fn remove(&self, name: &str) -> Result<Option<u32>> {
+        let name = normalize_alias(name)?;
+        let mut aliases = self.aliases.write();
+        let removed = aliases.remove(&name);
+        if removed.is_some() {
+            self.save(&aliases)?;
+        }
+        Ok(removed)
+    }
+
+    /// 按 `旧 ID -> 新 ID` 改写别名指向的 ID，有变化时写回，返回改写的别名数
+    pub fn remap(&self, redirects: &BTreeMap<u32, u32>) -> Result<usize> {
+        let mut aliases = self.aliases.write();
+        let mut remapped = 0;
+        for id in aliases.values_mut() {
+            if let Some(new_id) = redirects.get(id) {
+                *id = *new_id;
+                remapped += 1;
+            }
+        }
+        if remapped > 0 {
+            self.save(&aliases)?;
+        }
+        Ok(remapped)
+    }
+
+    fn save(&self, aliases: &BTreeMap<String, u32>) -> Result<()> {
+        let content = serde_yaml::to_string(aliases)
+            .map_err(|e| AppError::Internal(format!("序列化别名失败: {}", e)))?;
+        persist::write(&self.path, &content)
+    }
+}
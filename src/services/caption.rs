@@ -0,0 +1,199 @@
+use ab_glyph::{point, Font, FontArc, PxScale, ScaleFont};
+use image::{ImageFormat, Rgba, RgbaImage};
+use std::io::Cursor;
+use tracing::info;
+use crate::config::CaptionConfig;
+use crate::utils::error::{AppError, Result};
+use crate::utils::media;
+
+/// 文字区域占图片宽度的比例
+const TEXT_WIDTH_RATIO: f32 = 0.94;
+/// 每段文字最多的行数，超出时缩小字号
+const MAX_LINES: usize = 3;
+/// 最小字号（像素）
+const MIN_FONT_PX: f32 = 12.0;
+
+const FILL: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const STROKE: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+/// 经典表情包上下文字渲染：白字黑边，自动换行并按图片大小选择字号
+pub struct CaptionRenderer {
+    font: FontArc,
+}
+
+impl std::fmt::Debug for CaptionRenderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CaptionRenderer").finish_non_exhaustive()
+    }
+}
+
+/// 排版结果：字号与换行后的各行文字
+struct Layout {
+    scale: PxScale,
+    lines: Vec<String>,
+}
+
+impl CaptionRenderer {
+    /// 加载配置的字体文件，字体需要包含中文字形 (例如 Noto Sans CJK)
+    pub fn load(config: &CaptionConfig) -> Result<Self> {
+        let bytes = std::fs::read(&config.font_path)
+            .map_err(|e| AppError::Config(format!("Failed to read caption font {}: {}", config.font_path, e)))?;
+        let font = FontArc::try_from_vec(bytes)
+            .map_err(|e| AppError::Config(format!("Invalid caption font {}: {}", config.font_path, e)))?;
+        info!("已加载配文字体 {}", config.font_path);
+        Ok(Self { font })
+    }
+
+    /// 在图片顶部与底部绘制文字，输出 PNG
+    pub fn render(&self, content: &[u8], top: &str, bottom: &str) -> Result<Vec<u8>> {
+        let mut img = media::decode(content)?.to_rgba8();
+
+        let (width, height) = img.dimensions();
+        let margin = (height as f32 * 0.03).max(2.0);
+
+        if !top.trim().is_empty() {
+            let layout = self.layout(top.trim(), width, height);
+            self.draw_lines(&mut img, &layout, margin);
+        }
+        if !bottom.trim().is_empty() {
+            let layout = self.layout(bottom.trim(), width, height);
+            let block_height = self.line_height(layout.scale) * layout.lines.len() as f32;
+            self.draw_lines(&mut img, &layout, height as f32 - margin - block_height);
+        }
+
+        let mut cursor = Cursor::new(Vec::new());
+        img.write_to(&mut cursor, ImageFormat::Png)
+            .map_err(|e| AppError::ImageProcessing(format!("Failed to encode image: {}", e)))?;
+        Ok(cursor.into_inner())
+    }
+
+    /// 在透明背景上绘制一行白字黑边文字，画布大小正好容纳文字，用作文字水印
+    pub fn render_label(&self, text: &str, size: f32) -> RgbaImage {
+        let scale = PxScale::from(size.max(MIN_FONT_PX));
+        let stroke = (scale.y / 16.0).max(1.0) as i32;
+        let width = self.text_width(scale, text).ceil() as u32 + stroke as u32 * 2;
+        let height = self.font.as_scaled(scale).height().ceil() as u32 + stroke as u32 * 2;
+        let mut img = RgbaImage::new(width.max(1), height.max(1));
+
+        let layout = Layout { scale, lines: vec![text.to_string()] };
+        self.draw_lines(&mut img, &layout, stroke as f32);
+        img
+    }
+
+    fn line_height(&self, scale: PxScale) -> f32 {
+        let scaled = self.font.as_scaled(scale);
+        scaled.height() + scaled.line_gap()
+    }
+
+    fn text_width(&self, scale: PxScale, text: &str) -> f32 {
+        let scaled = self.font.as_scaled(scale);
+        let mut width = 0.0;
+        let mut previous = None;
+        for c in text.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                width += scaled.kern(previous, id);
+            }
+            width += scaled.h_advance(id);
+            previous = Some(id);
+        }
+        width
+    }
+
+    /// 从图片高度的 1/8 开始尝试字号，行数过多时逐步缩小
+    fn layout(&self, text: &str, width: u32, height: u32) -> Layout {
+        let max_width = width as f32 * TEXT_WIDTH_RATIO;
+        let mut size = (height as f32 / 8.0).max(MIN_FONT_PX);
+
+        loop {
+            let scale = PxScale::from(size);
+            let lines = self.wrap(text, scale, max_width);
+            if lines.len() <= MAX_LINES || size <= MIN_FONT_PX {
+                return Layout { scale, lines };
+            }
+            size = (size * 0.85).max(MIN_FONT_PX);
+        }
+    }
+
+    /// 按字符贪心换行；英文优先在空格处断开，中文可在任意字符间断开
+    fn wrap(&self, text: &str, scale: PxScale, max_width: f32) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut current = String::new();
+
+        for c in text.chars() {
+            current.push(c);
+            if current.chars().count() > 1 && self.text_width(scale, &current) > max_width {
+                current.pop();
+                let rest = match current.rfind(' ') {
+                    Some(pos) if pos > 0 && c.is_ascii_alphanumeric() => current.split_off(pos + 1),
+                    _ => String::new(),
+                };
+                lines.push(current.trim().to_string());
+                current = rest;
+                current.push(c);
+            }
+        }
+        if !current.trim().is_empty() {
+            lines.push(current.trim().to_string());
+        }
+        lines
+    }
+
+    /// 从 `top` 开始逐行居中绘制
+    fn draw_lines(&self, img: &mut RgbaImage, layout: &Layout, top: f32) {
+        let scaled = self.font.as_scaled(layout.scale);
+        let line_height = self.line_height(layout.scale);
+        let stroke = (layout.scale.y / 16.0).max(1.0) as i32;
+
+        for (i, line) in layout.lines.iter().enumerate() {
+            let line_width = self.text_width(layout.scale, line);
+            let x = (img.width() as f32 - line_width) / 2.0;
+            let baseline = top + scaled.ascent() + line_height * i as f32;
+
+            // 先在周围一圈绘制黑色描边，再绘制白色文字
+            for dy in -stroke..=stroke {
+                for dx in -stroke..=stroke {
+                    if (dx != 0 || dy != 0) && dx * dx + dy * dy <= stroke * stroke {
+                        self.draw_text(img, layout.scale, line, x + dx as f32, baseline + dy as f32, STROKE);
+                    }
+                }
+            }
+            self.draw_text(img, layout.scale, line, x, baseline, FILL);
+        }
+    }
+
+    fn draw_text(&self, img: &mut RgbaImage, scale: PxScale, text: &str, x: f32, baseline: f32, color: Rgba<u8>) {
+        let scaled = self.font.as_scaled(scale);
+        let (width, height) = img.dimensions();
+        let mut caret = x;
+        let mut previous = None;
+
+        for c in text.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                caret += scaled.kern(previous, id);
+            }
+            let glyph = id.with_scale_and_position(scale, point(caret, baseline));
+            caret += scaled.h_advance(id);
+            previous = Some(id);
+
+            let Some(outlined) = self.font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i32 + gx as i32;
+                let py = bounds.min.y as i32 + gy as i32;
+                if px < 0 || py < 0 || px >= width as i32 || py >= height as i32 {
+                    return;
+                }
+                let pixel = img.get_pixel_mut(px as u32, py as u32);
+                let alpha = coverage.clamp(0.0, 1.0);
+                for (channel, target) in pixel.0.iter_mut().zip(color.0).take(3) {
+                    *channel = (*channel as f32 * (1.0 - alpha) + target as f32 * alpha).round() as u8;
+                }
+                pixel[3] = pixel[3].max((alpha * 255.0) as u8);
+            });
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    io::Write,
+};
+use axum::body::Bytes;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+use crate::models::meme::Meme;
+use crate::utils::error::{AppError, Result};
+
+/// brotli 压缩等级 (0-11)，目录在重载时压缩一次，取较高的压缩率
+const BROTLI_QUALITY: u32 = 9;
+const BROTLI_WINDOW: u32 = 22;
+/// zstd 压缩等级
+const ZSTD_LEVEL: i32 = 10;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CatalogEntry {
+    #[schema(example = 1)]
+    pub id: u32,
+    #[schema(example = "funny_meme.jpg")]
+    pub filename: String,
+    #[schema(example = "image/jpeg")]
+    pub mime_type: String,
+    #[schema(example = 1024)]
+    pub size_bytes: u64,
+    #[schema(example = 640)]
+    pub width: Option<u32>,
+    #[schema(example = 480)]
+    pub height: Option<u32>,
+    #[schema(example = "/memes/get/1")]
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Catalog {
+    /// 本次启动的标识，版本号只在同一标识下可比较
+    #[schema(example = "5f1c0a9e3b7d2c4e")]
+    pub epoch: String,
+    /// 目录版本号，每次成功重载后递增
+    #[schema(example = 1)]
+    pub generation: u64,
+    #[schema(example = 100)]
+    pub count: usize,
+    pub memes: Vec<CatalogEntry>,
+}
+
+/// 预序列化、预压缩的完整表情包目录，在每次重载后重新生成
+#[derive(Debug, Clone, Default)]
+pub struct CatalogSnapshot {
+    pub generation: u64,
+    /// JSON 内容的 SHA-256 (十六进制)
+    pub hash: String,
+    pub json: Bytes,
+    pub brotli: Bytes,
+    pub zstd: Bytes,
+}
+
+impl CatalogSnapshot {
+    /// 序列化并压缩目录，比较耗 CPU，应在阻塞线程池中调用
+    pub fn build<'a>(epoch: &str, generation: u64, memes: impl Iterator<Item = &'a Meme>) -> Result<Self> {
+        let mut entries: Vec<CatalogEntry> = memes
+            .map(|meme| CatalogEntry {
+                id: meme.id,
+                filename: meme.filename.clone(),
+                mime_type: meme.mime_type.clone(),
+                size_bytes: meme.size_bytes,
+                width: meme.width,
+                height: meme.height,
+                path: format!("/memes/get/{}", meme.id),
+            })
+            .collect();
+        entries.sort_by_key(|entry| entry.id);
+
+        let catalog = Catalog {
+            epoch: epoch.to_string(),
+            generation,
+            count: entries.len(),
+            memes: entries,
+        };
+        let json = serde_json::to_vec(&catalog)
+            .map_err(|e| AppError::Internal(format!("序列化表情包目录失败: {}", e)))?;
+
+        let mut brotli = Vec::new();
+        {
+            let mut writer = brotli::CompressorWriter::new(&mut brotli, 4096, BROTLI_QUALITY, BROTLI_WINDOW);
+            writer.write_all(&json)?;
+        }
+        let zstd = zstd::encode_all(&json[..], ZSTD_LEVEL)?;
+
+        Ok(Self {
+            generation,
+            hash: format!("{:x}", Sha256::digest(&json)),
+            json: Bytes::from(json),
+            brotli: Bytes::from(brotli),
+            zstd: Bytes::from(zstd),
+        })
+    }
+
+    /// 基于目录内容与编码方式的 ETag；版本号在重启后从头计数、各实例互不相同，不能作为 ETag
+    pub fn etag(&self, encoding: &str) -> String {
+        format!("\"catalog-{}-{}\"", &self.hash[..self.hash.len().min(32)], encoding)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CatalogChanges {
+    /// 本次启动的标识，客户端下次请求时带上
+    #[schema(example = "5f1c0a9e3b7d2c4e")]
+    pub epoch: String,
+    /// 当前目录版本号
+    #[schema(example = 5)]
+    pub generation: u64,
+    #[schema(example = 3)]
+    pub since_generation: u64,
+    /// 请求的版本过旧、变更记录已被淘汰，或版本号来自另一次启动时为 true，客户端需要重新获取完整目录
+    #[schema(example = false)]
+    pub resync_required: bool,
+    pub added: Vec<u32>,
+    pub removed: Vec<u32>,
+}
+
+/// 镜像端已有的一个表情包
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct KnownMeme {
+    #[schema(example = 1)]
+    pub id: u32,
+    /// 镜像端保存的内容的 SHA-256 (十六进制)，用于检测同一 ID 的内容变化
+    #[schema(example = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08")]
+    pub sha256: Option<String>,
+}
+
+/// 镜像端已有的表情包，可以只给 ID、只给内容哈希，或同时给出两者
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+pub struct DiffRequest {
+    /// 只按 ID 比较
+    #[serde(default)]
+    pub ids: Vec<u32>,
+    /// 只按内容比较 (SHA-256，十六进制)，适合按内容存储的镜像
+    #[serde(default)]
+    pub hashes: Vec<String>,
+    /// ID 与内容哈希，可检测内容变化
+    #[serde(default)]
+    pub memes: Vec<KnownMeme>,
+}
+
+impl DiffRequest {
+    pub fn entry_count(&self) -> usize {
+        self.ids.len() + self.hashes.len() + self.memes.len()
+    }
+}
+
+/// 镜像端与当前目录的差异
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CatalogDiff {
+    /// 当前目录版本号
+    #[schema(example = 5)]
+    pub generation: u64,
+    /// 目录中有、镜像端没有的表情包 ID (既不在已知 ID 中，内容哈希也不在已知哈希中)
+    pub missing: Vec<u32>,
+    /// 镜像端有、目录中已不存在的表情包 ID
+    pub extra: Vec<u32>,
+    /// ID 仍存在但内容哈希与镜像端不同的表情包 ID
+    pub changed: Vec<u32>,
+    /// 镜像端有、目录中已不存在的内容哈希
+    pub extra_hashes: Vec<String>,
+}
+
+impl CatalogDiff {
+    /// 比较目录与镜像端已有的表情包；未计算内容哈希的表情包无法判断是否变化，也无法按哈希匹配
+    pub fn compute<'a>(generation: u64, memes: impl Iterator<Item = &'a Meme>, request: &DiffRequest) -> Self {
+        let catalog: HashMap<u32, Option<&str>> = memes
+            .map(|meme| (meme.id, meme.content_hash.as_deref()))
+            .collect();
+        let catalog_hashes: HashSet<&str> = catalog.values().flatten().copied().collect();
+
+        let known_ids: HashSet<u32> = request.ids.iter()
+            .copied()
+            .chain(request.memes.iter().map(|meme| meme.id))
+            .collect();
+        let known_hashes: HashSet<String> = request.hashes.iter()
+            .chain(request.memes.iter().filter_map(|meme| meme.sha256.as_ref()))
+            .map(|hash| hash.to_ascii_lowercase())
+            .collect();
+
+        let mut missing: Vec<u32> = catalog.iter()
+            .filter(|(id, hash)| {
+                !known_ids.contains(*id) && !hash.is_some_and(|hash| known_hashes.contains(hash))
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        let mut extra: Vec<u32> = known_ids.iter()
+            .filter(|id| !catalog.contains_key(*id))
+            .copied()
+            .collect();
+        let changed: Vec<u32> = request.memes.iter()
+            .filter_map(|known| {
+                let current = (*catalog.get(&known.id)?)?;
+                let sha256 = known.sha256.as_deref()?;
+                (!sha256.eq_ignore_ascii_case(current)).then_some(known.id)
+            })
+            .collect::<BTreeSet<u32>>()
+            .into_iter()
+            .collect();
+        let extra_hashes: Vec<String> = request.hashes.iter()
+            .map(|hash| hash.to_ascii_lowercase())
+            .filter(|hash| !catalog_hashes.contains(hash.as_str()))
+            .collect::<BTreeSet<String>>()
+            .into_iter()
+            .collect();
+
+        missing.sort_unstable();
+        extra.sort_unstable();
+        Self { generation, missing, extra, changed, extra_hashes }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct GenerationDelta {
+    generation: u64,
+    added: Vec<u32>,
+    removed: Vec<u32>,
+}
+
+/// 最近若干次重载的目录变更记录，供客户端增量同步
+#[derive(Debug)]
+pub struct ChangeLog {
+    deltas: VecDeque<GenerationDelta>,
+    capacity: usize,
+}
+
+impl ChangeLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            deltas: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// 记录一次重载前后的 ID 变化
+    pub fn record(&mut self, generation: u64, previous: &HashSet<u32>, current: &HashSet<u32>) {
+        let mut added: Vec<u32> = current.difference(previous).copied().collect();
+        let mut removed: Vec<u32> = previous.difference(current).copied().collect();
+        added.sort_unstable();
+        removed.sort_unstable();
+
+        if self.deltas.len() == self.capacity {
+            self.deltas.pop_front();
+        }
+        self.deltas.push_back(GenerationDelta {
+            generation,
+            added,
+            removed,
+        });
+    }
+
+    /// 合并 `since` 之后的所有变更；所需记录已被淘汰，或 `since` 比当前版本还新 (来自重启前) 时返回 None
+    pub fn changes_since(&self, since: u64, current: u64) -> Option<(Vec<u32>, Vec<u32>)> {
+        if since > current {
+            return None;
+        }
+        if since == current {
+            return Some((Vec::new(), Vec::new()));
+        }
+        let oldest = self.deltas.front()?.generation;
+        if since + 1 < oldest {
+            return None;
+        }
+
+        let mut added = BTreeSet::new();
+        let mut removed = BTreeSet::new();
+        for delta in self.deltas.iter().filter(|d| d.generation > since) {
+            for id in &delta.added {
+                if !removed.remove(id) {
+                    added.insert(*id);
+                }
+            }
+            for id in &delta.removed {
+                if !added.remove(id) {
+                    removed.insert(*id);
+                }
+            }
+        }
+
+        Some((added.into_iter().collect(), removed.into_iter().collect()))
+    }
+}
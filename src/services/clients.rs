@@ -0,0 +1,89 @@
+use std::{
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use moka::future::Cache;
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::config::StatisticsConfig;
+use crate::services::user_agents::{AgentCount, UserAgentStats};
+
+/// 客户端的活跃窗口：超过该时间没有请求的客户端不再计入
+const ACTIVE_WINDOW: Duration = Duration::from_secs(3600);
+
+/// 单个客户端的请求统计
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ClientStat {
+    #[schema(example = "203.0.113.7")]
+    pub ip: String,
+    /// 本次活跃期间 (两次请求间隔不超过一小时) 的请求数
+    #[schema(example = 120)]
+    pub requests: u64,
+}
+
+/// 按客户端 IP 与 User-Agent 分组统计请求
+///
+/// 使用有容量上限、一小时空闲过期的缓存保存计数，内存占用有界；
+/// 达到上限时淘汰最少访问的客户端，因此统计结果在客户端极多时是近似值
+#[derive(Debug)]
+pub struct ClientTracker {
+    clients: Cache<IpAddr, Arc<AtomicU64>>,
+    agents: UserAgentStats,
+}
+
+impl ClientTracker {
+    pub fn new(config: &StatisticsConfig) -> Self {
+        Self {
+            clients: Cache::builder()
+                .max_capacity(config.max_tracked_clients)
+                .time_to_idle(ACTIVE_WINDOW)
+                .build(),
+            agents: UserAgentStats::default(),
+        }
+    }
+
+    pub async fn record(&self, ip: IpAddr) {
+        let counter = self.clients
+            .get_with(ip, async { Arc::new(AtomicU64::new(0)) })
+            .await;
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_user_agent(&self, user_agent: Option<&str>) {
+        self.agents.record(user_agent);
+    }
+
+    /// 本次启动以来各类 User-Agent 的请求数
+    pub fn user_agents(&self) -> Vec<AgentCount> {
+        self.agents.counts()
+    }
+
+    /// 最近一小时内有请求的客户端数
+    pub async fn unique_clients_last_hour(&self) -> u64 {
+        self.clients.run_pending_tasks().await;
+        self.clients.entry_count()
+    }
+
+    pub fn reset(&self) {
+        self.clients.invalidate_all();
+        self.agents.reset();
+    }
+
+    /// 请求数最多的 `limit` 个客户端
+    pub fn top_clients(&self, limit: usize) -> Vec<ClientStat> {
+        let mut clients: Vec<ClientStat> = self.clients
+            .iter()
+            .map(|(ip, counter)| ClientStat {
+                ip: ip.to_string(),
+                requests: counter.load(Ordering::Relaxed),
+            })
+            .collect();
+        clients.sort_by(|a, b| b.requests.cmp(&a.requests).then_with(|| a.ip.cmp(&b.ip)));
+        clients.truncate(limit);
+        clients
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use crate::config::{ClusterConfig, ShardMode, StorageConfig};
+use crate::services::scan;
+use crate::utils::secret;
+
+/// 集群内部请求携带共享密钥的请求头
+pub const CLUSTER_TOKEN_HEADER: &str = "x-cluster-token";
+
+/// 向单个节点发送失效通知的最大尝试次数
+const MAX_ATTEMPTS: u32 = 3;
+
+/// 节点间传递的缓存失效通知
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvalidateMessage {
+    /// 发出通知的节点
+    pub node_id: String,
+    /// 发出通知的节点重载后的目录版本号
+    pub generation: u64,
+}
+
+/// 基于 HTTP 的轻量缓存失效总线
+///
+/// 任一节点因本地文件变更而重载后，会通知配置中的所有其他节点一起重载并清空缓存；
+/// 由其他节点通知触发的重载不会再次广播，避免消息循环
+#[derive(Debug)]
+pub struct ClusterBus {
+    client: reqwest::Client,
+    node_id: String,
+    peers: Vec<String>,
+    token: String,
+}
+
+impl ClusterBus {
+    pub fn new(config: &ClusterConfig) -> Option<Arc<Self>> {
+        if !config.enabled || config.peers.is_empty() {
+            return None;
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .ok()?;
+
+        Some(Arc::new(Self {
+            client,
+            node_id: config.node_id.clone(),
+            peers: config.peers.iter().map(|p| p.trim_end_matches('/').to_string()).collect(),
+            token: config.token.clone(),
+        }))
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// 异步通知所有节点重载，失败时按指数退避重试
+    pub fn broadcast_invalidate(self: &Arc<Self>, generation: u64) {
+        for peer in &self.peers {
+            let bus = Arc::clone(self);
+            let url = format!("{}/cluster/invalidate", peer);
+            tokio::spawn(async move {
+                let message = InvalidateMessage {
+                    node_id: bus.node_id.clone(),
+                    generation,
+                };
+                let mut backoff = Duration::from_millis(500);
+
+                for attempt in 1..=MAX_ATTEMPTS {
+                    let result = bus.client
+                        .post(&url)
+                        .header(CLUSTER_TOKEN_HEADER, &bus.token)
+                        .json(&message)
+                        .send()
+                        .await
+                        .and_then(|resp| resp.error_for_status());
+
+                    match result {
+                        Ok(_) => {
+                            debug!("已通知节点 {} 重载", url);
+                            return;
+                        }
+                        Err(e) if attempt < MAX_ATTEMPTS => {
+                            debug!("通知节点 {} 失败 (第 {} 次)，{:?} 后重试: {}", url, attempt, backoff, e);
+                            tokio::time::sleep(backoff).await;
+                            backoff *= 2;
+                        }
+                        Err(e) => warn!("通知节点 {} 重载失败: {}", url, e),
+                    }
+                }
+            });
+        }
+    }
+
+    /// 校验其他节点发来的共享密钥
+    pub fn verify_token(&self, token: Option<&str>) -> bool {
+        !self.token.is_empty() && token.is_some_and(|token| secret::eq(&self.token, token))
+    }
+}
+
+/// 按表情包 ID 把请求分配到各节点，使同一表情包的压缩图只在一个节点上生成和缓存
+///
+/// 使用 jump consistent hash，分片数变化时只有约 1/n 的 ID 需要换节点
+#[derive(Debug)]
+pub struct ShardRouter {
+    client: reqwest::Client,
+    index: usize,
+    nodes: Vec<String>,
+    mode: ShardMode,
+    token: String,
+}
+
+impl ShardRouter {
+    pub fn new(config: &ClusterConfig) -> Option<Self> {
+        let shard = &config.shard;
+        if !config.enabled || !shard.enabled || shard.count <= 1 {
+            return None;
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .ok()?;
+
+        info!("已启用分片，本节点负责分片 {}/{} ({:?})", shard.index, shard.count, shard.mode);
+        Some(Self {
+            client,
+            index: shard.index,
+            nodes: shard.nodes.iter().map(|n| n.trim_end_matches('/').to_string()).collect(),
+            mode: shard.mode,
+            token: config.token.clone(),
+        })
+    }
+
+    /// 负责该 ID 的分片序号
+    pub fn shard_of(&self, id: u32) -> usize {
+        jump_hash(u64::from(id), self.nodes.len())
+    }
+
+    /// 该 ID 不由本节点负责时返回负责节点的地址
+    pub fn owner(&self, id: u32) -> Option<&str> {
+        let shard = self.shard_of(id);
+        (shard != self.index).then(|| self.nodes[shard].as_str())
+    }
+
+    pub fn mode(&self) -> ShardMode {
+        self.mode
+    }
+
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// 请求是否由其他节点转发而来，转发的请求总是在本地处理，避免节点配置不一致时来回转发
+    pub fn is_forwarded(&self, token: Option<&str>) -> bool {
+        !self.token.is_empty() && token.is_some_and(|token| secret::eq(&self.token, token))
+    }
+}
+
+/// Lamping & Veach, "A Fast, Minimal Memory, Consistent Hash Algorithm"
+fn jump_hash(mut key: u64, buckets: usize) -> usize {
+    let (mut b, mut j) = (-1i64, 0i64);
+    while j < buckets as i64 {
+        b = j;
+        key = key.wrapping_mul(2862933555777941757).wrapping_add(1);
+        j = ((b + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    b as usize
+}
+
+/// 计算目录下文件列表的指纹 (文件路径、大小、修改时间)，
+/// 用于共享存储 (如 NFS) 上收不到文件事件时由主节点轮询检测变更
+pub async fn directory_fingerprint(dir: &Path, recursive: bool, concurrency: usize) -> std::io::Result<u64> {
+    let mut files = Vec::new();
+    for path in scan::list_files(dir, recursive, concurrency).await? {
+        let metadata = tokio::fs::metadata(&path).await?;
+        files.push((path, metadata.len(), metadata.modified().ok()));
+    }
+
+    let mut hasher = DefaultHasher::new();
+    files.hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+/// 启动主节点的目录轮询任务，检测到变化时通过 `on_change` 触发重载
+pub fn start_poll_task<F>(dir: PathBuf, storage: &StorageConfig, interval_secs: u64, on_change: F)
+where
+    F: Fn() + Send + 'static,
+{
+    let (recursive, concurrency) = (storage.recursive, storage.scan_concurrency);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        let mut last = None;
+        info!("主节点开始轮询目录变更: {:?}", dir);
+
+        loop {
+            interval.tick().await;
+            match directory_fingerprint(&dir, recursive, concurrency).await {
+                Ok(fingerprint) => {
+                    if last.is_some_and(|prev| prev != fingerprint) {
+                        info!("轮询检测到目录变更");
+                        on_change();
+                    }
+                    last = Some(fingerprint);
+                }
+                Err(e) => warn!("轮询目录 {:?} 失败: {}", dir, e),
+            }
+        }
+    });
+}
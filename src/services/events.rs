@@ -0,0 +1,62 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+use crate::services::meme::ReloadTrigger;
+
+/// 每个订阅者最多积压的事件数，超出后订阅者会收到 `resync` 事件
+const EVENT_BUFFER: usize = 256;
+
+/// 推送给 `/events` 订阅者的目录事件
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum CatalogEvent {
+    ReloadStarted { trigger: ReloadTrigger },
+    ReloadCompleted { generation: u64, added: usize, removed: usize, duration_ms: u64 },
+    /// 失败原因可能包含文件路径，不推送，需要时通过管理接口查看
+    ReloadFailed { generation: u64 },
+    MemesAdded { generation: u64, ids: Vec<u32> },
+    MemesRemoved { generation: u64, ids: Vec<u32> },
+}
+
+impl CatalogEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CatalogEvent::ReloadStarted { .. } => "reload_started",
+            CatalogEvent::ReloadCompleted { .. } => "reload_completed",
+            CatalogEvent::ReloadFailed { .. } => "reload_failed",
+            CatalogEvent::MemesAdded { .. } => "memes_added",
+            CatalogEvent::MemesRemoved { .. } => "memes_removed",
+        }
+    }
+}
+
+/// 进程内的目录事件广播，没有订阅者时事件直接丢弃
+#[derive(Debug)]
+pub struct EventBus {
+    tx: broadcast::Sender<CatalogEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(EVENT_BUFFER);
+        Self { tx }
+    }
+
+    pub fn publish(&self, event: CatalogEvent) {
+        let _ = self.tx.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<CatalogEvent> {
+        self.tx.subscribe()
+    }
+
+    pub fn subscribers(&self) -> usize {
+        self.tx.receiver_count()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,264 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use crate::utils::error::{AppError, Result};
+use crate::utils::persist;
+
+/// ID 映射文件中的一个表情包
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdMapEntry {
+    pub id: u32,
+    /// NFC 形式的文件名 (相对于表情包目录)
+    pub filename: String,
+    /// 原图的 SHA-256，文件改名后据此找回原来的 ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// 迁移前由文件名哈希计算的 ID，迁移后新增的文件没有
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_id: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IdMapFile {
+    /// 下一个待分配的 ID
+    next_id: u32,
+    entries: Vec<IdMapEntry>,
+}
+
+/// 内存中的 ID 映射表：持久化的内容加上按文件名的索引与已占用的 ID (含旧 ID)
+#[derive(Debug, Default)]
+struct IdMap {
+    file: IdMapFile,
+    by_name: HashMap<String, usize>,
+    used: HashSet<u32>,
+}
+
+impl IdMap {
+    fn new(file: IdMapFile) -> Self {
+        let by_name = file.entries.iter()
+            .enumerate()
+            .map(|(index, entry)| (entry.filename.clone(), index))
+            .collect();
+        let used = file.entries.iter()
+            .flat_map(|entry| std::iter::once(entry.id).chain(entry.old_id))
+            .collect();
+        Self { file, by_name, used }
+    }
+
+    fn find(&self, filename: &str) -> Option<usize> {
+        self.by_name.get(filename).copied()
+    }
+
+    /// 分配一个未使用的 ID，跳过迁移前的旧 ID，避免旧链接指向别的表情包
+    fn allocate(&mut self) -> u32 {
+        let mut id = self.file.next_id.max(1);
+        while self.used.contains(&id) {
+            id += 1;
+        }
+        self.file.next_id = id + 1;
+        id
+    }
+
+    fn push(&mut self, entry: IdMapEntry) {
+        self.used.extend(std::iter::once(entry.id).chain(entry.old_id));
+        self.by_name.insert(entry.filename.clone(), self.file.entries.len());
+        self.file.entries.push(entry);
+    }
+
+    fn rename(&mut self, index: usize, filename: &str) {
+        let entry = &mut self.file.entries[index];
+        if self.by_name.get(&entry.filename) == Some(&index) {
+            self.by_name.remove(&entry.filename);
+        }
+        entry.filename = filename.to_string();
+        self.by_name.insert(entry.filename.clone(), index);
+    }
+}
+
+/// `storage.id_scheme: stable` 的 ID 分配表，持久化为 JSON 文件
+///
+/// 按文件名查找已分配的 ID；找不到时如果有内容相同、但文件已不存在的记录，视为改名并沿用其 ID，
+/// 否则分配新 ID。记录只增不删，删除后恢复的文件会拿回原来的 ID
+#[derive(Debug)]
+pub struct IdRegistry {
+    path: PathBuf,
+    map: Mutex<IdMap>,
+}
+
+impl IdRegistry {
+    /// 从文件加载，文件不存在时从空表开始 (供 `ids migrate` 使用)；文件无法解析时返回错误，
+    /// 不从空表重新分配，否则保存时会覆盖原文件、永久改变所有 ID
+    pub fn load(path: &str) -> Result<Self> {
+        let path = PathBuf::from(path);
+        let map = match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                let map = serde_json::from_str::<IdMapFile>(&content)
+                    .map_err(|e| AppError::Internal(format!("ID 映射文件 {:?} 无法解析: {}", path, e)))?;
+                info!("已加载 {} 个表情包的 ID 映射", map.entries.len());
+                IdMap::new(map)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => IdMap::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            path,
+            map: Mutex::new(map),
+        })
+    }
+
+    /// 服务启动时加载：文件必须已由 `ids migrate` 生成，避免未迁移就切换方式时整个目录被重新编号且没有跳转
+    pub fn open(path: &str) -> Result<Self> {
+        if !Path::new(path).exists() {
+            return Err(AppError::Internal(format!(
+                "storage.id_scheme 为 stable，但 ID 映射文件 {} 不存在，请先运行 `ids migrate`",
+                path
+            )));
+        }
+        Self::load(path)
+    }
+
+    /// 为一次完整扫描得到的全部文件 `(文件名, 内容哈希)` 分配 ID，顺序与输入一致；有变化时写回文件
+    pub fn assign(&self, files: &[(&str, Option<&str>)]) -> Result<Vec<u32>> {
+        self.assign_with(files, |_| None)
+    }
+
+    /// 同 [`IdRegistry::assign`]，新增的记录附带 `old_id(文件名)` 返回的旧 ID，供 `ids migrate` 使用
+    pub fn assign_with(&self, files: &[(&str, Option<&str>)], old_id: impl Fn(&str) -> Option<u32>) -> Result<Vec<u32>> {
+        let mut map = self.map.lock();
+        let mut changed = false;
+        let present: HashSet<&str> = files.iter().map(|(filename, _)| *filename).collect();
+
+        // 文件已不存在的记录按内容哈希索引，用于识别改名
+        let mut by_hash: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, entry) in map.file.entries.iter().enumerate() {
+            if let Some(hash) = entry.content_hash.as_ref().filter(|_| !present.contains(entry.filename.as_str())) {
+                by_hash.entry(hash.clone()).or_default().push(index);
+            }
+        }
+
+        // 先占用全部输入文件的旧 ID，否则较早分配的新 ID 可能等于列表中靠后文件的旧 ID
+        let old_ids: Vec<u32> = files.iter().filter_map(|(filename, _)| old_id(filename)).collect();
+        map.used.extend(old_ids);
+
+        let mut ids = Vec::with_capacity(files.len());
+        for &(filename, hash) in files {
+            let index = map.find(filename).or_else(|| {
+                let index = hash.and_then(|hash| by_hash.get_mut(hash)).and_then(|indices| indices.pop())?;
+                let entry = &map.file.entries[index];
+                info!("文件 {} 改名为 {}，沿用 ID {}", entry.filename, filename, entry.id);
+                map.rename(index, filename);
+                changed = true;
+                Some(index)
+            });
+
+            let id = match index {
+                Some(index) => {
+                    let entry = &mut map.file.entries[index];
+                    if hash.is_some() && entry.content_hash.as_deref() != hash {
+                        entry.content_hash = hash.map(str::to_string);
+                        changed = true;
+                    }
+                    entry.id
+                }
+                None => {
+                    let id = map.allocate();
+                    map.push(IdMapEntry {
+                        id,
+                        filename: filename.to_string(),
+                        content_hash: hash.map(str::to_string),
+                        old_id: old_id(filename),
+                    });
+                    changed = true;
+                    id
+                }
+            };
+            ids.push(id);
+        }
+
+        if changed {
+            self.save(&map)?;
+        }
+        Ok(ids)
+    }
+
+    /// 上传的新文件：按文件名查找或分配 ID，不按内容识别改名 (上传与已有文件内容相同时不应抢走其 ID)
+    pub fn reserve(&self, filename: &str, hash: Option<&str>) -> Result<u32> {
+        let mut map = self.map.lock();
+        if let Some(index) = map.find(filename) {
+            return Ok(map.file.entries[index].id);
+        }
+        let id = map.allocate();
+        map.push(IdMapEntry {
+            id,
+            filename: filename.to_string(),
+            content_hash: hash.map(str::to_string),
+            old_id: None,
+        });
+        self.save(&map)?;
+        Ok(id)
+    }
+
+    /// 已分配给该文件名的 ID
+    pub fn get(&self, filename: &str) -> Option<u32> {
+        let map = self.map.lock();
+        map.find(filename).map(|index| map.file.entries[index].id)
+    }
+
+    /// 旧 ID 到新 ID 的跳转表，只包含两者不同的记录
+    pub fn redirects(&self) -> BTreeMap<u32, u32> {
+        self.map.lock().file.entries.iter()
+            .filter_map(|entry| entry.old_id.filter(|old| *old != entry.id).map(|old| (old, entry.id)))
+            .collect()
+    }
+
+    /// 已分配的 ID 数
+    pub fn entry_count(&self) -> usize {
+        self.map.lock().file.entries.len()
+    }
+
+    fn save(&self, map: &IdMap) -> Result<()> {
+        let content = serde_json::to_string_pretty(&map.file)
+            .map_err(|e| AppError::Internal(format!("序列化 ID 映射失败: {}", e)))?;
+        persist::write(&self.path, &content)
+    }
+}
+
+/// 旧 ID 到新 ID 的 301 跳转表，启动时加载，只对当前目录中不存在的 ID 生效
+#[derive(Debug, Default)]
+pub struct IdRedirects(HashMap<u32, u32>);
+
+impl IdRedirects {
+    /// 文件不存在或为空路径时返回空表，无法解析时见 [`persist::load_json`]
+    pub fn load(path: &str) -> Self {
+        if path.is_empty() {
+            return Self::default();
+        }
+        let redirects = match persist::load_json::<BTreeMap<u32, u32>>(Path::new(path), "旧 ID 跳转表") {
+            Some(redirects) => {
+                info!("已加载 {} 条旧 ID 跳转", redirects.len());
+                redirects.into_iter().collect()
+            }
+            None => HashMap::new(),
+        };
+        Self(redirects)
+    }
+
+    pub fn save(path: &str, redirects: &BTreeMap<u32, u32>) -> Result<()> {
+        let content = serde_json::to_string_pretty(redirects)
+            .map_err(|e| AppError::Internal(format!("序列化旧 ID 跳转表失败: {}", e)))?;
+        persist::write(Path::new(path), &content)
+    }
+
+    pub fn get(&self, id: u32) -> Option<u32> {
+        self.0.get(&id).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
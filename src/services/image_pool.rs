@@ -0,0 +1,117 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::{oneshot, Semaphore};
+use tracing::{info, warn};
+use crate::config::ResizeConfig;
+use crate::metrics::{Timer, IMAGE_PROCESSING_TIME, IMAGE_QUEUE_DEPTH, IMAGE_QUEUE_REJECTED, IMAGE_QUEUE_TIMEOUTS};
+use crate::utils::error::{AppError, Result};
+
+/// 图片处理专用线程池
+///
+/// 缩放、重新编码都是 CPU 密集任务，放在 tokio 的阻塞线程池里会挤占文件读取，
+/// 因此单独开一个固定大小的线程池，并限制排队长度，积压过多时直接拒绝；
+/// 同时执行的任务数由信号量限制，等待超过 `queue_timeout_ms` 的任务同样被拒绝
+pub struct ImagePool {
+    pool: rayon::ThreadPool,
+    pending: Arc<AtomicUsize>,
+    max_queue: usize,
+    permits: Arc<Semaphore>,
+    queue_timeout: Duration,
+}
+
+impl std::fmt::Debug for ImagePool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ImagePool")
+            .field("threads", &self.pool.current_num_threads())
+            .field("pending", &self.pending)
+            .field("max_queue", &self.max_queue)
+            .field("available_permits", &self.permits.available_permits())
+            .field("queue_timeout", &self.queue_timeout)
+            .finish()
+    }
+}
+
+impl ImagePool {
+    pub fn new(config: &ResizeConfig) -> Result<Self> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(config.worker_threads)
+            .thread_name(|i| format!("image-worker-{}", i))
+            .build()
+            .map_err(|e| AppError::Internal(format!("创建图片处理线程池失败: {}", e)))?;
+
+        let max_concurrent = match config.max_concurrent {
+            0 => pool.current_num_threads(),
+            n => n,
+        };
+        info!(
+            "图片处理线程池已启动: {} 个线程，同时执行 {} 个任务，队列上限 {}",
+            pool.current_num_threads(),
+            max_concurrent,
+            config.max_queue
+        );
+
+        Ok(Self {
+            pool,
+            pending: Arc::new(AtomicUsize::new(0)),
+            max_queue: config.max_queue,
+            permits: Arc::new(Semaphore::new(max_concurrent)),
+            queue_timeout: Duration::from_millis(config.queue_timeout_ms),
+        })
+    }
+
+    /// 在线程池中执行图片任务，队列已满或等待超时时返回 [`AppError::ServiceUnavailable`]
+    pub async fn run<F, T>(&self, job: F) -> Result<T>
+    where
+        F: FnOnce() -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let depth = self.pending.fetch_add(1, Ordering::Relaxed);
+        let pending = PendingGuard(Arc::clone(&self.pending));
+        if depth >= self.max_queue {
+            IMAGE_QUEUE_REJECTED.inc();
+            warn!("图片处理队列已满 ({}), 拒绝请求", self.max_queue);
+            return Err(AppError::ServiceUnavailable("Image processing queue is full".to_string()));
+        }
+        IMAGE_QUEUE_DEPTH.set((depth + 1) as f64);
+
+        let permit = tokio::time::timeout(self.queue_timeout, Arc::clone(&self.permits).acquire_owned())
+            .await
+            .ok()
+            .and_then(|permit| permit.ok())
+            .ok_or_else(|| {
+                IMAGE_QUEUE_TIMEOUTS.inc();
+                warn!("图片任务排队超过 {:?}, 拒绝请求", self.queue_timeout);
+                AppError::ServiceUnavailable("Timed out waiting for image processing".to_string())
+            })?;
+
+        let (tx, rx) = oneshot::channel();
+        self.pool.spawn(move || {
+            let result = {
+                let _timer = Timer::new(&IMAGE_PROCESSING_TIME);
+                job()
+            };
+            // 在工作线程中归还名额，即使请求方已经断开也能正确计数
+            drop(permit);
+            drop(pending);
+            let _ = tx.send(result);
+        });
+
+        rx.await
+            .map_err(|_| AppError::Internal("Image worker dropped the job".to_string()))?
+    }
+}
+
+/// 排队计数，拒绝、超时或任务完成时归还，请求方在等待中断开也不会泄漏
+struct PendingGuard(Arc<AtomicUsize>);
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        let depth = self.0.fetch_sub(1, Ordering::Relaxed) - 1;
+        IMAGE_QUEUE_DEPTH.set(depth as f64);
+    }
+}
@@ -0,0 +1,199 @@
+use std::time::{Duration, Instant};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use parking_lot::RwLock;
+use serde_json::Value;
+use tracing::{debug, info, warn};
+use crate::config::JwtConfig;
+use crate::utils::error::{AppError, Result};
+
+/// 找不到令牌中的 kid 时重新拉取 JWKS 的最短间隔，避免伪造的 kid 反复触发请求
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(60);
+
+/// JWKS 拉取超时
+const JWKS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 校验通过的令牌
+#[derive(Debug, Clone)]
+pub struct VerifiedToken {
+    /// `sub` 声明，没有时为空
+    pub subject: Option<String>,
+    /// 角色声明中的全部取值
+    pub roles: Vec<String>,
+}
+
+/// 令牌签名的校验方式
+#[derive(Debug)]
+enum KeySource {
+    /// 共享密钥 (HS256/HS384/HS512)
+    Secret(String),
+    /// 从 JWKS 地址拉取的公钥 (RS*/ES*/PS*/EdDSA)，按 `jwks_refresh_secs` 定期刷新
+    Jwks {
+        url: String,
+        client: reqwest::Client,
+        refresh: Duration,
+        keys: RwLock<Option<(JwkSet, Instant)>>,
+    },
+}
+
+/// JWT 校验器，可直接使用组织 SSO 签发的 OIDC 令牌
+#[derive(Debug)]
+pub struct JwtVerifier {
+    source: KeySource,
+    issuer: Option<String>,
+    audience: Option<String>,
+    roles_claim: String,
+    leeway_secs: u64,
+}
+
+impl JwtVerifier {
+    /// 未配置 `secret` 与 `jwks_url` 时返回 None
+    pub fn new(config: &JwtConfig) -> Result<Option<Self>> {
+        let source = match (&config.secret, &config.jwks_url) {
+            (Some(secret), None) => KeySource::Secret(secret.clone()),
+            (None, Some(url)) => {
+                let client = reqwest::Client::builder()
+                    .timeout(JWKS_TIMEOUT)
+                    .build()
+                    .map_err(|e| AppError::Internal(format!("创建 JWKS 客户端失败: {}", e)))?;
+                info!("管理接口启用 JWT 鉴权，公钥来自 {}", url);
+                KeySource::Jwks {
+                    url: url.clone(),
+                    client,
+                    refresh: Duration::from_secs(config.jwks_refresh_secs),
+                    keys: RwLock::new(None),
+                }
+            }
+            (None, None) => return Ok(None),
+            (Some(_), Some(_)) => {
+                return Err(AppError::Internal("admin.jwt: secret and jwks_url are mutually exclusive".to_string()));
+            }
+        };
+
+        Ok(Some(Self {
+            source,
+            issuer: config.issuer.clone(),
+            audience: config.audience.clone(),
+            roles_claim: config.roles_claim.clone(),
+            leeway_secs: config.leeway_secs,
+        }))
+    }
+
+    /// 校验签名、过期时间以及配置的 `iss`/`aud`，返回主体与角色
+    pub async fn verify(&self, token: &str) -> Result<VerifiedToken> {
+        let header = decode_header(token).map_err(|e| unauthorized("Malformed token", e))?;
+        let key = self.decoding_key(header.alg, header.kid.as_deref()).await?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.leeway = self.leeway_secs;
+        match &self.issuer {
+            Some(issuer) => validation.set_issuer(&[issuer]),
+            None => validation.iss = None,
+        }
+        match &self.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+
+        let claims = decode::<Value>(token, &key, &validation)
+            .map_err(|e| unauthorized("Invalid token", e))?
+            .claims;
+
+        Ok(VerifiedToken {
+            subject: claims.get("sub").and_then(Value::as_str).map(str::to_string),
+            roles: roles_from_claims(&claims, &self.roles_claim),
+        })
+    }
+
+    async fn decoding_key(&self, alg: Algorithm, kid: Option<&str>) -> Result<DecodingKey> {
+        match &self.source {
+            KeySource::Secret(secret) => {
+                if !matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+                    return Err(AppError::Unauthorized("Unsupported token algorithm".to_string()));
+                }
+                Ok(DecodingKey::from_secret(secret.as_bytes()))
+            }
+            KeySource::Jwks { url, client, refresh, keys } => {
+                if matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+                    return Err(AppError::Unauthorized("Unsupported token algorithm".to_string()));
+                }
+
+                let cached = keys.read().clone();
+                let stale = match &cached {
+                    Some((_, fetched)) => fetched.elapsed() >= *refresh,
+                    None => true,
+                };
+                let mut set = match cached {
+                    Some((set, _)) if !stale => set,
+                    _ => self.fetch_jwks(url, client, keys).await?,
+                };
+
+                // 密钥轮换后旧缓存里没有新的 kid，限频重新拉取一次
+                if find_jwk(&set, kid).is_none() {
+                    let recently = keys.read().as_ref().is_some_and(|(_, fetched)| fetched.elapsed() < JWKS_MIN_REFRESH);
+                    if !recently {
+                        set = self.fetch_jwks(url, client, keys).await?;
+                    }
+                }
+
+                let jwk = find_jwk(&set, kid)
+                    .ok_or_else(|| AppError::Unauthorized("Unknown signing key".to_string()))?;
+                DecodingKey::from_jwk(jwk).map_err(|e| unauthorized("Unusable signing key", e))
+            }
+        }
+    }
+
+    async fn fetch_jwks(
+        &self,
+        url: &str,
+        client: &reqwest::Client,
+        keys: &RwLock<Option<(JwkSet, Instant)>>,
+    ) -> Result<JwkSet> {
+        let fetched = async {
+            client.get(url).send().await?.error_for_status()?.json::<JwkSet>().await
+        }
+        .await;
+
+        match fetched {
+            Ok(set) => {
+                debug!("已从 {} 拉取 {} 个公钥", url, set.keys.len());
+                *keys.write() = Some((set.clone(), Instant::now()));
+                Ok(set)
+            }
+            Err(e) => {
+                warn!("拉取 JWKS {} 失败: {}", url, e);
+                // 拉取失败时继续使用旧的公钥
+                keys.read()
+                    .as_ref()
+                    .map(|(set, _)| set.clone())
+                    .ok_or_else(|| AppError::ServiceUnavailable("Signing keys are unavailable".to_string()))
+            }
+        }
+    }
+}
+
+/// 令牌带 kid 时按 kid 查找；不带 kid 时只在 JWKS 仅有一个公钥时使用它
+fn find_jwk<'a>(set: &'a JwkSet, kid: Option<&str>) -> Option<&'a jsonwebtoken::jwk::Jwk> {
+    match kid {
+        Some(kid) => set.find(kid),
+        None if set.keys.len() == 1 => set.keys.first(),
+        None => None,
+    }
+}
+
+/// 按 `.` 分隔的路径读取角色声明 (例如 Keycloak 的 `realm_access.roles`)，
+/// 支持字符串数组或以空格分隔的字符串 (如 `scope`)
+fn roles_from_claims(claims: &Value, path: &str) -> Vec<String> {
+    let value = path
+        .split('.')
+        .try_fold(claims, |value, key| value.get(key));
+    match value {
+        Some(Value::Array(items)) => items.iter().filter_map(Value::as_str).map(str::to_string).collect(),
+        Some(Value::String(s)) => s.split_whitespace().map(str::to_string).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn unauthorized(message: &str, e: jsonwebtoken::errors::Error) -> AppError {
+    debug!("JWT 校验失败: {}", e);
+    AppError::Unauthorized(message.to_string())
+}
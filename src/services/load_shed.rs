@@ -0,0 +1,81 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tracing::{info, warn};
+use crate::config::LoadSheddingConfig;
+use crate::metrics::{PROCESS_RSS_BYTES, SHED_REQUESTS};
+use crate::utils::error::{AppError, Result};
+
+/// 内存过载保护
+///
+/// 后台定期采样进程常驻内存，超过阈值时拒绝新的图片缩放请求，
+/// 避免缩放产生的大块内存分配把进程推向 OOM
+#[derive(Debug)]
+pub struct LoadShedder {
+    max_rss_bytes: u64,
+    retry_after_secs: u64,
+    rss_bytes: AtomicU64,
+}
+
+impl LoadShedder {
+    pub fn start(config: &LoadSheddingConfig) -> Arc<Self> {
+        let shedder = Arc::new(Self {
+            max_rss_bytes: config.max_rss_mb * 1024 * 1024,
+            retry_after_secs: config.retry_after_secs,
+            rss_bytes: AtomicU64::new(0),
+        });
+
+        if shedder.max_rss_bytes == 0 {
+            return shedder;
+        }
+        if read_rss_bytes().is_none() {
+            warn!("无法读取进程内存占用，过载保护不会生效");
+            return shedder;
+        }
+        info!("已启用过载保护，内存上限 {} MB", config.max_rss_mb);
+
+        let interval = Duration::from_secs(config.check_interval_secs);
+        let sampler = Arc::clone(&shedder);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                if let Some(rss) = read_rss_bytes() {
+                    sampler.rss_bytes.store(rss, Ordering::Relaxed);
+                    PROCESS_RSS_BYTES.set(rss as f64);
+                }
+            }
+        });
+
+        shedder
+    }
+
+    /// 内存超过阈值时返回 [`AppError::Overloaded`]
+    pub fn check(&self) -> Result<()> {
+        if self.max_rss_bytes == 0 {
+            return Ok(());
+        }
+
+        let rss = self.rss_bytes.load(Ordering::Relaxed);
+        if rss > self.max_rss_bytes {
+            SHED_REQUESTS.inc();
+            warn!(rss_bytes = rss, max_rss_bytes = self.max_rss_bytes, "内存占用过高，拒绝图片缩放请求");
+            return Err(AppError::Overloaded {
+                retry_after_secs: self.retry_after_secs,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// 读取进程常驻内存（字节），目前只支持 Linux
+fn read_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
@@ -1,24 +1,899 @@
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    net::IpAddr,
     sync::Arc,
     time::{Duration, SystemTime, Instant},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
-use tokio::sync::{RwLock, broadcast};
+use tokio::sync::{OnceCell, RwLock, broadcast};
 use crate::utils::error::{Result, AppError};
-use crate::models::meme::Meme;
-use crate::metrics::{CACHE_HIT_RATE, CACHE_SIZE, CACHE_HITS, CACHE_MISSES, TOTAL_MEMES};
-use tracing::{info, error, debug};
-use notify::{RecursiveMode, Watcher};
-use std::sync::atomic::{AtomicU64, Ordering};
+use crate::models::meme::{Meme, MemeMetadata, MemeStatus, Orientation};
+use crate::config::{CacheEvictionPolicy, CachePolicy, Config, IdScheme};
+use crate::utils::{media, normalize, svg};
+use crate::services::alias::AliasStore;
+use crate::services::caption::CaptionRenderer;
+use crate::services::watermark::Watermarker;
+use crate::services::clients::ClientTracker;
+use crate::services::catalog::{CatalogChanges, CatalogDiff, CatalogSnapshot, ChangeLog, DiffRequest};
+use crate::services::cluster::{self, ClusterBus};
+use crate::services::events::{CatalogEvent, EventBus};
+use crate::services::image_pool::ImagePool;
+use crate::services::load_shed::LoadShedder;
+use crate::services::webhook::{WebhookEvent, WebhookNotifier};
+use crate::services::moderation::ModerationStore;
+use crate::services::nsfw::NsfwStore;
+use crate::services::reports::{ReportOutcome, ReportReason, ReportStore};
+use crate::services::sessions::RandomSessions;
+use crate::services::ids::{IdRedirects, IdRegistry};
+use crate::services::ocr::{OcrEngine, TextStore};
+use crate::services::pins::PinStore;
+use crate::services::snapshot::CatalogFile;
+use crate::services::tags::TagStore;
+#[cfg(feature = "ml")]
+use crate::services::tagger::Tagger;
+use crate::services::stats::{MemeStatsStore, RequestCounters, RequestWindow};
+use crate::services::trash::TrashService;
+use crate::services::storage::{FsStorage, Storage, StorageWatch};
+use crate::services::watcher::WatcherStatus;
+use crate::metrics::{CACHE_BYTES, CACHE_EARLY_REFRESHES, CACHE_ENTRIES, CACHE_EVICTIONS, CACHE_HIT_RATIO, CACHE_HITS, CACHE_MISSES, CACHE_WARMUP_LOADED, DUPLICATE_FILES, MIME_MISMATCHES, PREFETCHED_MEMES, STORAGE_DEGRADED, ID_COLLISIONS, RELOAD_DURATION, RELOADS_TOTAL, SKIPPED_FILES, STREAMED_RESPONSES, TOTAL_MEMES, COALESCED_READS};
+use tracing::{field, info, info_span, warn, error, debug, Instrument};
+use std::sync::atomic::{AtomicBool, Ordering};
 use parking_lot::Mutex;
 use sha2::{Sha256, Digest};
+use unicode_normalization::UnicodeNormalization;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 随机选择的筛选条件
+#[derive(Debug, Clone, Default)]
+pub struct RandomFilter {
+    pub orientation: Option<Orientation>,
+    pub min_width: Option<u32>,
+    pub max_width: Option<u32>,
+    pub min_height: Option<u32>,
+    pub max_height: Option<u32>,
+    /// 优先选择不超过该大小（字节）的图片
+    pub max_bytes: Option<u64>,
+    /// 排除 NSFW 表情包
+    pub safe: bool,
+    /// 只选择图片或只选择视频，未指定时不限
+    pub media: Option<MediaKind>,
+    /// 固定的随机种子，设置后按种子确定地选择
+    pub seed: Option<RandomSeed>,
+    /// 去重会话：同一会话在候选用完之前不会重复返回同一个表情包，与 `seed` 同时指定时忽略
+    pub session: Option<String>,
+    /// 标签条件
+    pub tags: TagFilter,
+    /// 只选择这些格式，元素为小写的 MIME 类型 (`image/png`) 或其子类型 (`png`)，为空时不限
+    pub formats: Vec<String>,
+    /// 文件大小下限（字节）
+    pub min_size: Option<u64>,
+    /// 文件大小上限（字节），与 `max_bytes` 不同，超出的表情包不会被选中
+    pub max_size: Option<u64>,
+}
+
+/// 按标签筛选，标签比较不区分大小写
+#[derive(Debug, Clone, Default)]
+pub struct TagFilter {
+    /// 至少包含其中一个
+    pub any: Vec<String>,
+    /// 包含全部
+    pub all: Vec<String>,
+    /// 不包含其中任何一个
+    pub none: Vec<String>,
+}
+
+impl TagFilter {
+    pub fn is_empty(&self) -> bool {
+        self.any.is_empty() && self.all.is_empty() && self.none.is_empty()
+    }
+
+    fn matches(&self, tags: &[String]) -> bool {
+        let has = |tag: &String| tags.iter().any(|t| t.to_lowercase() == tag.to_lowercase());
+        (self.any.is_empty() || self.any.iter().any(has))
+            && self.all.iter().all(has)
+            && !self.none.iter().any(has)
+    }
+}
+
+/// 可复现的随机选择：目录内容相同时，相同的种子、序号与筛选条件总是选中同一个表情包
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RandomSeed {
+    pub seed: String,
+    /// 同一种子下的序号，用于生成一串确定的结果
+    pub n: u64,
+}
+
+impl RandomSeed {
+    /// 对目录指纹、种子与序号做 SHA-256，取前 8 字节，不同实例、重启前后与不同版本的程序结果一致
+    fn index(&self, fingerprint: &str, len: usize) -> usize {
+        let digest = Sha256::digest(format!("{}:{}:{}", fingerprint, self.n, self.seed).as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        (u64::from_be_bytes(bytes) % len as u64) as usize
+    }
+}
+
+impl RandomFilter {
+    pub fn is_empty(&self) -> bool {
+        self.orientation.is_none()
+            && self.min_width.is_none()
+            && self.max_width.is_none()
+            && self.min_height.is_none()
+            && self.max_height.is_none()
+            && self.max_bytes.is_none()
+            && !self.safe
+            && self.media.is_none()
+            && self.tags.is_empty()
+            && self.formats.is_empty()
+            && self.min_size.is_none()
+            && self.max_size.is_none()
+    }
+
+    fn has_dimension_filter(&self) -> bool {
+        self.orientation.is_some()
+            || self.min_width.is_some()
+            || self.max_width.is_some()
+            || self.min_height.is_some()
+            || self.max_height.is_some()
+    }
+
+    /// 判断表情包是否满足筛选条件，尺寸未知的表情包不满足任何尺寸条件
+    fn matches(&self, meme: &Meme) -> bool {
+        if self.safe && meme.nsfw {
+            return false;
+        }
+        if self.media.is_some_and(|media| (media == MediaKind::Video) != meme.is_video()) {
+            return false;
+        }
+        if !self.formats.is_empty() {
+            let mime = meme.mime_type.to_ascii_lowercase();
+            let subtype = mime.split('/').nth(1).unwrap_or_default();
+            if !self.formats.iter().any(|format| *format == mime || format == subtype) {
+                return false;
+            }
+        }
+        if self.min_size.is_some_and(|min| meme.size_bytes < min) || self.max_size.is_some_and(|max| meme.size_bytes > max) {
+            return false;
+        }
+        if !self.tags.matches(&meme.tags) {
+            return false;
+        }
+        if !self.has_dimension_filter() {
+            return true;
+        }
+        let (Some(width), Some(height)) = (meme.width, meme.height) else {
+            return false;
+        };
+
+        self.orientation.is_none_or(|o| meme.orientation() == Some(o))
+            && self.min_width.is_none_or(|min| width >= min)
+            && self.max_width.is_none_or(|max| width <= max)
+            && self.min_height.is_none_or(|min| height >= min)
+            && self.max_height.is_none_or(|max| height <= max)
+    }
+}
+
+/// 表情包的媒体类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaKind {
+    Image,
+    /// mp4 或 webm 短视频，需要开启 `storage.allow_video`
+    Video,
+}
+
+/// 加水印的结果与原结果分开缓存
+fn watermark_suffix(watermark: &Option<Arc<Watermarker>>) -> &'static str {
+    if watermark.is_some() { ":wm" } else { "" }
+}
+
+/// 视频不经过图片处理
+fn reject_video(meme: &Meme) -> Result<()> {
+    if meme.is_video() {
+        return Err(AppError::BadRequest(format!("Meme {} is a video and cannot be processed as an image", meme.id)));
+    }
+    Ok(())
+}
+
+/// 重定向目标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RedirectTarget {
+    /// 来源信息中的 `canonical_url`
+    External,
+}
+
+/// 翻转方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, ToSchema)]
+pub enum Flip {
+    /// 水平翻转
+    #[serde(rename = "h")]
+    Horizontal,
+    /// 垂直翻转
+    #[serde(rename = "v")]
+    Vertical,
+}
+
+/// 同时指定宽高时的缩放方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Fit {
+    /// 保持宽高比缩放到宽高以内
+    #[default]
+    Contain,
+    /// 保持宽高比缩放后居中裁剪，填满宽高
+    Cover,
+    /// 拉伸到指定宽高，不保持宽高比
+    Fill,
+}
+
+impl Fit {
+    pub fn key(self) -> &'static str {
+        match self {
+            Fit::Contain => "contain",
+            Fit::Cover => "cover",
+            Fit::Fill => "fill",
+        }
+    }
+}
+
+/// 缩放与变换后的输出格式，未指定时为 PNG
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl OutputFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "png" => Some(OutputFormat::Png),
+            "jpeg" | "jpg" => Some(OutputFormat::Jpeg),
+            "webp" => Some(OutputFormat::Webp),
+            _ => None,
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "image/png",
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::Webp => "image/webp",
+        }
+    }
+
+    pub fn key(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpeg",
+            OutputFormat::Webp => "webp",
+        }
+    }
+}
+
+/// 未指定 `quality` 时的 JPEG 质量
+pub const DEFAULT_JPEG_QUALITY: u8 = 85;
+
+/// 要清空的缓存
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheTarget {
+    /// 原图缓存
+    Content,
+    /// 缩放、转码、配文等处理后的图片缓存
+    Resized,
+    #[default]
+    All,
+}
+
+impl CacheTarget {
+    fn includes_content(self) -> bool {
+        matches!(self, CacheTarget::Content | CacheTarget::All)
+    }
+
+    fn includes_resized(self) -> bool {
+        matches!(self, CacheTarget::Resized | CacheTarget::All)
+    }
+}
+
+/// 缓存中的一个条目
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CacheEntry {
+    /// 所在的缓存：content 或 resized
+    #[schema(example = "resized")]
+    pub cache: &'static str,
+    /// 原图缓存为表情包 ID，处理后的图片为 `ID:参数`
+    #[schema(example = "1:300x0")]
+    pub key: String,
+    #[schema(example = 10240)]
+    pub bytes: usize,
+    /// 写入缓存后经过的秒数
+    #[schema(example = 120)]
+    pub age_secs: Option<u64>,
+}
+
+/// 拼图最多包含的表情包数量
+pub const MAX_COLLAGE_IMAGES: usize = 16;
+/// 拼图格子边长的范围（像素）
+pub const COLLAGE_CELL_SIZES: std::ops::RangeInclusive<u32> = 64..=512;
+
+/// 图标允许的边长（像素）
+pub const ICON_SIZES: [u32; 6] = [16, 32, 48, 64, 128, 256];
+
+/// 图标格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum IconFormat {
+    #[default]
+    Png,
+    Ico,
+}
+
+impl IconFormat {
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            IconFormat::Png => "image/png",
+            IconFormat::Ico => "image/x-icon",
+        }
+    }
+
+    fn image_format(self) -> image::ImageFormat {
+        match self {
+            IconFormat::Png => image::ImageFormat::Png,
+            IconFormat::Ico => image::ImageFormat::Ico,
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            IconFormat::Png => "png",
+            IconFormat::Ico => "ico",
+        }
+    }
+}
+
+/// 裁剪区域，坐标与宽高均针对原图（像素）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CropRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CropRegion {
+    /// 解析 `x,y,w,h` 形式的参数
+    pub fn parse(value: &str) -> Result<Self> {
+        let invalid = || AppError::BadRequest("crop must be x,y,w,h with non-negative integers".to_string());
+        let parts = value
+            .split(',')
+            .map(|part| part.trim().parse::<u32>())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        let [x, y, width, height] = parts[..] else {
+            return Err(invalid());
+        };
+        if width == 0 || height == 0 {
+            return Err(AppError::BadRequest("crop width and height must be positive".to_string()));
+        }
+        Ok(Self { x, y, width, height })
+    }
+
+    /// 检查区域是否完整位于图片内，图片尺寸未知时无法裁剪
+    fn validate(&self, meme: &Meme) -> Result<()> {
+        let (Some(width), Some(height)) = (meme.width, meme.height) else {
+            return Err(AppError::BadRequest(format!("Meme {} has unknown dimensions and cannot be cropped", meme.id)));
+        };
+        let right = self.x.checked_add(self.width);
+        let bottom = self.y.checked_add(self.height);
+        if right.is_none_or(|r| r > width) || bottom.is_none_or(|b| b > height) {
+            return Err(AppError::BadRequest(format!(
+                "crop region {},{},{},{} exceeds image size {}x{}",
+                self.x, self.y, self.width, self.height, width, height
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 图片变换：先裁剪，再按顺时针角度旋转，最后翻转；缩放后按 `format` 编码
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImageTransform {
+    /// 0、90、180 或 270
+    pub rotate: u16,
+    pub flip: Option<Flip>,
+    pub crop: Option<CropRegion>,
+    /// 同时指定宽高时的缩放方式
+    pub fit: Fit,
+    pub format: Option<OutputFormat>,
+    /// JPEG 质量 (1-100)
+    pub quality: Option<u8>,
+}
+
+impl ImageTransform {
+    pub fn new(rotate: Option<u16>, flip: Option<Flip>) -> Result<Self> {
+        let rotate = rotate.unwrap_or(0);
+        if !matches!(rotate, 0 | 90 | 180 | 270) {
+            return Err(AppError::BadRequest("rotate must be one of 90, 180, 270".to_string()));
+        }
+        Ok(Self { rotate, flip, ..Default::default() })
+    }
+
+    pub fn with_crop(mut self, crop: Option<CropRegion>) -> Self {
+        self.crop = crop;
+        self
+    }
+
+    pub fn with_output(mut self, fit: Fit, format: Option<OutputFormat>, quality: Option<u8>) -> Self {
+        self.fit = fit;
+        self.format = format;
+        self.quality = quality;
+        self
+    }
+
+    /// 不改变图片内容；单独指定 `fit` 不需要处理
+    pub fn is_identity(&self) -> bool {
+        self.rotate == 0 && self.flip.is_none() && self.crop.is_none() && self.format.is_none()
+    }
+
+    /// 处理后内容的 Content-Type
+    pub fn content_type(&self) -> &'static str {
+        self.format.unwrap_or(OutputFormat::Png).mime_type()
+    }
+
+    fn resize(&self, img: image::DynamicImage, width: Option<u32>, height: Option<u32>) -> image::DynamicImage {
+        use image::imageops::FilterType;
+
+        let target_width = width.unwrap_or(img.width());
+        let target_height = height.unwrap_or(img.height());
+        // 使用更快的滤波器进行缩放；只指定一边时 cover 与 fill 没有意义，按 contain 处理
+        match (self.fit, width.is_some() && height.is_some()) {
+            (Fit::Cover, true) => img.resize_to_fill(target_width, target_height, FilterType::Triangle),
+            (Fit::Fill, true) => img.resize_exact(target_width, target_height, FilterType::Triangle),
+            _ => img.resize(target_width, target_height, FilterType::Triangle),
+        }
+    }
+
+    fn encode(&self, img: &image::DynamicImage) -> Result<Vec<u8>> {
+        use image::{codecs::jpeg::JpegEncoder, ImageFormat};
+        use std::io::Cursor;
+
+        let mut cursor = Cursor::new(Vec::new());
+        let encoded = match self.format.unwrap_or(OutputFormat::Png) {
+            OutputFormat::Png => img.write_to(&mut cursor, ImageFormat::Png),
+            // WebP 编码器只支持无损压缩，忽略质量
+            OutputFormat::Webp => img.write_to(&mut cursor, ImageFormat::WebP),
+            OutputFormat::Jpeg => JpegEncoder::new_with_quality(&mut cursor, self.quality.unwrap_or(DEFAULT_JPEG_QUALITY))
+                .encode_image(&image::DynamicImage::ImageRgb8(img.to_rgb8())),
+        };
+        encoded.map_err(|e| AppError::Internal(format!("Failed to encode image: {}", e)))?;
+        Ok(cursor.into_inner())
+    }
+
+    fn apply(&self, img: image::DynamicImage) -> image::DynamicImage {
+        let img = match self.crop {
+            Some(crop) => img.crop_imm(crop.x, crop.y, crop.width, crop.height),
+            None => img,
+        };
+        let img = match self.rotate {
+            90 => img.rotate90(),
+            180 => img.rotate180(),
+            270 => img.rotate270(),
+            _ => img,
+        };
+        match self.flip {
+            Some(Flip::Horizontal) => img.fliph(),
+            Some(Flip::Vertical) => img.flipv(),
+            None => img,
+        }
+    }
+}
+
+/// 缩放与变换参数对应的缓存键后缀，例如 `300x0`、`300x0:r90h`、`300x0:c10,20,200,150`
+/// 或 `300x300:fcover:jpeg80`
+pub fn variant_key(width: Option<u32>, height: Option<u32>, transform: ImageTransform) -> String {
+    let mut key = format!("{}x{}", width.unwrap_or(0), height.unwrap_or(0));
+    if transform.rotate != 0 || transform.flip.is_some() {
+        key.push_str(&format!(":r{}", transform.rotate));
+        match transform.flip {
+            Some(Flip::Horizontal) => key.push('h'),
+            Some(Flip::Vertical) => key.push('v'),
+            None => {}
+        }
+    }
+    if let Some(crop) = transform.crop {
+        key.push_str(&format!(":c{},{},{},{}", crop.x, crop.y, crop.width, crop.height));
+    }
+    if transform.fit != Fit::Contain && width.is_some() && height.is_some() {
+        key.push_str(&format!(":f{}", transform.fit.key()));
+    }
+    match transform.format {
+        Some(OutputFormat::Jpeg) => {
+            key.push_str(&format!(":jpeg{}", transform.quality.unwrap_or(DEFAULT_JPEG_QUALITY)));
+        }
+        Some(format @ (OutputFormat::Png | OutputFormat::Webp)) => {
+            key.push_str(&format!(":{}", format.key()));
+        }
+        None => {}
+    }
+    key
+}
+
+/// 内容是否来自缓存
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+    Hit,
+    Miss,
+    /// 大文件直接从磁盘发送，不经过缓存
+    Bypass,
+}
+
+impl CacheStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheStatus::Hit => "hit",
+            CacheStatus::Miss => "miss",
+            CacheStatus::Bypass => "bypass",
+        }
+    }
+}
+
+/// 原图内容：小文件经过内存缓存，大文件只返回本地路径，由调用方流式发送
+#[derive(Debug)]
+pub enum Original {
+    Bytes(Vec<u8>, CacheStatus),
+    File(PathBuf),
+}
+
+/// 一次目录重载的结果
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct ReloadReport {
+    #[schema(example = true)]
+    pub ok: bool,
+    /// 失败原因，成功时为空
+    pub error: Option<String>,
+    /// 完成时间 (Unix 时间戳，秒)
+    #[schema(example = 1700000000)]
+    pub finished_at: u64,
+    #[schema(example = 120)]
+    pub duration_ms: u64,
+    /// 扫描到的文件数 (含来源信息文件)
+    #[schema(example = 102)]
+    pub files_scanned: usize,
+    /// 相比上次新增的表情包数
+    #[schema(example = 1)]
+    pub added: usize,
+    /// 相比上次移除的表情包数
+    #[schema(example = 0)]
+    pub removed: usize,
+    /// 跳过的非图片或不允许的文件数
+    #[schema(example = 2)]
+    pub skipped: usize,
+    /// 读取失败的文件数
+    #[schema(example = 0)]
+    pub failures: usize,
+}
+
+/// 表情包来源信息文件的后缀，例如 `cat.jpg.meta.yml` 或 `cat.meta.yml`
+pub const METADATA_SUFFIX: &str = ".meta.yml";
+
+/// 保留的目录变更记录条数
+const CHANGE_HISTORY_LEN: usize = 100;
 
 const REQUEST_HISTORY_WINDOW: Duration = Duration::from_secs(60 * 15); // 扩展到15分钟
 const ONE_MINUTE: Duration = Duration::from_secs(60);
 const FIVE_MINUTES: Duration = Duration::from_secs(60 * 5);
 const FIFTEEN_MINUTES: Duration = Duration::from_secs(60 * 15);
 
+/// 搜索时比较的形式：NFC 规范化、小写并去除空白 (OCR 常在中文字符之间插入空格)
+fn search_key(value: &str) -> String {
+    value
+        .nfc()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// 文件名的 Unicode NFC 形式。macOS 返回 NFD 形式 (分解的字符) 的文件名，Linux 通常为 NFC，
+/// 统一后同一文件在不同平台上得到相同的文件名与 ID
+pub fn normalize_filename(filename: &str) -> String {
+    filename.nfc().collect()
+}
+
+/// 根据文件名计算表情包 ID：取 NFC 形式文件名 SHA-256 哈希值的前 4 个字节
+pub fn meme_id_for(filename: &str) -> u32 {
+    let mut hasher = Sha256::new();
+    hasher.update(normalize_filename(filename).as_bytes());
+    let hash = hasher.finalize();
+
+    u32::from_be_bytes([
+        hash[0],
+        hash[1],
+        hash[2],
+        hash[3],
+    ])
+}
+
+/// 发生 ID 冲突时的备用 ID：对 `文件名#序号` 计算哈希
+pub(crate) fn salted_meme_id(filename: &str, salt: u32) -> u32 {
+    meme_id_for(&format!("{}#{}", filename, salt))
+}
+
+/// 一组哈希前缀相同的文件：保留原 ID 的文件与被重新分配 ID 的文件
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct IdCollision {
+    /// 冲突的 ID
+    #[schema(example = 1)]
+    pub id: u32,
+    /// 继续使用该 ID 的文件
+    #[schema(example = "a.jpg")]
+    pub kept: String,
+    pub reassigned: Vec<ReassignedId>,
+}
+
+/// 实际内容与扩展名不符的文件，按实际类型提供
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MimeMismatch {
+    #[schema(example = "cat.png")]
+    pub filename: String,
+    /// 按扩展名推断的类型
+    #[schema(example = "image/png")]
+    pub extension_mime: String,
+    /// 按文件头识别出的实际类型
+    #[schema(example = "image/jpeg")]
+    pub detected_mime: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReassignedId {
+    #[schema(example = "b.jpg")]
+    pub filename: String,
+    #[schema(example = 2)]
+    pub id: u32,
+}
+
+/// 计算内容的 SHA-256 (十六进制)
+pub fn hash_content(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Prometheus 指标中原图缓存与压缩图缓存的标签
+const CONTENT_CACHE: &str = "content";
+const RESIZED_CACHE: &str = "resized";
+
+/// 缓存条目权重：按字节计算容量时为图片大小，否则每个条目计 1；同时累加该缓存的估算字节数
+fn cache_weight(cache: &'static str, weigh_bytes: bool, content: &[u8]) -> u32 {
+    CACHE_BYTES.with_label_values(&[cache]).add(content.len() as f64);
+    if weigh_bytes {
+        content.len().try_into().unwrap_or(u32::MAX)
+    } else {
+        1
+    }
+}
+
+/// 读入内存的 SVG 先清理脚本与外部引用，其他格式原样返回
+fn sanitized(is_svg: bool, content: Vec<u8>) -> Result<Vec<u8>> {
+    if is_svg {
+        svg::sanitize(&content)
+    } else {
+        Ok(content)
+    }
+}
+
+/// 缓存条目移除时扣除估算字节数，因容量或过期淘汰的计入淘汰次数
+fn cache_removed(cache: &'static str, key: &dyn std::fmt::Display, content: &[u8], cause: moka::notification::RemovalCause) {
+    CACHE_BYTES.with_label_values(&[cache]).sub(content.len() as f64);
+    if cause.was_evicted() {
+        CACHE_EVICTIONS.with_label_values(&[cache]).inc();
+        debug!(cache, key = %key, cause = ?cause, bytes = content.len(), "缓存条目被淘汰");
+    }
+}
+
+/// 处理后图片的缓存键涉及的表情包：一般以 `ID:` 开头，拼图为 `collage:列数:边长:ID-哈希,ID-哈希,...`，
+/// 后面可能还有水印等后缀
+fn cached_ids(key: &str) -> Vec<u32> {
+    match key.strip_prefix("collage:") {
+        Some(rest) => rest
+            .split(':')
+            .nth(2)
+            .map(|ids| {
+                ids.split(',')
+                    .filter_map(|item| item.split('-').next().and_then(|id| id.parse().ok()))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        None => key
+            .split_once(':')
+            .and_then(|(id, _)| id.parse().ok())
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// 原图缓存以表情包 ID 为键
+fn parse_cache_id(key: &str) -> Result<u32> {
+    key.parse()
+        .map_err(|_| AppError::BadRequest(format!("Invalid content cache key: {}", key)))
+}
+
+/// 同一表情包的并发冷读取共享的读取结果，读取失败时为 None
+type SharedRead = Arc<OnceCell<Option<Vec<u8>>>>;
+
+/// 缓存条目的写入时间：moka 不提供该信息，在 weigher (每次写入时调用) 中记录，
+/// 条目移除时删除；替换时移除回调晚于新值的 weigher，不能删除
+#[derive(Debug, Clone, Default)]
+struct CacheAges(Arc<Mutex<HashMap<String, Instant>>>);
+
+impl CacheAges {
+    fn inserted(&self, key: &dyn std::fmt::Display) {
+        self.0.lock().insert(key.to_string(), Instant::now());
+    }
+
+    fn removed(&self, key: &dyn std::fmt::Display, cause: moka::notification::RemovalCause) {
+        if cause != moka::notification::RemovalCause::Replaced {
+            self.0.lock().remove(&key.to_string());
+        }
+    }
+
+    fn age(&self, key: &str) -> Option<Duration> {
+        self.0.lock().get(key).map(Instant::elapsed)
+    }
+
+    /// 条目的剩余存活时间进入末尾 `ratio` 的窗口后按概率决定是否提前刷新，概率随剩余时间线性增加到 1；
+    /// 决定刷新时立即把写入时间记为现在，同一条目的并发请求不会重复刷新
+    fn should_refresh(&self, key: &str, ttl: Duration, ratio: f64) -> bool {
+        let mut ages = self.0.lock();
+        let Some(inserted) = ages.get_mut(key) else {
+            return false;
+        };
+        let remaining = ttl.saturating_sub(inserted.elapsed()).as_secs_f64();
+        let window = ttl.as_secs_f64() * ratio;
+        if remaining >= window || fastrand::f64() >= 1.0 - remaining / window {
+            return false;
+        }
+        *inserted = Instant::now();
+        true
+    }
+}
+
+/// 缓存提前刷新的参数，只有设置了存活时间的缓存才会提前刷新
+#[derive(Debug, Clone, Copy)]
+struct EarlyRefresh {
+    ratio: f64,
+    content_ttl: Option<Duration>,
+    resized_ttl: Option<Duration>,
+}
+
+/// 按配置设置缓存的存活时间、闲置时间、淘汰策略与初始容量
+fn with_policy<K, V, C>(
+    builder: moka::future::CacheBuilder<K, V, C>,
+    cache: &'static str,
+    policy: &CachePolicy,
+    default_ttl_secs: u64,
+) -> moka::future::CacheBuilder<K, V, C>
+where
+    K: Eq + std::hash::Hash + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    let (ttl, tti) = (policy.time_to_live(default_ttl_secs), policy.time_to_idle());
+    info!(
+        cache,
+        time_to_live = ?ttl,
+        time_to_idle = ?tti,
+        eviction_policy = ?policy.eviction_policy,
+        initial_capacity = ?policy.initial_capacity,
+        "缓存策略"
+    );
+    let builder = match ttl {
+        Some(ttl) => builder.time_to_live(ttl),
+        None => builder,
+    };
+    let builder = match tti {
+        Some(tti) => builder.time_to_idle(tti),
+        None => builder,
+    };
+    let builder = match policy.initial_capacity {
+        Some(capacity) => builder.initial_capacity(capacity),
+        None => builder,
+    };
+    builder.eviction_policy(match policy.eviction_policy {
+        CacheEvictionPolicy::TinyLfu => moka::policy::EvictionPolicy::tiny_lfu(),
+        CacheEvictionPolicy::Lru => moka::policy::EvictionPolicy::lru(),
+    })
+}
+
+/// 定期记录各缓存在最近一个窗口内的命中率与淘汰数，并更新 `meme_cache_hit_ratio`，
+/// 便于根据实际效果调整缓存容量
+fn start_cache_stats_task(interval_secs: u64) {
+    if interval_secs == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        interval.tick().await;
+        let mut previous: HashMap<&'static str, (f64, f64, f64)> = HashMap::new();
+
+        loop {
+            interval.tick().await;
+            for cache in [CONTENT_CACHE, RESIZED_CACHE] {
+                let totals = (
+                    CACHE_HITS.with_label_values(&[cache]).get(),
+                    CACHE_MISSES.with_label_values(&[cache]).get(),
+                    CACHE_EVICTIONS.with_label_values(&[cache]).get(),
+                );
+                let (hits, misses, evictions) = previous
+                    .insert(cache, totals)
+                    .map(|prev| (totals.0 - prev.0, totals.1 - prev.1, totals.2 - prev.2))
+                    .unwrap_or(totals);
+                let lookups = hits + misses;
+                let hit_ratio = if lookups > 0.0 { hits / lookups } else { 0.0 };
+                CACHE_HIT_RATIO.with_label_values(&[cache]).set(hit_ratio);
+                info!(
+                    cache,
+                    window_secs = interval_secs,
+                    hits,
+                    misses,
+                    hit_ratio = format_args!("{:.3}", hit_ratio),
+                    evictions,
+                    entries = CACHE_ENTRIES.with_label_values(&[cache]).get(),
+                    bytes = CACHE_BYTES.with_label_values(&[cache]).get(),
+                    "缓存效率"
+                );
+            }
+        }
+    });
+}
+
+/// 已读取过的文件信息（内容哈希、图片尺寸），文件大小与修改时间不变时复用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedFileInfo {
+    pub size_bytes: u64,
+    pub modified: Option<SystemTime>,
+    pub hash: Option<String>,
+    pub dimensions: Option<(u32, u32)>,
+    /// EXIF 中的拍摄日期 (YYYY-MM-DD)
+    #[serde(default)]
+    pub captured_on: Option<String>,
+}
+
+/// 扫描目录得到的新目录，在写锁下一次性替换到服务中
+struct ScannedCatalog {
+    memes: HashMap<u32, Meme>,
+    duplicate_ids: HashMap<u32, u32>,
+    collisions: Vec<IdCollision>,
+    mime_mismatches: Vec<MimeMismatch>,
+    file_info_cache: HashMap<PathBuf, CachedFileInfo>,
+}
+
+/// 在读锁下准备好的新目录：预压缩的目录与需要失效的缓存都已算好，替换时不再做 I/O
+struct PreparedCatalog {
+    scanned: ScannedCatalog,
+    catalog: Option<CatalogSnapshot>,
+    /// 文件已变化或已删除的表情包，其缓存在替换时失效，其余缓存继续使用
+    stale_ids: HashSet<u32>,
+}
+
+/// 触发重载的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReloadTrigger {
+    /// 文件监控检测到变更
+    Watcher,
+    /// 管理接口修改了表情包目录
+    Admin,
+    /// 主节点轮询检测到变更
+    Poll,
+    /// 其他节点的失效通知
+    Peer,
+    /// 从目录快照启动后的校验扫描
+    Startup,
+    /// 降级模式下的重试扫描
+    Retry,
+}
+
 #[derive(Debug)]
 pub struct MemeService {
     memes: HashMap<u32, Meme>,
@@ -28,355 +903,2521 @@ pub struct MemeService {
     content_cache: moka::future::Cache<u32, Vec<u8>>,
     // 添加压缩图片缓存
     resized_cache: moka::future::Cache<String, Vec<u8>>,
+    content_ages: CacheAges,
+    resized_ages: CacheAges,
+    // 未开启 `cache.early_refresh` 时为空
+    early_refresh: Option<EarlyRefresh>,
     memes_dir: PathBuf,
-    reload_tx: broadcast::Sender<()>,
-    _watcher: notify::RecommendedWatcher,
-    request_count: AtomicU64,
-    cache_hits: AtomicU64,
-    cache_misses: AtomicU64,
+    config: Arc<Config>,
+    reload_tx: broadcast::Sender<ReloadTrigger>,
+    storage: Arc<dyn Storage>,
+    watcher: Arc<dyn StorageWatch>,
+    counters: Arc<RequestCounters>,
     start_time: SystemTime,
-    request_timestamps: Mutex<VecDeque<Instant>>,
+    request_window: RequestWindow,
+    // 预先选出的下几个随机表情包 ID，后台读入内容缓存
+    prefetch: Mutex<VecDeque<u32>>,
+    // 正在从存储读取的表情包，同一表情包的并发冷读取共享一次读取结果 (读取失败时为 None)
+    reads: Mutex<HashMap<u32, SharedRead>>,
     last_updated: Mutex<SystemTime>,
+    meme_stats: Arc<MemeStatsStore>,
+    trash: Arc<TrashService>,
+    aliases: AliasStore,
+    // 重复文件的 ID -> 保留的表情包 ID，保证旧 ID 仍可访问
+    duplicate_ids: HashMap<u32, u32>,
+    // 内容哈希 -> 表情包 ID，文件改名后哈希不变
+    hash_ids: HashMap<String, u32>,
+    file_info_cache: HashMap<PathBuf, CachedFileInfo>,
+    // 本次启动的随机标识，与 generation 一起组成客户端增量同步的版本
+    epoch: String,
+    // 目录版本号，每次成功重载后递增
+    generation: u64,
+    // 目录内容指纹，供带种子的随机选择使用，内容相同的实例之间一致
+    fingerprint: String,
+    catalog: Arc<CatalogSnapshot>,
+    changes: ChangeLog,
+    cluster: Option<Arc<ClusterBus>>,
+    image_pool: Arc<ImagePool>,
+    // 按需计算的平均颜色 (#rrggbb)，重载后清空
+    colors: Arc<Mutex<HashMap<u32, String>>>,
+    // 是否有后台任务正在补全平均颜色
+    filling_colors: Arc<AtomicBool>,
+    load_shedder: Arc<LoadShedder>,
+    webhooks: Option<Arc<WebhookNotifier>>,
+    events: EventBus,
+    caption: Option<Arc<CaptionRenderer>>,
+    watermark: Option<Arc<Watermarker>>,
+    clients: Arc<ClientTracker>,
+    moderation: ModerationStore,
+    nsfw: NsfwStore,
+    reports: ReportStore,
+    // 随机接口 `?session=` 的去重记录，`random.max_sessions` 为 0 时为空
+    sessions: Option<RandomSessions>,
+    // `storage.id_scheme: stable` 时的 ID 分配表
+    ids: Option<IdRegistry>,
+    // 旧 ID 到新 ID 的 301 跳转
+    id_redirects: IdRedirects,
+    pins: PinStore,
+    tags: Arc<TagStore>,
+    // 生成候选标签的模型，未配置 `ml.model_path` 时为空
+    #[cfg(feature = "ml")]
+    tagger: Option<Arc<Tagger>>,
+    // 是否有后台任务正在生成候选标签
+    #[cfg(feature = "ml")]
+    suggesting_tags: Arc<AtomicBool>,
+    texts: Arc<TextStore>,
+    // 文字识别，未启用 `ocr.enabled` 时为空
+    ocr: Option<Arc<OcrEngine>>,
+    // 是否有后台任务正在识别文字
+    extracting_text: Arc<AtomicBool>,
+    collisions: Vec<IdCollision>,
+    mime_mismatches: Vec<MimeMismatch>,
+    last_reload: Option<ReloadReport>,
+    // 表情包目录不可用，正在用缓存与上一次的目录提供服务
+    degraded: AtomicBool,
+    // 连续失败的重试次数，用于计算退避时间
+    retry_attempts: u32,
+    // 是否已安排了一次重试
+    retry_pending: Arc<AtomicBool>,
 }
 
-impl MemeService {
-    pub async fn new(memes_dir: &str, max_size: u64, ttl_secs: u64) -> Result<Arc<RwLock<Self>>> {
-        let memes_dir = PathBuf::from(memes_dir);
-        let (reload_tx, _) = broadcast::channel(1);
-        
-        // 创建文件监控
-        let reload_tx_clone = reload_tx.clone();
-        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
-            match res {
-                Ok(event) => {
-                    // 只输出变更的文件路径
-                    for path in event.paths {
-                        info!("检测到文件变更: {}", path.display());
-                    }
-                    if let Err(e) = reload_tx_clone.send(()) {
-                        error!("发送重载信号失败: {}", e);
-                    }
-                }
-                Err(e) => error!("监控文件出错: {}", e),
-            }
-        })?;
+impl MemeService {
+    pub async fn new(config: Arc<Config>) -> Result<Arc<RwLock<Self>>> {
+        let storage = Arc::new(FsStorage::new(&config.storage));
+        Self::with_storage(config, storage).await
+    }
+
+    /// 测试用：以内存中的文件 (文件名, 内容) 创建服务，不读写表情包目录、不启动文件监控，
+    /// 其余持久化文件写入 `root` (见 [`Config::for_test`])
+    #[cfg(any(test, feature = "test-util"))]
+    pub async fn new_for_test(root: &Path, files: Vec<(&str, Vec<u8>)>) -> Result<Arc<RwLock<Self>>> {
+        Self::new_for_test_with_config(Config::for_test(root), files).await
+    }
+
+    /// 同 [`Self::new_for_test`]，使用调整过的配置，配置应以 [`Config::for_test`] 为基础
+    #[cfg(any(test, feature = "test-util"))]
+    pub async fn new_for_test_with_config(config: Config, files: Vec<(&str, Vec<u8>)>) -> Result<Arc<RwLock<Self>>> {
+        let storage = Arc::new(crate::services::storage::MemoryStorage::new());
+        let memes_dir = PathBuf::from(&config.storage.memes_dir);
+        for (name, content) in files {
+            storage.insert(memes_dir.join(name), content);
+        }
+        Self::with_storage(Arc::new(config), storage).await
+    }
+
+    /// 使用指定的存储创建服务，测试中可传入 [`MemoryStorage`](crate::services::storage::MemoryStorage)
+    pub async fn with_storage(config: Arc<Config>, storage: Arc<dyn Storage>) -> Result<Arc<RwLock<Self>>> {
+        let memes_dir = PathBuf::from(&config.storage.memes_dir);
+        let max_size = config.cache.max_size;
+        let ttl_secs = config.cache.ttl_secs;
+        let (content_policy, resized_policy) = (&config.cache.content, &config.cache.resized);
+        let (reload_tx, _) = broadcast::channel(1);
+        
+        // 创建文件监控
+        let watcher = storage.watch(reload_tx.clone());
+
+        #[cfg(not(feature = "ml"))]
+        if config.ml.model_path.is_some() {
+            warn!("配置了 ml.model_path，但编译时未启用 ml 特性，不会生成候选标签");
+        }
+
+        // 配置了 max_memory_mb 时按图片字节数计算容量，否则按条目数
+        let max_memory_bytes = config.cache.max_memory_mb * 1024 * 1024;
+        let (content_capacity, resized_capacity) = if max_memory_bytes > 0 {
+            (max_memory_bytes, max_memory_bytes)
+        } else {
+            (max_size, max_size * 2) // 压缩图片缓存容量更大
+        };
+        let weigh_bytes = max_memory_bytes > 0;
+
+        // 初始化缓存 - 增加缓存容量
+        let content_ages = CacheAges::default();
+        let (inserted, removed) = (content_ages.clone(), content_ages.clone());
+        let content_cache = moka::future::Cache::builder()
+            .max_capacity(content_capacity)
+            .weigher(move |id: &u32, content: &Vec<u8>| {
+                inserted.inserted(id);
+                cache_weight(CONTENT_CACHE, weigh_bytes, content)
+            })
+            .eviction_listener(move |id: Arc<u32>, content: Vec<u8>, cause| {
+                removed.removed(&id, cause);
+                cache_removed(CONTENT_CACHE, &id, &content, cause)
+            });
+        let content_cache = with_policy(content_cache, CONTENT_CACHE, content_policy, ttl_secs).build();
+            
+        // 初始化压缩图片缓存，未单独配置时缓存时间更长
+        let resized_ages = CacheAges::default();
+        let (inserted, removed) = (resized_ages.clone(), resized_ages.clone());
+        let resized_cache = moka::future::Cache::builder()
+            .max_capacity(resized_capacity)
+            .weigher(move |key: &String, content: &Vec<u8>| {
+                inserted.inserted(key);
+                cache_weight(RESIZED_CACHE, weigh_bytes, content)
+            })
+            .eviction_listener(move |key: Arc<String>, content: Vec<u8>, cause| {
+                removed.removed(&key, cause);
+                cache_removed(RESIZED_CACHE, &key, &content, cause)
+            });
+        let resized_cache = with_policy(resized_cache, RESIZED_CACHE, resized_policy, ttl_secs * 2).build();
+        let early_refresh = config.cache.early_refresh.then(|| EarlyRefresh {
+            ratio: config.cache.early_refresh_ratio,
+            content_ttl: content_policy.time_to_live(ttl_secs),
+            resized_ttl: resized_policy.time_to_live(ttl_secs * 2),
+        });
+        start_cache_stats_task(config.cache.stats_interval_secs);
+
+        // 内存过载时拒绝缩放请求
+        let load_shedder = LoadShedder::start(&config.load_shedding);
+
+        // 配文字体可选，缺失时只禁用配文接口
+        let caption = match CaptionRenderer::load(&config.caption) {
+            Ok(renderer) => Some(Arc::new(renderer)),
+            Err(e) => {
+                warn!("配文接口不可用: {}", e);
+                None
+            }
+        };
+        // 启用水印但无法加载时拒绝启动，避免发出未加水印的图片
+        let watermark = Watermarker::load(&config, caption.as_deref())?.map(Arc::new);
+
+        // 加载单个表情包访问统计并定期持久化
+        let meme_stats = Arc::new(MemeStatsStore::load(&config.statistics));
+        MemeStatsStore::start_persist_task(Arc::clone(&meme_stats), config.statistics.persist_interval_secs);
+
+        // 恢复请求与缓存的累计计数并定期持久化
+        let counters = Arc::new(RequestCounters::load(&config.statistics.counters_path));
+        RequestCounters::start_persist_task(Arc::clone(&counters), config.statistics.persist_interval_secs);
+
+        // 初始化回收站并启动过期清理任务
+        let trash = Arc::new(TrashService::new(&memes_dir, config.storage.trash_retention_days));
+        TrashService::start_purge_task(Arc::clone(&trash), config.storage.trash_purge_interval_secs);
+
+        // 图片缩放使用独立线程池，避免占满 tokio 阻塞线程池
+        let image_pool = Arc::new(ImagePool::new(&config.resize)?);
+
+        // 稳定 ID：迁移前以文件名 ID 记录的别名与访问统计改用新 ID，原有别名与热度在切换后仍然有效
+        let aliases = AliasStore::load(&config.storage.aliases_file);
+        let ids = match config.storage.id_scheme {
+            IdScheme::Stable => Some(IdRegistry::open(&config.storage.id_map_file)?),
+            IdScheme::Filename => None,
+        };
+        if let Some(registry) = &ids {
+            let redirects = registry.redirects();
+            let (aliases, stats) = (aliases.remap(&redirects)?, meme_stats.remap(&redirects)?);
+            if aliases + stats > 0 {
+                info!("已将 {} 个别名与 {} 条访问统计迁移到稳定 ID", aliases, stats);
+            }
+        }
+
+        // 创建服务实例
+        let service = Arc::new(RwLock::new(Self {
+            memes: HashMap::new(),
+            meme_ids: Vec::new(),
+            total_count: 0,
+            content_cache,
+            resized_cache,
+            content_ages,
+            resized_ages,
+            early_refresh,
+            memes_dir: memes_dir.clone(),
+            config: Arc::clone(&config),
+            reload_tx,
+            storage,
+            watcher,
+            counters,
+            start_time: SystemTime::now(),
+            request_window: RequestWindow::new(REQUEST_HISTORY_WINDOW),
+            prefetch: Mutex::new(VecDeque::with_capacity(config.cache.prefetch)),
+            reads: Mutex::new(HashMap::new()),
+            last_updated: Mutex::new(SystemTime::now()),
+            meme_stats: Arc::clone(&meme_stats),
+            trash,
+            aliases,
+            duplicate_ids: HashMap::new(),
+            hash_ids: HashMap::new(),
+            file_info_cache: HashMap::new(),
+            epoch: format!("{:016x}", fastrand::u64(..)),
+            generation: 0,
+            fingerprint: String::new(),
+            catalog: Arc::new(CatalogSnapshot::default()),
+            changes: ChangeLog::new(CHANGE_HISTORY_LEN),
+            cluster: ClusterBus::new(&config.cluster),
+            image_pool,
+            colors: Arc::new(Mutex::new(HashMap::new())),
+            filling_colors: Arc::new(AtomicBool::new(false)),
+            load_shedder,
+            webhooks: WebhookNotifier::new(&config.webhooks),
+            events: EventBus::new(),
+            caption,
+            watermark,
+            clients: Arc::new(ClientTracker::new(&config.statistics)),
+            moderation: ModerationStore::load(&config.storage.moderation_file)?,
+            nsfw: NsfwStore::load(&config.storage.nsfw_file),
+            reports: ReportStore::load(
+                &config.storage.reports_file,
+                &config.storage.report_secret_file,
+                config.reports.max_per_client,
+                config.reports.window_secs,
+            )?,
+            sessions: RandomSessions::new(&config.random),
+            ids,
+            id_redirects: IdRedirects::load(&config.storage.id_redirects_file),
+            pins: PinStore::load(&config.storage.pins_file),
+            tags: Arc::new(TagStore::load(&config.storage.tags_file)),
+            #[cfg(feature = "ml")]
+            tagger: Tagger::load(&config.ml)?.map(Arc::new),
+            #[cfg(feature = "ml")]
+            suggesting_tags: Arc::new(AtomicBool::new(false)),
+            texts: Arc::new(TextStore::load(&config.storage.text_file)),
+            ocr: OcrEngine::new(&config.ocr).map(Arc::new),
+            extracting_text: Arc::new(AtomicBool::new(false)),
+            collisions: Vec::new(),
+            mime_mismatches: Vec::new(),
+            last_reload: None,
+            degraded: AtomicBool::new(false),
+            retry_attempts: 0,
+            retry_pending: Arc::new(AtomicBool::new(false)),
+        }));
+
+        // 初始加载表情包：有可用的目录快照时先用快照提供服务，再在后台重新扫描校验
+        let restored = service.write().await.restore_snapshot().await;
+        if !restored {
+            Self::reload(&service).await?;
+        }
+
+        // 启动重载监听器
+        let reload_rx = service.read().await.reload_tx.subscribe();
+        Self::start_reload_listener(Arc::clone(&service), reload_rx);
+        if restored {
+            service.read().await.request_reload(ReloadTrigger::Startup);
+        }
+
+        // 主节点定期轮询目录，弥补共享存储上收不到文件事件的问题
+        if config.cluster.enabled && config.cluster.leader && config.cluster.poll_interval_secs > 0 {
+            let reload_tx = service.read().await.reload_tx.clone();
+            cluster::start_poll_task(memes_dir.clone(), &config.storage, config.cluster.poll_interval_secs, move || {
+                if let Err(e) = reload_tx.send(ReloadTrigger::Poll) {
+                    error!("发送重载信号失败: {}", e);
+                }
+            });
+        }
+
+        Ok(service)
+    }
+
+    /// 重新扫描表情包目录，结果记录在 `reload` span、Prometheus 指标与 [`last_reload`](Self::last_reload) 中；
+    /// 扫描与生成新目录期间只持有读锁，请求照常由旧目录与缓存处理，全部完成后才获取写锁替换目录；
+    /// 未变化的表情包的缓存在重载后继续有效
+    async fn reload(service: &Arc<RwLock<Self>>) -> Result<()> {
+        let span = info_span!(
+            "reload",
+            files_scanned = field::Empty,
+            added = field::Empty,
+            removed = field::Empty,
+            skipped = field::Empty,
+            failures = field::Empty,
+            duration_ms = field::Empty,
+        );
+        let started = Instant::now();
+        let mut report = ReloadReport::default();
+        // 扫描与生成新目录期间继续用旧目录与缓存提供服务，全部准备好后在写锁下一次性替换
+        let prepared = {
+            let service = service.read().await;
+            match service.scan_memes(&mut report).instrument(span.clone()).await {
+                Ok(scanned) => Ok(service.prepare_catalog(scanned).await),
+                Err(e) => Err(e),
+            }
+        };
+        let result = match prepared {
+            Ok(prepared) => {
+                service.write().await.apply_scan(prepared, &mut report).instrument(span.clone()).await;
+                // 固定的表情包在替换后重新读取，期间继续使用旧内容
+                service.read().await.refresh_pins().await;
+                Ok(())
+            }
+            Err(e) => Err(e),
+        };
+        let mut service = service.write().await;
+
+        let elapsed = started.elapsed();
+        RELOAD_DURATION.observe(elapsed.as_secs_f64());
+        report.duration_ms = elapsed.as_millis() as u64;
+        report.finished_at = SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        report.ok = result.is_ok();
+        report.error = result.as_ref().err().map(|e| e.to_string());
+        RELOADS_TOTAL.with_label_values(&[if report.ok { "ok" } else { "error" }]).inc();
+
+        span.record("files_scanned", report.files_scanned);
+        span.record("added", report.added);
+        span.record("removed", report.removed);
+        span.record("skipped", report.skipped);
+        span.record("failures", report.failures);
+        span.record("duration_ms", report.duration_ms);
+        span.in_scope(|| match &report.error {
+            None => info!(total = service.total_count, "重新加载了 {} 个表情包", service.total_count),
+            Some(e) => warn!(error = %e, "表情包重载失败"),
+        });
+
+        if report.ok {
+            service.save_snapshot();
+            service.leave_degraded();
+        } else if service.total_count > 0 {
+            // 保留上一次的目录继续提供服务，按退避时间重试
+            service.enter_degraded();
+            service.schedule_retry();
+        }
+        service.last_reload = Some(report);
+        result
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Acquire)
+    }
+
+    /// 标记为降级，返回之前是否已处于降级模式
+    fn enter_degraded(&self) -> bool {
+        let was_degraded = self.degraded.swap(true, Ordering::AcqRel);
+        if !was_degraded {
+            warn!("表情包目录不可用，进入降级模式，继续使用缓存与上一次的目录提供服务");
+            STORAGE_DEGRADED.set(1.0);
+        }
+        was_degraded
+    }
+
+    fn leave_degraded(&mut self) {
+        self.retry_attempts = 0;
+        if self.degraded.swap(false, Ordering::AcqRel) {
+            info!("表情包目录已恢复，退出降级模式");
+            STORAGE_DEGRADED.set(0.0);
+        }
+    }
+
+    /// 按指数退避安排一次重试扫描，已有等待中的重试时不重复安排
+    fn schedule_retry(&mut self) {
+        if self.retry_pending.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let storage = &self.config.storage;
+        let delay = storage.rescan_backoff_initial_secs
+            .saturating_mul(1u64 << self.retry_attempts.min(16))
+            .min(storage.rescan_backoff_max_secs);
+        self.retry_attempts += 1;
+        info!("将在 {} 秒后重试扫描表情包目录 (第 {} 次)", delay, self.retry_attempts);
+
+        let reload_tx = self.reload_tx.clone();
+        let pending = Arc::clone(&self.retry_pending);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(delay)).await;
+            pending.store(false, Ordering::Release);
+            if let Err(e) = reload_tx.send(ReloadTrigger::Retry) {
+                error!("发送重载信号失败: {}", e);
+            }
+        });
+    }
+
+    /// 读取表情包文件失败：文件已不存在时 (两次重载之间被删除) 返回 404 并触发一次扫描更新目录；
+    /// 其他错误 (I/O、权限等) 视为存储不可用，进入降级模式并立即触发一次扫描确认，返回 503 而不是 500。
+    /// 整个目录消失时扫描本身会失败，由重载进入降级模式
+    fn read_failed(&self, meme: &Meme, e: std::io::Error) -> AppError {
+        warn!(meme_id = meme.id, "读取表情包 {} 失败: {}", meme.filename, e);
+        if e.kind() == std::io::ErrorKind::NotFound {
+            self.request_reload(ReloadTrigger::Retry);
+            return AppError::MemeNotFound { id: meme.id };
+        }
+        if !self.enter_degraded() {
+            self.request_reload(ReloadTrigger::Retry);
+        }
+        AppError::ServiceUnavailable("Meme storage is temporarily unavailable".to_string())
+    }
+
+    /// 从目录快照恢复，成功时返回 true
+    async fn restore_snapshot(&mut self) -> bool {
+        let path = &self.config.storage.snapshot_file;
+        if path.is_empty() {
+            return false;
+        }
+        let path = PathBuf::from(path);
+        let memes_dir = self.config.storage.memes_dir.clone();
+        let snapshot = tokio::task::spawn_blocking(move || CatalogFile::load(&path, &memes_dir))
+            .await
+            .ok()
+            .flatten();
+        let Some(snapshot) = snapshot else {
+            return false;
+        };
+
+        let scanned = ScannedCatalog {
+            memes: snapshot.memes.into_iter().map(|meme| (meme.id, meme)).collect(),
+            duplicate_ids: snapshot.duplicate_ids,
+            collisions: Vec::new(),
+            mime_mismatches: Vec::new(),
+            file_info_cache: snapshot.files,
+        };
+        let prepared = self.prepare_catalog(scanned).await;
+        self.apply_scan(prepared, &mut ReloadReport::default()).await;
+        self.refresh_pins().await;
+        info!("从目录快照恢复了 {} 个表情包，将在后台重新扫描校验", self.total_count);
+        true
+    }
+
+    /// 在后台写入目录快照，不阻塞重载
+    fn save_snapshot(&self) {
+        let path = &self.config.storage.snapshot_file;
+        if path.is_empty() {
+            return;
+        }
+        let path = PathBuf::from(path);
+        let snapshot = CatalogFile::new(
+            &self.config.storage.memes_dir,
+            self.memes.values().cloned().collect(),
+            self.duplicate_ids.clone(),
+            self.file_info_cache.clone(),
+        );
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = snapshot.save(&path) {
+                warn!("保存目录快照 {:?} 失败: {}", path, e);
+            }
+        });
+    }
+
+    /// 扫描表情包目录，只读取服务状态
+    async fn scan_memes(&self, report: &mut ReloadReport) -> Result<ScannedCatalog> {
+        let mut candidates = Vec::new();
+        let mut skipped = 0;
+        let mut file_info_cache = HashMap::new();
+        let mut filenames = HashSet::new();
+        let mut mime_mismatches = Vec::new();
+        let deduplicate = self.config.storage.deduplicate;
+
+        let files = self.storage.list().await?;
+        report.files_scanned = files.len();
+        for path in files {
+            // 子目录中的文件以相对路径 (如 `cats/cat.jpg`) 作为文件名，根目录下的文件 ID 保持不变；
+            // 文件名统一为 NFC 形式，磁盘上的形式不同时 (如 macOS 的 NFD) 另外保存，两者都可用于查找
+            let disk_filename = path.strip_prefix(&self.memes_dir)
+                .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_else(|_| "unknown".to_string());
+            let filename = normalize_filename(&disk_filename);
+            let original_filename = (disk_filename != filename).then_some(disk_filename);
+            let basename = path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            // 来源信息文件随图片一起加载，不计入跳过的文件
+            if basename.ends_with(METADATA_SUFFIX) {
+                continue;
+            }
+
+            let mime_type = match self.check_file(&path, &basename).await {
+                Ok((mime_type, None)) => mime_type,
+                Ok((mime_type, Some(extension_mime))) => {
+                    warn!("文件 {} 的实际类型为 {}，与扩展名推断的 {} 不符，按实际类型提供", filename, mime_type, extension_mime);
+                    mime_mismatches.push(MimeMismatch {
+                        filename: filename.clone(),
+                        extension_mime,
+                        detected_mime: mime_type.clone(),
+                    });
+                    mime_type
+                }
+                Err(reason) => {
+                    debug!("跳过文件 {}: {}", path.display(), reason);
+                    SKIPPED_FILES.with_label_values(&[reason]).inc();
+                    skipped += 1;
+                    if reason == "unreadable" {
+                        report.failures += 1;
+                    }
+                    continue;
+                }
+            };
+
+            let metadata = match self.storage.metadata(&path).await {
+                Ok(metadata) => Some(metadata),
+                Err(e) => {
+                    warn!("读取文件 {} 的信息失败: {}", path.display(), e);
+                    report.failures += 1;
+                    None
+                }
+            };
+            let size_bytes = metadata.map(|m| m.len).unwrap_or(0);
+
+            let modified = metadata.and_then(|m| m.modified);
+            let file_info = self.file_info(&path, size_bytes, modified).await;
+            let content_hash = file_info.hash.clone();
+            let captured_on = file_info.captured_on.clone();
+            let (width, height) = match file_info.dimensions {
+                Some((width, height)) => (Some(width), Some(height)),
+                None => (None, None),
+            };
+            file_info_cache.insert(path.clone(), file_info);
+
+            let id = meme_id_for(&filename);
+            // 在 macOS 上记录的审核、NSFW 与标签可能以磁盘上的文件名为键
+            let names: Vec<&str> = std::iter::once(filename.as_str())
+                .chain(original_filename.as_deref())
+                .collect();
+            let status = if names.iter().any(|name| self.moderation.is_pending(name)) {
+                MemeStatus::Pending
+            } else {
+                MemeStatus::Approved
+            };
+            filenames.extend(names.iter().map(|name| name.to_string()));
+            let attribution = self.load_metadata(&path).await;
+            let nsfw = attribution.as_ref().is_some_and(|m| m.nsfw)
+                || names.iter().any(|name| self.nsfw.is_flagged(name));
+            let tags = names.iter()
+                .map(|name| self.tags.approved(name))
+                .find(|tags| !tags.is_empty())
+                .unwrap_or_default();
+            let created_on = attribution.as_ref()
+                .and_then(|m| m.date.clone())
+                .or(captured_on);
+
+            candidates.push(Meme {
+                id,
+                path,
+                mime_type,
+                filename,
+                original_filename,
+                size_bytes,
+                content_hash,
+                duplicates: Vec::new(),
+                duplicate_paths: Vec::new(),
+                status,
+                metadata: attribution,
+                width,
+                height,
+                nsfw,
+                tags,
+                created_on,
+            });
+        }
+
+        if let Err(e) = self.moderation.retain(&filenames) {
+            warn!("更新待审核列表失败: {}", e);
+        }
+        if let Err(e) = self.nsfw.retain(&filenames) {
+            warn!("更新 NSFW 标记列表失败: {}", e);
+        }
+        if let Err(e) = self.reports.retain(&filenames) {
+            warn!("更新举报记录失败: {}", e);
+        }
+        if let Err(e) = self.tags.retain(&filenames) {
+            warn!("更新标签文件失败: {}", e);
+        }
+        if let Err(e) = self.texts.retain(&filenames) {
+            warn!("更新识别文字文件失败: {}", e);
+        }
+
+        report.skipped = skipped;
+        if let Some(registry) = &self.ids {
+            let files: Vec<(&str, Option<&str>)> = candidates.iter()
+                .map(|meme| (meme.filename.as_str(), meme.content_hash.as_deref()))
+                .collect();
+            let ids = registry.assign(&files)?;
+            for (meme, id) in candidates.iter_mut().zip(ids) {
+                meme.id = id;
+            }
+        }
+        let collisions = self.resolve_collisions(&mut candidates);
+        let (memes, duplicate_ids) = Self::deduplicate(candidates, deduplicate);
+        if memes.is_empty() {
+            if !self.config.storage.allow_empty {
+                return Err(AppError::Internal("No memes found".to_string()));
+            }
+            warn!("表情包目录为空，图片接口在有文件加入前返回 503");
+        }
+
+        mime_mismatches.sort_by(|a, b| a.filename.cmp(&b.filename));
+        Ok(ScannedCatalog {
+            memes,
+            duplicate_ids,
+            collisions,
+            mime_mismatches,
+            file_info_cache,
+        })
+    }
+
+    /// 为扫描结果生成预压缩的目录，并找出文件已变化的表情包；只读取服务状态
+    async fn prepare_catalog(&self, scanned: ScannedCatalog) -> PreparedCatalog {
+        let stale_ids = self.memes.iter()
+            .filter(|(id, meme)| {
+                let Some(current) = scanned.memes.get(id) else {
+                    return true;
+                };
+                let previous_info = self.file_info_cache.get(&meme.path);
+                let current_info = scanned.file_info_cache.get(&current.path);
+                let unchanged = current.path == meme.path
+                    && previous_info.zip(current_info).is_some_and(|(previous, current)| {
+                        previous.size_bytes == current.size_bytes
+                            && previous.modified == current.modified
+                            && previous.hash == current.hash
+                    });
+                !unchanged
+            })
+            .map(|(id, _)| *id)
+            .collect();
+
+        let generation = self.generation + 1;
+        let memes: Vec<Meme> = scanned.memes.values()
+            .filter(|meme| meme.is_approved())
+            .cloned()
+            .collect();
+        let epoch = self.epoch.clone();
+        let catalog = match tokio::task::spawn_blocking(move || CatalogSnapshot::build(&epoch, generation, memes.iter())).await {
+            Ok(Ok(snapshot)) => {
+                debug!(
+                    generation,
+                    json_bytes = snapshot.json.len(),
+                    brotli_bytes = snapshot.brotli.len(),
+                    zstd_bytes = snapshot.zstd.len(),
+                    "表情包目录已生成"
+                );
+                Some(snapshot)
+            }
+            Ok(Err(e)) => {
+                error!("生成表情包目录失败: {}", e);
+                None
+            }
+            Err(e) => {
+                error!("生成表情包目录任务执行失败: {}", e);
+                None
+            }
+        };
+
+        PreparedCatalog { scanned, catalog, stale_ids }
+    }
+
+    /// 用准备好的目录替换当前目录，只使文件已变化或已删除的表情包的缓存失效
+    async fn apply_scan(&mut self, prepared: PreparedCatalog, report: &mut ReloadReport) {
+        let PreparedCatalog { scanned, catalog, stale_ids } = prepared;
+        let ScannedCatalog { memes, duplicate_ids, collisions, mime_mismatches, file_info_cache } = scanned;
+
+        // 更新服务状态
+        let previous_ids: HashSet<u32> = self.meme_ids.iter().copied().collect();
+        self.memes = memes;
+        // 预计算ID向量以提高随机选择性能，待审核的表情包不参与随机选择
+        self.meme_ids = self.memes.values()
+            .filter(|meme| meme.is_approved())
+            .map(|meme| meme.id)
+            .collect();
+        let count = self.meme_ids.len() as u32;
+        let pending = self.memes.len() - self.meme_ids.len();
+        self.total_count = count;
+        self.file_info_cache = file_info_cache;
+        self.invalidate_stale(&stale_ids).await;
+        let current_ids: HashSet<u32> = self.meme_ids.iter().copied().collect();
+        self.prefetch.lock().retain(|id| current_ids.contains(id));
+        self.colors.lock().retain(|id, _| !stale_ids.contains(id));
+        *self.last_updated.lock() = SystemTime::now();
+        
+        // 更新 Prometheus 指标
+        TOTAL_MEMES.set(count as f64);
+        DUPLICATE_FILES.set(duplicate_ids.len() as f64);
+
+        if !duplicate_ids.is_empty() {
+            info!("发现 {} 个内容重复的文件，已合并到对应的表情包", duplicate_ids.len());
+        }
+        self.duplicate_ids = duplicate_ids;
+        self.hash_ids = Self::index_hashes(&self.memes);
+        self.fingerprint = Self::fingerprint(&self.memes, &self.meme_ids);
+
+        ID_COLLISIONS.set(collisions.len() as f64);
+        self.collisions = collisions;
+        MIME_MISMATCHES.set(mime_mismatches.len() as f64);
+        self.mime_mismatches = mime_mismatches;
+
+        // 递增目录版本号，记录变更并换上预先生成的目录
+        self.generation += 1;
+        report.added = current_ids.difference(&previous_ids).count();
+        report.removed = previous_ids.difference(&current_ids).count();
+        self.changes.record(self.generation, &previous_ids, &current_ids);
+        if let Some(catalog) = catalog {
+            self.catalog = Arc::new(catalog);
+        }
+        #[cfg(feature = "ml")]
+        self.suggest_tags();
+        self.extract_text();
+
+        if pending > 0 {
+            info!("{} 个表情包等待审核", pending);
+        }
+    }
+
+    /// 读取图片旁的来源信息文件，依次尝试 `<文件名>.meta.yml` 与 `<不含扩展名的文件名>.meta.yml`
+    async fn load_metadata(&self, path: &Path) -> Option<MemeMetadata> {
+        let filename = path.file_name()?.to_string_lossy();
+        let stem = path.file_stem()?.to_string_lossy();
+        let candidates = [
+            path.with_file_name(format!("{}{}", filename, METADATA_SUFFIX)),
+            path.with_file_name(format!("{}{}", stem, METADATA_SUFFIX)),
+        ];
+
+        for candidate in candidates {
+            let Ok(content) = self.storage.read(&candidate).await else {
+                continue;
+            };
+            let content = String::from_utf8_lossy(&content);
+            match serde_yaml::from_str::<MemeMetadata>(&content) {
+                Ok(mut metadata) => {
+                    // 只允许 http(s) 地址作为重定向目标
+                    let invalid_url = metadata.canonical_url.as_deref().is_some_and(|url| {
+                        !reqwest::Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+                    });
+                    if invalid_url {
+                        warn!("来源信息文件 {} 中的 canonical_url 不是 http(s) 地址，已忽略", candidate.display());
+                        metadata.canonical_url = None;
+                    }
+                    if metadata.date.as_deref().is_some_and(|date| !media::is_valid_date(date)) {
+                        warn!("来源信息文件 {} 中的 date 不是 YYYY-MM-DD 格式，已忽略", candidate.display());
+                        metadata.date = None;
+                    }
+                    return Some(metadata);
+                }
+                Err(e) => {
+                    warn!("解析来源信息文件 {} 失败: {}", candidate.display(), e);
+                    return None;
+                }
+            }
+        }
+        None
+    }
+
+    /// 移除指定表情包的原图缓存与全部处理结果 (键以 `{id}:` 开头)
+    async fn invalidate_stale(&self, stale_ids: &HashSet<u32>) {
+        if stale_ids.is_empty() {
+            return;
+        }
+        for id in stale_ids {
+            self.content_cache.invalidate(id).await;
+        }
+        let stale_keys: Vec<Arc<String>> = self.resized_cache.iter()
+            .filter(|(key, _)| cached_ids(key).iter().any(|id| stale_ids.contains(id)))
+            .map(|(key, _)| key)
+            .collect();
+        for key in &stale_keys {
+            self.resized_cache.invalidate(key.as_str()).await;
+        }
+        self.update_cache_metrics();
+        debug!(memes = stale_ids.len(), resized = stale_keys.len(), "已移除变化的表情包的缓存");
+    }
+
+    /// 读取文件的图片尺寸，并在需要时计算内容哈希；文件未变化时复用上次的结果
+    async fn file_info(&self, path: &Path, size_bytes: u64, modified: Option<SystemTime>) -> CachedFileInfo {
+        if let Some(cached) = self.file_info_cache.get(path) {
+            let unchanged = cached.size_bytes == size_bytes && cached.modified == modified;
+            if unchanged && cached.hash.is_some() {
+                return cached.clone();
+            }
+        }
+
+        let content = match self.storage.read(path).await {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("读取文件 {} 失败: {}", path.display(), e);
+                return CachedFileInfo {
+                    size_bytes,
+                    modified,
+                    hash: None,
+                    dimensions: None,
+                    captured_on: None,
+                };
+            }
+        };
+        let owned_path = path.to_path_buf();
+        let result = tokio::task::spawn_blocking(move || {
+            // 只解析文件头，不解码整张图片；SVG 使用声明的尺寸
+            let dimensions = if svg::looks_like_svg(&content[..content.len().min(media::SNIFF_LEN)]) {
+                svg::dimensions(&content)
+            } else {
+                image::io::Reader::new(std::io::Cursor::new(&content))
+                    .with_guessed_format()
+                    .map_err(image::ImageError::IoError)
+                    .and_then(|reader| reader.into_dimensions())
+                    .map_err(|e| debug!("读取图片 {} 的尺寸失败: {}", owned_path.display(), e))
+                    .ok()
+            };
+            let hash = Some(hash_content(&content));
+            (hash, dimensions, media::capture_date(&content))
+        }).await;
+
+        let (hash, dimensions, captured_on) = result.unwrap_or_else(|e| {
+            error!("读取文件信息任务执行失败: {}", e);
+            (None, None, None)
+        });
+
+        CachedFileInfo {
+            size_bytes,
+            modified,
+            hash,
+            dimensions,
+            captured_on,
+        }
+    }
+
+    /// 检测文件名哈希前缀相同的 ID 冲突：之前已占用该 ID 的文件（或文件名最小的文件）保留原 ID，
+    /// 其余文件依次尝试 `文件名#1`、`文件名#2`… 的哈希，直到找到未被占用的 ID
+    fn resolve_collisions(&self, candidates: &mut [Meme]) -> Vec<IdCollision> {
+        let mut by_id: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+        for (index, meme) in candidates.iter().enumerate() {
+            by_id.entry(meme.id).or_default().push(index);
+        }
+        if by_id.len() == candidates.len() {
+            return Vec::new();
+        }
+
+        let mut used: HashSet<u32> = by_id.keys().copied().collect();
+        let mut collisions = Vec::new();
+
+        for (id, mut indices) in by_id {
+            if indices.len() < 2 {
+                continue;
+            }
+
+            let previous_owner = self.memes.get(&id).map(|meme| meme.filename.as_str());
+            indices.sort_by(|&a, &b| {
+                let a = &candidates[a].filename;
+                let b = &candidates[b].filename;
+                (Some(a.as_str()) != previous_owner, a).cmp(&(Some(b.as_str()) != previous_owner, b))
+            });
+
+            let kept = candidates[indices[0]].filename.clone();
+            let mut reassigned = Vec::new();
+            for &index in &indices[1..] {
+                let meme = &mut candidates[index];
+                let new_id = (1..)
+                    .map(|salt| salted_meme_id(&meme.filename, salt))
+                    .find(|candidate| !used.contains(candidate))
+                    .expect("u32 ID 空间耗尽");
+                used.insert(new_id);
+
+                warn!("表情包 ID {} 冲突: {} 与 {}，后者改用 ID {}", id, kept, meme.filename, new_id);
+                meme.id = new_id;
+                reassigned.push(ReassignedId {
+                    filename: meme.filename.clone(),
+                    id: new_id,
+                });
+            }
+
+            collisions.push(IdCollision { id, kept, reassigned });
+        }
+
+        collisions
+    }
+
+    /// 按内容哈希合并重复文件：同一内容优先保留已审核的、文件名排序最靠前的一个，
+    /// 其余文件名记录在 `duplicates` 中，其 ID 映射到保留的表情包
+    fn deduplicate(candidates: Vec<Meme>, enabled: bool) -> (HashMap<u32, Meme>, HashMap<u32, u32>) {
+        let mut memes = HashMap::new();
+        let mut duplicate_ids = HashMap::new();
+        let mut groups: BTreeMap<String, Vec<Meme>> = BTreeMap::new();
+
+        for meme in candidates {
+            match meme.content_hash.clone().filter(|_| enabled) {
+                Some(hash) => groups.entry(hash).or_default().push(meme),
+                None => {
+                    memes.insert(meme.id, meme);
+                }
+            }
+        }
+
+        for (_, mut group) in groups {
+            group.sort_by(|a, b| {
+                (!a.is_approved(), &a.filename).cmp(&(!b.is_approved(), &b.filename))
+            });
+            let mut group = group.into_iter();
+            let Some(mut canonical) = group.next() else {
+                continue;
+            };
+            for duplicate in group {
+                debug!("文件 {} 与 {} 内容相同", duplicate.filename, canonical.filename);
+                duplicate_ids.insert(duplicate.id, canonical.id);
+                // 内容相同，任一文件被标记为 NSFW 时整体视为 NSFW
+                canonical.nsfw |= duplicate.nsfw;
+                canonical.duplicates.push(duplicate.filename);
+                canonical.duplicate_paths.push(duplicate.path);
+            }
+            memes.insert(canonical.id, canonical);
+        }
+
+        (memes, duplicate_ids)
+    }
+
+    /// 按 ID 排序后对参与随机选择的表情包 ID 与内容哈希做 SHA-256
+    fn fingerprint(memes: &HashMap<u32, Meme>, ids: &[u32]) -> String {
+        let mut ids = ids.to_vec();
+        ids.sort_unstable();
+        let mut hasher = Sha256::new();
+        for id in ids {
+            let hash = memes.get(&id).and_then(|meme| meme.content_hash.as_deref()).unwrap_or("");
+            hasher.update(format!("{}:{}\n", id, hash).as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// 按内容哈希索引表情包；未去重时内容相同的文件按与去重相同的顺序取第一个
+    fn index_hashes(memes: &HashMap<u32, Meme>) -> HashMap<String, u32> {
+        let mut sorted: Vec<&Meme> = memes.values().collect();
+        sorted.sort_by(|a, b| {
+            (!a.is_approved(), &a.filename).cmp(&(!b.is_approved(), &b.filename))
+        });
+        let mut hash_ids = HashMap::with_capacity(sorted.len());
+        for meme in sorted {
+            if let Some(hash) = &meme.content_hash {
+                hash_ids.entry(hash.clone()).or_insert(meme.id);
+            }
+        }
+        hash_ids
+    }
+
+    /// 将重复文件的 ID 解析为实际保留的表情包 ID
+    fn resolve_id(&self, id: u32) -> u32 {
+        if self.memes.contains_key(&id) {
+            return id;
+        }
+        self.duplicate_ids.get(&id).copied().unwrap_or(id)
+    }
+
+    /// 检查文件是否应当加入表情包目录，返回其 MIME 类型与 (和实际内容不符时) 按扩展名推断的类型；
+    /// 否则返回跳过原因（同时作为指标标签）
+    async fn check_file(&self, path: &std::path::Path, filename: &str) -> std::result::Result<(String, Option<String>), &'static str> {
+        let storage = &self.config.storage;
+
+        if media::is_hidden_or_temp(filename) {
+            return Err("hidden");
+        }
+
+        // 开启 allow_video 时 mp4 与 webm 不受扩展名与 MIME 类型列表限制
+        let is_video = storage.allow_video && path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| media::VIDEO_EXTENSIONS.iter().any(|v| v.eq_ignore_ascii_case(ext)));
+        if !is_video && !media::extension_allowed(path, &storage.allowed_extensions) {
+            return Err("extension");
+        }
+
+        let extension_mime = mime_guess::from_path(path)
+            .first_or_octet_stream()
+            .to_string();
+
+        // 按文件头识别实际类型，扩展名写错的文件 (如扩展名为 .png 的 JPEG) 也能返回正确的 Content-Type
+        let (mime_type, mismatch) = if storage.sniff_content {
+            let Ok(header) = self.storage.read_head(path, media::SNIFF_LEN).await else {
+                return Err("unreadable");
+            };
+            let sniffed = if is_video {
+                media::sniff_video_mime(&header)
+            } else {
+                media::sniff_image_mime(&header)
+            };
+            let Some(sniffed) = sniffed else {
+                return Err("content");
+            };
+            let mismatch = (sniffed != extension_mime).then_some(extension_mime);
+            (sniffed.to_string(), mismatch)
+        } else {
+            (extension_mime, None)
+        };
+
+        if !is_video && !media::mime_allowed(&mime_type, &storage.allowed_mime_types) {
+            return Err("mime");
+        }
+
+        Ok((mime_type, mismatch))
+    }
+
+    fn start_reload_listener(service: Arc<RwLock<Self>>, mut rx: broadcast::Receiver<ReloadTrigger>) {
+        tokio::spawn(async move {
+            loop {
+                // 等待重载信号
+                while let Ok(trigger) = rx.recv().await {
+                    info!(?trigger, "正在重新加载表情包...");
+                    service.read().await.events.publish(CatalogEvent::ReloadStarted { trigger });
+                    let result = Self::reload(&service).await;
+                    let service = service.read().await;
+                    if let Err(e) = result {
+                        error!("重新加载表情包失败: {}", e);
+                        if let Some(webhooks) = &service.webhooks {
+                            webhooks.notify(WebhookEvent::ReloadFailed { error: e.to_string() });
+                        }
+                        service.events.publish(CatalogEvent::ReloadFailed { generation: service.generation });
+                        continue;
+                    }
+                    if let Some(report) = &service.last_reload {
+                        service.events.publish(CatalogEvent::ReloadCompleted {
+                            generation: service.generation,
+                            added: report.added,
+                            removed: report.removed,
+                            duration_ms: report.duration_ms,
+                        });
+                    }
+                    service.notify_catalog_changes();
+
+                    // 本地变更触发的重载需要通知其他节点
+                    if trigger != ReloadTrigger::Peer {
+                        if let Some(bus) = &service.cluster {
+                            bus.broadcast_invalidate(service.generation);
+                        }
+                    }
+                }
+
+                // 如果 channel 关闭，等待一段时间后重试
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                rx = service.read().await.reload_tx.subscribe();
+            }
+        });
+    }
+
+    /// 向 Webhook 发送最近一次重载新增与移除的表情包
+    fn notify_catalog_changes(&self) {
+        if self.webhooks.is_none() && self.events.subscribers() == 0 {
+            return;
+        }
+        let generation = self.generation;
+        let Some((added, removed)) = self.changes.changes_since(generation.saturating_sub(1), generation) else {
+            return;
+        };
+
+        if !added.is_empty() {
+            self.events.publish(CatalogEvent::MemesAdded { generation, ids: added.clone() });
+            if let Some(webhooks) = &self.webhooks {
+                webhooks.notify(WebhookEvent::MemesAdded { generation, count: added.len(), ids: added });
+            }
+        }
+        if !removed.is_empty() {
+            self.events.publish(CatalogEvent::MemesRemoved { generation, ids: removed.clone() });
+            if let Some(webhooks) = &self.webhooks {
+                webhooks.notify(WebhookEvent::MemesRemoved { generation, count: removed.len(), ids: removed });
+            }
+        }
+    }
+
+    /// 目录事件广播，供 `/events` 订阅
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    pub async fn get_random(&self, filter: &RandomFilter) -> Result<(&Meme, Vec<u8>, CacheStatus)> {
+        let meme = self.select_random(filter)?;
+        let (content, cache) = self.read_content(meme).await?;
+        Ok((meme, content, cache))
+    }
+
+    /// 与 [`get_random`](Self::get_random) 相同，但大文件不读入内存
+    pub async fn get_random_original(&self, filter: &RandomFilter) -> Result<(&Meme, Original)> {
+        let meme = self.select_random(filter)?;
+        Ok((meme, self.open_original(meme).await?))
+    }
+
+    /// 按筛选条件随机选择最多 `count` 个互不相同的表情包，没有符合条件的表情包时返回 404
+    pub fn get_random_memes(&self, filter: &RandomFilter, count: usize) -> Result<Vec<&Meme>> {
+        self.counters.requests.fetch_add(1, Ordering::Relaxed);
+        self.record_request();
+
+        if self.meme_ids.is_empty() {
+            return Err(AppError::NotFound("No memes available".to_string()));
+        }
+        let ids = self.pick_filtered_many(filter, count);
+        if ids.is_empty() {
+            return Err(AppError::NotFound("No memes match the given filters".to_string()));
+        }
+        Ok(ids
+            .into_iter()
+            .filter_map(|id| self.memes.get(&id))
+            .inspect(|meme| self.meme_stats.record_hit(meme.id))
+            .collect())
+    }
+
+    fn select_random(&self, filter: &RandomFilter) -> Result<&Meme> {
+        // 增加请求计数并记录时间戳
+        self.counters.requests.fetch_add(1, Ordering::Relaxed);
+        self.record_request();
+        
+        // 使用预计算的ID向量进行随机选择，避免每次重新收集
+        if self.meme_ids.is_empty() {
+            return Err(AppError::NotFound("No memes available".to_string()));
+        }
+        
+        let session = filter.session.as_ref().filter(|_| self.sessions.is_some());
+        let meme_id = if filter.is_empty() && filter.seed.is_none() && session.is_none() {
+            let meme_id = self.next_random_id();
+            self.schedule_prefetch();
+            meme_id
+        } else {
+            self.pick_filtered(filter)
+                .ok_or_else(|| AppError::NotFound("No memes match the given filters".to_string()))?
+        };
+        
+        let meme = self.memes.get(&meme_id)
+            .ok_or_else(|| AppError::NotFound("Meme not found".to_string()))?;
+        self.meme_stats.record_hit(meme_id);
+        Ok(meme)
+    }
+
+    /// 优先取预取队列中的 ID；队列中的表情包可能已在重载或审核后下线，此时跳过
+    fn next_random_id(&self) -> u32 {
+        let mut queue = self.prefetch.lock();
+        while let Some(id) = queue.pop_front() {
+            if self.memes.get(&id).is_some_and(|meme| meme.is_approved()) {
+                return id;
+            }
+        }
+        self.meme_ids[fastrand::usize(..self.meme_ids.len())]
+    }
+
+    /// 将预取队列补满 `cache.prefetch` 个随机 ID，并在后台把尚未缓存的读入内容缓存
+    fn schedule_prefetch(&self) {
+        let depth = self.config.cache.prefetch;
+        if depth == 0 {
+            return;
+        }
+
+        let mut to_load = Vec::new();
+        {
+            let mut queue = self.prefetch.lock();
+            while queue.len() < depth {
+                let id = self.meme_ids[fastrand::usize(..self.meme_ids.len())];
+                queue.push_back(id);
+                let Some(meme) = self.memes.get(&id) else {
+                    continue;
+                };
+                if !self.is_streamed(meme) && !self.is_pinned(id) && !self.content_cache.contains_key(&id) {
+                    to_load.push((id, meme.path.clone(), meme.is_svg()));
+                }
+            }
+        }
+        if to_load.is_empty() {
+            return;
+        }
+
+        let storage = Arc::clone(&self.storage);
+        let cache = self.content_cache.clone();
+        tokio::spawn(async move {
+            for (id, path, is_svg) in to_load {
+                let content = storage.read(&path).await.map_err(AppError::from);
+                match content.and_then(|content| sanitized(is_svg, content)) {
+                    Ok(content) => {
+                        cache.insert(id, content).await;
+                        PREFETCHED_MEMES.inc();
+                        debug!(meme_id = id, "已预取表情包");
+                    }
+                    Err(e) => debug!(meme_id = id, "预取表情包失败: {}", e),
+                }
+            }
+        });
+    }
+
+    /// 重载后重新读取固定的表情包；不在目录中的文件保留在固定列表中，等文件恢复
+    async fn refresh_pins(&self) {
+        let mut contents = HashMap::new();
+        let mut missing = 0;
+        for filename in self.pins.filenames() {
+            let meme = self.memes.values().find(|meme| {
+                meme.filename == filename
+                    || meme.original_filename.as_deref() == Some(filename.as_str())
+                    || meme.duplicates.contains(&filename)
+            });
+            let Some(meme) = meme else {
+                missing += 1;
+                continue;
+            };
+            let content = self.storage.read(&meme.path).await.map_err(AppError::from);
+            match content.and_then(|content| sanitized(meme.is_svg(), content)) {
+                Ok(content) => {
+                    contents.insert(meme.id, content);
+                }
+                Err(e) => warn!("读取固定的表情包 {} 失败: {}", filename, e),
+            }
+        }
+        if missing > 0 {
+            info!("{} 个固定的表情包当前不在目录中，文件恢复后重新加载", missing);
+        }
+        self.pins.replace(contents);
+    }
+
+    /// 固定表情包，使其常驻内存；返回之前是否未固定
+    pub async fn pin(&self, meme: &Meme) -> Result<bool> {
+        let content = sanitized(meme.is_svg(), self.storage.read(&meme.path).await?)?;
+        self.pins.pin(&meme.filename, meme.id, content)
+    }
+
+    /// 取消固定，返回之前是否已固定
+    pub fn unpin(&self, meme: &Meme) -> Result<bool> {
+        let mut removed = false;
+        for filename in meme.filenames() {
+            removed |= self.pins.unpin(filename, meme.id)?;
+        }
+        Ok(removed)
+    }
+
+    pub fn is_pinned(&self, id: u32) -> bool {
+        self.pins.contains(id)
+    }
+
+    /// 从固定表、内容缓存或存储读取原图，超过 `cache.stream_threshold_kb` 的文件不写入缓存
+    async fn read_content(&self, meme: &Meme) -> Result<(Vec<u8>, CacheStatus)> {
+        // 固定的表情包常驻内存，视为缓存命中
+        if let Some(content) = self.pins.get(meme.id) {
+            self.counters.cache_hits.fetch_add(1, Ordering::Relaxed);
+            CACHE_HITS.with_label_values(&[CONTENT_CACHE]).inc();
+            debug!(meme_id = meme.id, cache_type = "pinned", "Cache hit");
+            return Ok((content, CacheStatus::Hit));
+        }
+
+        // 尝试从缓存获取
+        if let Some(content) = self.content_cache.get(&meme.id).await {
+            self.counters.cache_hits.fetch_add(1, Ordering::Relaxed);
+            CACHE_HITS.with_label_values(&[CONTENT_CACHE]).inc(); // 更新 Prometheus 计数器
+            self.update_cache_metrics();
+            self.maybe_refresh_content(meme);
+            debug!(
+                meme_id = meme.id,
+                cache_type = "content",
+                "Cache hit"
+            );
+            return Ok((content, CacheStatus::Hit));
+        }
+
+        // 如果缓存未命中，从文件读取
+        self.counters.cache_misses.fetch_add(1, Ordering::Relaxed);
+        CACHE_MISSES.with_label_values(&[CONTENT_CACHE]).inc(); // 更新 Prometheus 计数器
+        self.update_cache_metrics();
+        debug!(
+            meme_id = meme.id,
+            cache_type = "content",
+            "Cache miss"
+        );
+        let content = self.read_coalesced(meme).await?;
+        Ok((content, CacheStatus::Miss))
+    }
+
+    /// 原图缓存命中后，接近过期的条目按概率在后台重新读取，热门条目不会在同一时刻过期、集中读盘
+    fn maybe_refresh_content(&self, meme: &Meme) {
+        let Some(EarlyRefresh { ratio, content_ttl: Some(ttl), .. }) = self.early_refresh else {
+            return;
+        };
+        if !self.content_ages.should_refresh(&meme.id.to_string(), ttl, ratio) {
+            return;
+        }
+
+        let storage = Arc::clone(&self.storage);
+        let (content_cache, resized_cache) = (self.content_cache.clone(), self.resized_cache.clone());
+        let (id, path, is_svg) = (meme.id, meme.path.clone(), meme.is_svg());
+        tokio::spawn(async move {
+            let content = storage.read(&path).await.map_err(AppError::from);
+            match content.and_then(|content| sanitized(is_svg, content)) {
+                Ok(content) => {
+                    // 文件内容已变化时一并移除旧的处理结果
+                    let changed = content_cache.get(&id).await.is_some_and(|cached| cached != content);
+                    content_cache.insert(id, content).await;
+                    if changed {
+                        let stale_keys: Vec<Arc<String>> = resized_cache.iter()
+                            .filter(|(key, _)| cached_ids(key).contains(&id))
+                            .map(|(key, _)| key)
+                            .collect();
+                        for key in &stale_keys {
+                            resized_cache.invalidate(key.as_str()).await;
+                        }
+                    }
+                    CACHE_EARLY_REFRESHES.with_label_values(&[CONTENT_CACHE]).inc();
+                    debug!(meme_id = id, changed, "已提前刷新原图缓存");
+                }
+                Err(e) => debug!(meme_id = id, "提前刷新原图缓存失败: {}", e),
+            }
+        });
+    }
+
+    /// 读取处理结果缓存，接近过期的条目按概率重新写入以延长存活时间；
+    /// 原图变化时处理结果会被整体移除，重新写入相同内容不会延续过期的结果
+    async fn get_variant(&self, key: &str) -> Option<Vec<u8>> {
+        let content = self.resized_cache.get(key).await?;
+        if let Some(EarlyRefresh { ratio, resized_ttl: Some(ttl), .. }) = self.early_refresh {
+            if self.resized_ages.should_refresh(key, ttl, ratio) {
+                self.resized_cache.insert(key.to_string(), content.clone()).await;
+                CACHE_EARLY_REFRESHES.with_label_values(&[RESIZED_CACHE]).inc();
+                debug!(cache_key = key, "已提前刷新处理结果缓存");
+            }
+        }
+        Some(content)
+    }
+
+    /// 从存储读取并写入内容缓存。同一表情包已有读取在进行时等待其结果而不再读盘，
+    /// 避免缓存失效后热门表情包的并发请求同时读取同一个文件
+    async fn read_coalesced(&self, meme: &Meme) -> Result<Vec<u8>> {
+        let cell = Arc::clone(self.reads.lock().entry(meme.id).or_default());
+        // 实际执行读取的请求记录自己的错误，等待结果的请求只知道读取失败
+        let (mut leader, mut error) = (false, None);
+        let (is_leader, leader_error) = (&mut leader, &mut error);
+        let content = cell
+            .get_or_init(|| async move {
+                *is_leader = true;
+                let result = match self.storage.read(&meme.path).await {
+                    Ok(content) => sanitized(meme.is_svg(), content),
+                    Err(e) => Err(self.read_failed(meme, e)),
+                };
+                match result {
+                    Ok(content) => {
+                        if !self.is_streamed(meme) {
+                            self.content_cache.insert(meme.id, content.clone()).await;
+                        }
+                        Some(content)
+                    }
+                    Err(e) => {
+                        *leader_error = Some(e);
+                        None
+                    }
+                }
+            })
+            .await
+            .clone();
+
+        // 读取完成后移除，之后的请求走内容缓存或重新读取
+        {
+            let mut reads = self.reads.lock();
+            if reads.get(&meme.id).is_some_and(|current| Arc::ptr_eq(current, &cell)) {
+                reads.remove(&meme.id);
+            }
+        }
+
+        if !leader {
+            COALESCED_READS.inc();
+            debug!(meme_id = meme.id, "合并读取");
+        }
+        match (content, error) {
+            (Some(content), _) => Ok(content),
+            (None, Some(e)) => Err(e),
+            (None, None) => Err(AppError::ServiceUnavailable("Meme storage is temporarily unavailable".to_string())),
+        }
+    }
+
+    /// 大文件直接从本地磁盘发送，避免先读入内存再复制到响应体；SVG 需要清理，总是读入内存
+    /// 视频总是直接从磁盘发送，以支持 Range 请求
+    fn is_streamed(&self, meme: &Meme) -> bool {
+        let threshold = self.config.cache.stream_threshold_kb * 1024;
+        meme.is_video() || (threshold > 0 && meme.size_bytes > threshold && !meme.is_svg())
+    }
+
+    async fn open_original(&self, meme: &Meme) -> Result<Original> {
+        // 降级模式下不直接发送磁盘文件，经过缓存读取，读取失败时返回 503
+        if self.is_streamed(meme) && !self.is_pinned(meme.id) && !self.is_degraded() {
+            if let Some(path) = self.storage.local_path(&meme.path) {
+                STREAMED_RESPONSES.inc();
+                debug!(meme_id = meme.id, "直接发送文件");
+                return Ok(Original::File(path));
+            }
+        }
+        let (content, cache) = self.read_content(meme).await?;
+        Ok(Original::Bytes(content, cache))
+    }
+
+    /// 需要处理图片内容 (例如按大小重新压缩) 时将文件读入内存
+    pub async fn original_bytes(&self, original: Original) -> Result<(Vec<u8>, CacheStatus)> {
+        match original {
+            Original::Bytes(content, cache) => Ok((content, cache)),
+            Original::File(path) => Ok((self.storage.read(&path).await?, CacheStatus::Bypass)),
+        }
+    }
+
+    /// 按筛选条件随机选择；`max_bytes` 为软条件，没有足够小的图片时从其余符合条件的图片中选择。
+    /// 指定了种子时按 ID 排序后确定地选择
+    fn pick_filtered(&self, filter: &RandomFilter) -> Option<u32> {
+        self.pick_filtered_many(filter, 1).first().copied()
+    }
+
+    /// 选择最多 `count` 个互不相同的表情包；指定种子时结果依次为序号 `n`、`n + 1`… 的单个结果，
+    /// 跳过已选中的，会话去重时每个结果都计入会话历史
+    fn pick_filtered_many(&self, filter: &RandomFilter, count: usize) -> Vec<u32> {
+        let candidates: Vec<&Meme> = self.meme_ids
+            .iter()
+            .filter_map(|id| self.memes.get(id))
+            .filter(|meme| filter.matches(meme))
+            .collect();
+
+        let fitting: Vec<&Meme> = match filter.max_bytes {
+            Some(max_bytes) => candidates.iter().copied().filter(|m| m.size_bytes <= max_bytes).collect(),
+            None => Vec::new(),
+        };
+        let pool = if fitting.is_empty() { candidates } else { fitting };
+        let mut ids: Vec<u32> = pool.iter().map(|meme| meme.id).collect();
+        let count = count.min(ids.len());
+
+        match (&filter.seed, self.sessions.as_ref().zip(filter.session.as_deref())) {
+            (Some(seed), _) => {
+                ids.sort_unstable();
+                let mut picked = Vec::with_capacity(count);
+                let mut seen = HashSet::with_capacity(count);
+                // 在完整的候选中抽取，与单个结果一致；候选几乎取完时重复很多，抽取次数有上限，
+                // 超出后按 ID 顺序补足
+                let max_draws = ids.len() as u64 * 4 + 16;
+                for n in (0..max_draws).map(|i| seed.n.wrapping_add(i)) {
+                    if picked.len() == count {
+                        break;
+                    }
+                    let id = ids[RandomSeed { seed: seed.seed.clone(), n }.index(&self.fingerprint, ids.len())];
+                    if seen.insert(id) {
+                        picked.push(id);
+                    }
+                }
+                for id in ids {
+                    if picked.len() == count {
+                        break;
+                    }
+                    if seen.insert(id) {
+                        picked.push(id);
+                    }
+                }
+                picked
+            }
+            (None, Some((sessions, session))) => (0..count)
+                .map(|_| {
+                    let id = sessions.pick(session, &ids);
+                    ids.retain(|other| *other != id);
+                    id
+                })
+                .collect(),
+            (None, None) => {
+                fastrand::shuffle(&mut ids);
+                ids.truncate(count);
+                ids
+            }
+        }
+    }
+
+    /// 预热内容缓存：按文件大小降序加载前 `count` 个表情包，
+    /// 大文件冷读最慢，优先放入缓存收益最大
+    pub async fn warmup_cache(&self, count: usize) -> usize {
+        // 直接从磁盘发送的大文件不进入缓存
+        let mut candidates: Vec<&Meme> = self.memes.values()
+            .filter(|meme| meme.is_approved() && !self.is_streamed(meme))
+            .collect();
+        candidates.sort_by_key(|meme| std::cmp::Reverse(meme.size_bytes));
+        candidates.truncate(count);
+
+        let total = candidates.len();
+        let mut loaded = 0;
+        CACHE_WARMUP_LOADED.set(0.0);
+        info!("开始预热缓存，计划加载 {} 个表情包", total);
+
+        for meme in candidates {
+            let content = self.storage.read(&meme.path).await.map_err(AppError::from);
+            match content.and_then(|content| sanitized(meme.is_svg(), content)) {
+                Ok(content) => {
+                    self.content_cache.insert(meme.id, content).await;
+                    loaded += 1;
+                    CACHE_WARMUP_LOADED.set(loaded as f64);
+                    debug!(meme_id = meme.id, loaded, total, "缓存预热进度");
+                }
+                Err(e) => warn!("预热缓存时读取 {} 失败: {}", meme.path.display(), e),
+            }
+        }
+
+        self.update_cache_metrics();
+        info!("缓存预热完成，已加载 {}/{} 个表情包", loaded, total);
+        loaded
+    }
+
+    pub fn get_request_count(&self) -> u64 {
+        self.counters.requests.load(Ordering::Relaxed)
+    }
+
+    pub fn counters(&self) -> &RequestCounters {
+        &self.counters
+    }
+
+    pub fn get_total_memes(&self) -> usize {
+        self.meme_ids.len()
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn get_start_time(&self) -> SystemTime {
+        self.start_time
+    }
+
+    fn record_request(&self) {
+        self.request_window.record();
+    }
+
+    pub fn get_requests_in_window(&self, window: Duration) -> u64 {
+        self.request_window.count(window)
+    }
+
+    pub fn get_requests_last_minute(&self) -> u64 {
+        self.get_requests_in_window(ONE_MINUTE)
+    }
+
+    pub fn get_requests_last_5_minutes(&self) -> u64 {
+        self.get_requests_in_window(FIVE_MINUTES)
+    }
+
+    pub fn get_requests_last_15_minutes(&self) -> u64 {
+        self.get_requests_in_window(FIFTEEN_MINUTES)
+    }
+
+    pub fn get_last_updated(&self) -> SystemTime {
+        *self.last_updated.lock()
+    }
+
+    pub fn get_cache_stats(&self) -> (u64, u64) {
+        let hits = self.counters.cache_hits.load(Ordering::Relaxed);
+        let misses = self.counters.cache_misses.load(Ordering::Relaxed);
+        (hits, misses)
+    }
+
+    /// 获取所有已审核的表情包
+    pub fn get_all_memes(&self) -> Vec<(&u32, &Meme)> {
+        self.memes.iter()
+            .filter(|(_, meme)| meme.is_approved())
+            .collect()
+    }
+
+    /// 按文件名、标签与识别出的文字搜索已审核的表情包，忽略大小写与空白；
+    /// 文件名匹配的排在标签匹配之前，标签匹配的排在文字匹配之前
+    pub fn search(&self, query: &str, safe: bool) -> Vec<&Meme> {
+        let query = search_key(query);
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        let mut matches: Vec<(u8, &Meme)> = self.memes.values()
+            .filter(|meme| meme.is_approved() && !(safe && meme.nsfw))
+            .filter_map(|meme| {
+                let rank = if search_key(&meme.filename).contains(&query) {
+                    0
+                } else if meme.tags.iter().any(|tag| search_key(tag).contains(&query)) {
+                    1
+                } else if self.texts.get(&meme.filename).is_some_and(|text| search_key(&text).contains(&query)) {
+                    2
+                } else {
+                    return None;
+                };
+                Some((rank, meme))
+            })
+            .collect();
+        matches.sort_by_key(|(rank, meme)| (*rank, meme.id));
+        matches.into_iter().map(|(_, meme)| meme).collect()
+    }
+
+    /// 创作日期的月与日与 `month_day` (`MM-DD`) 相同的已审核表情包，按 ID 排序；
+    /// `leap_day` 为 true 时 (平年的 2 月 28 日) 同时包含 2 月 29 日的表情包
+    pub fn on_this_day(&self, month_day: &str, leap_day: bool, safe: bool) -> Vec<&Meme> {
+        let mut memes: Vec<&Meme> = self.memes.values()
+            .filter(|meme| meme.is_approved() && !(safe && meme.nsfw))
+            .filter(|meme| meme.anniversary().is_some_and(|day| day == month_day || (leap_day && day == "02-29")))
+            .collect();
+        memes.sort_by_key(|meme| meme.id);
+        memes
+    }
+
+    /// 识别出的表情包文字
+    pub fn meme_text(&self, meme: &Meme) -> Option<String> {
+        self.texts.get(&meme.filename)
+    }
+
+    /// 按审核状态筛选表情包，供管理接口使用
+    pub fn get_memes_by_status(&self, status: Option<MemeStatus>) -> Vec<&Meme> {
+        let mut memes: Vec<&Meme> = self.memes.values()
+            .filter(|meme| status.is_none_or(|s| meme.status == s))
+            .collect();
+        memes.sort_by_key(|meme| meme.id);
+        memes
+    }
+
+    /// 获取已审核的表情包
+    pub fn get_meme(&self, id: u32) -> Option<&Meme> {
+        self.find_meme(id).filter(|meme| meme.is_approved())
+    }
+
+    /// 按内容哈希 (SHA-256 十六进制，不区分大小写) 查找已审核的表情包 ID
+    pub fn id_for_hash(&self, hash: &str) -> Option<u32> {
+        let id = *self.hash_ids.get(&hash.to_ascii_lowercase())?;
+        self.get_meme(id).map(|meme| meme.id)
+    }
+
+    /// 获取表情包，包括待审核的表情包，供管理接口使用
+    pub fn find_meme(&self, id: u32) -> Option<&Meme> {
+        self.memes.get(&self.resolve_id(id))
+    }
+
+    /// 文件名对应的 ID：`id_scheme: stable` 时查 ID 分配表，否则由文件名计算
+    pub fn id_for_filename(&self, filename: &str) -> u32 {
+        self.ids.as_ref()
+            .and_then(|registry| registry.get(filename))
+            .unwrap_or_else(|| meme_id_for(filename))
+    }
+
+    /// 迁移前的旧 ID 对应的新 ID；ID 仍属于当前目录中的表情包时不跳转
+    pub fn redirected_id(&self, id: u32) -> Option<u32> {
+        if self.find_meme(id).is_some() {
+            return None;
+        }
+        self.id_redirects.get(id)
+    }
+
+    pub fn has_id_redirects(&self) -> bool {
+        !self.id_redirects.is_empty()
+    }
+
+    pub fn moderation(&self) -> &ModerationStore {
+        &self.moderation
+    }
+
+    pub fn nsfw(&self) -> &NsfwStore {
+        &self.nsfw
+    }
+
+    pub fn reports(&self) -> &ReportStore {
+        &self.reports
+    }
+
+    /// 举报已审核的表情包，返回当前举报数与是否因此被自动转为待审核；
+    /// 达到 `reports.quarantine_threshold` 时标记为待审核并重新加载，等待管理员处理
+    pub fn report_meme(
+        &self,
+        id: u32,
+        reason: ReportReason,
+        note: Option<String>,
+        ip: Option<IpAddr>,
+    ) -> Result<(usize, bool)> {
+        let meme = self.get_meme(id).ok_or(AppError::MemeNotFound { id })?;
+        let count = match self.reports.report(&meme.filename, reason, note, ip)? {
+            ReportOutcome::Recorded(count) => {
+                crate::metrics::MEME_REPORTS.with_label_values(&[reason.key()]).inc();
+                count
+            }
+            ReportOutcome::Duplicate(count) => return Ok((count, false)),
+        };
+
+        let threshold = self.config.reports.quarantine_threshold;
+        if threshold == 0 || count < threshold {
+            return Ok((count, false));
+        }
+        for filename in meme.filenames() {
+            self.moderation.mark_pending(filename)?;
+        }
+        crate::metrics::MEMES_QUARANTINED.inc();
+        warn!("表情包 {} ({}) 收到 {} 次举报，已转为待审核", id, meme.filename, count);
+        self.request_reload(ReloadTrigger::Admin);
+        Ok((count, true))
+    }
+
+    /// 保存上传的表情包，需要审核时标记为待审核，返回表情包 ID、保存的文件名与审核状态；
+    /// 格式被转换时文件名的扩展名随之改变
+    pub async fn store_upload(&self, filename: &str, content: &[u8]) -> Result<(u32, String, MemeStatus)> {
+        let storage = &self.config.storage;
+        let filename = filename.trim();
+
+        if filename.is_empty()
+            || filename.contains(['/', '\\'])
+            || filename.contains("..")
+            || media::is_hidden_or_temp(filename)
+        {
+            return Err(AppError::BadRequest(format!("Invalid filename: {}", filename)));
+        }
+
+        let mut filename = normalize_filename(filename);
+        let normalized;
+        let sanitized_svg;
+        let content = if svg::looks_like_svg(&content[..content.len().min(media::SNIFF_LEN)]) {
+            // SVG 不做位图规范化，保存清理后的内容
+            sanitized_svg = svg::sanitize(content)
+                .map_err(|e| AppError::BadRequest(format!("Uploaded SVG could not be parsed: {}", e)))?;
+            &sanitized_svg[..]
+        } else if storage.normalize_uploads {
+            let owned = content.to_vec();
+            let max_dimension = storage.upload_max_dimension;
+            normalized = self.image_pool.run(move || normalize::normalize_upload(&owned, max_dimension)).await?;
+            if let Some(extension) = normalized.extension {
+                filename = Path::new(&filename).with_extension(extension).to_string_lossy().to_string();
+            }
+            if !normalized.actions.is_empty() {
+                debug!("上传的表情包 {} 已规范化: {:?}", filename, normalized.actions);
+            }
+            &normalized.content[..]
+        } else {
+            content
+        };
+        let filename = filename.as_str();
+
+        let path = self.memes_dir.join(filename);
+        if !media::extension_allowed(&path, &storage.allowed_extensions) {
+            return Err(AppError::BadRequest(format!("File extension of {} is not allowed", filename)));
+        }
+
+        let header = &content[..content.len().min(media::SNIFF_LEN)];
+        let mime_type = media::sniff_image_mime(header)
+            .ok_or_else(|| AppError::BadRequest("Uploaded file is not a supported image".to_string()))?;
+        if !media::mime_allowed(mime_type, &storage.allowed_mime_types) {
+            return Err(AppError::BadRequest(format!("MIME type {} is not allowed", mime_type)));
+        }
+
+        if tokio::fs::try_exists(&path).await? {
+            return Err(AppError::BadRequest(format!("File {} already exists", filename)));
+        }
+
+        let id = match &self.ids {
+            Some(registry) => registry.reserve(filename, Some(&hash_content(content)))?,
+            None => meme_id_for(filename),
+        };
+
+        // 先写入隐藏的临时文件再重命名，避免文件监控读到写了一半的文件
+        let tmp_path = self.memes_dir.join(format!(".upload-{}", filename));
+        tokio::fs::write(&tmp_path, content).await?;
+
+        let status = if storage.moderate_uploads {
+            if let Err(e) = self.moderation.mark_pending(filename) {
+                let _ = tokio::fs::remove_file(&tmp_path).await;
+                return Err(e);
+            }
+            MemeStatus::Pending
+        } else {
+            MemeStatus::Approved
+        };
+
+        tokio::fs::rename(&tmp_path, &path).await?;
+        info!("已上传表情包 {} ({} 字节, {:?})", filename, content.len(), status);
+        self.request_reload(ReloadTrigger::Admin);
+
+        Ok((id, filename.to_string(), status))
+    }
+
+    pub fn get_meme_stats(&self) -> &MemeStatsStore {
+        &self.meme_stats
+    }
+
+    pub fn trash(&self) -> Arc<TrashService> {
+        Arc::clone(&self.trash)
+    }
+
+    /// 目录版本号，每次成功重载后递增
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// 目录内容指纹，带种子的随机选择结果只取决于它
+    pub fn catalog_fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    pub fn catalog(&self) -> Arc<CatalogSnapshot> {
+        Arc::clone(&self.catalog)
+    }
+
+    /// 比较镜像端已有的表情包与当前目录
+    pub fn diff_catalog(&self, request: &DiffRequest) -> CatalogDiff {
+        let memes = self.memes.values().filter(|meme| meme.is_approved());
+        CatalogDiff::compute(self.generation, memes, request)
+    }
+
+    /// 本次启动的标识，目录版本号每次启动从 0 开始、各实例互不相同，只在同一标识下可比较
+    pub fn epoch(&self) -> &str {
+        &self.epoch
+    }
 
-        // 开始监控目录
-        watcher.watch(&memes_dir, RecursiveMode::Recursive)?;
-        info!("开始监控目录: {:?}", memes_dir);
+    /// `since_generation` 之后的目录变更；`epoch` 与本次启动不同时要求客户端重新同步
+    pub fn changes_since(&self, since_generation: u64, epoch: Option<&str>) -> CatalogChanges {
+        let changes = match epoch {
+            Some(epoch) if epoch != self.epoch => None,
+            _ => self.changes.changes_since(since_generation, self.generation),
+        };
+        let (resync_required, added, removed) = match changes {
+            Some((added, removed)) => (false, added, removed),
+            None => (true, Vec::new(), Vec::new()),
+        };
 
-        // 初始化缓存 - 增加缓存容量
-        let content_cache = moka::future::Cache::builder()
-            .max_capacity(max_size)
-            .time_to_live(Duration::from_secs(ttl_secs))
-            .build();
-            
-        // 初始化压缩图片缓存
-        let resized_cache = moka::future::Cache::builder()
-            .max_capacity(max_size * 2) // 压缩图片缓存容量更大
-            .time_to_live(Duration::from_secs(ttl_secs * 2)) // 压缩图片缓存时间更长
-            .build();
+        CatalogChanges {
+            epoch: self.epoch.clone(),
+            generation: self.generation,
+            since_generation,
+            resync_required,
+            added,
+            removed,
+        }
+    }
 
-        // 创建服务实例
-        let service = Arc::new(RwLock::new(Self {
-            memes: HashMap::new(),
-            meme_ids: Vec::new(),
-            total_count: 0,
-            content_cache,
-            resized_cache,
-            memes_dir: memes_dir.clone(),
-            reload_tx,
-            _watcher: watcher,
-            request_count: AtomicU64::new(0),
-            cache_hits: AtomicU64::new(0),
-            cache_misses: AtomicU64::new(0),
-            start_time: SystemTime::now(),
-            request_timestamps: Mutex::new(VecDeque::with_capacity(2000)), // 增加容量
-            last_updated: Mutex::new(SystemTime::now()),
-        }));
+    /// 随机选择一个表情包文件路径，用于存储探测，不计入访问统计
+    pub fn random_meme_path(&self) -> Option<PathBuf> {
+        if self.meme_ids.is_empty() {
+            return None;
+        }
+        let id = self.meme_ids[fastrand::usize(..self.meme_ids.len())];
+        self.memes.get(&id).map(|meme| meme.path.clone())
+    }
 
-        // 初始加载表情包
-        service.write().await.reload_memes().await?;
+    /// 表情包文件的修改时间
+    pub fn modified_time(&self, meme: &Meme) -> Option<SystemTime> {
+        self.file_info_cache.get(&meme.path)?.modified
+    }
 
-        // 启动重载监听器
-        Self::start_reload_listener(Arc::clone(&service));
+    /// 按文件修改时间倒序返回最近加入的表情包，修改时间未知的不计入，`safe` 时排除 NSFW 表情包
+    pub fn recent_memes(&self, limit: usize, safe: bool) -> Vec<(&Meme, SystemTime)> {
+        let mut recent: Vec<(&Meme, SystemTime)> = self.meme_ids
+            .iter()
+            .filter_map(|id| self.memes.get(id))
+            .filter(|meme| !(safe && meme.nsfw))
+            .filter_map(|meme| {
+                let modified = self.file_info_cache.get(&meme.path)?.modified?;
+                Some((meme, modified))
+            })
+            .collect();
+        recent.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.id.cmp(&b.0.id)));
+        recent.truncate(limit);
+        recent
+    }
 
-        Ok(service)
+    pub fn clients(&self) -> Arc<ClientTracker> {
+        Arc::clone(&self.clients)
     }
 
-    async fn reload_memes(&mut self) -> Result<()> {
-        let mut memes = HashMap::new();
-        let mut count = 0;
-
-        let mut entries = tokio::fs::read_dir(&self.memes_dir).await?;
-        while let Some(entry) = entries.next_entry().await? {
-            if entry.file_type().await?.is_file() {
-                let path = entry.path();
-                let mime_type = mime_guess::from_path(&path)
-                    .first_or_octet_stream()
-                    .to_string();
-
-                // 使用 to_string_lossy 来处理包含 emoji 或其他 Unicode 字符的文件名
-                // 这样可以避免在 macOS 和 Linux 上因为 Unicode 规范化差异导致的问题
-                let filename = path.file_name()
-                    .map(|name| name.to_string_lossy().to_string())
-                    .unwrap_or_else(|| "unknown".to_string());
-
-                let size_bytes = tokio::fs::metadata(&path)
-                    .await
-                    .map(|metadata| metadata.len())
-                    .unwrap_or(0);
-
-                // 计算文件名的 SHA-256 哈希值
-                let mut hasher = Sha256::new();
-                hasher.update(filename.as_bytes());
-                let hash = hasher.finalize();
-                
-                // 使用哈希值的前 4 个字节作为 ID
-                let id = u32::from_be_bytes([
-                    hash[0],
-                    hash[1],
-                    hash[2],
-                    hash[3],
-                ]);
-
-                let meme = Meme {
-                    id,
-                    path,
-                    mime_type,
-                    filename,
-                    size_bytes,
-                };
-                
-                memes.insert(id, meme);
-                count += 1;
+    /// 清空表情包访问统计、请求与缓存计数 (含累计值)、请求速率窗口与客户端统计
+    pub fn reset_statistics(&self) -> Result<()> {
+        self.meme_stats.reset()?;
+        self.counters.reset()?;
+        self.request_window.reset();
+        self.clients.reset();
+        info!("统计数据已重置");
+        Ok(())
+    }
+
+    /// 清空缓存，返回清除的条目数；固定的表情包不受影响
+    pub async fn clear_cache(&self, target: CacheTarget) -> u64 {
+        let mut cleared = 0;
+        if target.includes_content() {
+            self.content_cache.run_pending_tasks().await;
+            cleared += self.content_cache.entry_count();
+            self.content_cache.invalidate_all();
+            self.content_cache.run_pending_tasks().await;
+        }
+        if target.includes_resized() {
+            self.resized_cache.run_pending_tasks().await;
+            cleared += self.resized_cache.entry_count();
+            self.resized_cache.invalidate_all();
+            self.resized_cache.run_pending_tasks().await;
+        }
+        self.update_cache_metrics();
+        info!(?target, cleared, "缓存已清空");
+        cleared
+    }
+
+    /// 列出缓存中的所有条目，按缓存与键排序
+    pub fn cache_entries(&self) -> Vec<CacheEntry> {
+        let content = self.content_cache.iter().map(|(id, content)| {
+            let key = id.to_string();
+            CacheEntry {
+                cache: CONTENT_CACHE,
+                age_secs: self.content_ages.age(&key).map(|age| age.as_secs()),
+                key,
+                bytes: content.len(),
             }
+        });
+        let resized = self.resized_cache.iter().map(|(key, content)| CacheEntry {
+            cache: RESIZED_CACHE,
+            key: key.to_string(),
+            bytes: content.len(),
+            age_secs: self.resized_ages.age(&key).map(|age| age.as_secs()),
+        });
+        let mut entries: Vec<CacheEntry> = content.chain(resized).collect();
+        entries.sort_by(|a, b| (a.cache, &a.key).cmp(&(b.cache, &b.key)));
+        entries
+    }
+
+    /// 查看单个缓存条目，不存在时返回 None；读取会刷新条目的闲置时间
+    pub async fn cache_entry(&self, target: CacheTarget, key: &str) -> Result<Option<CacheEntry>> {
+        let (cache, content, ages) = match target {
+            CacheTarget::Content => (CONTENT_CACHE, self.content_cache.get(&parse_cache_id(key)?).await, &self.content_ages),
+            CacheTarget::Resized => (RESIZED_CACHE, self.resized_cache.get(key).await, &self.resized_ages),
+            CacheTarget::All => return Err(AppError::BadRequest("Cache must be content or resized".to_string())),
+        };
+        Ok(content.map(|content| CacheEntry {
+            cache,
+            key: key.to_string(),
+            bytes: content.len(),
+            age_secs: ages.age(key).map(|age| age.as_secs()),
+        }))
+    }
+
+    /// 移除单个缓存条目，返回条目是否存在；用于文件修复后清除仍在提供的错误缓存
+    pub async fn evict_cache_entry(&self, target: CacheTarget, key: &str) -> Result<bool> {
+        let evicted = match target {
+            CacheTarget::Content => self.content_cache.remove(&parse_cache_id(key)?).await.is_some(),
+            CacheTarget::Resized => self.resized_cache.remove(key).await.is_some(),
+            CacheTarget::All => return Err(AppError::BadRequest("Cache must be content or resized".to_string())),
+        };
+        if evicted {
+            self.update_cache_metrics();
+            info!(?target, key, "已移除缓存条目");
         }
+        Ok(evicted)
+    }
+
+    /// 最近一次重载检测到的 ID 冲突
+    pub fn collisions(&self) -> &[IdCollision] {
+        &self.collisions
+    }
+
+    /// 最近一次重载发现的内容与扩展名不符的文件
+    pub fn mime_mismatches(&self) -> &[MimeMismatch] {
+        &self.mime_mismatches
+    }
+
+    pub fn aliases(&self) -> &AliasStore {
+        &self.aliases
+    }
+
+    pub fn storage(&self) -> Arc<dyn Storage> {
+        Arc::clone(&self.storage)
+    }
+
+    /// 最近一次重载的结果
+    pub fn last_reload(&self) -> Option<&ReloadReport> {
+        self.last_reload.as_ref()
+    }
+
+    pub fn watcher_status(&self) -> WatcherStatus {
+        self.watcher.status()
+    }
 
-        if count == 0 {
-            return Err(AppError::Internal("No memes found".to_string()));
+    /// 向压缩图片缓存写入占位条目，返回写入后的条目数
+    #[cfg(feature = "debug")]
+    pub async fn debug_fill_cache(&self, count: usize, size_bytes: usize) -> u64 {
+        for i in 0..count {
+            self.resized_cache
+                .insert(format!("debug:{}", i), vec![0u8; size_bytes])
+                .await;
         }
+        self.resized_cache.run_pending_tasks().await;
+        self.resized_cache.entry_count()
+    }
 
-        // 更新服务状态
-        self.memes = memes;
-        // 预计算ID向量以提高随机选择性能
-        self.meme_ids = self.memes.keys().copied().collect();
-        self.total_count = count;
-        self.content_cache.invalidate_all();
-        self.resized_cache.invalidate_all();
-        *self.last_updated.lock() = SystemTime::now();
-        
-        // 更新 Prometheus 指标
-        TOTAL_MEMES.set(count as f64);
+    pub fn cluster(&self) -> Option<&ClusterBus> {
+        self.cluster.as_deref()
+    }
 
-        info!("重新加载了 {} 个表情包", count);
-        Ok(())
+    /// 触发一次表情包重新加载（与文件监控使用同一通道）
+    pub fn request_reload(&self, trigger: ReloadTrigger) {
+        if let Err(e) = self.reload_tx.send(trigger) {
+            error!("发送重载信号失败: {}", e);
+        }
     }
 
-    fn start_reload_listener(service: Arc<RwLock<Self>>) {
-        tokio::spawn(async move {
-            loop {
-                let mut rx = {
-                    let service = service.read().await;
-                    service.reload_tx.subscribe()
-                };
+    /// 条目数由 moka 维护 (惰性更新)，字节数由 weigher 与移除回调增减
+    fn update_cache_metrics(&self) {
+        CACHE_ENTRIES.with_label_values(&[CONTENT_CACHE]).set(self.content_cache.entry_count() as f64);
+        CACHE_ENTRIES.with_label_values(&[RESIZED_CACHE]).set(self.resized_cache.entry_count() as f64);
+    }
 
-                // 等待重载信号
-                while let Ok(()) = rx.recv().await {
-                    info!("正在重新加载表情包...");
-                    if let Err(e) = service.write().await.reload_memes().await {
-                        error!("重新加载表情包失败: {}", e);
-                    }
-                }
+    pub async fn get_by_id(&self, id: u32) -> Result<(&Meme, Vec<u8>, CacheStatus)> {
+        let meme = self.select_by_id(id)?;
+        let (content, cache) = self.read_content(meme).await?;
+        Ok((meme, content, cache))
+    }
 
-                // 如果 channel 关闭，等待一段时间后重试
-                tokio::time::sleep(Duration::from_secs(1)).await;
-            }
-        });
+    /// 与 [`get_by_id`](Self::get_by_id) 相同，但大文件不读入内存
+    pub async fn get_original(&self, id: u32) -> Result<(&Meme, Original)> {
+        let meme = self.select_by_id(id)?;
+        Ok((meme, self.open_original(meme).await?))
     }
 
-    pub async fn get_random(&self) -> Result<(&Meme, Vec<u8>)> {
+    fn select_by_id(&self, id: u32) -> Result<&Meme> {
         // 增加请求计数并记录时间戳
-        self.request_count.fetch_add(1, Ordering::Relaxed);
+        self.counters.requests.fetch_add(1, Ordering::Relaxed);
         self.record_request();
         
-        // 使用预计算的ID向量进行随机选择，避免每次重新收集
-        if self.meme_ids.is_empty() {
-            return Err(AppError::NotFound("No memes available".to_string()));
+        let id = self.resolve_id(id);
+        let meme = self.memes.get(&id)
+            .filter(|meme| meme.is_approved())
+            .ok_or_else(|| AppError::NotFound(format!("Meme with id {} not found", id)))?;
+        self.meme_stats.record_hit(id);
+        Ok(meme)
+    }
+
+    /// 将图片压缩到不超过 `max_bytes`，结果为 JPEG 并写入压缩图片缓存；
+    /// `variant` 区分同一表情包的不同尺寸
+    pub async fn fit_to_max_bytes(&self, id: u32, variant: &str, content: Vec<u8>, max_bytes: usize) -> Result<(Vec<u8>, CacheStatus)> {
+        if max_bytes < media::MIN_MAX_BYTES {
+            return Err(AppError::BadRequest(format!("max_bytes must be at least {}", media::MIN_MAX_BYTES)));
         }
-        
-        let random_index = fastrand::usize(..self.meme_ids.len());
-        let meme_id = self.meme_ids[random_index];
-        
-        let meme = self.memes.get(&meme_id)
-            .ok_or_else(|| AppError::NotFound("Meme not found".to_string()))?;
 
-        // 尝试从缓存获取
-        if let Some(content) = self.content_cache.get(&meme_id).await {
-            self.cache_hits.fetch_add(1, Ordering::Relaxed);
-            CACHE_HITS.inc(); // 更新 Prometheus 计数器
+        let cache_key = format!("{}:{}:max{}", id, variant, max_bytes);
+        if let Some(content) = self.get_variant(&cache_key).await {
+            self.counters.cache_hits.fetch_add(1, Ordering::Relaxed);
+            CACHE_HITS.with_label_values(&[RESIZED_CACHE]).inc();
             self.update_cache_metrics();
-            debug!(
-                meme_id = meme_id,
-                cache_type = "content",
-                "Cache hit"
-            );
-            return Ok((meme, content));
+            debug!(meme_id = id, cache_type = "fitted", cache_key = cache_key, "Cache hit");
+            return Ok((content, CacheStatus::Hit));
         }
 
-        // 如果缓存未命中，从文件读取
-        self.cache_misses.fetch_add(1, Ordering::Relaxed);
-        CACHE_MISSES.inc(); // 更新 Prometheus 计数器
+        self.load_shedder.check()?;
+        let original_len = content.len();
+        let fitted = self.image_pool
+            .run(move || media::compress_to_fit(&content, max_bytes))
+            .await?;
+
+        self.resized_cache.insert(cache_key.clone(), fitted.clone()).await;
+        self.counters.cache_misses.fetch_add(1, Ordering::Relaxed);
+        CACHE_MISSES.with_label_values(&[RESIZED_CACHE]).inc();
         self.update_cache_metrics();
         debug!(
-            meme_id = meme_id,
-            cache_type = "content",
+            meme_id = id,
+            cache_type = "fitted",
+            cache_key = cache_key,
+            original_bytes = original_len,
+            fitted_bytes = fitted.len(),
             "Cache miss"
         );
-        let content = tokio::fs::read(&meme.path).await?;
-        self.content_cache.insert(meme_id, content.clone()).await;
-        
-        Ok((meme, content))
+
+        Ok((fitted, CacheStatus::Miss))
     }
 
-    pub fn get_request_count(&self) -> u64 {
-        self.request_count.load(Ordering::Relaxed)
+    /// 尝试将图片编码为 WebP，比原内容更小时返回 WebP，否则返回 None；
+    /// 结果写入压缩图片缓存 (原内容更小时缓存空条目，避免重复编码)。
+    /// 内存过载或图片队列已满时直接返回 None，由调用方发送原内容
+    pub async fn negotiate_webp(&self, id: u32, variant: &str, content: &[u8]) -> Option<(Vec<u8>, CacheStatus)> {
+        let cache_key = format!("{}:{}:webp", id, variant);
+        if let Some(encoded) = self.get_variant(&cache_key).await {
+            self.counters.cache_hits.fetch_add(1, Ordering::Relaxed);
+            CACHE_HITS.with_label_values(&[RESIZED_CACHE]).inc();
+            self.update_cache_metrics();
+            debug!(meme_id = id, cache_type = "negotiated", cache_key = cache_key, "Cache hit");
+            return (!encoded.is_empty()).then_some((encoded, CacheStatus::Hit));
+        }
+
+        if self.load_shedder.check().is_err() {
+            return None;
+        }
+        let owned = content.to_vec();
+        let encoded = match self.image_pool.run(move || media::encode_webp(&owned)).await {
+            Ok(encoded) => encoded,
+            Err(e) => {
+                debug!(meme_id = id, "编码 WebP 失败，返回原格式: {}", e);
+                return None;
+            }
+        };
+
+        let smaller = encoded.len() < content.len();
+        let cached = if smaller { encoded.clone() } else { Vec::new() };
+        self.resized_cache.insert(cache_key.clone(), cached).await;
+        self.counters.cache_misses.fetch_add(1, Ordering::Relaxed);
+        CACHE_MISSES.with_label_values(&[RESIZED_CACHE]).inc();
+        self.update_cache_metrics();
+        debug!(
+            meme_id = id,
+            cache_type = "negotiated",
+            cache_key = cache_key,
+            original_bytes = content.len(),
+            webp_bytes = encoded.len(),
+            "Cache miss"
+        );
+
+        smaller.then_some((encoded, CacheStatus::Miss))
     }
 
-    pub fn get_total_memes(&self) -> usize {
-        self.memes.len()
+    /// 在图片上绘制上下两段文字，`watermark` 时在配文后叠加水印，结果写入压缩图片缓存
+    pub async fn get_captioned(&self, id: u32, top: &str, bottom: &str, watermark: bool) -> Result<(&Meme, Vec<u8>, CacheStatus)> {
+        let renderer = self.caption.clone()
+            .ok_or_else(|| AppError::ServiceUnavailable("Caption font is not configured".to_string()))?;
+        let max_len = self.config.caption.max_text_len;
+        if top.chars().count() > max_len || bottom.chars().count() > max_len {
+            return Err(AppError::BadRequest(format!("Caption text must not exceed {} characters", max_len)));
+        }
+
+        let id = self.resolve_id(id);
+        let meme = self.memes.get(&id)
+            .filter(|meme| meme.is_approved())
+            .ok_or_else(|| AppError::NotFound(format!("Meme with id {} not found", id)))?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(top.as_bytes());
+        hasher.update([0]);
+        hasher.update(bottom.as_bytes());
+        reject_video(meme)?;
+        let watermark = self.watermark.clone().filter(|_| watermark);
+        let cache_key = format!("{}:caption:{:x}{}", id, hasher.finalize(), watermark_suffix(&watermark));
+
+        if let Some(content) = self.get_variant(&cache_key).await {
+            self.counters.cache_hits.fetch_add(1, Ordering::Relaxed);
+            CACHE_HITS.with_label_values(&[RESIZED_CACHE]).inc();
+            self.update_cache_metrics();
+            debug!(meme_id = id, cache_type = "caption", cache_key = cache_key, "Cache hit");
+            return Ok((meme, content, CacheStatus::Hit));
+        }
+
+        self.load_shedder.check()?;
+        let (_, original_content, _) = self.get_by_id(id).await?;
+        let (top, bottom) = (top.to_string(), bottom.to_string());
+        let captioned = self.image_pool
+            .run(move || {
+                let captioned = renderer.render(&original_content, &top, &bottom)?;
+                match watermark {
+                    Some(watermark) => watermark.apply(&captioned, "image/png"),
+                    None => Ok(captioned),
+                }
+            })
+            .await?;
+
+        self.resized_cache.insert(cache_key.clone(), captioned.clone()).await;
+        self.counters.cache_misses.fetch_add(1, Ordering::Relaxed);
+        CACHE_MISSES.with_label_values(&[RESIZED_CACHE]).inc();
+        self.update_cache_metrics();
+        debug!(meme_id = id, cache_type = "caption", cache_key = cache_key, "Cache miss");
+
+        Ok((meme, captioned, CacheStatus::Miss))
     }
 
-    pub fn get_start_time(&self) -> SystemTime {
-        self.start_time
+    /// 启用的水印
+    pub fn watermarker(&self) -> Option<&Watermarker> {
+        self.watermark.as_deref()
     }
 
-    fn record_request(&self) {
-        let mut timestamps = self.request_timestamps.lock();
-        let now = Instant::now();
-        
-        // 移除超过一分钟的时间戳
-        while timestamps.front()
-            .map(|&t| now.duration_since(t) > REQUEST_HISTORY_WINDOW)
-            .unwrap_or(false) 
-        {
-            timestamps.pop_front();
+    /// 在图片上叠加水印，结果以 `{id}:{variant}:wm` 写入压缩图片缓存，
+    /// 内容类型变为 [`Watermarker::output_mime`]；未启用水印或动图时原样返回
+    pub async fn watermarked(
+        &self,
+        id: u32,
+        variant: &str,
+        (content, cache): (Vec<u8>, CacheStatus),
+        mime_type: &str,
+    ) -> Result<(Vec<u8>, CacheStatus)> {
+        let Some(watermark) = self.watermark.clone().filter(|_| Watermarker::applies_to(mime_type)) else {
+            return Ok((content, cache));
+        };
+        let cache_key = format!("{}:{}:wm", id, variant);
+
+        if let Some(content) = self.get_variant(&cache_key).await {
+            self.counters.cache_hits.fetch_add(1, Ordering::Relaxed);
+            CACHE_HITS.with_label_values(&[RESIZED_CACHE]).inc();
+            self.update_cache_metrics();
+            debug!(meme_id = id, cache_type = "watermark", cache_key = cache_key, "Cache hit");
+            return Ok((content, CacheStatus::Hit));
         }
-        
-        timestamps.push_back(now);
+
+        self.load_shedder.check()?;
+        let mime_type = mime_type.to_string();
+        let marked = self.image_pool
+            .run(move || watermark.apply(&content, &mime_type))
+            .await?;
+
+        self.resized_cache.insert(cache_key.clone(), marked.clone()).await;
+        self.counters.cache_misses.fetch_add(1, Ordering::Relaxed);
+        CACHE_MISSES.with_label_values(&[RESIZED_CACHE]).inc();
+        self.update_cache_metrics();
+        debug!(meme_id = id, cache_type = "watermark", cache_key = cache_key, "Cache miss");
+
+        Ok((marked, CacheStatus::Miss))
     }
 
-    pub fn get_requests_in_window(&self, window: Duration) -> u64 {
-        let now = Instant::now();
-        let mut timestamps = self.request_timestamps.lock();
-        
-        // 清理超过窗口时间的记录
-        while let Some(timestamp) = timestamps.front() {
-            if now.duration_since(*timestamp) > REQUEST_HISTORY_WINDOW {
-                timestamps.pop_front();
-            } else {
-                break;
-            }
+    /// 获取正方形图标 (透明背景、完整放入)，与缩放图片共用缓存；`watermark` 时先在原图上叠加水印再缩放，
+    /// 水印随图标等比缩小，ICO 格式也不受影响
+    pub async fn get_icon(&self, id: u32, size: u32, format: IconFormat, watermark: bool) -> Result<(&Meme, Vec<u8>, CacheStatus)> {
+        if !ICON_SIZES.contains(&size) {
+            return Err(AppError::BadRequest(format!("size must be one of {:?}", ICON_SIZES)));
         }
-        
-        // 计算指定窗口内的请求数
-        timestamps.iter()
-            .filter(|&timestamp| now.duration_since(*timestamp) <= window)
-            .count() as u64
+
+        let id = self.resolve_id(id);
+        let meme = self.memes.get(&id)
+            .filter(|meme| meme.is_approved())
+            .ok_or_else(|| AppError::NotFound(format!("Meme with id {} not found", id)))?;
+
+        reject_video(meme)?;
+        let watermark = self.watermark.clone().filter(|_| watermark);
+        let cache_key = format!("{}:icon:{}:{}{}", id, size, format.key(), watermark_suffix(&watermark));
+        if let Some(content) = self.get_variant(&cache_key).await {
+            self.counters.cache_hits.fetch_add(1, Ordering::Relaxed);
+            CACHE_HITS.with_label_values(&[RESIZED_CACHE]).inc();
+            self.update_cache_metrics();
+            debug!(meme_id = id, cache_type = "icon", cache_key = cache_key, "Cache hit");
+            return Ok((meme, content, CacheStatus::Hit));
+        }
+
+        self.load_shedder.check()?;
+        let (_, original_content, _) = self.get_by_id(id).await?;
+        let icon = self.image_pool
+            .run(move || match watermark {
+                // 图标本来就是静态图片，动图取首帧加水印，中间结果用无损的 PNG
+                Some(watermark) => media::render_icon(&watermark.apply(&original_content, "image/png")?, size, format.image_format()),
+                None => media::render_icon(&original_content, size, format.image_format()),
+            })
+            .await?;
+
+        self.resized_cache.insert(cache_key.clone(), icon.clone()).await;
+        self.counters.cache_misses.fetch_add(1, Ordering::Relaxed);
+        CACHE_MISSES.with_label_values(&[RESIZED_CACHE]).inc();
+        self.update_cache_metrics();
+        debug!(meme_id = id, cache_type = "icon", cache_key = cache_key, "Cache miss");
+
+        Ok((meme, icon, CacheStatus::Miss))
     }
 
-    pub fn get_requests_last_minute(&self) -> u64 {
-        self.get_requests_in_window(ONE_MINUTE)
+    /// 随机选出最多 `count` 个不同的已审核表情包作为拼图，`safe` 时排除 NSFW 表情包
+    pub fn random_collage_ids(&self, count: usize, safe: bool) -> Vec<u32> {
+        let mut ids: Vec<u32> = self.meme_ids
+            .iter()
+            .copied()
+            .filter(|id| self.memes.get(id).is_some_and(|meme| !(meme.is_video() || safe && meme.nsfw)))
+            .collect();
+        fastrand::shuffle(&mut ids);
+        ids.truncate(count);
+        ids
     }
 
-    pub fn get_requests_last_5_minutes(&self) -> u64 {
-        self.get_requests_in_window(FIVE_MINUTES)
+    /// 将多个表情包拼成一张 JPEG，按表情包与布局缓存，`watermark` 时在整张拼图上叠加水印；
+    /// 任一表情包不存在时返回 404
+    pub async fn get_collage(&self, ids: &[u32], cols: u32, cell: u32, watermark: bool) -> Result<(Vec<u8>, CacheStatus)> {
+        if ids.is_empty() || ids.len() > MAX_COLLAGE_IMAGES {
+            return Err(AppError::BadRequest(format!("Collage must contain 1 to {} memes", MAX_COLLAGE_IMAGES)));
+        }
+        if cols == 0 || cols as usize > ids.len() {
+            return Err(AppError::BadRequest("cols must be between 1 and the number of memes".to_string()));
+        }
+        if !COLLAGE_CELL_SIZES.contains(&cell) {
+            return Err(AppError::BadRequest(format!("cell must be between {} and {}", COLLAGE_CELL_SIZES.start(), COLLAGE_CELL_SIZES.end())));
+        }
+
+        let memes = ids.iter()
+            .map(|&id| self.get_meme(id).ok_or_else(|| AppError::NotFound(format!("Meme with id {} not found", id))))
+            .collect::<Result<Vec<&Meme>>>()?;
+        memes.iter().try_for_each(|meme| reject_video(meme))?;
+        // 键中带上内容哈希，文件被替换后不会命中旧的拼图
+        let key_ids: Vec<String> = memes.iter()
+            .map(|meme| match &meme.content_hash {
+                Some(hash) => format!("{}-{}", meme.id, &hash[..hash.len().min(12)]),
+                None => meme.id.to_string(),
+            })
+            .collect();
+        let watermark = self.watermark.clone().filter(|_| watermark);
+        let cache_key = format!("collage:{}:{}:{}{}", cols, cell, key_ids.join(","), watermark_suffix(&watermark));
+        if let Some(content) = self.get_variant(&cache_key).await {
+            self.counters.cache_hits.fetch_add(1, Ordering::Relaxed);
+            CACHE_HITS.with_label_values(&[RESIZED_CACHE]).inc();
+            self.update_cache_metrics();
+            debug!(cache_type = "collage", cache_key = cache_key, "Cache hit");
+            return Ok((content, CacheStatus::Hit));
+        }
+
+        self.load_shedder.check()?;
+        let mut contents = Vec::with_capacity(memes.len());
+        for meme in memes {
+            contents.push(self.read_content(meme).await?.0);
+        }
+        let collage = self.image_pool
+            .run(move || {
+                let collage = media::render_collage(&contents, cols, cell)?;
+                match watermark {
+                    Some(watermark) => watermark.apply(&collage, "image/jpeg"),
+                    None => Ok(collage),
+                }
+            })
+            .await?;
+
+        self.resized_cache.insert(cache_key.clone(), collage.clone()).await;
+        self.counters.cache_misses.fetch_add(1, Ordering::Relaxed);
+        CACHE_MISSES.with_label_values(&[RESIZED_CACHE]).inc();
+        self.update_cache_metrics();
+        debug!(cache_type = "collage", cache_key = cache_key, "Cache miss");
+
+        Ok((collage, CacheStatus::Miss))
     }
 
-    pub fn get_requests_last_15_minutes(&self) -> u64 {
-        self.get_requests_in_window(FIFTEEN_MINUTES)
+    /// 已计算过的平均颜色，不触发计算
+    pub fn known_color(&self, id: u32) -> Option<String> {
+        self.colors.lock().get(&id).cloned()
     }
 
-    pub fn get_last_updated(&self) -> SystemTime {
-        *self.last_updated.lock()
+    /// 表情包的平均颜色 (`#rrggbb`)，首次请求时解码图片计算并缓存；无法解码或图片线程池繁忙时返回 None
+    pub async fn dominant_color(&self, meme: &Meme) -> Option<String> {
+        if let Some(color) = self.known_color(meme.id) {
+            return Some(color);
+        }
+        if meme.is_video() {
+            return None;
+        }
+
+        let content = match self.storage.read(&meme.path).await {
+            Ok(content) => content,
+            Err(e) => {
+                warn!(meme_id = meme.id, "读取表情包以计算颜色失败: {}", e);
+                return None;
+            }
+        };
+        match self.image_pool.run(move || media::average_color(&content)).await {
+            Ok(color) => {
+                self.colors.lock().insert(meme.id, color.clone());
+                Some(color)
+            }
+            Err(e) => {
+                debug!(meme_id = meme.id, "计算表情包颜色失败: {}", e);
+                None
+            }
+        }
     }
 
-    pub fn get_cache_stats(&self) -> (u64, u64) {
-        let hits = self.cache_hits.load(Ordering::Relaxed);
-        let misses = self.cache_misses.load(Ordering::Relaxed);
-        (hits, misses)
+    /// 在后台为尚未计算颜色的已上线表情包逐个计算平均颜色，同一时间只运行一个补全任务；
+    /// 图片线程池繁忙时提前结束，下次调用再继续
+    pub fn fill_colors(&self) {
+        let missing: Vec<(u32, PathBuf)> = {
+            let colors = self.colors.lock();
+            self.meme_ids
+                .iter()
+                .filter(|id| !colors.contains_key(*id))
+                .filter_map(|id| self.memes.get(id).filter(|meme| !meme.is_video()).map(|meme| (*id, meme.path.clone())))
+                .collect()
+        };
+        if missing.is_empty() || self.filling_colors.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let storage = Arc::clone(&self.storage);
+        let pool = Arc::clone(&self.image_pool);
+        let colors = Arc::clone(&self.colors);
+        let filling = Arc::clone(&self.filling_colors);
+        tokio::spawn(async move {
+            let total = missing.len();
+            let mut computed = 0;
+            for (id, path) in missing {
+                let Ok(content) = storage.read(&path).await else {
+                    continue;
+                };
+                match pool.run(move || media::average_color(&content)).await {
+                    Ok(color) => {
+                        colors.lock().insert(id, color);
+                        computed += 1;
+                    }
+                    Err(AppError::ServiceUnavailable(_)) => break,
+                    Err(e) => debug!(meme_id = id, "计算表情包颜色失败: {}", e),
+                }
+            }
+            debug!("后台计算了 {}/{} 个表情包的颜色", computed, total);
+            filling.store(false, Ordering::Release);
+        });
     }
 
-    pub fn get_all_memes(&self) -> Vec<(&u32, &Meme)> {
-        self.memes.iter().collect()
+    pub fn tags(&self) -> &TagStore {
+        &self.tags
     }
 
-    fn update_cache_metrics(&self) {
-        let hits = self.cache_hits.load(Ordering::Relaxed);
-        let misses = self.cache_misses.load(Ordering::Relaxed);
-        let total = hits + misses;
-        
-        if total > 0 {
-            let hit_rate = hits as f64 / total as f64;
-            CACHE_HIT_RATE.set(hit_rate);
+    /// 在后台为新的或没有标签的表情包生成候选标签，图片线程池繁忙时留到下次重载
+    #[cfg(feature = "ml")]
+    fn suggest_tags(&self) {
+        let Some(tagger) = &self.tagger else {
+            return;
+        };
+        let missing: Vec<(String, PathBuf)> = self.memes
+            .values()
+            .filter(|meme| !meme.is_video() && self.tags.needs_classification(&meme.filename))
+            .map(|meme| (meme.filename.clone(), meme.path.clone()))
+            .collect();
+        if missing.is_empty() || self.suggesting_tags.swap(true, Ordering::AcqRel) {
+            return;
         }
-        
-        CACHE_SIZE.set(self.content_cache.entry_count() as f64);
-    }
 
-    pub async fn get_by_id(&self, id: u32) -> Result<(&Meme, Vec<u8>)> {
-        // 增加请求计数并记录时间戳
-        self.request_count.fetch_add(1, Ordering::Relaxed);
-        self.record_request();
-        
-        let meme = self.memes.get(&id)
-            .ok_or_else(|| AppError::NotFound(format!("Meme with id {} not found", id)))?;
+        let storage = Arc::clone(&self.storage);
+        let pool = Arc::clone(&self.image_pool);
+        let tagger = Arc::clone(tagger);
+        let tags = Arc::clone(&self.tags);
+        let suggesting = Arc::clone(&self.suggesting_tags);
+        tokio::spawn(async move {
+            let total = missing.len();
+            let mut suggested = 0;
+            for (filename, path) in missing {
+                let Ok(content) = storage.read(&path).await else {
+                    continue;
+                };
+                let tagger = Arc::clone(&tagger);
+                match pool.run(move || tagger.suggest(&content)).await {
+                    Ok(suggestions) => {
+                        tags.suggest(&filename, suggestions);
+                        suggested += 1;
+                    }
+                    Err(AppError::ServiceUnavailable(_)) => break,
+                    Err(e) => debug!("为 {} 生成候选标签失败: {}", filename, e),
+                }
+            }
+            if let Err(e) = tags.flush().await {
+                warn!("保存候选标签失败: {}", e);
+            }
+            info!("后台为 {}/{} 个表情包生成了候选标签", suggested, total);
+            suggesting.store(false, Ordering::Release);
+        });
+    }
 
-        // 尝试从缓存获取
-        if let Some(content) = self.content_cache.get(&id).await {
-            self.cache_hits.fetch_add(1, Ordering::Relaxed);
-            CACHE_HITS.inc(); // 更新 Prometheus 计数器
-            self.update_cache_metrics();
-            debug!(
-                meme_id = id,
-                cache_type = "content",
-                "Cache hit"
-            );
-            return Ok((meme, content));
+    /// 在后台识别尚未识别过的表情包中的文字，识别服务或图片线程池不可用时留到下次重载
+    fn extract_text(&self) {
+        let Some(engine) = &self.ocr else {
+            return;
+        };
+        let missing: Vec<(String, PathBuf, String)> = self.memes
+            .values()
+            .filter(|meme| !meme.is_video() && !self.texts.contains(&meme.filename))
+            .map(|meme| (meme.filename.clone(), meme.path.clone(), meme.mime_type.clone()))
+            .collect();
+        if missing.is_empty() || self.extracting_text.swap(true, Ordering::AcqRel) {
+            return;
         }
 
-        // 如果缓存未命中，从文件读取
-        self.cache_misses.fetch_add(1, Ordering::Relaxed);
-        CACHE_MISSES.inc(); // 更新 Prometheus 计数器
-        self.update_cache_metrics();
-        debug!(
-            meme_id = id,
-            cache_type = "content",
-            "Cache miss"
-        );
-        let content = tokio::fs::read(&meme.path).await?;
-        self.content_cache.insert(id, content.clone()).await;
-        
-        Ok((meme, content))
+        let storage = Arc::clone(&self.storage);
+        let pool = Arc::clone(&self.image_pool);
+        let engine = Arc::clone(engine);
+        let texts = Arc::clone(&self.texts);
+        let extracting = Arc::clone(&self.extracting_text);
+        tokio::spawn(async move {
+            let total = missing.len();
+            let mut extracted = 0;
+            for (filename, path, mime_type) in missing {
+                let Ok(content) = storage.read(&path).await else {
+                    continue;
+                };
+                match engine.extract(content, &mime_type, &pool).await {
+                    Ok(text) => {
+                        texts.set(&filename, text);
+                        extracted += 1;
+                    }
+                    Err(AppError::ServiceUnavailable(e)) => {
+                        warn!("文字识别暂不可用，下次重载时继续: {}", e);
+                        break;
+                    }
+                    Err(e) => debug!("识别 {} 中的文字失败: {}", filename, e),
+                }
+            }
+            if let Err(e) = texts.flush().await {
+                warn!("保存识别文字失败: {}", e);
+            }
+            info!("后台识别了 {}/{} 个表情包中的文字", extracted, total);
+            extracting.store(false, Ordering::Release);
+        });
     }
 
-    /// 获取压缩后的图片，支持缓存
-    pub async fn get_resized_image(&self, id: u32, width: Option<u32>, height: Option<u32>) -> Result<(&Meme, Vec<u8>)> {
+    /// 获取缩放或旋转、翻转后的图片，支持缓存
+    pub async fn get_resized_image(
+        &self,
+        id: u32,
+        width: Option<u32>,
+        height: Option<u32>,
+        transform: ImageTransform,
+    ) -> Result<(&Meme, Vec<u8>, CacheStatus)> {
+        let id = self.resolve_id(id);
         let meme = self.memes.get(&id)
+            .filter(|meme| meme.is_approved())
             .ok_or_else(|| AppError::NotFound(format!("Meme with id {} not found", id)))?;
 
-        // 如果没有指定尺寸，直接返回原图
-        if width.is_none() && height.is_none() {
+        // 如果没有指定尺寸与变换，直接返回原图
+        if width.is_none() && height.is_none() && transform.is_identity() {
             return self.get_by_id(id).await;
         }
+        reject_video(meme)?;
+        if let Some(crop) = &transform.crop {
+            crop.validate(meme)?;
+        }
 
         // 生成缓存键
-        let cache_key = format!("{}:{}x{}", id, width.unwrap_or(0), height.unwrap_or(0));
+        let cache_key = format!("{}:{}", id, variant_key(width, height, transform));
         
         // 尝试从压缩图片缓存获取
-        if let Some(content) = self.resized_cache.get(&cache_key).await {
-            self.cache_hits.fetch_add(1, Ordering::Relaxed);
-            CACHE_HITS.inc(); // 更新 Prometheus 计数器
+        if let Some(content) = self.get_variant(&cache_key).await {
+            self.counters.cache_hits.fetch_add(1, Ordering::Relaxed);
+            CACHE_HITS.with_label_values(&[RESIZED_CACHE]).inc(); // 更新 Prometheus 计数器
             self.update_cache_metrics();
             debug!(
                 meme_id = id,
@@ -384,37 +3425,46 @@ impl MemeService {
                 cache_key = cache_key,
                 "Cache hit"
             );
-            return Ok((meme, content));
+            return Ok((meme, content, CacheStatus::Hit));
         }
 
+        // 内存过载时不再进行缩放
+        self.load_shedder.check()?;
+
         // 获取原图
-        let (_, original_content) = self.get_by_id(id).await?;
+        let (_, original_content, _) = self.get_by_id(id).await?;
+        // 需要裁剪的矢量图按声明的尺寸光栅化，与目录中的尺寸一致；cover 与 fill 在光栅化后再缩放
+        let vector_resize = meme.is_svg() && transform.crop.is_none() && transform.fit == Fit::Contain;
+        let is_svg = meme.is_svg();
         
-        // 压缩图片
-        let resized_content = tokio::task::spawn_blocking(move || {
-            use image::{ImageFormat, imageops::FilterType};
-            use std::io::Cursor;
-            
-            let img = image::load_from_memory(&original_content)
-                .map_err(|e| AppError::Internal(format!("Failed to load image: {}", e)))?;
-            
-            let target_width = width.unwrap_or(img.width());
-            let target_height = height.unwrap_or(img.height());
-            
-            // 使用更快的滤波器进行缩放
-            let resized = img.resize(target_width, target_height, FilterType::Triangle);
-            
-            let mut cursor = Cursor::new(Vec::new());
-            resized.write_to(&mut cursor, ImageFormat::Png)
-                .map_err(|e| AppError::Internal(format!("Failed to encode image: {}", e)))?;
+        // 压缩图片，队列已满时返回 503
+        let resized_content = self.image_pool.run(move || {
+            // 先旋转、翻转，宽高参数针对变换后的图片
+            let img = if vector_resize {
+                // 矢量图直接按目标尺寸光栅化，旋转 90/270 度时交换宽高
+                let (w, h) = if transform.rotate % 180 == 90 { (height, width) } else { (width, height) };
+                transform.apply(svg::rasterize(&original_content, w, h)?)
+            } else if is_svg {
+                transform.apply(svg::rasterize(&original_content, None, None)?)
+            } else {
+                let img = image::load_from_memory(&original_content)
+                    .map_err(|e| AppError::Internal(format!("Failed to load image: {}", e)))?;
+                transform.apply(img)
+            };
             
-            Ok::<Vec<u8>, AppError>(cursor.into_inner())
-        }).await
-        .map_err(|e| AppError::Internal(format!("Task join error: {}", e)))??;
+            let resized = if !vector_resize && (width.is_some() || height.is_some()) {
+                transform.resize(img, width, height)
+            } else {
+                img
+            };
+
+            transform.encode(&resized)
+        }).await?;
 
         // 缓存压缩后的图片
         self.resized_cache.insert(cache_key.clone(), resized_content.clone()).await;
-        self.cache_misses.fetch_add(1, Ordering::Relaxed);
+        self.counters.cache_misses.fetch_add(1, Ordering::Relaxed);
+        CACHE_MISSES.with_label_values(&[RESIZED_CACHE]).inc();
         self.update_cache_metrics();
         debug!(
             meme_id = id,
@@ -423,6 +3473,6 @@ impl MemeService {
             "Cache miss"
         );
         
-        Ok((meme, resized_content))
+        Ok((meme, resized_content, CacheStatus::Miss))
     }
 }
\ No newline at end of file
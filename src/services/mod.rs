@@ -1 +1,29 @@
-pub mod meme;
\ No newline at end of file
+pub mod alias;
+pub mod caption;
+pub mod catalog;
+pub mod clients;
+pub mod cluster;
+pub mod events;
+pub mod ids;
+pub mod image_pool;
+pub mod jwt;
+pub mod load_shed;
+pub mod meme;
+pub mod moderation;
+pub mod nsfw;
+pub mod ocr;
+pub mod pins;
+pub mod reports;
+pub mod scan;
+pub mod sessions;
+pub mod snapshot;
+pub mod stats;
+pub mod storage;
+pub mod tags;
+#[cfg(feature = "ml")]
+pub mod tagger;
+pub mod trash;
+pub mod user_agents;
+pub mod watcher;
+pub mod watermark;
+pub mod webhook;
\ No newline at end of file
@@ -0,0 +1,77 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::PathBuf,
+};
+use parking_lot::RwLock;
+use tracing::info;
+use crate::utils::error::{AppError, Result};
+use crate::utils::persist;
+
+/// 待审核表情包存储，按文件名记录上传后尚未审核的表情包，
+/// 持久化为 JSON 文件，重启后仍保持待审核状态
+#[derive(Debug)]
+pub struct ModerationStore {
+    path: PathBuf,
+    pending: RwLock<BTreeSet<String>>,
+}
+
+impl ModerationStore {
+    /// 从文件加载待审核列表，文件不存在时从空表开始；文件存在但无法读取或解析时返回错误，
+    /// 从空表开始会把所有待审核的上传当作已通过公开
+    pub fn load(path: &str) -> Result<Self> {
+        let path = PathBuf::from(path);
+        let pending = match persist::load_json_required::<BTreeSet<String>>(&path, "待审核列表")? {
+            Some(pending) => {
+                info!("已加载 {} 个待审核表情包", pending.len());
+                pending
+            }
+            None => BTreeSet::new(),
+        };
+
+        Ok(Self {
+            path,
+            pending: RwLock::new(pending),
+        })
+    }
+
+    pub fn is_pending(&self, filename: &str) -> bool {
+        self.pending.read().contains(filename)
+    }
+
+    /// 标记文件为待审核
+    pub fn mark_pending(&self, filename: &str) -> Result<()> {
+        let mut pending = self.pending.write();
+        if pending.insert(filename.to_string()) {
+            self.save(&pending)?;
+        }
+        Ok(())
+    }
+
+    /// 结束审核（通过或拒绝），返回该文件之前是否处于待审核状态
+    pub fn resolve(&self, filename: &str) -> Result<bool> {
+        let mut pending = self.pending.write();
+        let removed = pending.remove(filename);
+        if removed {
+            self.save(&pending)?;
+        }
+        Ok(removed)
+    }
+
+    /// 清理已不存在的文件（例如被手动删除的待审核表情包）
+    pub fn retain(&self, existing: &HashSet<String>) -> Result<()> {
+        let mut pending = self.pending.write();
+        let before = pending.len();
+        pending.retain(|filename| existing.contains(filename));
+        if pending.len() != before {
+            info!("清理了 {} 个已不存在的待审核表情包", before - pending.len());
+            self.save(&pending)?;
+        }
+        Ok(())
+    }
+
+    fn save(&self, pending: &BTreeSet<String>) -> Result<()> {
+        let content = serde_json::to_string_pretty(pending)
+            .map_err(|e| AppError::Internal(format!("序列化待审核列表失败: {}", e)))?;
+        persist::write(&self.path, &content)
+    }
+}
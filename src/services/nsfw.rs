@@ -0,0 +1,71 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::PathBuf,
+};
+use parking_lot::RwLock;
+use tracing::info;
+use crate::utils::error::{AppError, Result};
+use crate::utils::persist;
+
+/// 通过管理接口标记的 NSFW 表情包，按文件名记录并持久化为 JSON 文件；
+/// 来源信息文件中的 `nsfw: true` 不写入这里
+#[derive(Debug)]
+pub struct NsfwStore {
+    path: PathBuf,
+    flagged: RwLock<BTreeSet<String>>,
+}
+
+impl NsfwStore {
+    /// 从文件加载标记列表，文件不存在或无法解析时从空表开始
+    pub fn load(path: &str) -> Self {
+        let path = PathBuf::from(path);
+        let flagged = match persist::load_json::<BTreeSet<String>>(&path, "NSFW 标记列表") {
+            Some(flagged) => {
+                info!("已加载 {} 个 NSFW 标记", flagged.len());
+                flagged
+            }
+            None => BTreeSet::new(),
+        };
+
+        Self {
+            path,
+            flagged: RwLock::new(flagged),
+        }
+    }
+
+    pub fn is_flagged(&self, filename: &str) -> bool {
+        self.flagged.read().contains(filename)
+    }
+
+    /// 设置或取消标记，返回标记是否发生变化
+    pub fn set(&self, filename: &str, nsfw: bool) -> Result<bool> {
+        let mut flagged = self.flagged.write();
+        let changed = if nsfw {
+            flagged.insert(filename.to_string())
+        } else {
+            flagged.remove(filename)
+        };
+        if changed {
+            self.save(&flagged)?;
+        }
+        Ok(changed)
+    }
+
+    /// 清理已不存在的文件
+    pub fn retain(&self, existing: &HashSet<String>) -> Result<()> {
+        let mut flagged = self.flagged.write();
+        let before = flagged.len();
+        flagged.retain(|filename| existing.contains(filename));
+        if flagged.len() != before {
+            info!("清理了 {} 个已不存在的 NSFW 标记", before - flagged.len());
+            self.save(&flagged)?;
+        }
+        Ok(())
+    }
+
+    fn save(&self, flagged: &BTreeSet<String>) -> Result<()> {
+        let content = serde_json::to_string_pretty(flagged)
+            .map_err(|e| AppError::Internal(format!("序列化 NSFW 标记列表失败: {}", e)))?;
+        persist::write(&self.path, &content)
+    }
+}
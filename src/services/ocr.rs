@@ -0,0 +1,195 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use parking_lot::RwLock;
+use serde::Deserialize;
+use tracing::{info, warn};
+use crate::config::OcrConfig;
+use crate::services::image_pool::ImagePool;
+use crate::utils::error::{AppError, Result};
+use crate::utils::persist;
+
+/// 识别图片中的文字：优先使用配置的外部服务，否则使用本地 tesseract
+#[derive(Debug)]
+pub enum OcrEngine {
+    Remote {
+        client: reqwest::Client,
+        endpoint: String,
+    },
+    #[cfg(feature = "ocr")]
+    Tesseract {
+        languages: String,
+    },
+}
+
+#[derive(Deserialize)]
+struct RemoteResponse {
+    #[serde(default)]
+    text: String,
+}
+
+impl OcrEngine {
+    /// 未启用或没有可用的识别方式时返回 None
+    pub fn new(config: &OcrConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+
+        if let Some(endpoint) = &config.endpoint {
+            let client = reqwest::Client::builder()
+                .timeout(Duration::from_secs(config.timeout_secs))
+                .build()
+                .ok()?;
+            info!("已启用文字识别，使用外部服务 {}", endpoint);
+            return Some(Self::Remote { client, endpoint: endpoint.clone() });
+        }
+
+        #[cfg(feature = "ocr")]
+        {
+            info!("已启用文字识别，使用本地 tesseract ({})", config.languages);
+            Some(Self::Tesseract { languages: config.languages.clone() })
+        }
+        #[cfg(not(feature = "ocr"))]
+        {
+            warn!("启用了 ocr 但未配置 ocr.endpoint，且编译时未启用 ocr 特性，不会识别文字");
+            None
+        }
+    }
+
+    /// 识别图片中的文字，返回合并空白后的文本。外部服务无法连接时返回 ServiceUnavailable
+    #[cfg_attr(not(feature = "ocr"), allow(unused_variables))]
+    pub async fn extract(&self, content: Vec<u8>, mime_type: &str, pool: &ImagePool) -> Result<String> {
+        let text = match self {
+            Self::Remote { client, endpoint } => {
+                let response = client
+                    .post(endpoint)
+                    .header(reqwest::header::CONTENT_TYPE, mime_type)
+                    .body(content)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        if e.is_connect() || e.is_timeout() {
+                            AppError::ServiceUnavailable(format!("OCR service unavailable: {}", e))
+                        } else {
+                            AppError::Internal(format!("OCR request failed: {}", e))
+                        }
+                    })?
+                    .error_for_status()
+                    .map_err(|e| AppError::Internal(format!("OCR request failed: {}", e)))?;
+                response
+                    .json::<RemoteResponse>()
+                    .await
+                    .map_err(|e| AppError::Internal(format!("Invalid OCR response: {}", e)))?
+                    .text
+            }
+            #[cfg(feature = "ocr")]
+            Self::Tesseract { languages } => {
+                let languages = languages.clone();
+                pool.run(move || tesseract(&content, &languages)).await?
+            }
+        };
+
+        Ok(text.split_whitespace().collect::<Vec<_>>().join(" "))
+    }
+}
+
+/// 先统一解码再转为 PNG，避免依赖 leptonica 编译时支持的格式 (WebP、SVG 等)
+#[cfg(feature = "ocr")]
+fn tesseract(content: &[u8], languages: &str) -> Result<String> {
+    let img = crate::utils::media::decode(content)?;
+    let mut png = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut png, image::ImageOutputFormat::Png)
+        .map_err(|e| AppError::ImageProcessing(format!("Failed to encode image for OCR: {}", e)))?;
+
+    let mut engine = leptess::LepTess::new(None, languages)
+        .map_err(|e| AppError::Internal(format!("Failed to initialize tesseract: {}", e)))?;
+    engine
+        .set_image_from_mem(png.get_ref())
+        .map_err(|e| AppError::ImageProcessing(format!("Failed to load image for OCR: {}", e)))?;
+    engine
+        .get_utf8_text()
+        .map_err(|e| AppError::Internal(format!("OCR failed: {}", e)))
+}
+
+/// 识别出的表情包文字，按文件名记录并持久化为 JSON 文件；
+/// 没有识别出文字的文件记录为空字符串，不再重复识别
+#[derive(Debug)]
+pub struct TextStore {
+    path: PathBuf,
+    entries: RwLock<BTreeMap<String, String>>,
+    // 有尚未保存的识别结果
+    dirty: AtomicBool,
+}
+
+impl TextStore {
+    /// 从文件加载识别结果，文件不存在或无法解析时从空表开始
+    pub fn load(path: &str) -> Self {
+        let path = PathBuf::from(path);
+        let entries = match persist::load_json::<BTreeMap<String, String>>(&path, "识别文字文件") {
+            Some(entries) => {
+                info!("已加载 {} 个表情包的识别文字", entries.len());
+                entries
+            }
+            None => BTreeMap::new(),
+        };
+
+        Self {
+            path,
+            entries: RwLock::new(entries),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// 识别出的文字，未识别或没有文字时为空
+    pub fn get(&self, filename: &str) -> Option<String> {
+        self.entries
+            .read()
+            .get(filename)
+            .filter(|text| !text.is_empty())
+            .cloned()
+    }
+
+    pub fn contains(&self, filename: &str) -> bool {
+        self.entries.read().contains_key(filename)
+    }
+
+    /// 记录识别结果；只修改内存，由 [`TextStore::flush`] 批量保存
+    pub fn set(&self, filename: &str, text: String) {
+        self.entries.write().insert(filename.to_string(), text);
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    /// 保存 [`TextStore::set`] 记录的识别结果，在阻塞线程池中写入
+    pub async fn flush(&self) -> Result<()> {
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
+        let content = Self::serialize(&self.entries.read())?;
+        persist::write_blocking(self.path.clone(), content).await
+    }
+
+    /// 清理已不存在的文件
+    pub fn retain(&self, existing: &HashSet<String>) -> Result<()> {
+        let mut entries = self.entries.write();
+        let before = entries.len();
+        entries.retain(|filename, _| existing.contains(filename));
+        if entries.len() != before {
+            info!("清理了 {} 个已不存在的表情包识别文字", before - entries.len());
+            self.save(&entries)?;
+        }
+        Ok(())
+    }
+
+    fn save(&self, entries: &BTreeMap<String, String>) -> Result<()> {
+        self.dirty.store(false, Ordering::Release);
+        persist::write(&self.path, &Self::serialize(entries)?)
+    }
+
+    fn serialize(entries: &BTreeMap<String, String>) -> Result<String> {
+        serde_json::to_string_pretty(entries)
+            .map_err(|e| AppError::Internal(format!("序列化识别文字文件失败: {}", e)))
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::PathBuf,
+};
+use parking_lot::RwLock;
+use tracing::info;
+use crate::metrics::{PINNED_BYTES, PINNED_MEMES};
+use crate::utils::error::{AppError, Result};
+use crate::utils::persist;
+
+/// 常驻内存的表情包
+///
+/// 按文件名持久化固定列表，内容保存在独立的表中，不受内容缓存的容量与 TTL 淘汰影响，
+/// 每次重载后重新读取
+#[derive(Debug)]
+pub struct PinStore {
+    path: PathBuf,
+    filenames: RwLock<BTreeSet<String>>,
+    contents: RwLock<HashMap<u32, Vec<u8>>>,
+}
+
+impl PinStore {
+    /// 从文件加载固定列表，文件不存在或无法解析时从空表开始
+    pub fn load(path: &str) -> Self {
+        let path = PathBuf::from(path);
+        let filenames = match persist::load_json::<BTreeSet<String>>(&path, "固定列表") {
+            Some(filenames) => {
+                info!("已加载 {} 个固定的表情包", filenames.len());
+                filenames
+            }
+            None => BTreeSet::new(),
+        };
+
+        Self {
+            path,
+            filenames: RwLock::new(filenames),
+            contents: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn filenames(&self) -> Vec<String> {
+        self.filenames.read().iter().cloned().collect()
+    }
+
+    pub fn contains(&self, id: u32) -> bool {
+        self.contents.read().contains_key(&id)
+    }
+
+    pub fn get(&self, id: u32) -> Option<Vec<u8>> {
+        self.contents.read().get(&id).cloned()
+    }
+
+    /// 固定表情包并保存内容，返回之前是否未固定
+    pub fn pin(&self, filename: &str, id: u32, content: Vec<u8>) -> Result<bool> {
+        let added = {
+            let mut filenames = self.filenames.write();
+            let added = filenames.insert(filename.to_string());
+            if added {
+                self.save(&filenames)?;
+            }
+            added
+        };
+        self.contents.write().insert(id, content);
+        self.update_metrics();
+        Ok(added)
+    }
+
+    /// 取消固定，返回之前是否已固定
+    pub fn unpin(&self, filename: &str, id: u32) -> Result<bool> {
+        let removed = {
+            let mut filenames = self.filenames.write();
+            let removed = filenames.remove(filename);
+            if removed {
+                self.save(&filenames)?;
+            }
+            removed
+        };
+        self.contents.write().remove(&id);
+        self.update_metrics();
+        Ok(removed)
+    }
+
+    /// 重载后替换全部内容；文件暂时不存在的表情包 (如存储未挂载) 仍保留在列表中，文件恢复后重新加载
+    pub fn replace(&self, contents: HashMap<u32, Vec<u8>>) {
+        *self.contents.write() = contents;
+        self.update_metrics();
+    }
+
+    fn update_metrics(&self) {
+        let contents = self.contents.read();
+        PINNED_MEMES.set(contents.len() as f64);
+        PINNED_BYTES.set(contents.values().map(|c| c.len()).sum::<usize>() as f64);
+    }
+
+    fn save(&self, filenames: &BTreeSet<String>) -> Result<()> {
+        let content = serde_json::to_string_pretty(filenames)
+            .map_err(|e| AppError::Internal(format!("序列化固定列表失败: {}", e)))?;
+        persist::write(&self.path, &content)
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    io::Write,
+    net::{IpAddr, Ipv6Addr},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tracing::info;
+use utoipa::ToSchema;
+use crate::utils::error::{AppError, Result};
+use crate::utils::persist;
+
+/// 举报理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportReason {
+    Spam,
+    Nsfw,
+    Offensive,
+    Copyright,
+    Other,
+}
+
+impl ReportReason {
+    /// 指标标签
+    pub fn key(self) -> &'static str {
+        match self {
+            ReportReason::Spam => "spam",
+            ReportReason::Nsfw => "nsfw",
+            ReportReason::Offensive => "offensive",
+            ReportReason::Copyright => "copyright",
+            ReportReason::Other => "other",
+        }
+    }
+}
+
+/// 一条用户举报
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Report {
+    pub reason: ReportReason,
+    /// 补充说明
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// 举报者标识 (客户端 IP 的 HMAC，IPv6 按 /64 网段)，同一客户端对同一表情包只记一次
+    #[schema(example = "3f2a9c0d1b7e4a55")]
+    pub reporter: String,
+    /// 举报时间 (Unix 时间戳，秒)
+    #[schema(example = 1704067200)]
+    pub reported_at: u64,
+}
+
+/// 举报结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportOutcome {
+    /// 新增了一条举报，附带该文件当前的举报数
+    Recorded(usize),
+    /// 该客户端已经举报过这个文件
+    Duplicate(usize),
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 举报者所在的客户端：IPv6 地址按 /64 网段归为同一客户端 (一个用户通常拥有整个 /64)，
+/// IPv4 映射地址按 IPv4 处理
+fn reporter_client(ip: Option<IpAddr>) -> Option<IpAddr> {
+    match ip? {
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => Some(IpAddr::V4(ip)),
+            None => Some(IpAddr::V6(Ipv6Addr::from(u128::from(ip) & !((1u128 << 64) - 1)))),
+        },
+        ip => Some(ip),
+    }
+}
+
+/// 读取举报者标识的密钥，文件不存在时生成 32 字节随机密钥并保存
+fn load_secret(path: &Path) -> Result<Vec<u8>> {
+    match std::fs::read(path) {
+        Ok(secret) if !secret.is_empty() => return Ok(secret),
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    let mut secret = vec![0u8; 32];
+    getrandom::getrandom(&mut secret)
+        .map_err(|e| AppError::Internal(format!("生成举报密钥失败: {}", e)))?;
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(&secret)?;
+    info!("已生成举报者标识密钥 {:?}", path);
+    Ok(secret)
+}
+
+/// 持久化时不保存原始 IP，只保存以本实例密钥计算的 HMAC-SHA256 的前 16 位，
+/// 没有密钥无法从记录反推出 IP
+fn reporter_id(secret: &[u8], client: Option<IpAddr>) -> String {
+    let client = client.map(|ip| ip.to_string()).unwrap_or_else(|| "unknown".to_string());
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC 接受任意长度的密钥");
+    mac.update(client.as_bytes());
+    format!("{:x}", mac.finalize().into_bytes())[..16].to_string()
+}
+
+/// 用户举报存储，按文件名记录举报并持久化为 JSON 文件；
+/// 同时在内存中按客户端 IP 限制举报频率
+#[derive(Debug)]
+pub struct ReportStore {
+    path: PathBuf,
+    reports: RwLock<BTreeMap<String, Vec<Report>>>,
+    secret: Vec<u8>,
+    recent: Mutex<HashMap<Option<IpAddr>, VecDeque<Instant>>>,
+    max_per_client: usize,
+    window: Duration,
+}
+
+impl ReportStore {
+    /// 从文件加载举报记录，文件不存在或无法解析时从空表开始；密钥文件无法读取或创建时返回错误
+    pub fn load(path: &str, secret_path: &str, max_per_client: usize, window_secs: u64) -> Result<Self> {
+        let path = PathBuf::from(path);
+        let reports = match persist::load_json::<BTreeMap<String, Vec<Report>>>(&path, "举报记录") {
+            Some(reports) => {
+                info!("已加载 {} 个表情包的举报记录", reports.len());
+                reports
+            }
+            None => BTreeMap::new(),
+        };
+
+        Ok(Self {
+            path,
+            reports: RwLock::new(reports),
+            secret: load_secret(Path::new(secret_path))?,
+            recent: Mutex::new(HashMap::new()),
+            max_per_client,
+            window: Duration::from_secs(window_secs),
+        })
+    }
+
+    /// 检查并占用客户端的举报额度，超出时返回需要等待的秒数
+    fn acquire(&self, ip: Option<IpAddr>) -> Result<()> {
+        if self.max_per_client == 0 {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut recent = self.recent.lock();
+        // 顺带清理窗口外的记录，避免表无限增长
+        recent.retain(|_, times| {
+            while times.front().is_some_and(|t| now.duration_since(*t) >= self.window) {
+                times.pop_front();
+            }
+            !times.is_empty()
+        });
+
+        let times = recent.entry(ip).or_default();
+        if times.len() >= self.max_per_client {
+            let oldest = times.front().copied().unwrap_or(now);
+            let retry_after_secs = self.window.saturating_sub(now.duration_since(oldest)).as_secs().max(1);
+            return Err(AppError::TooManyRequests { retry_after_secs });
+        }
+        times.push_back(now);
+        Ok(())
+    }
+
+    /// 记录一条举报，超出频率限制时返回 429
+    pub fn report(&self, filename: &str, reason: ReportReason, note: Option<String>, ip: Option<IpAddr>) -> Result<ReportOutcome> {
+        let client = reporter_client(ip);
+        self.acquire(client)?;
+
+        let reporter = reporter_id(&self.secret, client);
+        let mut reports = self.reports.write();
+        let entries = reports.entry(filename.to_string()).or_default();
+        if entries.iter().any(|r| r.reporter == reporter) {
+            return Ok(ReportOutcome::Duplicate(entries.len()));
+        }
+        entries.push(Report {
+            reason,
+            note,
+            reporter,
+            reported_at: now_secs(),
+        });
+        let count = entries.len();
+        self.save(&reports)?;
+        Ok(ReportOutcome::Recorded(count))
+    }
+
+    /// 所有举报，按文件名分组
+    pub fn list(&self) -> BTreeMap<String, Vec<Report>> {
+        self.reports.read().clone()
+    }
+
+    /// 清除文件的全部举报（管理员处理后），返回清除的条数
+    pub fn clear(&self, filename: &str) -> Result<usize> {
+        let mut reports = self.reports.write();
+        let removed = reports.remove(filename).map(|r| r.len()).unwrap_or(0);
+        if removed > 0 {
+            self.save(&reports)?;
+        }
+        Ok(removed)
+    }
+
+    /// 清理已不存在的文件的举报
+    pub fn retain(&self, existing: &HashSet<String>) -> Result<()> {
+        let mut reports = self.reports.write();
+        let before = reports.len();
+        reports.retain(|filename, _| existing.contains(filename));
+        if reports.len() != before {
+            info!("清理了 {} 个已不存在的表情包的举报记录", before - reports.len());
+            self.save(&reports)?;
+        }
+        Ok(())
+    }
+
+    fn save(&self, reports: &BTreeMap<String, Vec<Report>>) -> Result<()> {
+        let content = serde_json::to_string_pretty(reports)
+            .map_err(|e| AppError::Internal(format!("序列化举报记录失败: {}", e)))?;
+        persist::write(&self.path, &content)
+    }
+}
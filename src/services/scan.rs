@@ -0,0 +1,89 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+use tokio::{sync::Semaphore, task::JoinSet};
+use tracing::{debug, info, warn};
+
+/// 每扫描到这么多文件输出一次进度
+const PROGRESS_INTERVAL: usize = 5000;
+
+/// 单个目录的扫描结果：(目录, 其中的文件与子目录)
+type DirListing = (PathBuf, io::Result<(Vec<PathBuf>, Vec<PathBuf>)>);
+
+/// 列出目录下的所有文件，按路径排序
+///
+/// `recursive` 为 true 时并发遍历子目录 (跳过以 `.` 开头的隐藏目录，例如回收站)，
+/// 同时读取的目录数不超过 `concurrency`；子目录读取失败只记录警告，根目录读取失败返回错误
+pub async fn list_files(root: &Path, recursive: bool, concurrency: usize) -> io::Result<Vec<PathBuf>> {
+    let started = Instant::now();
+    let (mut files, subdirs) = read_dir(root).await?;
+    if !recursive {
+        files.sort();
+        return Ok(files);
+    }
+
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+    let spawn = |tasks: &mut JoinSet<DirListing>, dir: PathBuf| {
+        let semaphore = Arc::clone(&semaphore);
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let result = read_dir(&dir).await;
+            (dir, result)
+        });
+    };
+    for dir in subdirs {
+        spawn(&mut tasks, dir);
+    }
+
+    let mut directories = 1;
+    let mut reported = files.len() / PROGRESS_INTERVAL;
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((_, Ok((dir_files, subdirs)))) => {
+                directories += 1;
+                files.extend(dir_files);
+                for dir in subdirs {
+                    spawn(&mut tasks, dir);
+                }
+            }
+            Ok((dir, Err(e))) => warn!("读取子目录 {:?} 失败: {}", dir, e),
+            Err(e) => warn!("目录扫描任务异常退出: {}", e),
+        }
+
+        if files.len() / PROGRESS_INTERVAL > reported {
+            reported = files.len() / PROGRESS_INTERVAL;
+            info!("正在扫描表情包目录: 已扫描 {} 个目录，发现 {} 个文件", directories, files.len());
+        }
+    }
+
+    debug!(
+        "扫描表情包目录完成: {} 个目录，{} 个文件，耗时 {:?}",
+        directories,
+        files.len(),
+        started.elapsed()
+    );
+    files.sort();
+    Ok(files)
+}
+
+/// 读取单个目录，返回其中的文件与非隐藏子目录
+async fn read_dir(dir: &Path) -> io::Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let mut files = Vec::new();
+    let mut subdirs = Vec::new();
+
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let file_type = entry.file_type().await?;
+        if file_type.is_file() {
+            files.push(entry.path());
+        } else if file_type.is_dir() && !entry.file_name().to_string_lossy().starts_with('.') {
+            subdirs.push(entry.path());
+        }
+    }
+
+    Ok((files, subdirs))
+}
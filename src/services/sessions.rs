@@ -0,0 +1,89 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+use moka::{policy::EvictionPolicy, sync::Cache};
+use parking_lot::Mutex;
+use crate::config::RandomConfig;
+use crate::metrics::RANDOM_SESSIONS;
+
+/// 会话最近看过的表情包 ID，按返回顺序保存，超过上限时忘记最早的
+#[derive(Debug)]
+struct SessionHistory {
+    order: VecDeque<u32>,
+    seen: HashSet<u32>,
+    max_len: usize,
+}
+
+impl SessionHistory {
+    fn new(max_len: usize) -> Self {
+        Self {
+            order: VecDeque::new(),
+            seen: HashSet::new(),
+            max_len,
+        }
+    }
+
+    /// 从候选中选出本会话还没看过的一个；候选都看过时开始新一轮，
+    /// 只忘记这些候选，且不会与上一次返回的重复 (只剩一个候选时除外)
+    fn pick(&mut self, pool: &[u32]) -> u32 {
+        let unseen: Vec<u32> = pool.iter().copied().filter(|id| !self.seen.contains(id)).collect();
+        let id = if unseen.is_empty() {
+            let last = self.order.back().copied();
+            let pool_ids: HashSet<u32> = pool.iter().copied().collect();
+            self.order.retain(|id| !pool_ids.contains(id));
+            self.seen.retain(|id| !pool_ids.contains(id));
+            let rest: Vec<u32> = pool.iter().copied().filter(|id| pool.len() == 1 || Some(*id) != last).collect();
+            rest[fastrand::usize(..rest.len())]
+        } else {
+            unseen[fastrand::usize(..unseen.len())]
+        };
+
+        if self.order.len() >= self.max_len {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.order.push_back(id);
+        self.seen.insert(id);
+        id
+    }
+}
+
+/// 随机接口 `?session=` 的去重记录
+///
+/// 每个会话记住最近返回过的 ID，在候选用完之前不重复；会话保存在有容量上限、
+/// 空闲过期的 LRU 缓存中，内存占用有界
+#[derive(Debug)]
+pub struct RandomSessions {
+    sessions: Cache<String, Arc<Mutex<SessionHistory>>>,
+    history_len: usize,
+}
+
+impl RandomSessions {
+    /// `random.max_sessions` 为 0 时不启用，`?session=` 参数被忽略
+    pub fn new(config: &RandomConfig) -> Option<Self> {
+        if config.max_sessions == 0 {
+            return None;
+        }
+        Some(Self {
+            sessions: Cache::builder()
+                .max_capacity(config.max_sessions)
+                .time_to_idle(Duration::from_secs(config.session_ttl_secs))
+                .eviction_policy(EvictionPolicy::lru())
+                .build(),
+            history_len: config.session_history,
+        })
+    }
+
+    /// 为会话从候选 ID 中选出一个，并记入该会话的历史
+    pub fn pick(&self, session: &str, pool: &[u32]) -> u32 {
+        let history = self.sessions.get_with(session.to_string(), || {
+            Arc::new(Mutex::new(SessionHistory::new(self.history_len)))
+        });
+        RANDOM_SESSIONS.set(self.sessions.entry_count() as f64);
+        let mut history = history.lock();
+        history.pick(pool)
+    }
+}
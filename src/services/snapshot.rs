@@ -0,0 +1,71 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use crate::models::meme::Meme;
+use crate::services::meme::CachedFileInfo;
+use crate::utils::error::{AppError, Result};
+
+/// 快照格式版本，结构变化时递增，旧版本的快照直接丢弃
+const SNAPSHOT_VERSION: u32 = 2;
+
+/// 上次成功重载后的表情包目录，启动时先用它提供服务，再在后台重新扫描校验
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CatalogFile {
+    version: u32,
+    /// 生成快照时的表情包目录，与当前配置不同时不使用
+    pub memes_dir: String,
+    pub memes: Vec<Meme>,
+    pub duplicate_ids: HashMap<u32, u32>,
+    /// 文件大小、修改时间、哈希与尺寸，后台扫描时未变化的文件无需重新读取
+    pub files: HashMap<PathBuf, CachedFileInfo>,
+}
+
+impl CatalogFile {
+    pub fn new(
+        memes_dir: &str,
+        memes: Vec<Meme>,
+        duplicate_ids: HashMap<u32, u32>,
+        files: HashMap<PathBuf, CachedFileInfo>,
+    ) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            memes_dir: memes_dir.to_string(),
+            memes,
+            duplicate_ids,
+            files,
+        }
+    }
+
+    /// 读取快照，文件不存在、格式不符或表情包目录已变更时返回 None
+    pub fn load(path: &Path, memes_dir: &str) -> Option<Self> {
+        let content = std::fs::read(path).ok()?;
+        let snapshot = match serde_json::from_slice::<Self>(&content) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                warn!("解析目录快照 {:?} 失败: {}", path, e);
+                return None;
+            }
+        };
+        if snapshot.version != SNAPSHOT_VERSION || snapshot.memes_dir != memes_dir || snapshot.memes.is_empty() {
+            info!("目录快照 {:?} 已过期，忽略", path);
+            return None;
+        }
+        Some(snapshot)
+    }
+
+    /// 先写入临时文件再重命名，避免中途退出留下不完整的快照
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_vec(self)
+            .map_err(|e| AppError::Internal(format!("序列化目录快照失败: {}", e)))?;
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
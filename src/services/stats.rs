@@ -0,0 +1,396 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::Arc,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::{info, error};
+use crate::config::StatisticsConfig;
+use crate::utils::error::{AppError, Result};
+use crate::utils::persist;
+
+/// 单个表情包的访问统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MemeHitStats {
+    /// 累计访问次数
+    pub total_hits: u64,
+    /// 按半衰期衰减后的热度分数
+    pub score: f64,
+    /// 分数最后一次更新的 Unix 时间戳（秒）
+    pub updated_at: u64,
+}
+
+/// 表情包访问统计存储，与请求速率窗口相互独立，
+/// 定期持久化到磁盘以便重启后恢复
+#[derive(Debug)]
+pub struct MemeStatsStore {
+    stats: Mutex<HashMap<u32, MemeHitStats>>,
+    path: PathBuf,
+    half_life_secs: f64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl MemeHitStats {
+    /// 计算衰减到 `now` 时刻的热度分数
+    fn decayed_score(&self, now: u64, half_life_secs: f64) -> f64 {
+        let elapsed = now.saturating_sub(self.updated_at) as f64;
+        self.score * 0.5f64.powf(elapsed / half_life_secs)
+    }
+}
+
+impl MemeStatsStore {
+    /// 创建统计存储，如果持久化文件存在则从中恢复
+    pub fn load(config: &StatisticsConfig) -> Self {
+        let path = PathBuf::from(&config.persist_path);
+        let stats: HashMap<u32, MemeHitStats> = persist::load_json(&path, "表情包访问统计").unwrap_or_default();
+        if !stats.is_empty() {
+            info!("已从 {:?} 恢复 {} 条表情包访问统计", path, stats.len());
+        }
+
+        Self {
+            stats: Mutex::new(stats),
+            path,
+            half_life_secs: config.trending_half_life_hours * 3600.0,
+        }
+    }
+
+    /// 记录一次表情包访问
+    pub fn record_hit(&self, id: u32) {
+        let now = now_secs();
+        let mut stats = self.stats.lock();
+        let entry = stats.entry(id).or_default();
+        entry.score = entry.decayed_score(now, self.half_life_secs) + 1.0;
+        entry.updated_at = now;
+        entry.total_hits += 1;
+    }
+
+    /// 按衰减后的热度分数返回前 `limit` 个表情包 (id, 热度分数, 累计访问次数)
+    pub fn trending(&self, limit: usize) -> Vec<(u32, f64, u64)> {
+        let now = now_secs();
+        let mut entries: Vec<(u32, f64, u64)> = self.stats.lock()
+            .iter()
+            .map(|(id, s)| (*id, s.decayed_score(now, self.half_life_secs), s.total_hits))
+            .collect();
+        entries.sort_by(|a, b| b.1.total_cmp(&a.1));
+        entries.truncate(limit);
+        entries
+    }
+
+    /// 按 `旧 ID -> 新 ID` 改写统计的键，新 ID 已有统计时合并，有变化时立即持久化，返回改写的条数
+    pub fn remap(&self, redirects: &BTreeMap<u32, u32>) -> Result<usize> {
+        let now = now_secs();
+        let remapped = {
+            let mut stats = self.stats.lock();
+            let old: Vec<(u32, MemeHitStats)> = redirects.keys()
+                .filter_map(|id| stats.remove(id).map(|s| (*id, s)))
+                .collect();
+            for (old_id, old_stats) in &old {
+                let entry = stats.entry(redirects[old_id]).or_default();
+                entry.score = entry.decayed_score(now, self.half_life_secs) + old_stats.decayed_score(now, self.half_life_secs);
+                entry.updated_at = now;
+                entry.total_hits += old_stats.total_hits;
+            }
+            old.len()
+        };
+        if remapped > 0 {
+            self.persist()?;
+        }
+        Ok(remapped)
+    }
+
+    /// 清空所有表情包的访问统计并立即持久化
+    pub fn reset(&self) -> Result<()> {
+        self.stats.lock().clear();
+        self.persist()
+    }
+
+    /// 将统计数据写入磁盘（先写临时文件再重命名，避免写入中途崩溃损坏文件）
+    pub fn persist(&self) -> Result<()> {
+        let content = {
+            let stats = self.stats.lock();
+            serde_json::to_string(&*stats)
+                .map_err(|e| AppError::Internal(format!("序列化表情包访问统计失败: {}", e)))?
+        };
+
+        persist::write(&self.path, &content)
+    }
+
+    /// 启动定期持久化任务
+    pub fn start_persist_task(store: Arc<Self>, interval_secs: u64) {
+        spawn_persist_loop(interval_secs, "表情包访问统计", move || store.persist());
+    }
+}
+
+/// 请求与缓存的累计计数，持久化到磁盘
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CounterSnapshot {
+    pub requests: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    /// 累计运行时间（秒）
+    pub uptime_secs: u64,
+    /// 首次启动的 Unix 时间戳（秒）
+    pub first_started_at: u64,
+    /// 重启次数
+    pub restarts: u64,
+}
+
+/// 请求与缓存计数
+///
+/// 本次运行的计数保存在原子变量中，历次运行的累计值在启动时从文件恢复，
+/// 定期与本次计数合并后写回，部署重启后不会清零
+#[derive(Debug)]
+pub struct RequestCounters {
+    pub requests: AtomicU64,
+    pub cache_hits: AtomicU64,
+    pub cache_misses: AtomicU64,
+    started_at: Instant,
+    baseline: Mutex<CounterSnapshot>,
+    path: PathBuf,
+}
+
+impl RequestCounters {
+    /// 创建计数器，如果持久化文件存在则以其中的累计值为基准
+    pub fn load(path: &str) -> Self {
+        let path = PathBuf::from(path);
+        let baseline = match persist::load_json::<CounterSnapshot>(&path, "累计计数") {
+            Some(mut baseline) => {
+                baseline.restarts += 1;
+                info!(
+                    "已从 {:?} 恢复累计计数: {} 次请求，第 {} 次重启",
+                    path, baseline.requests, baseline.restarts
+                );
+                baseline
+            }
+            None => CounterSnapshot::default(),
+        };
+
+        let baseline = CounterSnapshot {
+            first_started_at: if baseline.first_started_at == 0 { now_secs() } else { baseline.first_started_at },
+            ..baseline
+        };
+
+        Self {
+            requests: AtomicU64::new(0),
+            cache_hits: AtomicU64::new(0),
+            cache_misses: AtomicU64::new(0),
+            started_at: Instant::now(),
+            baseline: Mutex::new(baseline),
+            path,
+        }
+    }
+
+    /// 本次启动以来的计数
+    pub fn since_restart(&self) -> CounterSnapshot {
+        let baseline = self.baseline.lock();
+        CounterSnapshot {
+            requests: self.requests.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            cache_misses: self.cache_misses.load(Ordering::Relaxed),
+            uptime_secs: self.started_at.elapsed().as_secs(),
+            first_started_at: baseline.first_started_at,
+            restarts: baseline.restarts,
+        }
+    }
+
+    /// 包含历次运行的累计计数
+    pub fn lifetime(&self) -> CounterSnapshot {
+        let current = self.since_restart();
+        let baseline = self.baseline.lock();
+        CounterSnapshot {
+            requests: baseline.requests + current.requests,
+            cache_hits: baseline.cache_hits + current.cache_hits,
+            cache_misses: baseline.cache_misses + current.cache_misses,
+            uptime_secs: baseline.uptime_secs + current.uptime_secs,
+            ..current
+        }
+    }
+
+    /// 将请求与缓存计数（包括历次运行的累计值）清零并立即持久化；运行时间与重启次数保留
+    pub fn reset(&self) -> Result<()> {
+        {
+            let mut baseline = self.baseline.lock();
+            baseline.requests = 0;
+            baseline.cache_hits = 0;
+            baseline.cache_misses = 0;
+            self.requests.store(0, Ordering::Relaxed);
+            self.cache_hits.store(0, Ordering::Relaxed);
+            self.cache_misses.store(0, Ordering::Relaxed);
+        }
+        self.persist()
+    }
+
+    pub fn persist(&self) -> Result<()> {
+        let content = serde_json::to_string(&self.lifetime())
+            .map_err(|e| AppError::Internal(format!("序列化累计计数失败: {}", e)))?;
+        persist::write(&self.path, &content)
+    }
+
+    /// 启动定期持久化任务
+    pub fn start_persist_task(counters: Arc<Self>, interval_secs: u64) {
+        spawn_persist_loop(interval_secs, "累计计数", move || counters.persist());
+    }
+}
+
+/// 按固定间隔在阻塞线程池中执行持久化
+fn spawn_persist_loop<F>(interval_secs: u64, what: &'static str, persist: F)
+where
+    F: Fn() -> Result<()> + Send + Sync + 'static,
+{
+    let persist = Arc::new(persist);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        // 第一次 tick 会立即完成，跳过
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let persist = Arc::clone(&persist);
+            let result = tokio::task::spawn_blocking(move || persist()).await;
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => error!("持久化{}失败: {}", what, e),
+                Err(e) => error!("持久化任务执行失败: {}", e),
+            }
+        }
+    });
+}
+
+/// 按秒分桶的滑动窗口请求计数
+///
+/// 每个桶是一个原子整数，高 32 位为桶对应的秒数 (自创建起)，低 32 位为该秒的请求数；
+/// 记录请求只需一次 CAS，不持有锁，也不需要逐条清理过期时间戳。
+/// 桶按秒数循环复用，秒数不符的桶视为已过期
+#[derive(Debug)]
+pub struct RequestWindow {
+    origin: Instant,
+    buckets: Box<[AtomicU64]>,
+}
+
+impl RequestWindow {
+    /// 创建能统计最近 `window` 内请求的计数器
+    pub fn new(window: Duration) -> Self {
+        // 多留一个桶，当前秒写入时不会覆盖窗口内最早的一秒
+        let len = window.as_secs().max(1) as usize + 1;
+        Self {
+            origin: Instant::now(),
+            buckets: (0..len).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    fn now_secs(&self) -> u64 {
+        self.origin.elapsed().as_secs()
+    }
+
+    pub fn record(&self) {
+        self.record_at(self.now_secs());
+    }
+
+    fn record_at(&self, now: u64) {
+        let bucket = &self.buckets[now as usize % self.buckets.len()];
+        let mut current = bucket.load(Ordering::Relaxed);
+        loop {
+            let (second, count) = unpack(current);
+            // 读取时间后被挂起、桶已被之后的某一秒复用时放弃，不能用旧的秒数覆盖新的计数
+            if second > now {
+                return;
+            }
+            let next = if second == now { pack(now, count.saturating_add(1)) } else { pack(now, 1) };
+            match bucket.compare_exchange_weak(current, next, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => return,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// 最近 `window` 内 (含当前这一秒) 的请求数，超过创建时的窗口按创建时的窗口计算
+    pub fn count(&self, window: Duration) -> u64 {
+        self.count_at(self.now_secs(), window)
+    }
+
+    fn count_at(&self, now: u64, window: Duration) -> u64 {
+        let window = window.as_secs().clamp(1, self.buckets.len() as u64 - 1);
+        self.buckets
+            .iter()
+            .map(|bucket| unpack(bucket.load(Ordering::Relaxed)))
+            .filter(|&(second, _)| second <= now && now - second < window)
+            .map(|(_, count)| count as u64)
+            .sum()
+    }
+
+    pub fn reset(&self) {
+        for bucket in self.buckets.iter() {
+            bucket.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+fn pack(second: u64, count: u32) -> u64 {
+    (second << 32) | count as u64
+}
+
+fn unpack(value: u64) -> (u64, u32) {
+    (value >> 32, value as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_requests_within_window() {
+        let window = RequestWindow::new(Duration::from_secs(3));
+        for _ in 0..3 {
+            window.record_at(0);
+        }
+        window.record_at(1);
+        window.record_at(2);
+        window.record_at(2);
+        assert_eq!(window.count_at(2, Duration::from_secs(3)), 6);
+        assert_eq!(window.count_at(2, Duration::from_secs(1)), 2);
+        // 超过创建时的窗口按创建时的窗口计算
+        assert_eq!(window.count_at(2, Duration::from_secs(60)), 6);
+        assert_eq!(window.count_at(3, Duration::from_secs(3)), 3);
+    }
+
+    #[test]
+    fn reused_bucket_starts_a_new_count() {
+        let window = RequestWindow::new(Duration::from_secs(2));
+        window.record_at(0);
+        window.record_at(0);
+        // 3 个桶，第 3 秒复用第 0 秒的桶
+        window.record_at(3);
+        assert_eq!(window.count_at(3, Duration::from_secs(2)), 1);
+        assert_eq!(unpack(window.buckets[0].load(Ordering::Relaxed)), (3, 1));
+    }
+
+    #[test]
+    fn counts_across_wrap_around() {
+        let window = RequestWindow::new(Duration::from_secs(2));
+        for second in 0..10 {
+            window.record_at(second);
+        }
+        window.record_at(9);
+        assert_eq!(window.count_at(9, Duration::from_secs(2)), 3);
+        assert_eq!(window.count_at(10, Duration::from_secs(2)), 2);
+        assert_eq!(window.count_at(12, Duration::from_secs(2)), 0);
+    }
+
+    #[test]
+    fn stale_writer_does_not_clobber_newer_bucket() {
+        let window = RequestWindow::new(Duration::from_secs(2));
+        window.record_at(3);
+        window.record_at(3);
+        window.record_at(0);
+        assert_eq!(unpack(window.buckets[0].load(Ordering::Relaxed)), (3, 2));
+        assert_eq!(window.count_at(3, Duration::from_secs(2)), 2);
+    }
+}
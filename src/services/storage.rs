@@ -0,0 +1,207 @@
+use std::{
+    fmt::Debug,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+use axum::async_trait;
+use tokio::{io::AsyncReadExt, sync::broadcast};
+use crate::config::StorageConfig;
+use crate::services::meme::ReloadTrigger;
+use crate::services::scan;
+use crate::services::watcher::{WatcherHandle, WatcherStatus};
+#[cfg(any(test, feature = "test-util"))]
+use std::collections::BTreeMap;
+#[cfg(any(test, feature = "test-util"))]
+use parking_lot::RwLock;
+#[cfg(any(test, feature = "test-util"))]
+use tracing::error;
+
+/// 文件的大小与修改时间
+#[derive(Debug, Clone, Copy)]
+pub struct FileMeta {
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// 存储变更监控，用于就绪检查报告状态
+pub trait StorageWatch: Send + Sync + Debug {
+    fn status(&self) -> WatcherStatus;
+}
+
+impl StorageWatch for WatcherHandle {
+    fn status(&self) -> WatcherStatus {
+        WatcherHandle::status(self)
+    }
+}
+
+/// 表情包文件的读取接口
+///
+/// 路径均为 `memes_dir` 下的完整路径；上传、回收站等写入操作目前仍直接操作本地文件系统
+#[async_trait]
+pub trait Storage: Send + Sync + Debug {
+    /// 列出所有文件，按路径排序
+    async fn list(&self) -> io::Result<Vec<PathBuf>>;
+
+    async fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// 读取文件开头最多 `len` 字节，用于识别文件类型
+    async fn read_head(&self, path: &Path, len: usize) -> io::Result<Vec<u8>>;
+
+    async fn metadata(&self, path: &Path) -> io::Result<FileMeta>;
+
+    /// 文件在本地磁盘上的路径，可用于零拷贝发送；非本地存储返回 None
+    fn local_path(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+
+    /// 开始监控变更，有变更时发送重载信号
+    fn watch(&self, reload_tx: broadcast::Sender<ReloadTrigger>) -> Arc<dyn StorageWatch>;
+}
+
+/// 本地文件系统存储
+#[derive(Debug)]
+pub struct FsStorage {
+    root: PathBuf,
+    recursive: bool,
+    scan_concurrency: usize,
+}
+
+impl FsStorage {
+    pub fn new(config: &StorageConfig) -> Self {
+        Self {
+            root: PathBuf::from(&config.memes_dir),
+            recursive: config.recursive,
+            scan_concurrency: config.scan_concurrency,
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for FsStorage {
+    async fn list(&self) -> io::Result<Vec<PathBuf>> {
+        scan::list_files(&self.root, self.recursive, self.scan_concurrency).await
+    }
+
+    async fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        tokio::fs::read(path).await
+    }
+
+    async fn read_head(&self, path: &Path, len: usize) -> io::Result<Vec<u8>> {
+        let file = tokio::fs::File::open(path).await?;
+        let mut head = Vec::with_capacity(len);
+        file.take(len as u64).read_to_end(&mut head).await?;
+        Ok(head)
+    }
+
+    async fn metadata(&self, path: &Path) -> io::Result<FileMeta> {
+        let metadata = tokio::fs::metadata(path).await?;
+        Ok(FileMeta {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        Some(path.to_path_buf())
+    }
+
+    fn watch(&self, reload_tx: broadcast::Sender<ReloadTrigger>) -> Arc<dyn StorageWatch> {
+        // 出错时由后台任务自动重新注册
+        WatcherHandle::start(self.root.clone(), reload_tx)
+    }
+}
+
+/// 内存存储，供测试在不访问磁盘的情况下驱动完整的加载与接口流程
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    files: RwLock<BTreeMap<PathBuf, (Vec<u8>, SystemTime)>>,
+    reload_tx: RwLock<Option<broadcast::Sender<ReloadTrigger>>>,
+}
+
+/// 内存存储的变更总是能送达，监控始终健康
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug)]
+struct MemoryWatch;
+
+#[cfg(any(test, feature = "test-util"))]
+impl StorageWatch for MemoryWatch {
+    fn status(&self) -> WatcherStatus {
+        WatcherStatus {
+            healthy: true,
+            restarts: 0,
+            last_error: None,
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 写入文件并触发重载
+    pub fn insert(&self, path: impl Into<PathBuf>, content: Vec<u8>) {
+        self.files.write().insert(path.into(), (content, SystemTime::now()));
+        self.notify();
+    }
+
+    /// 删除文件并触发重载
+    pub fn remove(&self, path: &Path) -> bool {
+        let removed = self.files.write().remove(path).is_some();
+        if removed {
+            self.notify();
+        }
+        removed
+    }
+
+    fn notify(&self) {
+        if let Some(tx) = self.reload_tx.read().as_ref() {
+            if let Err(e) = tx.send(ReloadTrigger::Watcher) {
+                error!("发送重载信号失败: {}", e);
+            }
+        }
+    }
+
+    fn get(&self, path: &Path) -> io::Result<(Vec<u8>, SystemTime)> {
+        self.files
+            .read()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} not found", path.display())))
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn list(&self) -> io::Result<Vec<PathBuf>> {
+        Ok(self.files.read().keys().cloned().collect())
+    }
+
+    async fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.get(path).map(|(content, _)| content)
+    }
+
+    async fn read_head(&self, path: &Path, len: usize) -> io::Result<Vec<u8>> {
+        let (mut content, _) = self.get(path)?;
+        content.truncate(len);
+        Ok(content)
+    }
+
+    async fn metadata(&self, path: &Path) -> io::Result<FileMeta> {
+        let (content, modified) = self.get(path)?;
+        Ok(FileMeta {
+            len: content.len() as u64,
+            modified: Some(modified),
+        })
+    }
+
+    fn watch(&self, reload_tx: broadcast::Sender<ReloadTrigger>) -> Arc<dyn StorageWatch> {
+        *self.reload_tx.write() = Some(reload_tx);
+        Arc::new(MemoryWatch)
+    }
+}
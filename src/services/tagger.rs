@@ -0,0 +1,115 @@
+use image::imageops::FilterType;
+use tracing::info;
+use tract_onnx::prelude::*;
+use crate::config::MlConfig;
+use crate::services::tags::SuggestedTag;
+use crate::utils::error::{AppError, Result};
+use crate::utils::media;
+
+/// 用本地 ONNX 图像分类模型为表情包生成候选标签
+pub struct Tagger {
+    model: TypedRunnableModel<TypedModel>,
+    labels: Vec<String>,
+    config: MlConfig,
+}
+
+impl std::fmt::Debug for Tagger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tagger")
+            .field("labels", &self.labels.len())
+            .field("input_size", &self.config.input_size)
+            .finish()
+    }
+}
+
+impl Tagger {
+    /// 未配置 `ml.model_path` 时返回 None；模型或标签文件无法加载时报错
+    pub fn load(config: &MlConfig) -> Result<Option<Self>> {
+        let Some(model_path) = &config.model_path else {
+            return Ok(None);
+        };
+        let labels_path = config.labels_path.as_deref().unwrap_or_default();
+        let labels: Vec<String> = std::fs::read_to_string(labels_path)
+            .map_err(|e| AppError::Config(format!("Failed to read ML labels {}: {}", labels_path, e)))?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        let size = config.input_size as usize;
+        let model = tract_onnx::onnx()
+            .model_for_path(model_path)
+            .and_then(|model| model.with_input_fact(0, f32::fact([1, 3, size, size]).into()))
+            .and_then(|model| model.into_optimized())
+            .and_then(|model| model.into_runnable())
+            .map_err(|e| AppError::Config(format!("Failed to load ONNX model {}: {}", model_path, e)))?;
+
+        info!("已加载标签模型 {} ({} 个标签)", model_path, labels.len());
+        Ok(Some(Self {
+            model,
+            labels,
+            config: config.clone(),
+        }))
+    }
+
+    /// 按置信度降序返回最多 `top_k` 个不低于 `min_score` 的标签。需要解码图片并推理，应在图片线程池中调用
+    pub fn suggest(&self, content: &[u8]) -> Result<Vec<SuggestedTag>> {
+        let size = self.config.input_size;
+        let img = media::decode(content)?
+            .resize_exact(size, size, FilterType::Triangle)
+            .to_rgb8();
+        let (mean, std) = (self.config.mean, self.config.std);
+        let input: Tensor = tract_ndarray::Array4::from_shape_fn(
+            (1, 3, size as usize, size as usize),
+            |(_, channel, y, x)| {
+                let value = img.get_pixel(x as u32, y as u32)[channel] as f32 / 255.0;
+                (value - mean[channel]) / std[channel]
+            },
+        )
+        .into();
+
+        let outputs = self
+            .model
+            .run(tvec!(input.into()))
+            .map_err(|e| AppError::ImageProcessing(format!("Model inference failed: {}", e)))?;
+        let mut scores: Vec<f32> = outputs[0]
+            .to_array_view::<f32>()
+            .map_err(|e| AppError::ImageProcessing(format!("Unexpected model output: {}", e)))?
+            .iter()
+            .copied()
+            .collect();
+        if self.config.softmax {
+            softmax(&mut scores);
+        }
+
+        let mut ranked: Vec<(usize, f32)> = scores
+            .into_iter()
+            .enumerate()
+            .filter(|(_, score)| *score >= self.config.min_score)
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranked.truncate(self.config.top_k);
+
+        Ok(ranked
+            .into_iter()
+            .filter_map(|(index, score)| {
+                self.labels.get(index).map(|tag| SuggestedTag { tag: tag.clone(), score })
+            })
+            .collect())
+    }
+}
+
+fn softmax(scores: &mut [f32]) {
+    let max = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let mut sum = 0.0;
+    for score in scores.iter_mut() {
+        *score = (*score - max).exp();
+        sum += *score;
+    }
+    if sum > 0.0 {
+        for score in scores.iter_mut() {
+            *score /= sum;
+        }
+    }
+}
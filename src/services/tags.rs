@@ -0,0 +1,162 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use utoipa::ToSchema;
+use crate::utils::error::{AppError, Result};
+use crate::utils::persist;
+
+/// 模型给出的候选标签
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SuggestedTag {
+    #[schema(example = "cat")]
+    pub tag: String,
+    /// 模型置信度 (0-1)
+    #[schema(example = 0.87)]
+    pub score: f32,
+}
+
+/// 单个表情包的标签
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct TagEntry {
+    /// 审核通过的标签
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    approved: BTreeSet<String>,
+    /// 等待审核的候选标签
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    suggested: Vec<SuggestedTag>,
+    /// 是否已经由模型处理过，处理过的表情包不再重复推理
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    classified: bool,
+}
+
+/// 表情包标签，按文件名记录并持久化为 JSON 文件；
+/// 模型给出的标签先作为候选保存，经管理接口审核后才会公开
+#[derive(Debug)]
+pub struct TagStore {
+    path: PathBuf,
+    entries: RwLock<BTreeMap<String, TagEntry>>,
+    // 有尚未保存的候选标签
+    dirty: AtomicBool,
+}
+
+impl TagStore {
+    /// 从文件加载标签，文件不存在或无法解析时从空表开始
+    pub fn load(path: &str) -> Self {
+        let path = PathBuf::from(path);
+        let entries = match persist::load_json::<BTreeMap<String, TagEntry>>(&path, "标签文件") {
+            Some(entries) => {
+                info!("已加载 {} 个表情包的标签", entries.len());
+                entries
+            }
+            None => BTreeMap::new(),
+        };
+
+        Self {
+            path,
+            entries: RwLock::new(entries),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// 审核通过的标签
+    pub fn approved(&self, filename: &str) -> Vec<String> {
+        self.entries
+            .read()
+            .get(filename)
+            .map(|entry| entry.approved.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 尚未由模型处理且没有人工标签的文件
+    pub fn needs_classification(&self, filename: &str) -> bool {
+        !self.entries
+            .read()
+            .get(filename)
+            .is_some_and(|entry| entry.classified || !entry.approved.is_empty())
+    }
+
+    /// 所有带候选标签的文件
+    pub fn pending(&self) -> Vec<(String, Vec<SuggestedTag>)> {
+        self.entries
+            .read()
+            .iter()
+            .filter(|(_, entry)| !entry.suggested.is_empty())
+            .map(|(filename, entry)| (filename.clone(), entry.suggested.clone()))
+            .collect()
+    }
+
+    /// 记录模型给出的候选标签，已审核通过的标签不再作为候选；只修改内存，由 [`TagStore::flush`] 批量保存
+    pub fn suggest(&self, filename: &str, suggestions: Vec<SuggestedTag>) {
+        let mut entries = self.entries.write();
+        let entry = entries.entry(filename.to_string()).or_default();
+        entry.classified = true;
+        entry.suggested = suggestions
+            .into_iter()
+            .filter(|s| !entry.approved.contains(&s.tag))
+            .collect();
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    /// 保存 [`TagStore::suggest`] 记录的候选标签，在阻塞线程池中写入
+    pub async fn flush(&self) -> Result<()> {
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
+        let content = Self::serialize(&self.entries.read())?;
+        persist::write_blocking(self.path.clone(), content).await
+    }
+
+    /// 通过候选标签；`tags` 为空时通过全部候选，否则只通过其中列出的标签 (可包含候选之外的标签)。
+    /// 剩余的候选视为被拒绝并清除，返回通过后的全部标签
+    pub fn approve(&self, filename: &str, tags: Option<Vec<String>>) -> Result<Vec<String>> {
+        let mut entries = self.entries.write();
+        let entry = entries.entry(filename.to_string()).or_default();
+        let accepted = match tags {
+            Some(tags) => tags.into_iter().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect(),
+            None => entry.suggested.iter().map(|s| s.tag.clone()).collect::<Vec<_>>(),
+        };
+        entry.approved.extend(accepted);
+        entry.suggested.clear();
+        let approved = entry.approved.iter().cloned().collect();
+        self.save(&entries)?;
+        Ok(approved)
+    }
+
+    /// 拒绝全部候选标签，返回是否存在候选
+    pub fn reject(&self, filename: &str) -> Result<bool> {
+        let mut entries = self.entries.write();
+        let Some(entry) = entries.get_mut(filename).filter(|e| !e.suggested.is_empty()) else {
+            return Ok(false);
+        };
+        entry.suggested.clear();
+        self.save(&entries)?;
+        Ok(true)
+    }
+
+    /// 清理已不存在的文件
+    pub fn retain(&self, existing: &HashSet<String>) -> Result<()> {
+        let mut entries = self.entries.write();
+        let before = entries.len();
+        entries.retain(|filename, _| existing.contains(filename));
+        if entries.len() != before {
+            info!("清理了 {} 个已不存在的表情包标签", before - entries.len());
+            self.save(&entries)?;
+        }
+        Ok(())
+    }
+
+    fn save(&self, entries: &BTreeMap<String, TagEntry>) -> Result<()> {
+        self.dirty.store(false, Ordering::Release);
+        persist::write(&self.path, &Self::serialize(entries)?)
+    }
+
+    fn serialize(entries: &BTreeMap<String, TagEntry>) -> Result<String> {
+        serde_json::to_string_pretty(entries)
+            .map_err(|e| AppError::Internal(format!("序列化标签文件失败: {}", e)))
+    }
+}
@@ -0,0 +1,196 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use serde::Serialize;
+use tracing::{info, error, warn};
+use utoipa::ToSchema;
+use crate::utils::error::{AppError, Result};
+
+/// 回收站目录名，位于表情包目录下
+pub const TRASH_DIR_NAME: &str = ".trash";
+
+/// 回收站中的表情包
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TrashEntry {
+    #[schema(example = 1)]
+    pub id: u32,
+    /// 相对于表情包目录的路径，子目录中的表情包包含目录部分
+    #[schema(example = "cats/funny_meme.jpg")]
+    pub filename: String,
+    /// 删除时间 (Unix 时间戳，秒)
+    #[schema(example = 1704067200)]
+    pub deleted_at: u64,
+    #[schema(example = 1024)]
+    pub size_bytes: u64,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+/// 软删除：被删除的表情包先移动到回收站，保留一段时间后再由后台任务清理
+#[derive(Debug)]
+pub struct TrashService {
+    memes_dir: PathBuf,
+    trash_dir: PathBuf,
+    retention: Duration,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl TrashService {
+    pub fn new(memes_dir: &Path, retention_days: u64) -> Self {
+        Self {
+            memes_dir: memes_dir.to_path_buf(),
+            trash_dir: memes_dir.join(TRASH_DIR_NAME),
+            retention: Duration::from_secs(retention_days * 24 * 3600),
+        }
+    }
+
+    /// 将 ID 为 `id` 的表情包文件移入回收站，回收站内的文件名为 `{删除时间}-{转义后的相对路径}`，
+    /// 子目录中的表情包恢复时回到原来的子目录
+    pub async fn move_to_trash(&self, path: &Path, id: u32) -> Result<TrashEntry> {
+        let filename = path.strip_prefix(&self.memes_dir)
+            .ok()
+            .filter(|relative| relative.file_name().is_some())
+            .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+            .ok_or_else(|| AppError::Internal(format!("Invalid meme path: {}", path.display())))?;
+
+        tokio::fs::create_dir_all(&self.trash_dir).await?;
+
+        let deleted_at = now_secs();
+        let trash_path = self.trash_dir.join(format!("{}-{}", deleted_at, encode_name(&filename)));
+        let size_bytes = tokio::fs::metadata(path).await.map(|m| m.len()).unwrap_or(0);
+        tokio::fs::rename(path, &trash_path).await?;
+        info!("表情包 {} 已移入回收站", filename);
+
+        Ok(TrashEntry {
+            id,
+            filename,
+            deleted_at,
+            size_bytes,
+            path: trash_path,
+        })
+    }
+
+    /// 列出回收站中的所有文件，按删除时间倒序；`id_for` 给出原相对路径对应的表情包 ID
+    pub async fn list(&self, id_for: impl Fn(&str) -> u32) -> Result<Vec<TrashEntry>> {
+        let mut result = Vec::new();
+        let mut entries = match tokio::fs::read_dir(&self.trash_dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(result),
+            Err(e) => return Err(e.into()),
+        };
+
+        while let Some(entry) = entries.next_entry().await? {
+            if !entry.file_type().await?.is_file() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Some((timestamp, encoded)) = name.split_once('-') else {
+                continue;
+            };
+            let Ok(deleted_at) = timestamp.parse::<u64>() else {
+                continue;
+            };
+            let filename = decode_name(encoded);
+            let size_bytes = entry.metadata().await.map(|m| m.len()).unwrap_or(0);
+
+            result.push(TrashEntry {
+                id: id_for(&filename),
+                filename,
+                deleted_at,
+                size_bytes,
+                path: entry.path(),
+            });
+        }
+
+        result.sort_by_key(|entry| std::cmp::Reverse(entry.deleted_at));
+        Ok(result)
+    }
+
+    /// 恢复指定 ID 的表情包（存在多个同名删除记录时恢复最近的一个）
+    pub async fn restore(&self, id: u32, id_for: impl Fn(&str) -> u32) -> Result<TrashEntry> {
+        let entry = self.list(id_for).await?
+            .into_iter()
+            .find(|entry| entry.id == id)
+            .ok_or_else(|| AppError::NotFound(format!("Meme with id {} not found in trash", id)))?;
+
+        let target = self.memes_dir.join(&entry.filename);
+        if !target.starts_with(&self.memes_dir) || entry.filename.split('/').any(|part| part == "..") {
+            return Err(AppError::Internal(format!("Invalid trash entry: {}", entry.filename)));
+        }
+        if tokio::fs::try_exists(&target).await.unwrap_or(false) {
+            return Err(AppError::BadRequest(format!("File {} already exists", entry.filename)));
+        }
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        tokio::fs::rename(&entry.path, &target).await?;
+        info!("表情包 {} 已从回收站恢复", entry.filename);
+        Ok(entry)
+    }
+
+    /// 删除超过保留期的文件，返回清理的数量
+    pub async fn purge_expired(&self) -> Result<usize> {
+        let cutoff = now_secs().saturating_sub(self.retention.as_secs());
+        let mut purged = 0;
+
+        for entry in self.list(|_| 0).await? {
+            if entry.deleted_at < cutoff {
+                match tokio::fs::remove_file(&entry.path).await {
+                    Ok(()) => purged += 1,
+                    Err(e) => warn!("清理回收站文件 {} 失败: {}", entry.path.display(), e),
+                }
+            }
+        }
+
+        if purged > 0 {
+            info!("已从回收站清理 {} 个过期文件", purged);
+        }
+        Ok(purged)
+    }
+
+    /// 启动回收站定期清理任务
+    pub fn start_purge_task(trash: Arc<Self>, interval_secs: u64) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+            loop {
+                interval.tick().await;
+                if let Err(e) = trash.purge_expired().await {
+                    error!("清理回收站失败: {}", e);
+                }
+            }
+        });
+    }
+}
+
+/// 回收站内的文件名不能包含 `/`，相对路径中的 `%` 与 `/` 转义为 `%25` 与 `%2F`
+fn encode_name(relative: &str) -> String {
+    relative.replace('%', "%25").replace('/', "%2F")
+}
+
+/// [`encode_name`] 的逆操作；旧版本写入的文件名只有原文件名，不含转义
+fn decode_name(name: &str) -> String {
+    let mut decoded = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(index) = rest.find('%') {
+        decoded.push_str(&rest[..index]);
+        let escaped = &rest[index..];
+        if let Some(after) = escaped.strip_prefix("%2F") {
+            decoded.push('/');
+            rest = after;
+        } else {
+            decoded.push('%');
+            rest = escaped.strip_prefix("%25").unwrap_or(&escaped[1..]);
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
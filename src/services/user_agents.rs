@@ -0,0 +1,157 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::metrics::REQUESTS_BY_USER_AGENT;
+
+/// User-Agent 所属的客户端类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentCategory {
+    /// 聊天机器人框架与链接预览
+    Bot,
+    /// 搜索引擎等爬虫
+    Crawler,
+    Browser,
+    /// 命令行工具
+    Cli,
+    /// 编程语言的 HTTP 库
+    Library,
+    Other,
+    /// 请求没有 User-Agent
+    None,
+}
+
+impl AgentCategory {
+    fn label(self) -> &'static str {
+        match self {
+            AgentCategory::Bot => "bot",
+            AgentCategory::Crawler => "crawler",
+            AgentCategory::Browser => "browser",
+            AgentCategory::Cli => "cli",
+            AgentCategory::Library => "library",
+            AgentCategory::Other => "other",
+            AgentCategory::None => "none",
+        }
+    }
+}
+
+/// 识别规则：按顺序匹配小写后的 User-Agent，第一条命中的规则决定分组。
+/// 机器人与爬虫通常在 UA 中伪装成浏览器，必须排在浏览器之前；Edge 与 Chrome 都含 `chrome`，Edge 在前
+const RULES: &[(&str, &str, AgentCategory)] = &[
+    ("discord", "discord", AgentCategory::Bot),
+    ("telegrambot", "telegram", AgentCategory::Bot),
+    ("slackbot", "slack", AgentCategory::Bot),
+    ("slack-imgproxy", "slack", AgentCategory::Bot),
+    ("twitterbot", "twitter", AgentCategory::Bot),
+    ("whatsapp", "whatsapp", AgentCategory::Bot),
+    ("qq/", "qq", AgentCategory::Bot),
+    ("micromessenger", "wechat", AgentCategory::Bot),
+    ("googlebot", "google", AgentCategory::Crawler),
+    ("bingbot", "bing", AgentCategory::Crawler),
+    ("baiduspider", "baidu", AgentCategory::Crawler),
+    ("bot", "other_crawler", AgentCategory::Crawler),
+    ("spider", "other_crawler", AgentCategory::Crawler),
+    ("crawl", "other_crawler", AgentCategory::Crawler),
+    ("curl/", "curl", AgentCategory::Cli),
+    ("wget/", "wget", AgentCategory::Cli),
+    ("httpie/", "httpie", AgentCategory::Cli),
+    ("python", "python", AgentCategory::Library),
+    ("aiohttp", "python", AgentCategory::Library),
+    ("go-http-client", "go", AgentCategory::Library),
+    ("node", "node", AgentCategory::Library),
+    ("axios", "node", AgentCategory::Library),
+    ("undici", "node", AgentCategory::Library),
+    ("okhttp", "java", AgentCategory::Library),
+    ("java/", "java", AgentCategory::Library),
+    ("apache-httpclient", "java", AgentCategory::Library),
+    ("reqwest", "rust", AgentCategory::Library),
+    ("edg/", "edge", AgentCategory::Browser),
+    ("firefox/", "firefox", AgentCategory::Browser),
+    ("chrome/", "chrome", AgentCategory::Browser),
+    ("safari/", "safari", AgentCategory::Browser),
+];
+
+/// 未命中任何规则的 User-Agent
+const OTHER: usize = RULES.len();
+/// 没有 User-Agent 的请求
+const MISSING: usize = RULES.len() + 1;
+
+/// 请求最多的客户端分组
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AgentCount {
+    #[schema(example = "bot")]
+    pub category: AgentCategory,
+    /// 客户端名称，如 discord、chrome、curl
+    #[schema(example = "discord")]
+    pub client: &'static str,
+    #[schema(example = 1200)]
+    pub requests: u64,
+}
+
+/// 按 User-Agent 分组统计本次启动以来的请求
+///
+/// 只保存固定数量的分组计数，不保存原始 User-Agent，内存占用不随客户端数增长
+#[derive(Debug)]
+pub struct UserAgentStats {
+    counts: Vec<AtomicU64>,
+}
+
+impl Default for UserAgentStats {
+    fn default() -> Self {
+        Self {
+            counts: (0..=MISSING).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+}
+
+impl UserAgentStats {
+    pub fn record(&self, user_agent: Option<&str>) {
+        let index = classify(user_agent);
+        self.counts[index].fetch_add(1, Ordering::Relaxed);
+        let (client, category) = describe(index);
+        REQUESTS_BY_USER_AGENT.with_label_values(&[category.label(), client]).inc();
+    }
+
+    /// 按请求数降序合并同名分组，省略没有请求的分组
+    pub fn counts(&self) -> Vec<AgentCount> {
+        let mut merged: Vec<AgentCount> = Vec::new();
+        for (index, count) in self.counts.iter().enumerate() {
+            let requests = count.load(Ordering::Relaxed);
+            if requests == 0 {
+                continue;
+            }
+            let (client, category) = describe(index);
+            match merged.iter_mut().find(|c| c.client == client) {
+                Some(existing) => existing.requests += requests,
+                None => merged.push(AgentCount { category, client, requests }),
+            }
+        }
+        merged.sort_by(|a, b| b.requests.cmp(&a.requests).then_with(|| a.client.cmp(b.client)));
+        merged
+    }
+
+    pub fn reset(&self) {
+        for count in &self.counts {
+            count.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+fn classify(user_agent: Option<&str>) -> usize {
+    let Some(user_agent) = user_agent.map(str::trim).filter(|ua| !ua.is_empty()) else {
+        return MISSING;
+    };
+    let user_agent = user_agent.to_ascii_lowercase();
+    RULES
+        .iter()
+        .position(|(pattern, _, _)| user_agent.contains(pattern))
+        .unwrap_or(OTHER)
+}
+
+fn describe(index: usize) -> (&'static str, AgentCategory) {
+    match index {
+        OTHER => ("other", AgentCategory::Other),
+        MISSING => ("none", AgentCategory::None),
+        _ => (RULES[index].1, RULES[index].2),
+    }
+}
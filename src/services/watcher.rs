@@ -0,0 +1,165 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Weak},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+use notify::{RecursiveMode, Watcher};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::{info, error, warn};
+use utoipa::ToSchema;
+use crate::metrics::{WATCHER_HEALTHY, WATCHER_RESTARTS};
+use crate::services::meme::ReloadTrigger;
+
+/// 健康检查间隔
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(5);
+/// 重新注册失败时的最大退避时间
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// 文件监控状态
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WatcherStatus {
+    #[schema(example = true)]
+    pub healthy: bool,
+    /// 自动重新注册的次数
+    #[schema(example = 0)]
+    pub restarts: u64,
+    pub last_error: Option<String>,
+}
+
+/// 带健康跟踪的文件监控
+///
+/// notify 出错（例如 inotify 数量上限、目录被重新挂载）后会被标记为不健康，
+/// 由后台任务按指数退避重新注册，恢复后主动触发一次重载以补上遗漏的变更
+pub struct WatcherHandle {
+    memes_dir: PathBuf,
+    reload_tx: broadcast::Sender<ReloadTrigger>,
+    watcher: Mutex<Option<notify::RecommendedWatcher>>,
+    healthy: AtomicBool,
+    restarts: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+impl std::fmt::Debug for WatcherHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WatcherHandle")
+            .field("memes_dir", &self.memes_dir)
+            .field("healthy", &self.healthy)
+            .field("restarts", &self.restarts)
+            .finish()
+    }
+}
+
+impl WatcherHandle {
+    /// 注册文件监控并启动健康检查任务；首次注册失败不会中断启动，而是交给后台重试
+    pub fn start(memes_dir: PathBuf, reload_tx: broadcast::Sender<ReloadTrigger>) -> Arc<Self> {
+        let handle = Arc::new(Self {
+            memes_dir,
+            reload_tx,
+            watcher: Mutex::new(None),
+            healthy: AtomicBool::new(false),
+            restarts: AtomicU64::new(0),
+            last_error: Mutex::new(None),
+        });
+
+        match handle.register() {
+            Ok(()) => info!("开始监控目录: {:?}", handle.memes_dir),
+            Err(e) => handle.mark_unhealthy(format!("注册文件监控失败: {}", e)),
+        }
+
+        Self::spawn_supervisor(Arc::clone(&handle));
+        handle
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    pub fn status(&self) -> WatcherStatus {
+        WatcherStatus {
+            healthy: self.is_healthy(),
+            restarts: self.restarts.load(Ordering::Relaxed),
+            last_error: self.last_error.lock().clone(),
+        }
+    }
+
+    fn register(self: &Arc<Self>) -> notify::Result<()> {
+        let weak: Weak<Self> = Arc::downgrade(self);
+        let reload_tx = self.reload_tx.clone();
+
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            match res {
+                Ok(event) => {
+                    // 只输出变更的文件路径
+                    for path in event.paths {
+                        info!("检测到文件变更: {}", path.display());
+                    }
+                    if let Err(e) = reload_tx.send(ReloadTrigger::Watcher) {
+                        error!("发送重载信号失败: {}", e);
+                    }
+                }
+                Err(e) => {
+                    if let Some(handle) = weak.upgrade() {
+                        handle.mark_unhealthy(format!("监控文件出错: {}", e));
+                    }
+                }
+            }
+        })?;
+        watcher.watch(&self.memes_dir, RecursiveMode::Recursive)?;
+
+        *self.watcher.lock() = Some(watcher);
+        *self.last_error.lock() = None;
+        self.healthy.store(true, Ordering::Relaxed);
+        WATCHER_HEALTHY.set(1.0);
+        Ok(())
+    }
+
+    fn mark_unhealthy(&self, reason: String) {
+        error!("{}", reason);
+        *self.last_error.lock() = Some(reason);
+        self.healthy.store(false, Ordering::Relaxed);
+        WATCHER_HEALTHY.set(0.0);
+    }
+
+    fn spawn_supervisor(handle: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut backoff = Duration::from_secs(1);
+            loop {
+                tokio::time::sleep(SUPERVISE_INTERVAL).await;
+
+                // 目录被删除或卸载时，原有的监控已经失效
+                if handle.is_healthy() && !handle.memes_dir.is_dir() {
+                    handle.mark_unhealthy(format!("监控目录不可用: {:?}", handle.memes_dir));
+                }
+
+                if handle.is_healthy() {
+                    backoff = Duration::from_secs(1);
+                    continue;
+                }
+
+                // 丢弃旧的监控再重新注册
+                handle.watcher.lock().take();
+                match handle.register() {
+                    Ok(()) => {
+                        handle.restarts.fetch_add(1, Ordering::Relaxed);
+                        WATCHER_RESTARTS.inc();
+                        info!("文件监控已恢复: {:?}", handle.memes_dir);
+                        // 监控中断期间可能错过了文件变更
+                        if let Err(e) = handle.reload_tx.send(ReloadTrigger::Watcher) {
+                            error!("发送重载信号失败: {}", e);
+                        }
+                        backoff = Duration::from_secs(1);
+                    }
+                    Err(e) => {
+                        warn!("重新注册文件监控失败，{:?} 后重试: {}", backoff, e);
+                        *handle.last_error.lock() = Some(format!("重新注册文件监控失败: {}", e));
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(MAX_BACKOFF);
+                    }
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,127 @@
+use image::{codecs::jpeg::JpegEncoder, imageops::{self, FilterType}, DynamicImage, ImageFormat, RgbaImage};
+use std::io::Cursor;
+use tracing::info;
+use crate::config::{Config, WatermarkPosition};
+use crate::services::caption::CaptionRenderer;
+use crate::utils::error::{AppError, Result};
+use crate::utils::media;
+use crate::utils::secret;
+
+/// 文字水印的绘制字号，叠加时再按图片宽度缩放
+const LABEL_FONT_PX: f32 = 96.0;
+/// 水印与图片边缘的距离占短边的比例
+const MARGIN_RATIO: f32 = 0.02;
+/// JPEG 图片加水印后重新编码的质量
+const JPEG_QUALITY: u8 = 90;
+
+/// 水印：加载时准备好水印图像 (图片或渲染好的文字)，叠加时按目标图片宽度缩放
+pub struct Watermarker {
+    mark: RgbaImage,
+    position: WatermarkPosition,
+    opacity: f32,
+    scale: f32,
+    bypass_keys: Vec<String>,
+}
+
+impl std::fmt::Debug for Watermarker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Watermarker")
+            .field("position", &self.position)
+            .field("opacity", &self.opacity)
+            .field("scale", &self.scale)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Watermarker {
+    /// 未启用时返回 None；文字水印需要配文字体
+    pub fn load(config: &Config, caption: Option<&CaptionRenderer>) -> Result<Option<Self>> {
+        let watermark = &config.watermark;
+        if !watermark.enabled {
+            return Ok(None);
+        }
+
+        let mark = if !watermark.image_path.is_empty() {
+            let bytes = std::fs::read(&watermark.image_path)
+                .map_err(|e| AppError::Config(format!("Failed to read watermark image {}: {}", watermark.image_path, e)))?;
+            media::decode(&bytes)?.to_rgba8()
+        } else {
+            let renderer = caption
+                .ok_or_else(|| AppError::Config("Text watermark requires caption.font_path".to_string()))?;
+            renderer.render_label(watermark.text.trim(), LABEL_FONT_PX)
+        };
+        info!("已启用水印，位置 {:?}，不透明度 {}", watermark.position, watermark.opacity);
+
+        let bypass_keys = watermark.bypass_keys.iter()
+            .chain(&config.admin.api_keys)
+            .cloned()
+            .collect();
+        Ok(Some(Self {
+            mark,
+            position: watermark.position,
+            opacity: watermark.opacity,
+            scale: watermark.scale,
+            bypass_keys,
+        }))
+    }
+
+    /// 请求 `nowatermark=true` 且携带允许的 API Key 时跳过水印
+    pub fn is_bypassed(&self, nowatermark: bool, api_key: Option<&str>) -> bool {
+        nowatermark && api_key.is_some_and(|key| secret::contains(&self.bypass_keys, key))
+    }
+
+    /// 是否对该类型的内容加水印；动图重新编码会丢失动画，视频无法处理，都不加水印
+    pub fn applies_to(mime_type: &str) -> bool {
+        mime_type != "image/gif" && !mime_type.starts_with("video/")
+    }
+
+    /// 加水印后的 Content-Type：JPEG 保持 JPEG，其余输出 PNG
+    pub fn output_mime(mime_type: &str) -> &'static str {
+        if mime_type == "image/jpeg" {
+            "image/jpeg"
+        } else {
+            "image/png"
+        }
+    }
+
+    /// 解码图片、叠加水印并按 [`Self::output_mime`] 重新编码。需要解码图片，应在图片线程池中调用
+    pub fn apply(&self, content: &[u8], mime_type: &str) -> Result<Vec<u8>> {
+        let mut img = media::decode(content)?.to_rgba8();
+        self.overlay(&mut img);
+
+        let mut cursor = Cursor::new(Vec::new());
+        let encoded = if Self::output_mime(mime_type) == "image/jpeg" {
+            JpegEncoder::new_with_quality(&mut cursor, JPEG_QUALITY)
+                .encode_image(&DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(img).to_rgb8()))
+        } else {
+            img.write_to(&mut cursor, ImageFormat::Png)
+        };
+        encoded.map_err(|e| AppError::ImageProcessing(format!("Failed to encode image: {}", e)))?;
+        Ok(cursor.into_inner())
+    }
+
+    fn overlay(&self, img: &mut RgbaImage) {
+        let (width, height) = img.dimensions();
+        let target_width = ((width as f32 * self.scale).round() as u32).max(1);
+        let target_height = ((self.mark.height() as f32 * target_width as f32 / self.mark.width() as f32).round() as u32).max(1);
+        if target_width > width || target_height > height {
+            return;
+        }
+
+        let mut mark = imageops::resize(&self.mark, target_width, target_height, FilterType::Triangle);
+        for pixel in mark.pixels_mut() {
+            pixel[3] = (pixel[3] as f32 * self.opacity).round() as u8;
+        }
+
+        let margin = (width.min(height) as f32 * MARGIN_RATIO) as i64;
+        let (max_x, max_y) = ((width - target_width) as i64, (height - target_height) as i64);
+        let (x, y) = match self.position {
+            WatermarkPosition::TopLeft => (margin, margin),
+            WatermarkPosition::TopRight => (max_x - margin, margin),
+            WatermarkPosition::BottomLeft => (margin, max_y - margin),
+            WatermarkPosition::BottomRight => (max_x - margin, max_y - margin),
+            WatermarkPosition::Center => (max_x / 2, max_y / 2),
+        };
+        imageops::overlay(img, &mark, x.clamp(0, max_x), y.clamp(0, max_y));
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use tracing::{debug, warn};
+use crate::config::WebhookConfig;
+
+/// 请求体签名头，值为 `sha256=<十六进制 HMAC>`
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+/// 事件类型头
+pub const EVENT_HEADER: &str = "x-webhook-event";
+
+/// 发送给下游的目录事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    MemesAdded { generation: u64, count: usize, ids: Vec<u32> },
+    MemesRemoved { generation: u64, count: usize, ids: Vec<u32> },
+    ReloadFailed { error: String },
+}
+
+impl WebhookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WebhookEvent::MemesAdded { .. } => "memes_added",
+            WebhookEvent::MemesRemoved { .. } => "memes_removed",
+            WebhookEvent::ReloadFailed { .. } => "reload_failed",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    #[serde(flatten)]
+    event: &'a WebhookEvent,
+    timestamp: u64,
+}
+
+/// 目录变更的 Webhook 通知
+#[derive(Debug)]
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    urls: Vec<String>,
+    secret: String,
+    max_attempts: u32,
+}
+
+impl WebhookNotifier {
+    pub fn new(config: &WebhookConfig) -> Option<Arc<Self>> {
+        if config.urls.is_empty() {
+            return None;
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .ok()?;
+
+        Some(Arc::new(Self {
+            client,
+            urls: config.urls.clone(),
+            secret: config.secret.clone(),
+            max_attempts: config.max_attempts,
+        }))
+    }
+
+    /// 异步向所有地址发送事件，失败时按指数退避重试
+    pub fn notify(self: &Arc<Self>, event: WebhookEvent) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let body = match serde_json::to_vec(&Payload { event: &event, timestamp }) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("序列化 Webhook 事件失败: {}", e);
+                return;
+            }
+        };
+        let signature = self.sign(&body);
+
+        for url in &self.urls {
+            let notifier = Arc::clone(self);
+            let url = url.clone();
+            let body = body.clone();
+            let signature = signature.clone();
+            let event_name = event.name();
+
+            tokio::spawn(async move {
+                let mut backoff = Duration::from_secs(1);
+
+                for attempt in 1..=notifier.max_attempts {
+                    let mut request = notifier.client
+                        .post(&url)
+                        .header(reqwest::header::CONTENT_TYPE, "application/json")
+                        .header(EVENT_HEADER, event_name)
+                        .body(body.clone());
+                    if let Some(signature) = &signature {
+                        request = request.header(SIGNATURE_HEADER, signature);
+                    }
+
+                    match request.send().await.and_then(|resp| resp.error_for_status()) {
+                        Ok(_) => {
+                            debug!("已发送 Webhook 事件 {} 到 {}", event_name, url);
+                            return;
+                        }
+                        Err(e) if attempt < notifier.max_attempts => {
+                            debug!("发送 Webhook 到 {} 失败 (第 {} 次)，{:?} 后重试: {}", url, attempt, backoff, e);
+                            tokio::time::sleep(backoff).await;
+                            backoff *= 2;
+                        }
+                        Err(e) => warn!("发送 Webhook 事件 {} 到 {} 失败: {}", event_name, url, e),
+                    }
+                }
+            });
+        }
+    }
+
+    fn sign(&self, body: &[u8]) -> Option<String> {
+        if self.secret.is_empty() {
+            return None;
+        }
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes()).ok()?;
+        mac.update(body);
+        let digest: String = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        Some(format!("sha256={}", digest))
+    }
+}
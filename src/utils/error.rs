@@ -1,9 +1,10 @@
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use serde_json::json;
+use crate::utils::i18n::current_locale;
 
 #[derive(Debug, thiserror::Error)]
 #[allow(dead_code)]
@@ -35,31 +36,103 @@ pub enum AppError {
     #[error("Bad request: {0}")]
     BadRequest(String),
     
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+    
+    #[error("Server overloaded, retry after {retry_after_secs}s")]
+    Overloaded { retry_after_secs: u64 },
+    
+    #[error("Too many requests, retry after {retry_after_secs}s")]
+    TooManyRequests { retry_after_secs: u64 },
+    
     #[error("File system error: {0}")]
     FileSystem(#[from] notify::Error),
 }
 
+impl AppError {
+    /// 错误类别，同时作为语言文件中的键名后缀与响应中的 `code`
+    fn code(&self) -> &'static str {
+        match self {
+            AppError::Io(_) => "io",
+            AppError::ImageProcessing(_) => "image_processing",
+            AppError::Cache(_) => "cache",
+            AppError::Config(_) => "config",
+            AppError::MemeNotFound { .. } => "meme_not_found",
+            AppError::InvalidRequest(_) => "invalid_request",
+            AppError::NotFound(_) => "not_found",
+            AppError::Internal(_) => "internal",
+            AppError::BadRequest(_) => "bad_request",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::ServiceUnavailable(_) => "service_unavailable",
+            AppError::Overloaded { .. } => "overloaded",
+            AppError::TooManyRequests { .. } => "too_many_requests",
+            AppError::FileSystem(_) => "file_system",
+        }
+    }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            AppError::MemeNotFound { .. } | AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::InvalidRequest(_) | AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::ServiceUnavailable(_) | AppError::Overloaded { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// 错误详情（不含类别前缀）
+    fn detail(&self) -> String {
+        match self {
+            AppError::Io(e) => e.to_string(),
+            AppError::FileSystem(e) => e.to_string(),
+            AppError::MemeNotFound { id } => id.to_string(),
+            AppError::Overloaded { retry_after_secs }
+            | AppError::TooManyRequests { retry_after_secs } => format!("retry after {}s", retry_after_secs),
+            AppError::ImageProcessing(msg)
+            | AppError::Cache(msg)
+            | AppError::Config(msg)
+            | AppError::InvalidRequest(msg)
+            | AppError::NotFound(msg)
+            | AppError::Internal(msg)
+            | AppError::BadRequest(msg)
+            | AppError::Unauthorized(msg)
+            | AppError::Forbidden(msg)
+            | AppError::ServiceUnavailable(msg) => msg.clone(),
+        }
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AppError::Io(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error"),
-            AppError::ImageProcessing(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Image processing error"),
-            AppError::Cache(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Cache error"),
-            AppError::Config(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Configuration error"),
-            AppError::MemeNotFound { .. } => (StatusCode::NOT_FOUND, "Meme not found"),
-            AppError::InvalidRequest(_) => (StatusCode::BAD_REQUEST, "Invalid request"),
-            AppError::NotFound(_) => (StatusCode::NOT_FOUND, "Not found"),
-            AppError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error"),
-            AppError::BadRequest(_) => (StatusCode::BAD_REQUEST, "Bad request"),
-            AppError::FileSystem(_) => (StatusCode::INTERNAL_SERVER_ERROR, "File system error"),
-        };
+        // 按请求的 Accept-Language 选择错误信息的语言
+        let locale = current_locale();
+        let code = self.code();
+        let error_message = locale.translate(&format!("error.{}", code)).to_string();
 
         let body = Json(json!({
+            "code": code,
             "error": error_message,
-            "message": self.to_string()
+            "message": format!("{}: {}", error_message, self.detail())
         }));
 
-        (status, body).into_response()
+        let mut response = (self.status(), body).into_response();
+        response.headers_mut().insert(
+            header::CONTENT_LANGUAGE,
+            HeaderValue::from_static(locale.as_str()),
+        );
+        if let AppError::Overloaded { retry_after_secs } | AppError::TooManyRequests { retry_after_secs } = self {
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(retry_after_secs));
+        }
+        response
     }
 }
 
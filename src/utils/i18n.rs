@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use lazy_static::lazy_static;
+
+/// 支持的语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    ZhCn,
+    En,
+}
+
+lazy_static! {
+    static ref ZH_CN: HashMap<String, String> = load_catalog(include_str!("../../locales/zh-CN.yml"));
+    static ref EN: HashMap<String, String> = load_catalog(include_str!("../../locales/en.yml"));
+}
+
+tokio::task_local! {
+    /// 当前请求协商出的语言，由 locale 中间件设置
+    static CURRENT_LOCALE: Locale;
+}
+
+fn load_catalog(source: &str) -> HashMap<String, String> {
+    serde_yaml::from_str(source).expect("内置语言文件格式错误")
+}
+
+impl Locale {
+    /// 解析语言标签，只看主标签 (例如 `zh-TW` 也使用中文)
+    pub fn parse(tag: &str) -> Option<Self> {
+        let primary = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+        match primary.as_str() {
+            "zh" => Some(Locale::ZhCn),
+            "en" => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::ZhCn => "zh-CN",
+            Locale::En => "en",
+        }
+    }
+
+    /// 按 `Accept-Language` 的 q 值选择支持的语言，没有可用语言时返回 None
+    pub fn from_accept_language(header_value: &str) -> Option<Self> {
+        let mut best: Option<(Locale, f32)> = None;
+
+        for item in header_value.split(',') {
+            let mut parts = item.split(';');
+            let Some(locale) = parts.next().and_then(Locale::parse) else {
+                continue;
+            };
+            let q = parts
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .filter_map(|v| v.trim().parse::<f32>().ok())
+                .next()
+                .unwrap_or(1.0);
+
+            if q > 0.0 && best.is_none_or(|(_, best_q)| q > best_q) {
+                best = Some((locale, q));
+            }
+        }
+
+        best.map(|(locale, _)| locale)
+    }
+
+    fn catalog(&self) -> &'static HashMap<String, String> {
+        match self {
+            Locale::ZhCn => &ZH_CN,
+            Locale::En => &EN,
+        }
+    }
+
+    /// 查找翻译，缺失时返回键名本身
+    pub fn translate<'a>(&self, key: &'a str) -> &'a str {
+        self.catalog().get(key).map(String::as_str).unwrap_or(key)
+    }
+}
+
+/// 当前请求的语言，不在请求上下文中时使用默认语言
+pub fn current_locale() -> Locale {
+    CURRENT_LOCALE.try_with(|locale| *locale).unwrap_or_default()
+}
+
+/// 在指定语言下执行 future
+pub async fn with_locale<F: std::future::Future>(locale: Locale, future: F) -> F::Output {
+    CURRENT_LOCALE.scope(locale, future).await
+}
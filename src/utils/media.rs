@@ -0,0 +1,220 @@
+use std::path::Path;
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, ImageFormat};
+use crate::utils::error::{AppError, Result};
+use crate::utils::svg;
+
+/// 嗅探文件头时读取的字节数，SVG 的 `<svg` 标签前可能有较长的 XML 声明与注释
+pub const SNIFF_LEN: usize = 256;
+
+/// 根据文件头的魔数识别图片的 MIME 类型，只接受能够解码的格式 (HEIC、JPEG XL 等不支持)
+pub fn sniff_image_mime(header: &[u8]) -> Option<&'static str> {
+    let Some(kind) = infer::get(header) else {
+        return svg::looks_like_svg(header).then_some(svg::SVG_MIME);
+    };
+    match kind.mime_type() {
+        mime @ ("image/png" | "image/jpeg" | "image/gif" | "image/webp" | "image/bmp" | "image/tiff" | "image/avif") => Some(mime),
+        // 与扩展名推断的类型保持一致
+        "image/vnd.microsoft.icon" => Some("image/x-icon"),
+        _ => None,
+    }
+}
+
+/// `storage.allow_video` 开启时加载的视频扩展名
+pub const VIDEO_EXTENSIONS: [&str; 2] = ["mp4", "webm"];
+
+/// 根据文件头识别短视频的 MIME 类型，只接受浏览器普遍能播放的 mp4 与 webm
+pub fn sniff_video_mime(header: &[u8]) -> Option<&'static str> {
+    match infer::get(header)?.mime_type() {
+        mime @ ("video/mp4" | "video/webm") => Some(mime),
+        _ => None,
+    }
+}
+
+/// 从 EXIF 中读取拍摄日期 (`DateTimeOriginal`，没有时使用 `DateTime`)，返回 `YYYY-MM-DD`；
+/// 没有 EXIF 或日期无效 (如相机未设置时间写入的 `0000:00:00`) 时返回 None
+pub fn capture_date(content: &[u8]) -> Option<String> {
+    let exif = exif::Reader::new()
+        .read_from_container(&mut std::io::Cursor::new(content))
+        .ok()?;
+    [exif::Tag::DateTimeOriginal, exif::Tag::DateTime]
+        .into_iter()
+        .find_map(|tag| {
+            let field = exif.get_field(tag, exif::In::PRIMARY)?;
+            let exif::Value::Ascii(values) = &field.value else {
+                return None;
+            };
+            let datetime = exif::DateTime::from_ascii(values.first()?).ok()?;
+            (datetime.year > 0 && (1..=12).contains(&datetime.month) && (1..=31).contains(&datetime.day))
+                .then(|| format!("{:04}-{:02}-{:02}", datetime.year, datetime.month, datetime.day))
+        })
+}
+
+/// 校验 `YYYY-MM-DD` 形式的日期
+pub fn is_valid_date(date: &str) -> bool {
+    let mut parts = date.split('-');
+    let (Some(year), Some(month), Some(day), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+        return false;
+    };
+    year.len() == 4
+        && year.parse::<u16>().is_ok()
+        && month.len() == 2
+        && month.parse::<u8>().is_ok_and(|m| (1..=12).contains(&m))
+        && day.len() == 2
+        && day.parse::<u8>().is_ok_and(|d| (1..=31).contains(&d))
+}
+
+/// 判断文件是否为隐藏文件或同步过程中的临时文件
+/// (例如 `.DS_Store`、rsync 的 `.name.XXXXXX`、`*.part`、`*~`)
+pub fn is_hidden_or_temp(filename: &str) -> bool {
+    filename.starts_with('.')
+        || filename.ends_with('~')
+        || filename.ends_with(".part")
+        || filename.ends_with(".tmp")
+        || filename.ends_with(".crdownload")
+}
+
+/// 判断文件扩展名是否在允许列表中（忽略大小写）
+pub fn extension_allowed(path: &Path, allowed: &[String]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| allowed.iter().any(|a| a.trim_start_matches('.').eq_ignore_ascii_case(ext)))
+        .unwrap_or(false)
+}
+
+/// 判断 MIME 类型是否匹配允许列表，支持 `image/*` 形式的通配
+pub fn mime_allowed(mime: &str, allowed: &[String]) -> bool {
+    allowed.iter().any(|pattern| match pattern.strip_suffix("/*") {
+        Some(prefix) => mime
+            .split('/')
+            .next()
+            .map(|top| top.eq_ignore_ascii_case(prefix))
+            .unwrap_or(false),
+        None => pattern.eq_ignore_ascii_case(mime),
+    })
+}
+
+/// 解码图片，SVG 按声明的尺寸光栅化
+pub fn decode(content: &[u8]) -> Result<DynamicImage> {
+    if svg::looks_like_svg(&content[..content.len().min(SNIFF_LEN)]) {
+        return svg::rasterize(content, None, None);
+    }
+    image::load_from_memory(content)
+        .map_err(|e| AppError::ImageProcessing(format!("Failed to load image: {}", e)))
+}
+
+/// 格式协商时 WebP 的编码质量；image 自带的编码器只支持无损模式，照片类图片往往比原图更大
+const WEBP_QUALITY: f32 = 80.0;
+
+/// 用 libwebp 将图片重新编码为有损 WebP (保留透明通道)，动图只保留第一帧。比较耗 CPU，应在图片线程池中调用
+pub fn encode_webp(content: &[u8]) -> Result<Vec<u8>> {
+    let img = decode(content)?;
+    let rgba = img.to_rgba8();
+    let encoded = webp::Encoder::from_rgba(rgba.as_raw(), rgba.width(), rgba.height())
+        .encode_simple(false, WEBP_QUALITY)
+        .map_err(|e| AppError::ImageProcessing(format!("Failed to encode image: {:?}", e)))?;
+    Ok(encoded.to_vec())
+}
+
+/// `max_bytes` 允许的最小值，再小的图片已经没有意义
+pub const MIN_MAX_BYTES: usize = 1024;
+/// 压缩到指定大小时依次尝试的 JPEG 质量
+const FIT_JPEG_QUALITIES: [u8; 4] = [85, 70, 55, 40];
+/// 缩小尺寸的下限，到达后直接返回最小的结果
+const FIT_MIN_DIMENSION: u32 = 16;
+
+/// 将图片重新编码为 JPEG，使其不超过 `max_bytes`：
+/// 先逐步降低质量，仍然超出时按比例缩小尺寸后重试。比较耗 CPU，应在图片线程池中调用
+pub fn compress_to_fit(content: &[u8], max_bytes: usize) -> Result<Vec<u8>> {
+    let img = decode(content)?;
+    // JPEG 不支持透明通道，动图也只保留第一帧
+    let mut img = DynamicImage::ImageRgb8(img.to_rgb8());
+
+    loop {
+        let mut smallest = Vec::new();
+        for quality in FIT_JPEG_QUALITIES {
+            let mut encoded = Vec::new();
+            JpegEncoder::new_with_quality(&mut encoded, quality)
+                .encode_image(&img)
+                .map_err(|e| AppError::ImageProcessing(format!("Failed to encode image: {}", e)))?;
+            if encoded.len() <= max_bytes {
+                return Ok(encoded);
+            }
+            smallest = encoded;
+        }
+
+        if img.width() <= FIT_MIN_DIMENSION && img.height() <= FIT_MIN_DIMENSION {
+            return Ok(smallest);
+        }
+
+        // 文件大小大致与像素数成正比，据此估算缩放比例
+        let scale = (max_bytes as f64 / smallest.len() as f64).sqrt().clamp(0.5, 0.9);
+        let width = ((img.width() as f64 * scale) as u32).max(1);
+        let height = ((img.height() as f64 * scale) as u32).max(1);
+        img = img.resize(width, height, FilterType::Triangle);
+    }
+}
+
+/// 生成边长为 `size` 的正方形图标：等比缩放到完整放入，居中放在透明背景上，
+/// 按 `format` (PNG 或 ICO) 编码。比较耗 CPU，应在图片线程池中调用
+pub fn render_icon(content: &[u8], size: u32, format: ImageFormat) -> Result<Vec<u8>> {
+    let img = decode(content)?;
+    let fitted = img.resize(size, size, FilterType::Lanczos3).to_rgba8();
+
+    let mut canvas = image::RgbaImage::new(size, size);
+    let x = (size - fitted.width()) / 2;
+    let y = (size - fitted.height()) / 2;
+    image::imageops::overlay(&mut canvas, &fitted, x as i64, y as i64);
+
+    let mut encoded = std::io::Cursor::new(Vec::new());
+    DynamicImage::ImageRgba8(canvas)
+        .write_to(&mut encoded, format)
+        .map_err(|e| AppError::ImageProcessing(format!("Failed to encode image: {}", e)))?;
+    Ok(encoded.into_inner())
+}
+
+/// 拼图中相邻图片之间与四周的留白（像素）
+const COLLAGE_GAP: u32 = 4;
+
+/// 将多张图片按 `cols` 列拼成一张 JPEG：每张图片等比缩放到完整放入 `cell` 见方的格子并居中，
+/// 透明区域与留白为白色，动图只取第一帧。比较耗 CPU，应在图片线程池中调用
+pub fn render_collage(contents: &[Vec<u8>], cols: u32, cell: u32) -> Result<Vec<u8>> {
+    let rows = (contents.len() as u32).div_ceil(cols);
+    let width = cols * cell + (cols + 1) * COLLAGE_GAP;
+    let height = rows * cell + (rows + 1) * COLLAGE_GAP;
+    let mut canvas = image::RgbaImage::from_pixel(width, height, image::Rgba([255, 255, 255, 255]));
+
+    for (i, content) in contents.iter().enumerate() {
+        let fitted = decode(content)?.resize(cell, cell, FilterType::Lanczos3).to_rgba8();
+        let (col, row) = (i as u32 % cols, i as u32 / cols);
+        let x = COLLAGE_GAP + col * (cell + COLLAGE_GAP) + (cell - fitted.width()) / 2;
+        let y = COLLAGE_GAP + row * (cell + COLLAGE_GAP) + (cell - fitted.height()) / 2;
+        image::imageops::overlay(&mut canvas, &fitted, x as i64, y as i64);
+    }
+
+    let mut encoded = Vec::new();
+    JpegEncoder::new_with_quality(&mut encoded, 85)
+        .encode_image(&DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(canvas).to_rgb8()))
+        .map_err(|e| AppError::ImageProcessing(format!("Failed to encode image: {}", e)))?;
+    Ok(encoded)
+}
+
+/// 计算图片的平均颜色，返回 `#rrggbb`。先缩小到 32x32 再按不透明度加权平均，
+/// 透明区域不影响结果；完全透明的图片返回白色。需要解码图片，应在图片线程池中调用
+pub fn average_color(content: &[u8]) -> Result<String> {
+    let img = decode(content)?;
+    let thumbnail = img.thumbnail(32, 32).to_rgba8();
+
+    let (mut r, mut g, mut b, mut weight) = (0u64, 0u64, 0u64, 0u64);
+    for pixel in thumbnail.pixels() {
+        let [pr, pg, pb, alpha] = pixel.0;
+        let alpha = alpha as u64;
+        r += pr as u64 * alpha;
+        g += pg as u64 * alpha;
+        b += pb as u64 * alpha;
+        weight += alpha;
+    }
+    if weight == 0 {
+        return Ok("#ffffff".to_string());
+    }
+    Ok(format!("#{:02x}{:02x}{:02x}", r / weight, g / weight, b / weight))
+}
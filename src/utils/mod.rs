@@ -1 +1,9 @@
-pub mod error;
\ No newline at end of file
+pub mod error;
+pub mod i18n;
+pub mod media;
+pub mod negotiate;
+pub mod normalize;
+pub mod persist;
+pub mod secret;
+pub mod svg;
+pub mod url;
\ No newline at end of file
@@ -0,0 +1,42 @@
+/// 解析 `Accept` / `Accept-Encoding` 这类带 q 值的请求头，
+/// 返回 `token` 是否被客户端接受 (q=0 视为拒绝，`*` 与 `type/*` 通配也会匹配)
+pub fn accepts(header_value: &str, token: &str) -> bool {
+    quality(header_value, token) > 0.0
+}
+
+/// 返回 `token` 在请求头中的 q 值，未出现时为 0
+pub fn quality(header_value: &str, token: &str) -> f32 {
+    let token_type = token.split('/').next().unwrap_or(token);
+    let mut best: Option<(u8, f32)> = None;
+
+    for item in header_value.split(',') {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or("").trim();
+        if name.is_empty() {
+            continue;
+        }
+
+        // 精确匹配优先于 type/* 通配，type/* 优先于 *
+        let specificity = if name.eq_ignore_ascii_case(token) {
+            2
+        } else if name.strip_suffix("/*").is_some_and(|t| t.eq_ignore_ascii_case(token_type)) {
+            1
+        } else if name == "*" || name == "*/*" {
+            0
+        } else {
+            continue;
+        };
+
+        let q = parts
+            .filter_map(|p| p.trim().strip_prefix("q="))
+            .filter_map(|v| v.trim().parse::<f32>().ok())
+            .next()
+            .unwrap_or(1.0);
+
+        if best.is_none_or(|(s, _)| specificity > s) {
+            best = Some((specificity, q));
+        }
+    }
+
+    best.map(|(_, q)| q).unwrap_or(0.0)
+}
@@ -0,0 +1,238 @@
+use std::io::Cursor;
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, ImageFormat};
+use crate::utils::error::{AppError, Result};
+
+/// 重新编码 JPEG 时使用的质量
+const JPEG_QUALITY: u8 = 90;
+
+/// 上传图片规范化的结果
+#[derive(Debug)]
+pub struct Normalized {
+    pub content: Vec<u8>,
+    /// 转换格式后的扩展名，格式不变时为空
+    pub extension: Option<&'static str>,
+    /// 执行过的处理，用于日志
+    pub actions: Vec<&'static str>,
+}
+
+/// 规范化上传的图片：完整解码校验，按 EXIF 方向旋转，长边超过 `max_dimension` 时缩小，
+/// BMP / TIFF 等格式转换为 PNG，并去除 EXIF、XMP、文本注释等元数据。
+/// PNG / JPEG / WebP 在无需改动像素时只删除元数据块，不重新编码；GIF 动图只做校验。
+/// 比较耗 CPU，应在图片线程池中调用
+pub fn normalize_upload(content: &[u8], max_dimension: u32) -> Result<Normalized> {
+    let format = image::guess_format(content)
+        .map_err(|_| AppError::BadRequest("Uploaded file is not a supported image".to_string()))?;
+    let img = image::load_from_memory_with_format(content, format)
+        .map_err(|e| AppError::BadRequest(format!("Uploaded image could not be decoded: {}", e)))?;
+
+    if format == ImageFormat::Gif {
+        return Ok(Normalized { content: content.to_vec(), extension: None, actions: Vec::new() });
+    }
+
+    let mut actions = Vec::new();
+    let orientation = if format == ImageFormat::Jpeg { jpeg_orientation(content).unwrap_or(1) } else { 1 };
+    let oversized = max_dimension > 0 && img.width().max(img.height()) > max_dimension;
+    let mut target = match format {
+        ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP => format,
+        _ => ImageFormat::Png,
+    };
+
+    if target == format && !oversized && orientation == 1 {
+        let stripped = match format {
+            ImageFormat::Jpeg => strip_jpeg(content),
+            ImageFormat::Png => strip_png(content),
+            _ => strip_webp(content),
+        };
+        return Ok(match stripped {
+            Some(stripped) if stripped.len() < content.len() => {
+                actions.push("strip_metadata");
+                Normalized { content: stripped, extension: None, actions }
+            }
+            _ => Normalized { content: content.to_vec(), extension: None, actions },
+        });
+    }
+
+    let mut img = apply_orientation(img, orientation);
+    if orientation != 1 {
+        actions.push("orient");
+    }
+    if oversized {
+        img = img.resize(max_dimension, max_dimension, FilterType::Lanczos3);
+        actions.push("resize");
+    }
+    // image 只支持无损 WebP 编码，需要改动像素的 WebP 转为 PNG
+    if target == ImageFormat::WebP {
+        target = ImageFormat::Png;
+    }
+    if target != format {
+        actions.push("convert");
+    }
+
+    let mut encoded = Vec::new();
+    if target == ImageFormat::Jpeg {
+        JpegEncoder::new_with_quality(&mut encoded, JPEG_QUALITY)
+            .encode_image(&DynamicImage::ImageRgb8(img.to_rgb8()))
+            .map_err(|e| AppError::ImageProcessing(format!("Failed to encode image: {}", e)))?;
+    } else {
+        img.write_to(&mut Cursor::new(&mut encoded), ImageFormat::Png)
+            .map_err(|e| AppError::ImageProcessing(format!("Failed to encode image: {}", e)))?;
+    }
+
+    Ok(Normalized {
+        content: encoded,
+        extension: (target != format).then_some("png"),
+        actions,
+    })
+}
+
+/// 按 EXIF 方向值 (1-8) 旋转、翻转图片
+fn apply_orientation(img: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+/// JPEG 段：(标记, 起始, 结束)
+type Segment = (u8, usize, usize);
+
+/// 解析 JPEG 在图像数据之前的各个段，返回段列表与 SOS 段的位置
+fn jpeg_segments(content: &[u8]) -> Option<(Vec<Segment>, usize)> {
+    if !content.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut segments = Vec::new();
+    let mut pos = 2;
+    loop {
+        if *content.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *content.get(pos + 1)?;
+        if marker == 0xDA {
+            return Some((segments, pos));
+        }
+        let len = u16::from_be_bytes([*content.get(pos + 2)?, *content.get(pos + 3)?]) as usize;
+        let end = pos + 2 + len;
+        if len < 2 || end > content.len() {
+            return None;
+        }
+        segments.push((marker, pos, end));
+        pos = end;
+    }
+}
+
+/// 读取 JPEG EXIF 中的方向值
+fn jpeg_orientation(content: &[u8]) -> Option<u16> {
+    let (segments, _) = jpeg_segments(content)?;
+    let (_, start, end) = segments
+        .into_iter()
+        .find(|(marker, start, _)| *marker == 0xE1 && content[start + 4..].starts_with(b"Exif\0\0"))?;
+    let tiff = &content[start + 10..end];
+
+    let little_endian = match tiff.get(..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let u16_at = |pos: usize| -> Option<u16> {
+        let bytes = [*tiff.get(pos)?, *tiff.get(pos + 1)?];
+        Some(if little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+    };
+    let u32_at = |pos: usize| -> Option<u32> {
+        let bytes: [u8; 4] = tiff.get(pos..pos + 4)?.try_into().ok()?;
+        Some(if little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    };
+
+    let ifd = u32_at(4)? as usize;
+    let entries = u16_at(ifd)? as usize;
+    (0..entries)
+        .map(|i| ifd + 2 + i * 12)
+        .find(|&entry| u16_at(entry) == Some(0x0112))
+        .and_then(|entry| u16_at(entry + 8))
+        .filter(|orientation| (1..=8).contains(orientation))
+}
+
+/// 删除 JPEG 的 APP1 (EXIF / XMP)、APP3-APP13、APP15 与注释段，
+/// 保留 JFIF、ICC 颜色配置 (APP2) 与 Adobe (APP14) 段以免颜色错误
+fn strip_jpeg(content: &[u8]) -> Option<Vec<u8>> {
+    let (segments, scan_start) = jpeg_segments(content)?;
+    let mut stripped = Vec::with_capacity(content.len());
+    stripped.extend_from_slice(&content[..2]);
+    for (marker, start, end) in segments {
+        let drop = marker == 0xFE
+            || marker == 0xE1
+            || (0xE3..=0xED).contains(&marker)
+            || marker == 0xEF;
+        if !drop {
+            stripped.extend_from_slice(&content[start..end]);
+        }
+    }
+    stripped.extend_from_slice(&content[scan_start..]);
+    Some(stripped)
+}
+
+/// 删除 PNG 的文本、EXIF 与时间块
+fn strip_png(content: &[u8]) -> Option<Vec<u8>> {
+    const SIGNATURE_LEN: usize = 8;
+    const DROPPED: [&[u8; 4]; 5] = [b"tEXt", b"zTXt", b"iTXt", b"eXIf", b"tIME"];
+
+    let mut stripped = Vec::with_capacity(content.len());
+    stripped.extend_from_slice(content.get(..SIGNATURE_LEN)?);
+    let mut pos = SIGNATURE_LEN;
+    while pos < content.len() {
+        let len = u32::from_be_bytes(content.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let end = pos + 12 + len;
+        let kind = content.get(pos + 4..pos + 8)?;
+        if end > content.len() {
+            return None;
+        }
+        if !DROPPED.iter().any(|dropped| kind == dropped.as_slice()) {
+            stripped.extend_from_slice(&content[pos..end]);
+        }
+        pos = end;
+    }
+    Some(stripped)
+}
+
+/// 删除 WebP 的 EXIF 与 XMP 块，并清除 VP8X 中对应的标志位
+fn strip_webp(content: &[u8]) -> Option<Vec<u8>> {
+    const HEADER_LEN: usize = 12;
+    const EXIF_FLAG: u8 = 0x08;
+    const XMP_FLAG: u8 = 0x04;
+
+    if content.get(..4)? != b"RIFF" || content.get(8..12)? != b"WEBP" {
+        return None;
+    }
+    let mut stripped = content[..HEADER_LEN].to_vec();
+    let mut pos = HEADER_LEN;
+    while pos < content.len() {
+        let kind = content.get(pos..pos + 4)?;
+        let len = u32::from_le_bytes(content.get(pos + 4..pos + 8)?.try_into().ok()?) as usize;
+        let end = (pos + 8 + len + (len & 1)).min(content.len());
+        if pos + 8 + len > content.len() {
+            return None;
+        }
+        match kind {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" => {
+                let flags_at = stripped.len() + 8;
+                stripped.extend_from_slice(&content[pos..end]);
+                if let Some(flags) = stripped.get_mut(flags_at) {
+                    *flags &= !(EXIF_FLAG | XMP_FLAG);
+                }
+            }
+            _ => stripped.extend_from_slice(&content[pos..end]),
+        }
+        pos = end;
+    }
+
+    let riff_size = (stripped.len() - 8) as u32;
+    stripped[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Some(stripped)
+}
@@ -0,0 +1,76 @@
+use std::path::{Path, PathBuf};
+use serde::de::DeserializeOwned;
+use tracing::{error, warn};
+use crate::utils::error::{AppError, Result};
+
+/// 读取 JSON 持久化文件，文件不存在时返回 None；解析失败时见 [`load_with`]
+pub fn load_json<T: DeserializeOwned>(path: &Path, what: &str) -> Option<T> {
+    load_with(path, what, |content| serde_json::from_str(content).map_err(|e| e.to_string()))
+}
+
+/// 读取 YAML 持久化文件，规则同 [`load_json`]
+pub fn load_yaml<T: DeserializeOwned>(path: &Path, what: &str) -> Option<T> {
+    load_with(path, what, |content| serde_yaml::from_str(content).map_err(|e| e.to_string()))
+}
+
+/// 读取 JSON 持久化文件，文件不存在时返回 None；无法读取或解析时返回错误且不改动文件，
+/// 用于丢失内容就会出错的数据 (例如待审核列表从空表开始会直接公开未审核的上传)，由调用方拒绝启动
+pub fn load_json_required<T: DeserializeOwned>(path: &Path, what: &str) -> Result<Option<T>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(AppError::Internal(format!("读取{} {:?} 失败: {}", what, path, e))),
+    };
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| AppError::Internal(format!("{} {:?} 无法解析，请修复或删除后再启动: {}", what, path, e)))
+}
+
+/// 解析失败的文件改名为 `<文件名>.corrupt` 保留下来后返回 None，之后的保存不会覆盖运维人员的原始数据
+fn load_with<T>(path: &Path, what: &str, parse: impl FnOnce(&str) -> std::result::Result<T, String>) -> Option<T> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            warn!("读取{} {:?} 失败: {}", what, path, e);
+            return None;
+        }
+    };
+    match parse(&content) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            let corrupt = corrupt_path(path);
+            match std::fs::rename(path, &corrupt) {
+                Ok(()) => error!("解析{} {:?} 失败，已改名为 {:?}，从空表开始: {}", what, path, corrupt, e),
+                Err(rename_error) => error!("解析{} {:?} 失败，改名保留也失败，从空表开始: {}; {}", what, path, e, rename_error),
+            }
+            None
+        }
+    }
+}
+
+fn corrupt_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".corrupt");
+    path.with_file_name(name)
+}
+
+/// 写入持久化文件，必要时创建上级目录；先写临时文件再重命名，避免写入中途崩溃损坏文件
+pub fn write(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    std::fs::write(&tmp_path, content)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// 在阻塞线程池中写入，供后台任务在异步运行时中批量保存
+pub async fn write_blocking(path: PathBuf, content: String) -> Result<()> {
+    tokio::task::spawn_blocking(move || write(&path, &content))
+        .await
+        .map_err(|e| AppError::Internal(format!("写入任务失败: {}", e)))?
+}
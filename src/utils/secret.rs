@@ -0,0 +1,12 @@
+use subtle::ConstantTimeEq;
+
+/// 以恒定时间比较密钥，耗时不随相同前缀的长度变化，避免通过响应时间逐字节猜测
+/// (长度不同时直接返回 false，只暴露长度)
+pub fn eq(expected: &str, provided: &str) -> bool {
+    expected.as_bytes().ct_eq(provided.as_bytes()).into()
+}
+
+/// `provided` 是否是 `keys` 之一；与每个密钥都比较一次，不因提前匹配而缩短耗时
+pub fn contains(keys: &[String], provided: &str) -> bool {
+    keys.iter().fold(false, |found, key| found | eq(key, provided))
+}
@@ -0,0 +1,318 @@
+use image::{DynamicImage, RgbaImage};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, Writer};
+use resvg::{tiny_skia, usvg};
+use crate::utils::error::{AppError, Result};
+
+pub const SVG_MIME: &str = "image/svg+xml";
+
+/// 光栅化时长边的上限，防止声明了超大尺寸的 SVG 占用大量内存
+const MAX_RASTER_DIMENSION: u32 = 4096;
+
+/// 连同子元素一起删除的元素：脚本与可嵌入外部文档或 HTML 的元素
+const BLOCKED_ELEMENTS: &[&[u8]] = &[
+    b"script",
+    b"foreignobject",
+    b"iframe",
+    b"object",
+    b"embed",
+    b"audio",
+    b"video",
+    b"handler",
+    b"listener",
+];
+
+/// 判断文件头是否为 SVG：跳过 BOM、空白、XML 声明、注释与 DOCTYPE 后以 `<svg` 开头
+pub fn looks_like_svg(head: &[u8]) -> bool {
+    let mut rest = head.strip_prefix(b"\xef\xbb\xbf").unwrap_or(head);
+    loop {
+        rest = trim_start(rest);
+        let skip_until: &[u8] = if rest.starts_with(b"<?") {
+            b"?>"
+        } else if rest.starts_with(b"<!--") {
+            b"-->"
+        } else if rest.starts_with(b"<!") {
+            b">"
+        } else {
+            return rest.len() >= 4 && rest[..4].eq_ignore_ascii_case(b"<svg");
+        };
+        match find(rest, skip_until) {
+            Some(end) => rest = &rest[end + skip_until.len()..],
+            // 文件头被截断在声明或注释中间，无法判断
+            None => return false,
+        }
+    }
+}
+
+/// 清理 SVG 中可执行或引用外部资源的内容：删除脚本与 `foreignObject` 等元素、`on*` 事件属性、
+/// 指向外部地址的 `href`、含外部 `url()` 或 `@import` 的样式，以及 DOCTYPE (自定义实体)、注释与处理指令。
+/// 只保留片段引用 (`#id`) 与内嵌的位图 (`data:image/...`)
+pub fn sanitize(content: &[u8]) -> Result<Vec<u8>> {
+    let mut reader = Reader::from_reader(content);
+    let mut writer = Writer::new(Vec::with_capacity(content.len()));
+    let mut buf = Vec::new();
+    // 正在跳过的被删除元素的嵌套深度
+    let mut skipping = 0usize;
+    // 正在处理的 <style> 元素的嵌套深度
+    let mut in_style = 0usize;
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| AppError::ImageProcessing(format!("Invalid SVG: {}", e)))?;
+        if skipping > 0 {
+            match event {
+                Event::Start(_) => skipping += 1,
+                Event::End(_) => skipping -= 1,
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+            continue;
+        }
+
+        let kept = match event {
+            Event::Start(e) => {
+                if is_blocked(&e) {
+                    skipping = 1;
+                    None
+                } else {
+                    if local_name_is(&e, b"style") {
+                        in_style += 1;
+                    }
+                    Some(Event::Start(clean_attributes(&e)))
+                }
+            }
+            Event::Empty(e) => (!is_blocked(&e)).then(|| Event::Empty(clean_attributes(&e))),
+            Event::End(e) => {
+                if e.local_name().as_ref().eq_ignore_ascii_case(b"style") {
+                    in_style = in_style.saturating_sub(1);
+                }
+                Some(Event::End(e))
+            }
+            Event::Text(t) => {
+                // 按解码实体后的内容检查，防止 `&#64;import` 一类的写法
+                let unsafe_style = in_style > 0 && t.unescape().map_or(true, |css| unsafe_css(&css));
+                (!unsafe_style).then_some(Event::Text(t))
+            }
+            Event::CData(t) => {
+                let unsafe_style = in_style > 0 && unsafe_css(&String::from_utf8_lossy(&t));
+                (!unsafe_style).then_some(Event::CData(t))
+            }
+            Event::Decl(d) => Some(Event::Decl(d)),
+            Event::DocType(_) | Event::PI(_) | Event::Comment(_) => None,
+            Event::Eof => break,
+        };
+
+        if let Some(event) = kept {
+            writer
+                .write_event(event)
+                .map_err(|e| AppError::ImageProcessing(format!("Failed to write SVG: {}", e)))?;
+        }
+        buf.clear();
+    }
+
+    Ok(writer.into_inner())
+}
+
+/// 解析选项：`<image>` 只使用内嵌的位图，不读取本地文件
+fn options() -> usvg::Options<'static> {
+    usvg::Options {
+        image_href_resolver: usvg::ImageHrefResolver {
+            resolve_data: usvg::ImageHrefResolver::default_data_resolver(),
+            resolve_string: Box::new(|_, _| None),
+        },
+        ..usvg::Options::default()
+    }
+}
+
+/// 解析 SVG 声明的尺寸 (取整)，无法解析时返回 None
+pub fn dimensions(content: &[u8]) -> Option<(u32, u32)> {
+    let tree = usvg::Tree::from_data(content, &options()).ok()?;
+    let size = tree.size();
+    Some((size.width().ceil() as u32, size.height().ceil() as u32))
+}
+
+/// 将 SVG 光栅化为位图。指定了宽高时等比缩放到放入该范围内 (与位图缩放一致)，
+/// 否则使用声明的尺寸；长边不超过 4096。比较耗 CPU，应在图片线程池中调用
+pub fn rasterize(content: &[u8], width: Option<u32>, height: Option<u32>) -> Result<DynamicImage> {
+    let tree = usvg::Tree::from_data(content, &options())
+        .map_err(|e| AppError::ImageProcessing(format!("Failed to parse SVG: {}", e)))?;
+    let size = tree.size();
+    let (intrinsic_width, intrinsic_height) = (size.width(), size.height());
+
+    let bound_width = width.map(|w| w as f32 / intrinsic_width);
+    let bound_height = height.map(|h| h as f32 / intrinsic_height);
+    let mut scale = match (bound_width, bound_height) {
+        (Some(w), Some(h)) => w.min(h),
+        (Some(s), None) | (None, Some(s)) => s,
+        (None, None) => 1.0,
+    };
+    let long_side = intrinsic_width.max(intrinsic_height) * scale;
+    if long_side > MAX_RASTER_DIMENSION as f32 {
+        scale *= MAX_RASTER_DIMENSION as f32 / long_side;
+    }
+
+    let target_width = ((intrinsic_width * scale).round() as u32).max(1);
+    let target_height = ((intrinsic_height * scale).round() as u32).max(1);
+    let mut pixmap = tiny_skia::Pixmap::new(target_width, target_height)
+        .ok_or_else(|| AppError::ImageProcessing("Invalid SVG size".to_string()))?;
+    resvg::render(&tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+
+    // tiny-skia 使用预乘透明度，转换为 image 的普通 RGBA
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    RgbaImage::from_raw(target_width, target_height, pixels)
+        .map(DynamicImage::ImageRgba8)
+        .ok_or_else(|| AppError::ImageProcessing("Failed to convert SVG raster".to_string()))
+}
+
+fn is_blocked(element: &BytesStart) -> bool {
+    let name = element.local_name();
+    if BLOCKED_ELEMENTS.iter().any(|blocked| name.as_ref().eq_ignore_ascii_case(blocked)) {
+        return true;
+    }
+    // <set>/<animate> 可以把 href 或事件属性改成脚本
+    let animates = [b"set".as_slice(), b"animate"].iter().any(|n| name.as_ref().eq_ignore_ascii_case(n));
+    animates
+        && element.attributes().flatten().any(|attr| {
+            if !attr.key.local_name().as_ref().eq_ignore_ascii_case(b"attributename") {
+                return false;
+            }
+            let target = String::from_utf8_lossy(&attr.value).to_ascii_lowercase();
+            let target = target.rsplit(':').next().unwrap_or_default();
+            target == "href" || target.starts_with("on")
+        })
+}
+
+fn local_name_is(element: &BytesStart, name: &[u8]) -> bool {
+    element.local_name().as_ref().eq_ignore_ascii_case(name)
+}
+
+/// 复制元素并只保留安全的属性
+fn clean_attributes(element: &BytesStart) -> BytesStart<'static> {
+    let mut cleaned = element.to_owned();
+    cleaned.clear_attributes();
+    for attr in element.attributes().with_checks(false).flatten() {
+        let key = attr.key.local_name().as_ref().to_ascii_lowercase();
+        // 无法解码的实体视为不安全
+        let Ok(value) = attr.unescape_value().map(|v| v.to_string()) else {
+            continue;
+        };
+        let safe = if key.starts_with(b"on") {
+            false
+        } else if key == b"href" || key == b"src" {
+            safe_reference(&value)
+        } else if key == b"style" {
+            !unsafe_css(&value)
+        } else {
+            !compact(&value).contains("javascript:") && !unsafe_css(&value)
+        };
+        if safe {
+            cleaned.push_attribute(attr);
+        }
+    }
+    cleaned
+}
+
+/// 只允许片段引用与内嵌位图
+fn safe_reference(value: &str) -> bool {
+    let value = compact(value);
+    value.starts_with('#')
+        || ["data:image/png", "data:image/jpeg", "data:image/gif", "data:image/webp"]
+            .iter()
+            .any(|prefix| value.starts_with(prefix))
+}
+
+/// 样式中的 `@import` 或指向外部资源的 `url()`
+fn unsafe_css(css: &str) -> bool {
+    let css = compact(css);
+    if css.contains("@import") || css.contains("javascript:") || css.contains("expression(") {
+        return true;
+    }
+    css.match_indices("url(").any(|(start, _)| {
+        let target = css[start + 4..].trim_start_matches(['"', '\'']);
+        !target.starts_with('#') && !target.starts_with("data:image/")
+    })
+}
+
+/// 小写并去除空白与控制字符，防止 `java\tscript:` 一类的写法绕过检查
+fn compact(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn trim_start(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean(svg: &str) -> String {
+        String::from_utf8(sanitize(svg.as_bytes()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn removes_blocked_elements_with_children() {
+        let svg = r#"<svg><foreignObject><div><script>alert(1)</script></div></foreignObject><rect/></svg>"#;
+        assert_eq!(clean(svg), "<svg><rect/></svg>");
+        assert_eq!(clean("<svg><SCRIPT>alert(1)</SCRIPT><embed/></svg>"), "<svg></svg>");
+    }
+
+    #[test]
+    fn removes_animations_targeting_href_or_events() {
+        let svg = r#"<svg><a><set attributeName="xlink:href" to="javascript:alert(1)"/></a><animate attributeName="onclick"/><animate attributeName="opacity"/></svg>"#;
+        assert_eq!(clean(svg), r#"<svg><a></a><animate attributeName="opacity"/></svg>"#);
+    }
+
+    #[test]
+    fn removes_event_handlers_and_external_references() {
+        let svg = r##"<svg onload="alert(1)"><a href="https://example.com"/><use xlink:href="#icon"/><image href="data:image/png;base64,AA=="/><image src="file:///etc/passwd"/></svg>"##;
+        assert_eq!(
+            clean(svg),
+            r##"<svg><a/><use xlink:href="#icon"/><image href="data:image/png;base64,AA=="/><image/></svg>"##
+        );
+    }
+
+    #[test]
+    fn removes_javascript_hidden_by_whitespace() {
+        let svg = "<svg><a href=\"java\tscript:alert(1)\"/><rect fill=\"JAVA&#x0A;SCRIPT:alert(1)\"/></svg>";
+        assert_eq!(clean(svg), "<svg><a/><rect/></svg>");
+    }
+
+    #[test]
+    fn removes_styles_loading_external_urls() {
+        let svg = r##"<svg><rect style="fill:url('https://example.com/x')"/><rect style="fill:url(#grad)"/><rect fill="url(https://example.com/x)"/></svg>"##;
+        assert_eq!(clean(svg), r##"<svg><rect/><rect style="fill:url(#grad)"/><rect/></svg>"##);
+    }
+
+    #[test]
+    fn removes_style_text_with_entity_encoded_import() {
+        let svg = "<svg><style>&#64;import 'https://example.com/x.css';</style><style>rect { fill: red }</style></svg>";
+        assert_eq!(clean(svg), "<svg><style></style><style>rect { fill: red }</style></svg>");
+        let svg = "<svg><style><![CDATA[rect { background: url(https://example.com/x) }]]></style></svg>";
+        assert_eq!(clean(svg), "<svg><style></style></svg>");
+    }
+
+    #[test]
+    fn removes_doctype_comments_and_processing_instructions() {
+        let svg = r#"<?xml version="1.0"?><!DOCTYPE svg [<!ENTITY x "y">]><?xml-stylesheet href="x.css"?><!-- note --><svg/>"#;
+        assert_eq!(clean(svg), r#"<?xml version="1.0"?><svg/>"#);
+    }
+}
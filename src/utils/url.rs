@@ -0,0 +1,97 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use axum::http::{header, HeaderMap};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use crate::config::{CdnConfig, Config, ServerConfig};
+
+/// 构造对外可访问的绝对 URL
+///
+/// 优先使用配置的 `server.public_base_url`；未配置时根据请求头推断，
+/// 启用代理时会读取 `X-Forwarded-Proto` / `X-Forwarded-Host`。
+/// 配置了 `cdn.base_url` 时图片地址指向 CDN
+#[derive(Debug, Clone)]
+pub struct UrlBuilder {
+    base: String,
+    cdn: Option<CdnConfig>,
+}
+
+impl UrlBuilder {
+    pub fn from_request(config: &Config, headers: &HeaderMap) -> Self {
+        let cdn = config.cdn.base_url.as_deref()
+            .is_some_and(|b| !b.is_empty())
+            .then(|| config.cdn.clone());
+        Self {
+            base: Self::base_url(&config.server, headers),
+            cdn,
+        }
+    }
+
+    fn base_url(server: &ServerConfig, headers: &HeaderMap) -> String {
+        if let Some(base) = server.public_base_url.as_deref().filter(|b| !b.is_empty()) {
+            return base.trim_end_matches('/').to_string();
+        }
+
+        let header_value = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|h| h.to_str().ok())
+                .map(|s| s.split(',').next().unwrap_or(s).trim().to_string())
+                .filter(|s| !s.is_empty())
+        };
+
+        let (scheme, host) = if server.proxy.enabled {
+            (
+                header_value("x-forwarded-proto"),
+                header_value("x-forwarded-host").or_else(|| header_value(header::HOST.as_str())),
+            )
+        } else {
+            (None, header_value(header::HOST.as_str()))
+        };
+
+        let scheme = scheme.unwrap_or_else(|| "http".to_string());
+        let host = host.unwrap_or_else(|| format!("{}:{}", server.host, server.port));
+
+        format!("{}://{}", scheme, host)
+    }
+
+    /// 拼接站内路径，`path` 需以 `/` 开头
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base, path)
+    }
+
+    /// 获取指定表情包图片的地址，配置了 CDN 时指向 CDN
+    pub fn meme_url(&self, id: u32) -> String {
+        self.media_url(&format!("/memes/get/{}", id))
+    }
+
+    /// 图片路径的绝对地址，配置了 CDN 时指向 CDN
+    pub fn media_url(&self, path: &str) -> String {
+        self.cdn_url(path).unwrap_or_else(|| self.url(path))
+    }
+
+    /// 站内图片路径 (可含查询串) 在 CDN 上的地址，配置了签名密钥时附加 `expires` 与 `signature`；
+    /// 未配置 CDN 时返回 None
+    pub fn cdn_url(&self, path: &str) -> Option<String> {
+        let cdn = self.cdn.as_ref()?;
+        let base = cdn.base_url.as_deref()?.trim_end_matches('/');
+        if cdn.sign_key.is_empty() {
+            return Some(format!("{}{}", base, path));
+        }
+
+        // 过期时间按有效期取整，同一时间段内生成的 URL 相同，CDN 可以缓存
+        let ttl = cdn.sign_ttl_secs.max(1);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let expires = (now / ttl + 2) * ttl;
+        let mut mac = Hmac::<Sha256>::new_from_slice(cdn.sign_key.as_bytes()).ok()?;
+        mac.update(format!("{}{}", expires, path).as_bytes());
+        let signature: String = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+
+        let separator = if path.contains('?') { '&' } else { '?' };
+        Some(format!("{}{}{}expires={}&signature={}", base, path, separator, expires, signature))
+    }
+}